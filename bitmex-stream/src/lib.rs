@@ -13,10 +13,12 @@ pub use tokio_tungstenite::tungstenite::Error;
 ///
 /// To keep the connection alive, a websocket `Ping` will be sent every 5 seconds in case no other
 /// message was received in-between. This is according to BitMex's API documentation: https://www.bitmex.com/app/wsAPI#Heartbeats
-pub fn subscribe<const N: usize>(
-    topics: [String; N],
+pub fn subscribe(
+    topics: impl IntoIterator<Item = String>,
     network: Network,
 ) -> impl Stream<Item = Result<String, Error>> + Unpin {
+    let topics = topics.into_iter().collect::<Vec<_>>();
+
     let stream = stream! {
         tracing::debug!("Connecting to BitMex realtime API");
 
@@ -30,6 +30,11 @@ impl<E> State<E> {
         self.awaiting_status.keys().map(|(_, script)| script)
     }
 
+    /// The transactions being monitored, together with the script of one of their outputs.
+    pub fn monitoring(&self) -> impl Iterator<Item = &(Txid, Script)> + Clone {
+        self.awaiting_status.keys()
+    }
+
     pub fn monitor(&mut self, txid: Txid, script: Script, script_status: ScriptStatus, event: E) {
         self.awaiting_status
             .entry((txid, script))
@@ -257,6 +262,12 @@ impl From<usize> for BlockHeight {
     }
 }
 
+impl From<u32> for BlockHeight {
+    fn from(height: u32) -> Self {
+        Self(height)
+    }
+}
+
 impl fmt::Display for BlockHeight {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.0)
@@ -0,0 +1,279 @@
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use clap::ArgEnum;
+use clap::Parser;
+use clap::Subcommand;
+use model::OrderId;
+use model::Timestamp;
+use serde_json::Value;
+use sqlite_db::audit::ArchivedCfd;
+use sqlite_db::audit::AuditEvent;
+use std::path::Path;
+use std::path::PathBuf;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+/// Fields of events holding secret keys, see `model::Dlc` and `model::RevokedCommit`.
+const SECRET_FIELDS: &[&str] = &[
+    "identity",
+    "revocation",
+    "publish",
+    "revocation_sk_ours",
+    "revocation_sk_theirs",
+];
+
+/// Inspect the event log of the CFDs of a maker or taker.
+///
+/// The database is opened read-only, so this is safe to run next to a running daemon. Secret keys
+/// contained in events are redacted from the output.
+#[derive(Parser)]
+struct Opts {
+    /// The data directory of the daemon, containing `taker.sqlite` or `maker.sqlite`.
+    #[clap(long)]
+    data_dir: PathBuf,
+
+    /// Which database to open if the data directory contains both.
+    #[clap(long, arg_enum)]
+    daemon: Option<Daemon>,
+
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(ArgEnum, Clone, Copy)]
+enum Daemon {
+    Maker,
+    Taker,
+}
+
+impl Daemon {
+    fn database(&self) -> &'static str {
+        match self {
+            Daemon::Maker => "maker.sqlite",
+            Daemon::Taker => "taker.sqlite",
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List the open, closed and failed CFDs in the database with the number of their events.
+    List,
+    /// Print the events of a CFD and replay them, showing the state of the CFD after each.
+    Events {
+        /// The order id of the CFD.
+        id: Uuid,
+    },
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let opts = Opts::parse();
+
+    let path = database_path(&opts.data_dir, opts.daemon)?;
+    let db = sqlite_db::connect_read_only(path).await?;
+
+    match opts.command {
+        Command::List => list(&db).await?,
+        Command::Events { id } => events(&db, OrderId::from(id)).await?,
+    }
+
+    db.close().await;
+
+    Ok(())
+}
+
+fn database_path(data_dir: &Path, daemon: Option<Daemon>) -> Result<PathBuf> {
+    if let Some(daemon) = daemon {
+        return Ok(data_dir.join(daemon.database()));
+    }
+
+    let existing = [Daemon::Maker, Daemon::Taker]
+        .into_iter()
+        .map(|daemon| data_dir.join(daemon.database()))
+        .filter(|path| path.exists())
+        .collect::<Vec<_>>();
+
+    match existing.as_slice() {
+        [path] => Ok(path.clone()),
+        [] => bail!("No database found in {}", data_dir.display()),
+        _ => bail!(
+            "Found databases of maker and taker in {}, choose one with --daemon",
+            data_dir.display()
+        ),
+    }
+}
+
+#[allow(clippy::print_stdout)]
+async fn list(db: &sqlite_db::Connection) -> Result<()> {
+    for id in db.load_open_cfd_ids().await? {
+        let (cfd, events) = db.load_cfd_audit(id).await?;
+
+        let failed = events.iter().filter(|event| event.event.is_err()).count();
+        let last = events
+            .last()
+            .map(|event| event.name.as_str())
+            .unwrap_or("-");
+
+        println!(
+            "{id} open {:?} {} {} {} events={} failed={failed} last={last}",
+            cfd.role,
+            cfd.trading_pair,
+            cfd.position,
+            cfd.quantity_usd,
+            events.len(),
+        );
+    }
+
+    // Closed and failed CFDs only keep the names of their events
+    for (cfd, event_log) in db.load_archived_cfd_audits().await? {
+        let last = event_log
+            .last()
+            .map(|entry| entry.name.as_str())
+            .unwrap_or("-");
+
+        let (status, id, role, trading_pair, position, n_contracts) = match cfd {
+            ArchivedCfd::Closed(cfd) => (
+                "closed",
+                cfd.id,
+                cfd.role,
+                cfd.trading_pair,
+                cfd.position,
+                cfd.n_contracts,
+            ),
+            ArchivedCfd::Failed(cfd) => (
+                "failed",
+                cfd.id,
+                cfd.role,
+                cfd.trading_pair,
+                cfd.position,
+                cfd.n_contracts,
+            ),
+        };
+
+        println!(
+            "{id} {status} {role:?} {trading_pair} {position} {} events={} last={last}",
+            u64::from(n_contracts),
+            event_log.len(),
+        );
+    }
+
+    Ok(())
+}
+
+#[allow(clippy::print_stdout)]
+async fn events(db: &sqlite_db::Connection, id: OrderId) -> Result<()> {
+    let (row, events) = db
+        .load_cfd_audit(id)
+        .await
+        .with_context(|| format!("Failed to load CFD {id}"))?;
+
+    let mut cfd = model::Cfd::new(
+        row.id,
+        row.trading_pair,
+        row.position,
+        row.initial_price,
+        row.taker_leverage,
+        row.maker_leverage,
+        row.settlement_interval,
+        row.role,
+        row.quantity_usd,
+        row.counterparty_network_identity,
+        row.counterparty_peer_id,
+        row.opening_fee,
+        row.initial_funding_rate,
+        row.initial_tx_fee_rate,
+    );
+
+    let total = events.len();
+    let mut failed = 0;
+
+    for (i, event) in events.into_iter().enumerate() {
+        let AuditEvent {
+            name,
+            data,
+            timestamp,
+            event,
+        } = event;
+
+        println!("#{} {} {name}", i + 1, format_timestamp(timestamp)?);
+
+        match event {
+            Ok(event) => {
+                let (_, decoded) = event.event.to_json();
+                println!("  payload: {}", redacted_json(&decoded));
+
+                cfd = cfd.apply(event);
+                let snapshot = serde_json::to_string_pretty(&cfd.snapshot())?;
+                println!("  state: {}", snapshot.replace('\n', "\n  "));
+            }
+            Err(e) => {
+                failed += 1;
+                println!("  FAILED TO DECODE: {e:#}");
+                println!("  raw data: {}", redacted_json(&data));
+            }
+        }
+    }
+
+    if failed > 0 {
+        bail!("{failed} of {total} events of CFD {id} failed to decode");
+    }
+
+    Ok(())
+}
+
+fn format_timestamp(timestamp: Timestamp) -> Result<String> {
+    let datetime = OffsetDateTime::from_unix_timestamp(timestamp.seconds())?;
+
+    Ok(datetime.format(&Rfc3339)?)
+}
+
+/// Pretty print the JSON data of an event with all secret keys redacted
+///
+/// Data that is not valid JSON cannot be redacted reliably and is withheld.
+fn redacted_json(data: &str) -> String {
+    let mut value = match serde_json::from_str::<Value>(data) {
+        Ok(value) => value,
+        Err(e) => return format!("<{} bytes of invalid JSON withheld: {e}>", data.len()),
+    };
+    redact(&mut value);
+
+    serde_json::to_string_pretty(&value)
+        .map(|pretty| pretty.replace('\n', "\n  "))
+        .unwrap_or_else(|e| format!("<failed to print JSON: {e}>"))
+}
+
+fn redact(value: &mut Value) {
+    match value {
+        Value::Object(fields) => {
+            for (name, field) in fields.iter_mut() {
+                if SECRET_FIELDS.contains(&name.as_str()) {
+                    *field = Value::String("<redacted>".to_owned());
+                } else {
+                    redact(field);
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(redact),
+        Value::Null | Value::Bool(_) | Value::Number(_) | Value::String(_) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secret_keys_are_redacted_at_any_depth() {
+        let data = r#"{"dlc":{"identity":"aa","identity_counterparty":"bb","revoked_commit":[{"revocation_sk_theirs":"cc","txid":"dd"}]}}"#;
+
+        let redacted = redacted_json(data);
+
+        assert!(!redacted.contains("\"aa\""));
+        assert!(!redacted.contains("\"cc\""));
+        assert!(redacted.contains("\"bb\""));
+        assert!(redacted.contains("\"dd\""));
+    }
+}
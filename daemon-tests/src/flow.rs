@@ -3,27 +3,40 @@ use anyhow::Result;
 use daemon::projection::Cfd;
 use daemon::projection::CfdState;
 use daemon::projection::MakerOffers;
+use model::TradingPair;
 use std::time::Duration;
 use tokio::sync::watch;
 
 /// Waiting time for the time on the watch channel before returning error
 const NEXT_WAIT_TIME: Duration = Duration::from_secs(if cfg!(debug_assertions) { 90 } else { 30 });
 
+/// Returns the next BTC/USD offers of both channels.
 pub async fn next_maker_offers(
-    rx_a: &mut watch::Receiver<MakerOffers>,
-    rx_b: &mut watch::Receiver<MakerOffers>,
+    rx_a: &mut watch::Receiver<Vec<MakerOffers>>,
+    rx_b: &mut watch::Receiver<Vec<MakerOffers>>,
 ) -> Result<(MakerOffers, MakerOffers)> {
-    let wait_until_a = next(rx_a);
-    let wait_until_b = next(rx_b);
+    let wait_until_a = next_with(rx_a, offers_of(TradingPair::BtcUsd));
+    let wait_until_b = next_with(rx_b, offers_of(TradingPair::BtcUsd));
 
     let (a, b) = tokio::join!(wait_until_a, wait_until_b);
 
     Ok((a?, b?))
 }
 
-pub async fn is_next_offers_none(rx: &mut watch::Receiver<MakerOffers>) -> Result<bool> {
+pub async fn is_next_offers_none(rx: &mut watch::Receiver<Vec<MakerOffers>>) -> Result<bool> {
     let maker_offers = next(rx).await?;
-    Ok(maker_offers.long.is_none() && maker_offers.short.is_none())
+    Ok(maker_offers
+        .iter()
+        .all(|offers| offers.long.is_none() && offers.short.is_none()))
+}
+
+/// Drop-in filter-map function for [`next_with`] to pick the offers of a trading pair.
+pub fn offers_of(trading_pair: TradingPair) -> impl Fn(Vec<MakerOffers>) -> Option<MakerOffers> {
+    move |offers: Vec<MakerOffers>| {
+        offers
+            .into_iter()
+            .find(|offers| offers.trading_pair == trading_pair)
+    }
 }
 
 /// Returns watch channel value upon change
@@ -44,6 +44,7 @@ use model::OrderId;
 use model::Position;
 use model::Price;
 use model::Role;
+use model::TradingPair;
 use model::TxFeeRate;
 use model::Usd;
 use model::SETTLEMENT_INTERVAL;
@@ -64,6 +65,7 @@ use xtra::Actor;
 use xtra_bitmex_price_feed::Quote;
 use xtra_libp2p::libp2p::Multiaddr;
 use xtra_libp2p::multiaddress_ext::MultiaddrExt;
+use xtra_libp2p::ConnectionGating;
 
 pub mod flow;
 pub mod maia;
@@ -307,19 +309,21 @@ impl FeeStructure {
     ) -> Self {
         let initial_funding_fee = match maker_position {
             Position::Long => FundingFee::calculate(
+                offer_params.trading_pair,
                 offer_params.price_long.unwrap(),
                 quantity,
-                Leverage::ONE,
+                offer_params.leverage_maker,
                 taker_leverage,
                 offer_params.funding_rate_long,
                 SETTLEMENT_INTERVAL.whole_hours(),
             )
             .unwrap(),
             Position::Short => FundingFee::calculate(
+                offer_params.trading_pair,
                 offer_params.price_short.unwrap(),
                 quantity,
                 taker_leverage,
-                Leverage::ONE,
+                offer_params.leverage_maker,
                 offer_params.funding_rate_short,
                 SETTLEMENT_INTERVAL.whole_hours(),
             )
@@ -372,19 +376,21 @@ impl FeeStructure {
 
         let accumulated_hours_to_charge = match self.maker_position {
             Position::Long => FundingFee::calculate(
+                self.offer_params.trading_pair,
                 self.offer_params.price_long.unwrap(),
                 self.quantity,
-                Leverage::ONE,
+                self.offer_params.leverage_maker,
                 self.taker_leverage,
                 self.offer_params.funding_rate_long,
                 accumulated_rollover_hours_to_charge,
             )
             .unwrap(),
             Position::Short => FundingFee::calculate(
+                self.offer_params.trading_pair,
                 self.offer_params.price_short.unwrap(),
                 self.quantity,
                 self.taker_leverage,
-                Leverage::ONE,
+                self.offer_params.leverage_maker,
                 self.offer_params.funding_rate_short,
                 accumulated_rollover_hours_to_charge,
             )
@@ -426,7 +432,7 @@ pub async fn start_both() -> (Maker, Taker) {
     (maker, taker)
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 pub struct MakerConfig {
     oracle_pk: XOnlyPublicKey,
     seed: RandomSeed,
@@ -465,9 +471,8 @@ impl Default for MakerConfig {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 pub struct TakerConfig {
-    oracle_pk: XOnlyPublicKey,
     seed: RandomSeed,
     n_payouts: usize,
 }
@@ -475,7 +480,6 @@ pub struct TakerConfig {
 impl Default for TakerConfig {
     fn default() -> Self {
         Self {
-            oracle_pk: oracle_pk(),
             seed: RandomSeed::default(),
             n_payouts: N_PAYOUTS,
         }
@@ -521,7 +525,7 @@ impl Maker {
             .txid()
     }
 
-    pub fn offers_feed(&mut self) -> &mut watch::Receiver<MakerOffers> {
+    pub fn offers_feed(&mut self) -> &mut watch::Receiver<Vec<MakerOffers>> {
         &mut self.feeds.offers
     }
 
@@ -569,6 +573,10 @@ impl Maker {
             daemon::libp2p_utils::create_listen_tcp_multiaddr(&address.ip(), libp2p_port)
                 .expect("to parse properly");
 
+        let (proj_actor, feeds) =
+            projection::Actor::new(db.clone(), Network::Testnet, price_feed_addr.clone().into());
+        tasks.add(projection_context.run(proj_actor));
+
         let maker = maker::ActorSystem::new(
             db.clone(),
             wallet_addr,
@@ -588,10 +596,15 @@ impl Maker {
             settlement_interval,
             config.n_payouts,
             projection_actor,
+            feeds.cfds.clone(),
+            price_feed_addr.clone().into(),
             identities.clone(),
             config.heartbeat_interval,
             address,
-            endpoint_listen.clone(),
+            vec![endpoint_listen.clone()],
+            backup_path(),
+            None,
+            ConnectionGating::default(),
         )
         .unwrap();
 
@@ -602,10 +615,6 @@ impl Maker {
             oracle_mock.unwrap(),
         );
 
-        let (proj_actor, feeds) =
-            projection::Actor::new(db, Network::Testnet, price_feed_addr.into());
-        tasks.add(projection_context.run(proj_actor));
-
         Self {
             system: maker,
             feeds,
@@ -620,6 +629,7 @@ impl Maker {
 
     pub async fn set_offer_params(&mut self, offer_params: OfferParams) {
         let OfferParams {
+            trading_pair,
             price_long,
             price_short,
             min_quantity,
@@ -629,9 +639,11 @@ impl Maker {
             funding_rate_short,
             opening_fee,
             leverage_choices,
+            leverage_maker,
         } = offer_params;
         self.system
             .set_offer_params(
+                trading_pair,
                 price_long,
                 price_short,
                 min_quantity,
@@ -641,6 +653,7 @@ impl Maker {
                 funding_rate_short,
                 opening_fee,
                 leverage_choices,
+                leverage_maker,
             )
             .await
             .unwrap();
@@ -706,12 +719,12 @@ impl Taker {
         self.first_cfd().aggregated().latest_fees()
     }
 
-    pub fn offers_feed(&mut self) -> &mut watch::Receiver<MakerOffers> {
+    pub fn offers_feed(&mut self) -> &mut watch::Receiver<Vec<MakerOffers>> {
         &mut self.feeds.offers
     }
 
-    pub fn quote_feed(&mut self) -> &mut watch::Receiver<Option<projection::Quote>> {
-        &mut self.feeds.quote
+    pub fn quotes_feed(&mut self) -> &mut watch::Receiver<Vec<projection::Quote>> {
+        &mut self.feeds.quotes
     }
 
     pub fn maker_status_feed(&mut self) -> &mut watch::Receiver<ConnectionStatus> {
@@ -745,7 +758,6 @@ impl Taker {
         let taker = daemon::TakerActorSystem::new(
             db.clone(),
             wallet_addr,
-            config.oracle_pk,
             identities.clone(),
             |executor| {
                 let (oracle, mock) = OracleActor::new(executor);
@@ -766,6 +778,8 @@ impl Taker {
             maker_identity,
             maker_multiaddr.clone(),
             Environment::Test,
+            backup_path(),
+            None,
         )
         .unwrap();
 
@@ -879,6 +893,7 @@ pub fn dummy_offer_params(position_maker: Position) -> OfferParams {
     };
 
     OfferParams {
+        trading_pair: TradingPair::BtcUsd,
         price_long,
         price_short,
         min_quantity: Usd::new(dec!(100)),
@@ -889,11 +904,13 @@ pub fn dummy_offer_params(position_maker: Position) -> OfferParams {
         funding_rate_short: FundingRate::new(dec!(0.00024)).unwrap(),
         opening_fee: OpeningFee::new(Amount::from_sat(2)),
         leverage_choices: vec![Leverage::TWO],
+        leverage_maker: Leverage::ONE,
     }
 }
 
 fn dummy_funding_fee() -> FundingFee {
     FundingFee::calculate(
+        TradingPair::BtcUsd,
         Price::new(dec!(10000)).unwrap(),
         Usd::ZERO,
         Leverage::ONE,
@@ -922,3 +939,8 @@ pub async fn mock_oracle_announcements(
         .mock_oracle_announcement_with(announcement)
         .await;
 }
+
+/// A backup file that is unique to each daemon of a test.
+fn backup_path() -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("cfds-{}.backup", rand::random::<u64>()))
+}
@@ -46,6 +46,14 @@ impl MonitorActor {
     async fn handle(&mut self, _: monitor::MonitorCetFinality) -> Result<()> {
         Ok(())
     }
+
+    async fn handle(&mut self, _: monitor::MonitorPunishFinality) -> Result<()> {
+        Ok(())
+    }
+
+    async fn handle(&mut self, _: monitor::IsSpent) -> Result<bool> {
+        Ok(false)
+    }
 }
 
 pub struct MockMonitor {
@@ -43,7 +43,7 @@ impl OracleActor {
             .await
             .announcement
             .clone()
-            .ok_or(oracle::NoAnnouncement(msg.0))
+            .ok_or(oracle::NoAnnouncement(msg.event_id))
     }
 
     async fn handle(&mut self, _msg: oracle::MonitorAttestation) {}
@@ -67,7 +67,9 @@ impl MockOracle {
 
     pub async fn simulate_attestation(&mut self, id: OrderId, attestation: oracle::Attestation) {
         self.executor
-            .execute(id, |cfd| cfd.decrypt_cet(&attestation.into_inner()))
+            .execute(id, |cfd| {
+                cfd.decrypt_cet(&olivia::PUBLIC_KEY, &attestation.into_inner())
+            })
             .await
             .unwrap();
     }
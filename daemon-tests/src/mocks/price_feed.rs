@@ -30,9 +30,15 @@ impl xtra::Actor for PriceFeedActor {
 impl PriceFeedActor {
     async fn handle(
         &mut self,
-        _: xtra_bitmex_price_feed::LatestQuote,
-    ) -> Option<xtra_bitmex_price_feed::Quote> {
-        self.mock.lock().await.latest_quote()
+        _: xtra_bitmex_price_feed::LatestQuotes,
+    ) -> xtra_bitmex_price_feed::Quotes {
+        self.mock
+            .lock()
+            .await
+            .latest_quote()
+            .map(|quote| (xtra_bitmex_price_feed::XBTUSD.to_string(), quote))
+            .into_iter()
+            .collect()
     }
 }
 
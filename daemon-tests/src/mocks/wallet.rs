@@ -14,6 +14,7 @@ use daemon::maia_core::TxBuilderExt;
 use daemon::wallet;
 use mockall::*;
 use model::Timestamp;
+use model::TxFeeRate;
 use model::WalletInfo;
 use rand::thread_rng;
 use std::sync::Arc;
@@ -56,6 +57,18 @@ impl WalletActor {
     async fn handle(&mut self, msg: wallet::Sync) {
         self.mock.lock().await.sync(msg)
     }
+    async fn handle(&mut self, msg: wallet::ListUtxos) -> Result<Vec<wallet::Utxo>> {
+        self.mock.lock().await.list_utxos(msg)
+    }
+    async fn handle(&mut self, msg: wallet::EstimateFeeRate) -> Result<TxFeeRate> {
+        self.mock.lock().await.estimate_fee_rate(msg)
+    }
+    async fn handle(&mut self, msg: wallet::FreezeUtxo) -> Result<()> {
+        self.mock.lock().await.freeze_utxo(msg)
+    }
+    async fn handle(&mut self, msg: wallet::UnfreezeUtxo) -> Result<()> {
+        self.mock.lock().await.unfreeze_utxo(msg)
+    }
 }
 
 #[automock]
@@ -75,6 +88,22 @@ pub trait Wallet {
     fn sync(&mut self, _msg: wallet::Sync) {
         unreachable!("mockall will reimplement this method")
     }
+
+    fn list_utxos(&mut self, _msg: wallet::ListUtxos) -> Result<Vec<wallet::Utxo>> {
+        unreachable!("mockall will reimplement this method")
+    }
+
+    fn estimate_fee_rate(&mut self, _msg: wallet::EstimateFeeRate) -> Result<TxFeeRate> {
+        unreachable!("mockall will reimplement this method")
+    }
+
+    fn freeze_utxo(&mut self, _msg: wallet::FreezeUtxo) -> Result<()> {
+        unreachable!("mockall will reimplement this method")
+    }
+
+    fn unfreeze_utxo(&mut self, _msg: wallet::UnfreezeUtxo) -> Result<()> {
+        unreachable!("mockall will reimplement this method")
+    }
 }
 
 #[allow(dead_code)]
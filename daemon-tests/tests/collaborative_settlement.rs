@@ -27,7 +27,10 @@ async fn maker_rejects_collab_settlement_after_commit_finality() {
         start_from_open_cfd_state(OliviaData::example_0().announcement(), Position::Short).await;
     taker.mocks.mock_latest_quote(Some(dummy_quote())).await;
     maker.mocks.mock_latest_quote(Some(dummy_quote())).await;
-    next_with(taker.quote_feed(), |q| q).await.unwrap(); // if quote is available on feed, it propagated through the system
+    // if a quote is available on feed, it propagated through the system
+    next_with(taker.quotes_feed(), |quotes| quotes.first().copied())
+        .await
+        .unwrap();
 
     taker.system.propose_settlement(order_id).await.unwrap();
 
@@ -53,7 +56,10 @@ async fn maker_accepts_collab_settlement_after_commit_finality() {
         start_from_open_cfd_state(OliviaData::example_0().announcement(), Position::Short).await;
     taker.mocks.mock_latest_quote(Some(dummy_quote())).await;
     maker.mocks.mock_latest_quote(Some(dummy_quote())).await;
-    next_with(taker.quote_feed(), |q| q).await.unwrap(); // if quote is available on feed, it propagated through the system
+    // if a quote is available on feed, it propagated through the system
+    next_with(taker.quotes_feed(), |quotes| quotes.first().copied())
+        .await
+        .unwrap();
 
     taker.system.propose_settlement(order_id).await.unwrap();
 
@@ -78,7 +84,10 @@ async fn collaboratively_close_an_open_cfd(maker_position: Position) {
         start_from_open_cfd_state(OliviaData::example_0().announcement(), maker_position).await;
     taker.mocks.mock_latest_quote(Some(dummy_quote())).await;
     maker.mocks.mock_latest_quote(Some(dummy_quote())).await;
-    next_with(taker.quote_feed(), |q| q).await.unwrap(); // if quote is available on feed, it propagated through the system
+    // if a quote is available on feed, it propagated through the system
+    next_with(taker.quotes_feed(), |quotes| quotes.first().copied())
+        .await
+        .unwrap();
 
     taker.system.propose_settlement(order_id).await.unwrap();
 
@@ -10,8 +10,10 @@ use daemon_tests::flow::next_with;
 use daemon_tests::flow::one_cfd_with_state;
 use daemon_tests::start_both;
 use daemon_tests::wait_next_state;
+use maker::cfd::OfferParams;
 use model::Leverage;
 use model::Position;
+use model::TradingPair;
 use model::Usd;
 use otel_tests::otel_test;
 use rust_decimal_macros::dec;
@@ -35,6 +37,37 @@ async fn taker_receives_order_from_maker_on_publication() {
     assert_eq_offers(published, received);
 }
 
+#[otel_test]
+async fn taker_receives_offers_of_every_trading_pair() {
+    let (mut maker, mut taker) = start_both().await;
+
+    assert!(is_next_offers_none(taker.offers_feed()).await.unwrap());
+
+    maker
+        .set_offer_params(dummy_offer_params(Position::Short))
+        .await;
+    maker
+        .set_offer_params(OfferParams {
+            trading_pair: TradingPair::EthUsd,
+            ..dummy_offer_params(Position::Short)
+        })
+        .await;
+
+    let all_trading_pairs =
+        |offers: Vec<MakerOffers>| (offers.len() == TradingPair::ALL.len()).then(|| offers);
+    let published = next_with(maker.offers_feed(), all_trading_pairs)
+        .await
+        .unwrap();
+    let received = next_with(taker.offers_feed(), all_trading_pairs)
+        .await
+        .unwrap();
+
+    for (published, received) in published.into_iter().zip(received) {
+        assert_eq!(published.trading_pair, received.trading_pair);
+        assert_eq_offers(published, received);
+    }
+}
+
 #[otel_test]
 async fn taker_takes_order_and_maker_rejects() {
     let (mut maker, mut taker) = start_both().await;
@@ -123,7 +123,10 @@ async fn maker_rejects_rollover_after_commit_finality() {
 
     taker.mocks.mock_latest_quote(Some(dummy_quote())).await;
     maker.mocks.mock_latest_quote(Some(dummy_quote())).await;
-    next_with(taker.quote_feed(), |q| q).await.unwrap(); // if quote is available on feed, it propagated through the system
+    // if a quote is available on feed, it propagated through the system
+    next_with(taker.quotes_feed(), |quotes| quotes.first().copied())
+        .await
+        .unwrap();
 
     taker
         .trigger_rollover_with_latest_dlc_params(order_id)
@@ -155,7 +158,10 @@ async fn maker_accepts_rollover_after_commit_finality() {
 
     taker.mocks.mock_latest_quote(Some(dummy_quote())).await;
     maker.mocks.mock_latest_quote(Some(dummy_quote())).await;
-    next_with(taker.quote_feed(), |q| q).await.unwrap(); // if quote is available on feed, it propagated through the system
+    // if a quote is available on feed, it propagated through the system
+    next_with(taker.quotes_feed(), |quotes| quotes.first().copied())
+        .await
+        .unwrap();
 
     taker
         .trigger_rollover_with_latest_dlc_params(order_id)
@@ -0,0 +1,248 @@
+//! Encrypted backups of the DLCs of open CFDs.
+//!
+//! The backup file is rewritten whenever the DLC of a CFD changes and whenever a CFD closes or
+//! fails. Together with the seed it is enough to restore the open CFDs into a fresh database and
+//! enforce them.
+
+use anyhow::anyhow;
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use chacha20poly1305::aead::Aead;
+use chacha20poly1305::aead::NewAead;
+use chacha20poly1305::aead::Payload;
+use chacha20poly1305::Key;
+use chacha20poly1305::XChaCha20Poly1305;
+use chacha20poly1305::XNonce;
+use model::OrderId;
+use rand::Rng;
+use serde::Deserialize;
+use serde::Serialize;
+use sqlite_db::backup::CfdBackup;
+use std::fmt;
+use std::path::Path;
+use std::path::PathBuf;
+use tokio::io::AsyncWriteExt;
+use xtra_productivity::xtra_productivity;
+
+/// Prefix of backup files.
+const BACKUP_MAGIC: &[u8] = b"itchysats-cfd-backup";
+/// Version of the backup format, incremented on incompatible changes.
+const BACKUP_VERSION: u8 = 1;
+const NONCE_LEN: usize = 24;
+/// Length of the header of backup files: magic, version and nonce.
+const BACKUP_HEADER_LEN: usize = BACKUP_MAGIC.len() + 1 + NONCE_LEN;
+
+/// Key to encrypt backups with, derived from the seed.
+#[derive(Clone)]
+pub struct BackupKey([u8; 32]);
+
+impl From<[u8; 32]> for BackupKey {
+    fn from(key: [u8; 32]) -> Self {
+        Self(key)
+    }
+}
+
+impl fmt::Debug for BackupKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("BackupKey").field(&"...").finish()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Backup {
+    cfds: Vec<CfdBackup>,
+}
+
+/// Writes the backup file whenever the DLC of a CFD changes.
+pub struct Actor {
+    db: sqlite_db::Connection,
+    key: BackupKey,
+    path: PathBuf,
+}
+
+impl Actor {
+    pub fn new(db: sqlite_db::Connection, key: BackupKey, path: PathBuf) -> Self {
+        Self { db, key, path }
+    }
+
+    async fn write_backup(&self) -> Result<()> {
+        let mut cfds = Vec::new();
+        for id in self.db.load_open_cfd_ids().await? {
+            match self.db.load_cfd_backup(id).await {
+                Ok(Some(cfd)) => cfds.push(cfd),
+                Ok(None) => {}
+                Err(e) => tracing::warn!(order_id = %id, "Failed to back up CFD: {e:#}"),
+            }
+        }
+
+        let n_cfds = cfds.len();
+        let bytes = encrypt(&serde_json::to_vec(&Backup { cfds })?, &self.key)?;
+
+        // Write to a temporary file first to never leave a partially written backup behind
+        let tmp_path = self.path.with_extension("tmp");
+        let mut file = tokio::fs::File::create(&tmp_path).await?;
+        file.write_all(&bytes).await?;
+        // Make sure the content is on disk before the rename replaces the previous backup
+        file.sync_all().await?;
+        tokio::fs::rename(&tmp_path, &self.path).await?;
+
+        tracing::debug!(%n_cfds, path = %self.path.display(), "Wrote backup");
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn started(&mut self, _: &mut xtra::Context<Self>) {
+        if let Err(e) = self.write_backup().await {
+            tracing::error!("Failed to write backup: {e:#}");
+        }
+    }
+
+    async fn stopped(self) -> Self::Stop {}
+}
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, _: DlcChanged) {
+        if let Err(e) = self.write_backup().await {
+            tracing::error!("Failed to write backup: {e:#}");
+        }
+    }
+}
+
+/// The DLC of a CFD was set up or replaced, or the CFD closed or failed.
+pub struct DlcChanged;
+
+/// Restore the CFDs of the backup at `path` into the database.
+///
+/// CFDs which are already in the database are skipped. Returns the IDs of the restored CFDs.
+pub async fn import(
+    db: &sqlite_db::Connection,
+    path: &Path,
+    key: &BackupKey,
+) -> Result<Vec<OrderId>> {
+    let bytes = tokio::fs::read(path)
+        .await
+        .with_context(|| format!("Failed to read backup from {}", path.display()))?;
+    let backup = serde_json::from_slice::<Backup>(&decrypt(&bytes, key)?)?;
+
+    let mut restored = Vec::new();
+    for cfd in backup.cfds {
+        let id = cfd.id;
+
+        if db
+            .restore_cfd_backup(cfd)
+            .await
+            .with_context(|| format!("Failed to restore CFD {id}"))?
+        {
+            restored.push(id);
+        } else {
+            tracing::info!(order_id = %id, "CFD is already in the database");
+        }
+    }
+
+    Ok(restored)
+}
+
+/// Encrypt a backup with XChaCha20-Poly1305.
+///
+/// The encrypted backup is laid out as magic, version, nonce and ciphertext. The header in front
+/// of the ciphertext is authenticated as associated data.
+fn encrypt(backup: &[u8], key: &BackupKey) -> Result<Vec<u8>> {
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::thread_rng().fill(&mut nonce);
+
+    let header = [BACKUP_MAGIC, &[BACKUP_VERSION], &nonce].concat();
+
+    let cipher = XChaCha20Poly1305::new(Key::from_slice(&key.0));
+    let ciphertext = cipher
+        .encrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: backup,
+                aad: &header,
+            },
+        )
+        .map_err(|_| anyhow!("Failed to encrypt backup"))?;
+
+    Ok([header, ciphertext].concat())
+}
+
+fn decrypt(bytes: &[u8], key: &BackupKey) -> Result<Vec<u8>> {
+    if !bytes.starts_with(BACKUP_MAGIC) {
+        bail!("File is not a backup")
+    }
+    if bytes.len() < BACKUP_HEADER_LEN {
+        bail!("Backup is truncated")
+    }
+    let (header, ciphertext) = bytes.split_at(BACKUP_HEADER_LEN);
+
+    let (version, nonce) = header[BACKUP_MAGIC.len()..]
+        .split_first()
+        .expect("header to contain the version");
+    if *version != BACKUP_VERSION {
+        bail!("Unsupported backup version {version}, expected {BACKUP_VERSION}")
+    }
+
+    let cipher = XChaCha20Poly1305::new(Key::from_slice(&key.0));
+    let backup = cipher
+        .decrypt(
+            XNonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: header,
+            },
+        )
+        .map_err(|_| anyhow!("Failed to decrypt backup, wrong seed or corrupted backup"))?;
+
+    Ok(backup)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encrypted_backup_roundtrip() {
+        let key = BackupKey::from([42u8; 32]);
+
+        let encrypted = encrypt(b"backup", &key).unwrap();
+        let decrypted = decrypt(&encrypted, &key).unwrap();
+
+        assert_eq!(decrypted, b"backup");
+    }
+
+    #[test]
+    fn backup_cannot_be_decrypted_with_other_key() {
+        let encrypted = encrypt(b"backup", &BackupKey::from([42u8; 32])).unwrap();
+
+        decrypt(&encrypted, &BackupKey::from([7u8; 32])).expect_err("other key to be rejected");
+    }
+
+    #[test]
+    fn backup_of_unknown_version_is_rejected() {
+        let key = BackupKey::from([42u8; 32]);
+        let mut encrypted = encrypt(b"backup", &key).unwrap();
+        encrypted[BACKUP_MAGIC.len()] = BACKUP_VERSION + 1;
+
+        decrypt(&encrypted, &key).expect_err("unknown version to be rejected");
+    }
+
+    #[test]
+    fn backup_encrypted_without_header_is_rejected() {
+        let key = BackupKey::from([42u8; 32]);
+        let nonce = [1u8; NONCE_LEN];
+        let ciphertext = XChaCha20Poly1305::new(Key::from_slice(&key.0))
+            .encrypt(XNonce::from_slice(&nonce), b"backup".as_slice())
+            .unwrap();
+        let unbound = [BACKUP_MAGIC, &[BACKUP_VERSION], &nonce, &ciphertext].concat();
+
+        decrypt(&unbound, &key).expect_err("header to be authenticated");
+    }
+}
@@ -0,0 +1,584 @@
+use crate::bitcoin::consensus::encode::deserialize;
+use crate::bitcoin::consensus::encode::serialize_hex;
+use anyhow::anyhow;
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use bdk::bitcoin::Network;
+use bdk::bitcoin::OutPoint;
+use bdk::bitcoin::Script;
+use bdk::bitcoin::Transaction;
+use bdk::bitcoin::Txid;
+use bdk::blockchain::rpc::RpcBlockchain;
+use bdk::blockchain::rpc::RpcConfig;
+use bdk::blockchain::AnyBlockchain;
+use bdk::blockchain::ConfigurableBlockchain;
+use bdk::blockchain::ElectrumBlockchain;
+use bdk::blockchain::EsploraBlockchain;
+use bdk::electrum_client;
+use bdk::electrum_client::ElectrumApi;
+use bitcoincore_rpc::jsonrpc;
+use bitcoincore_rpc::RpcApi;
+use btsieve::TxStatus;
+use serde_json::json;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Number of consecutive unused addresses after which the wallet stops looking for more when
+/// syncing with Esplora.
+const ESPLORA_STOP_GAP: usize = 20;
+
+/// Bitcoin Core error code for a transaction that is neither in the mempool nor in the blockchain.
+const RPC_INVALID_ADDRESS_OR_KEY: i32 = -5;
+
+/// Bitcoin Core error code for loading a wallet that does not exist.
+const RPC_WALLET_NOT_FOUND: i32 = -18;
+
+/// Bitcoin Core error code for loading a wallet that is already loaded.
+const RPC_WALLET_ALREADY_LOADED: i32 = -35;
+
+/// Name of the watch-only wallet on Bitcoin Core into which the monitor imports the scripts of
+/// the transactions it follows.
+const MONITOR_WALLET_NAME: &str = "itchysats-monitor";
+
+/// Source of blockchain data for the wallet and the monitor.
+#[derive(Clone)]
+pub enum Backend {
+    /// An Electrum server, e.g. `ssl://blockstream.info:700`.
+    Electrum { url: String },
+    /// An Esplora HTTP API, e.g. `https://blockstream.info/api`.
+    Esplora { url: String },
+    /// The RPC interface of a Bitcoin Core node, e.g. `http://127.0.0.1:8332`.
+    ///
+    /// The monitor imports the scripts of the transactions of CFDs into a watch-only wallet on
+    /// the node, so the node does not need to run with `-txindex`. Scripts are imported without
+    /// rescanning the chain: transactions that confirmed before their script was imported, e.g.
+    /// of CFDs opened while using another backend, are only found after `rescanblockchain` on
+    /// the `itchysats-monitor` wallet.
+    BitcoinCore { url: String, auth: Auth },
+}
+
+/// How to authenticate with Bitcoin Core.
+#[derive(Clone)]
+pub enum Auth {
+    Cookie(PathBuf),
+    UserPass { username: String, password: String },
+}
+
+impl From<Auth> for bdk::blockchain::rpc::Auth {
+    fn from(auth: Auth) -> Self {
+        match auth {
+            Auth::Cookie(file) => bdk::blockchain::rpc::Auth::Cookie { file },
+            Auth::UserPass { username, password } => {
+                bdk::blockchain::rpc::Auth::UserPass { username, password }
+            }
+        }
+    }
+}
+
+impl From<Auth> for bitcoincore_rpc::Auth {
+    fn from(auth: Auth) -> Self {
+        match auth {
+            Auth::Cookie(file) => bitcoincore_rpc::Auth::CookieFile(file),
+            Auth::UserPass { username, password } => {
+                bitcoincore_rpc::Auth::UserPass(username, password)
+            }
+        }
+    }
+}
+
+impl Backend {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Backend::Electrum { .. } => "Electrum",
+            Backend::Esplora { .. } => "Esplora",
+            Backend::BitcoinCore { .. } => "Bitcoin Core",
+        }
+    }
+
+    /// Blockchain for the wallet to sync with and to broadcast its transactions.
+    ///
+    /// With Bitcoin Core the descriptors of the wallet are imported into a watch-only wallet
+    /// called `wallet_name` on the node.
+    pub fn wallet_blockchain(&self, network: Network, wallet_name: &str) -> Result<AnyBlockchain> {
+        let blockchain = match self {
+            Backend::Electrum { url } => {
+                let client = electrum_client::Client::new(url)
+                    .context("Failed to initialize Electrum RPC client")?;
+
+                AnyBlockchain::from(ElectrumBlockchain::from(client))
+            }
+            Backend::Esplora { url } => {
+                AnyBlockchain::from(EsploraBlockchain::new(url, ESPLORA_STOP_GAP))
+            }
+            Backend::BitcoinCore { url, auth } => {
+                let config = RpcConfig {
+                    url: url.clone(),
+                    auth: auth.clone().into(),
+                    network,
+                    wallet_name: wallet_name.to_string(),
+                    skip_blocks: None,
+                };
+                let blockchain = RpcBlockchain::from_config(&config)
+                    .context("Failed to initialize Bitcoin Core RPC client")?;
+
+                AnyBlockchain::from(blockchain)
+            }
+        };
+
+        Ok(blockchain)
+    }
+
+    /// Client for the monitor to follow the transactions of CFDs.
+    pub fn client(&self) -> Result<Client> {
+        let client = match self {
+            Backend::Electrum { url } => Client::Electrum(
+                electrum_client::Client::new(url)
+                    .context("Failed to initialize Electrum RPC client")?,
+            ),
+            Backend::Esplora { url } => Client::Esplora {
+                client: reqwest::Client::new(),
+                url: url.trim_end_matches('/').to_string(),
+            },
+            Backend::BitcoinCore { url, auth } => Client::BitcoinCore {
+                client: monitor_wallet(url, auth)?,
+                imported: Mutex::new(HashSet::new()),
+            },
+        };
+
+        Ok(client)
+    }
+}
+
+/// Load the watch-only wallet of the monitor on Bitcoin Core, creating it on first use.
+fn monitor_wallet(url: &str, auth: &Auth) -> Result<bitcoincore_rpc::Client> {
+    let node = bitcoincore_rpc::Client::new(url, auth.clone().into())
+        .context("Failed to initialize Bitcoin Core RPC client")?;
+
+    match node.load_wallet(MONITOR_WALLET_NAME) {
+        Ok(_) => {}
+        Err(bitcoincore_rpc::Error::JsonRpc(jsonrpc::error::Error::Rpc(e)))
+            if e.code == RPC_WALLET_ALREADY_LOADED => {}
+        Err(bitcoincore_rpc::Error::JsonRpc(jsonrpc::error::Error::Rpc(e)))
+            if e.code == RPC_WALLET_NOT_FOUND =>
+        {
+            // A legacy wallet without private keys, as only these support `importaddress`
+            node.call::<Value>(
+                "createwallet",
+                &[
+                    json!(MONITOR_WALLET_NAME),
+                    json!(true),
+                    json!(true),
+                    json!(""),
+                    json!(false),
+                    json!(false),
+                ],
+            )
+            .context("Failed to create monitor wallet")?;
+        }
+        Err(e) => return Err(e).context("Failed to load monitor wallet"),
+    }
+
+    let url = format!("{}/wallet/{MONITOR_WALLET_NAME}", url.trim_end_matches('/'));
+
+    bitcoincore_rpc::Client::new(&url, auth.clone().into())
+        .context("Failed to initialize Bitcoin Core wallet RPC client")
+}
+
+/// Blockchain client used by the monitor.
+///
+/// Electrum is queried by script, Esplora and Bitcoin Core by transaction id; either is enough
+/// for [`btsieve::State`] which only looks at the transactions it was asked to monitor.
+pub enum Client {
+    Electrum(electrum_client::Client),
+    Esplora {
+        client: reqwest::Client,
+        url: String,
+    },
+    BitcoinCore {
+        /// Client of the watch-only wallet of the monitor.
+        client: bitcoincore_rpc::Client,
+        /// Scripts already imported into the wallet since startup.
+        imported: Mutex<HashSet<Script>>,
+    },
+}
+
+/// Returned when asking a backend for a transaction by id which only supports queries by
+/// script.
+#[derive(thiserror::Error, Debug, Clone, Copy)]
+#[error("{0} does not support looking up transactions by id")]
+pub struct TxidLookupUnsupported(pub &'static str);
+
+/// An error returned by a Bitcoin node when rejecting a transaction.
+#[derive(thiserror::Error, serde::Deserialize, Debug, Clone)]
+#[error("RPC error {code}: {message}")]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+#[derive(serde::Deserialize)]
+struct EsploraTxStatus {
+    confirmed: bool,
+    block_height: Option<u32>,
+}
+
+#[derive(serde::Deserialize)]
+struct EsploraOutspend {
+    spent: bool,
+}
+
+impl Client {
+    pub async fn block_height(&self) -> Result<u32> {
+        let height = match self {
+            Client::Electrum(client) => {
+                // We do not act on this subscription, as we cannot rely on subscription push
+                // notifications because eventually the Electrum server will close the connection
+                // and subscriptions are not automatically renewed upon renewing the connection.
+                client
+                    .block_headers_subscribe()
+                    .context("Failed to subscribe to header notifications")?
+                    .height as u32
+            }
+            Client::Esplora { client, url } => client
+                .get(format!("{url}/blocks/tip/height"))
+                .send()
+                .await?
+                .error_for_status()?
+                .text()
+                .await?
+                .parse()
+                .context("Failed to parse block height")?,
+            Client::BitcoinCore { client, .. } => client
+                .get_block_count()
+                .context("Failed to get block count")?
+                as u32,
+        };
+
+        Ok(height)
+    }
+
+    /// The status of every transaction in `monitored` that is known to the backend.
+    ///
+    /// The result is in the shape expected by [`btsieve::State::update`].
+    pub async fn histories<'a>(
+        &self,
+        monitored: impl Iterator<Item = &'a (Txid, Script)> + Clone,
+    ) -> Result<Vec<Vec<TxStatus>>> {
+        if let Client::Electrum(client) = self {
+            let histories = client
+                .batch_script_get_history(monitored.map(|(_, script)| script))
+                .context("Failed to get script histories")?
+                .into_iter()
+                .map(|list| {
+                    list.into_iter()
+                        .map(|response| TxStatus {
+                            height: response.height,
+                            tx_hash: response.tx_hash,
+                        })
+                        .collect()
+                })
+                .collect();
+
+            return Ok(histories);
+        }
+
+        self.import_scripts(monitored.clone().map(|(_, script)| script))?;
+
+        let block_height = self.block_height().await?;
+
+        let mut histories = Vec::new();
+        for (txid, _) in monitored {
+            let history = self
+                .inclusion_height(*txid, block_height)
+                .await?
+                .map(|height| TxStatus {
+                    height,
+                    tx_hash: *txid,
+                })
+                .into_iter()
+                .collect();
+
+            histories.push(history);
+        }
+
+        Ok(histories)
+    }
+
+    /// The height of the block that includes `txid`, `0` or less if it is in the mempool and
+    /// `None` if it is unknown.
+    pub async fn transaction_height(&self, txid: Txid, script: &Script) -> Result<Option<i32>> {
+        if let Client::Electrum(client) = self {
+            let height = client
+                .script_get_history(script)
+                .context("Failed to get script history")?
+                .into_iter()
+                .find(|response| response.tx_hash == txid)
+                .map(|response| response.height);
+
+            return Ok(height);
+        }
+
+        self.import_scripts(std::iter::once(script))?;
+
+        let block_height = self.block_height().await?;
+
+        self.inclusion_height(txid, block_height).await
+    }
+
+    /// Whether `outpoint`, paying to `script`, was spent by a transaction in the blockchain or the
+    /// mempool.
+    ///
+    /// Returns `false` if the transaction of `outpoint` is unknown.
+    pub async fn is_spent(&self, outpoint: OutPoint, script: &Script) -> Result<bool> {
+        let spent = match self {
+            Client::Electrum(client) => client
+                .script_get_history(script)
+                .context("Failed to get script history")?
+                .iter()
+                .any(|response| response.tx_hash != outpoint.txid),
+            Client::Esplora { client, url } => {
+                client
+                    .get(format!(
+                        "{url}/tx/{}/outspend/{}",
+                        outpoint.txid, outpoint.vout
+                    ))
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json::<EsploraOutspend>()
+                    .await?
+                    .spent
+            }
+            Client::BitcoinCore { client, .. } => {
+                self.import_scripts(std::iter::once(script))?;
+
+                let block_height = self.block_height().await?;
+                if self
+                    .inclusion_height(outpoint.txid, block_height)
+                    .await?
+                    .is_none()
+                {
+                    return Ok(false);
+                }
+
+                client
+                    .get_tx_out(&outpoint.txid, outpoint.vout, Some(true))
+                    .context("Failed to get transaction output")?
+                    .is_none()
+            }
+        };
+
+        Ok(spent)
+    }
+
+    /// Import `scripts` into the watch-only wallet on Bitcoin Core so that it keeps track of the
+    /// transactions paying to and spending from them.
+    ///
+    /// Does nothing for the other backends.
+    fn import_scripts<'a>(&self, scripts: impl Iterator<Item = &'a Script>) -> Result<()> {
+        let (client, imported) = match self {
+            Client::BitcoinCore { client, imported } => (client, imported),
+            Client::Electrum(_) | Client::Esplora { .. } => return Ok(()),
+        };
+
+        let mut imported = imported.lock().expect("mutex not to be poisoned");
+        for script in scripts {
+            if imported.contains(script) {
+                continue;
+            }
+
+            client
+                .import_address_script(script, None, Some(false), None)
+                .with_context(|| {
+                    format!("Failed to import script {script:x} into monitor wallet")
+                })?;
+            imported.insert(script.clone());
+        }
+
+        Ok(())
+    }
+
+    async fn inclusion_height(&self, txid: Txid, block_height: u32) -> Result<Option<i32>> {
+        let height = match self {
+            Client::Electrum(_) => return Err(TxidLookupUnsupported("Electrum").into()),
+            Client::Esplora { client, url } => {
+                let response = client.get(format!("{url}/tx/{txid}/status")).send().await?;
+
+                if response.status() == reqwest::StatusCode::NOT_FOUND {
+                    return Ok(None);
+                }
+
+                let status = response
+                    .error_for_status()?
+                    .json::<EsploraTxStatus>()
+                    .await?;
+
+                match status {
+                    EsploraTxStatus {
+                        confirmed: true,
+                        block_height: Some(height),
+                    } => Some(height as i32),
+                    _ => Some(0),
+                }
+            }
+            Client::BitcoinCore { client, .. } => match wallet_transaction(client, txid)? {
+                Some(tx) if tx.info.confirmations > 0 => Some(tx.info.blockheight.map_or_else(
+                    || (block_height + 1).saturating_sub(tx.info.confirmations as u32) as i32,
+                    |height| height as i32,
+                )),
+                Some(tx) if tx.info.confirmations == 0 => Some(0),
+                // Conflicting with a confirmed transaction
+                Some(_) => None,
+                // Without `-txindex` only the mempool is known besides the wallet
+                None => match client.get_raw_transaction_info(&txid, None) {
+                    Ok(_) => Some(0),
+                    Err(bitcoincore_rpc::Error::JsonRpc(jsonrpc::error::Error::Rpc(e)))
+                        if e.code == RPC_INVALID_ADDRESS_OR_KEY =>
+                    {
+                        None
+                    }
+                    Err(e) => return Err(e).context("Failed to get mempool transaction"),
+                },
+            },
+        };
+
+        Ok(height)
+    }
+
+    pub async fn transaction(&self, txid: Txid) -> Result<Transaction> {
+        let tx = match self {
+            Client::Electrum(client) => client.transaction_get(&txid)?,
+            Client::Esplora { client, url } => {
+                let bytes = client
+                    .get(format!("{url}/tx/{txid}/raw"))
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .bytes()
+                    .await?;
+
+                deserialize(&bytes).context("Failed to deserialize transaction")?
+            }
+            Client::BitcoinCore { client, .. } => match wallet_transaction(client, txid)? {
+                Some(tx) => tx
+                    .transaction()
+                    .context("Failed to deserialize transaction")?,
+                None => client.get_raw_transaction(&txid, None)?,
+            },
+        };
+
+        Ok(tx)
+    }
+
+    pub async fn transactions(&self, txids: &[Txid]) -> Result<Vec<Transaction>> {
+        if let Client::Electrum(client) = self {
+            return Ok(client.batch_transaction_get(txids)?);
+        }
+
+        let mut txs = Vec::with_capacity(txids.len());
+        for txid in txids {
+            txs.push(self.transaction(*txid).await?);
+        }
+
+        Ok(txs)
+    }
+
+    /// Broadcast `tx`, returning the error of the node if it rejected the transaction.
+    pub async fn broadcast(&self, tx: &Transaction) -> Result<Result<(), RpcError>> {
+        match self {
+            Client::Electrum(client) => match client.transaction_broadcast(tx) {
+                Ok(_) => Ok(Ok(())),
+                Err(electrum_client::Error::Protocol(value)) => {
+                    let rpc_error = parse_rpc_protocol_error(&value).with_context(|| {
+                        format!("Failed to parse electrum error response '{value:?}'")
+                    })?;
+
+                    Ok(Err(rpc_error))
+                }
+                Err(e) => Err(e.into()),
+            },
+            Client::Esplora { client, url } => {
+                let response = client
+                    .post(format!("{url}/tx"))
+                    .body(serialize_hex(tx))
+                    .send()
+                    .await?;
+
+                if response.status().is_success() {
+                    return Ok(Ok(()));
+                }
+
+                let status = response.status();
+                let body = response.text().await?;
+
+                match parse_rpc_error(&body) {
+                    Ok(rpc_error) => Ok(Err(rpc_error)),
+                    Err(_) => bail!("Esplora responded with {status}: {body}"),
+                }
+            }
+            Client::BitcoinCore { client, .. } => match client.send_raw_transaction(tx) {
+                Ok(_) => Ok(Ok(())),
+                Err(bitcoincore_rpc::Error::JsonRpc(jsonrpc::error::Error::Rpc(e))) => {
+                    Ok(Err(RpcError {
+                        code: i64::from(e.code),
+                        message: e.message,
+                    }))
+                }
+                Err(e) => Err(anyhow!(e)),
+            },
+        }
+    }
+}
+
+/// The transaction `txid` as known to the watch-only wallet on Bitcoin Core, `None` if it does
+/// not touch any of the imported scripts.
+fn wallet_transaction(
+    client: &bitcoincore_rpc::Client,
+    txid: Txid,
+) -> Result<Option<bitcoincore_rpc::json::GetTransactionResult>> {
+    match client.get_transaction(&txid, Some(true)) {
+        Ok(tx) => Ok(Some(tx)),
+        Err(bitcoincore_rpc::Error::JsonRpc(jsonrpc::error::Error::Rpc(e)))
+            if e.code == RPC_INVALID_ADDRESS_OR_KEY =>
+        {
+            Ok(None)
+        }
+        Err(e) => Err(e).context("Failed to get wallet transaction"),
+    }
+}
+
+fn parse_rpc_protocol_error(error_value: &Value) -> Result<RpcError> {
+    parse_rpc_error(error_value.as_str().context("Not a string")?)
+}
+
+/// Parse an error of the form `sendrawtransaction RPC error: {"code":-27,"message":"..."}` as
+/// relayed by Electrum and Esplora servers.
+fn parse_rpc_error(error: &str) -> Result<RpcError> {
+    let json = error
+        .split_terminator("RPC error: ")
+        .nth(1)
+        .context("Unknown error code format")?;
+
+    let error = serde_json::from_str::<RpcError>(json).context("Error has unexpected format")?;
+
+    Ok(error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_rpc_error_relayed_by_esplora() {
+        let error = parse_rpc_error(
+            r#"sendrawtransaction RPC error: {"code":-27,"message":"Transaction already in block chain"}"#,
+        )
+        .unwrap();
+
+        assert_eq!(error.code, -27);
+        assert_eq!(error.message, "Transaction already in block chain");
+    }
+}
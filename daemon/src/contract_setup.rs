@@ -0,0 +1,3 @@
+pub mod protocol;
+
+pub const PROTOCOL: &str = "/itchysats/contract-setup/1.0.0";
@@ -0,0 +1,344 @@
+use crate::wire::SetupMsg;
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use asynchronous_codec::Framed;
+use asynchronous_codec::JsonCodec;
+use asynchronous_codec::JsonCodecError;
+use futures::future;
+use futures::Sink;
+use futures::SinkExt;
+use futures::Stream;
+use futures::StreamExt;
+use model::libp2p::PeerId;
+use model::Identity;
+use model::Leverage;
+use model::OrderId;
+use model::Usd;
+use rand::thread_rng;
+use rand::Rng;
+use serde::Deserialize;
+use serde::Serialize;
+use sha2::Digest;
+use sha2::Sha256;
+use std::time::Duration;
+use tokio_extras::FutureExt;
+use xtra_libp2p::Substream;
+
+/// How long the listener waits for the take order request after the substream was opened
+const TAKE_ORDER_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long either side waits for the other during the identity challenge
+const IDENTITY_CHALLENGE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Domain separator of the identity proof
+const IDENTITY_PROOF_TAG: &[u8] = b"itchysats/contract-setup/identity-proof";
+
+pub(crate) type DialerFramed = Framed<Substream, JsonCodec<DialerMessage, ListenerMessage>>;
+type ListenerFramed = Framed<Substream, JsonCodec<ListenerMessage, DialerMessage>>;
+
+#[derive(Serialize, Deserialize)]
+pub(crate) enum DialerMessage {
+    TakeOrder(TakeOrder),
+    IdentityProof([u8; 32]),
+    SetupMsg(Box<SetupMsg>),
+}
+
+impl DialerMessage {
+    fn into_take_order(self) -> Result<TakeOrder> {
+        match self {
+            DialerMessage::TakeOrder(take_order) => Ok(take_order),
+            DialerMessage::IdentityProof(_) => bail!("Expected TakeOrder but got IdentityProof"),
+            DialerMessage::SetupMsg(_) => bail!("Expected TakeOrder but got SetupMsg"),
+        }
+    }
+
+    fn into_identity_proof(self) -> Result<[u8; 32]> {
+        match self {
+            DialerMessage::IdentityProof(proof) => Ok(proof),
+            DialerMessage::TakeOrder(_) => bail!("Expected IdentityProof but got TakeOrder"),
+            DialerMessage::SetupMsg(_) => bail!("Expected IdentityProof but got SetupMsg"),
+        }
+    }
+
+    fn into_setup_msg(self) -> Result<SetupMsg> {
+        match self {
+            DialerMessage::SetupMsg(setup_msg) => Ok(*setup_msg),
+            DialerMessage::TakeOrder(_) => bail!("Expected SetupMsg but got TakeOrder"),
+            DialerMessage::IdentityProof(_) => bail!("Expected SetupMsg but got IdentityProof"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) enum ListenerMessage {
+    /// Ephemeral public key the taker has to prove its identity against
+    IdentityChallenge([u8; 32]),
+    Decision(Decision),
+    SetupMsg(Box<SetupMsg>),
+}
+
+impl ListenerMessage {
+    fn into_identity_challenge(self) -> Result<x25519_dalek::PublicKey> {
+        match self {
+            ListenerMessage::IdentityChallenge(challenge) => {
+                Ok(x25519_dalek::PublicKey::from(challenge))
+            }
+            ListenerMessage::Decision(_) => bail!("Expected IdentityChallenge but got Decision"),
+            ListenerMessage::SetupMsg(_) => bail!("Expected IdentityChallenge but got SetupMsg"),
+        }
+    }
+
+    pub fn into_decision(self) -> Result<Decision> {
+        match self {
+            ListenerMessage::Decision(decision) => Ok(decision),
+            ListenerMessage::IdentityChallenge(_) => {
+                bail!("Expected Decision but got IdentityChallenge")
+            }
+            ListenerMessage::SetupMsg(_) => bail!("Expected Decision but got SetupMsg"),
+        }
+    }
+
+    fn into_setup_msg(self) -> Result<SetupMsg> {
+        match self {
+            ListenerMessage::SetupMsg(setup_msg) => Ok(*setup_msg),
+            ListenerMessage::IdentityChallenge(_) => {
+                bail!("Expected SetupMsg but got IdentityChallenge")
+            }
+            ListenerMessage::Decision(_) => bail!("Expected SetupMsg but got Decision"),
+        }
+    }
+}
+
+/// Request of the taker to take an order of the maker
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct TakeOrder {
+    pub order_id: OrderId,
+    pub quantity: Usd,
+    pub leverage: Leverage,
+    /// The legacy network identity of the taker
+    ///
+    /// The maker still keys some of its bookkeeping by this identity, so the taker has to prove
+    /// that it owns the identity before the maker decides on the request, see
+    /// [`Listener::authenticate_taker`].
+    pub taker_id: Identity,
+}
+
+/// Whether the maker goes ahead with contract setup for a [`TakeOrder`] request
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Decision {
+    Accept,
+    Reject,
+    /// The order is not (or no longer) offered by the maker
+    InvalidOrderId,
+}
+
+/// The listening end of a `/itchysats/contract-setup/1.0.0` substream
+pub struct Listener {
+    framed: ListenerFramed,
+}
+
+impl Listener {
+    /// Wait for the taker to send its [`TakeOrder`] request on a new substream
+    pub async fn receive_take_order(stream: Substream) -> Result<(TakeOrder, Self)> {
+        let mut framed = Framed::new(stream, JsonCodec::<ListenerMessage, DialerMessage>::new());
+
+        let take_order = framed
+            .next()
+            .timeout(TAKE_ORDER_TIMEOUT, || {
+                tracing::debug_span!("receive take order")
+            })
+            .await
+            .with_context(|| {
+                format!(
+                    "Taker did not send take order request within {} seconds",
+                    TAKE_ORDER_TIMEOUT.as_secs()
+                )
+            })?
+            .context("End of stream while receiving take order request")?
+            .context("Failed to decode take order request")?
+            .into_take_order()?;
+
+        Ok((take_order, Self { framed }))
+    }
+
+    /// Make the taker prove that it owns the identity of its [`TakeOrder`] request
+    ///
+    /// The taker has to answer a challenge with an ephemeral key using the secret key of its
+    /// identity. The proof is bound to the peer ID of the substream, so it cannot be relayed to
+    /// the maker by another peer.
+    pub async fn authenticate_taker(
+        &mut self,
+        taker_id: Identity,
+        taker_peer_id: PeerId,
+    ) -> Result<()> {
+        let ephemeral_sk = x25519_dalek::StaticSecret::from(thread_rng().gen::<[u8; 32]>());
+        let challenge = x25519_dalek::PublicKey::from(&ephemeral_sk);
+
+        self.framed
+            .send(ListenerMessage::IdentityChallenge(challenge.to_bytes()))
+            .await
+            .context("Failed to send identity challenge to taker")?;
+
+        let proof = self
+            .framed
+            .next()
+            .timeout(IDENTITY_CHALLENGE_TIMEOUT, || {
+                tracing::debug_span!("receive identity proof")
+            })
+            .await
+            .with_context(|| {
+                format!(
+                    "Taker did not prove its identity within {} seconds",
+                    IDENTITY_CHALLENGE_TIMEOUT.as_secs()
+                )
+            })?
+            .context("End of stream while receiving identity proof")?
+            .context("Failed to decode identity proof")?
+            .into_identity_proof()?;
+
+        let shared_secret = ephemeral_sk.diffie_hellman(&taker_id.pk());
+        if proof != identity_proof(shared_secret.as_bytes(), taker_peer_id) {
+            bail!("Peer {taker_peer_id} failed to prove that it owns identity {taker_id}");
+        }
+
+        Ok(())
+    }
+
+    pub async fn send_decision(&mut self, decision: Decision) -> Result<()> {
+        self.framed
+            .send(ListenerMessage::Decision(decision))
+            .await
+            .context("Failed to send decision to taker")?;
+
+        Ok(())
+    }
+
+    /// Split the substream into the channels the contract setup protocol runs on
+    pub fn into_setup_channels(
+        self,
+    ) -> (
+        impl Sink<SetupMsg, Error = anyhow::Error> + Unpin,
+        impl Stream<Item = SetupMsg> + Unpin,
+    ) {
+        let (sink, stream) = self.framed.split();
+
+        let sink = sink
+            .sink_map_err(anyhow::Error::from)
+            .with(|msg| future::ok::<_, anyhow::Error>(ListenerMessage::SetupMsg(Box::new(msg))));
+        let stream = stream
+            .filter_map(|msg| future::ready(into_setup_msg(msg, DialerMessage::into_setup_msg)));
+
+        (sink, stream)
+    }
+}
+
+/// Answer the identity challenge of the maker, see [`Listener::authenticate_taker`]
+pub(crate) async fn prove_identity(
+    framed: &mut DialerFramed,
+    identity_sk: &x25519_dalek::StaticSecret,
+    peer_id: PeerId,
+) -> Result<()> {
+    let challenge = framed
+        .next()
+        .timeout(IDENTITY_CHALLENGE_TIMEOUT, || {
+            tracing::debug_span!("receive identity challenge")
+        })
+        .await
+        .with_context(|| {
+            format!(
+                "Maker did not send identity challenge within {} seconds",
+                IDENTITY_CHALLENGE_TIMEOUT.as_secs()
+            )
+        })?
+        .context("End of stream while receiving identity challenge")?
+        .context("Failed to decode identity challenge")?
+        .into_identity_challenge()?;
+
+    let shared_secret = identity_sk.diffie_hellman(&challenge);
+
+    framed
+        .send(DialerMessage::IdentityProof(identity_proof(
+            shared_secret.as_bytes(),
+            peer_id,
+        )))
+        .await
+        .context("Failed to send identity proof")?;
+
+    Ok(())
+}
+
+fn identity_proof(shared_secret: &[u8; 32], peer_id: PeerId) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(IDENTITY_PROOF_TAG);
+    hasher.update(shared_secret);
+    hasher.update(peer_id.inner().to_bytes());
+
+    hasher.finalize().into()
+}
+
+/// Split the dialing end of a substream into the channels the contract setup protocol runs on
+pub(crate) fn dialer_setup_channels(
+    framed: DialerFramed,
+) -> (
+    impl Sink<SetupMsg, Error = anyhow::Error> + Unpin,
+    impl Stream<Item = SetupMsg> + Unpin,
+) {
+    let (sink, stream) = framed.split();
+
+    let sink = sink
+        .sink_map_err(anyhow::Error::from)
+        .with(|msg| future::ok::<_, anyhow::Error>(DialerMessage::SetupMsg(Box::new(msg))));
+    let stream = stream
+        .filter_map(|msg| future::ready(into_setup_msg(msg, ListenerMessage::into_setup_msg)));
+
+    (sink, stream)
+}
+
+/// Unwrap a received contract setup message, discarding anything else
+///
+/// The contract setup protocol times out if an expected message is discarded.
+fn into_setup_msg<M>(
+    msg: Result<M, JsonCodecError>,
+    into_setup_msg: fn(M) -> Result<SetupMsg>,
+) -> Option<SetupMsg> {
+    match msg.map_err(anyhow::Error::from).and_then(into_setup_msg) {
+        Ok(setup_msg) => Some(setup_msg),
+        Err(e) => {
+            tracing::warn!("Discarding contract setup message: {e:#}");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PEER_ID: &str = "12D3KooWEsK2X8Tp24XtyWh7DM65VfwXtNH2cmfs2JsWmkmwKbV1";
+    const OTHER_PEER_ID: &str = "12D3KooWPMbq9YvqWBNqJmUdSxoLaoyEAYcjUxH2tKDgwzA3SApM";
+
+    #[test]
+    fn identity_proof_is_bound_to_identity_and_peer_id() {
+        let taker_sk = x25519_dalek::StaticSecret::from([1u8; 32]);
+        let ephemeral_sk = x25519_dalek::StaticSecret::from([2u8; 32]);
+        let other_sk = x25519_dalek::StaticSecret::from([3u8; 32]);
+        let challenge = x25519_dalek::PublicKey::from(&ephemeral_sk);
+        let peer_id = PEER_ID.parse().unwrap();
+
+        let proof = identity_proof(taker_sk.diffie_hellman(&challenge).as_bytes(), peer_id);
+
+        let taker_pk = x25519_dalek::PublicKey::from(&taker_sk);
+        let expected = identity_proof(ephemeral_sk.diffie_hellman(&taker_pk).as_bytes(), peer_id);
+        let other_identity =
+            identity_proof(other_sk.diffie_hellman(&challenge).as_bytes(), peer_id);
+        let other_peer = identity_proof(
+            taker_sk.diffie_hellman(&challenge).as_bytes(),
+            OTHER_PEER_ID.parse().unwrap(),
+        );
+
+        assert_eq!(proof, expected);
+        assert_ne!(other_identity, expected);
+        assert_ne!(other_peer, expected);
+    }
+}
@@ -0,0 +1,123 @@
+use model::TxFeeRate;
+use std::num::NonZeroU32;
+
+/// Decides when and how far to bump the fee of a transaction that does not confirm.
+///
+/// A transaction is bumped once it has been unconfirmed for `patience` blocks, and again after
+/// every further `patience` blocks, each time increasing the fee rate by `increase_percent`.
+/// Transactions that have to confirm before a timelock expires are bumped more often the closer
+/// the deadline is, and at the maximum fee rate once it is `urgency` blocks away.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Policy {
+    pub patience: u32,
+    pub increase_percent: u32,
+    pub max_fee_rate: TxFeeRate,
+    pub urgency: u32,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self {
+            patience: 6,
+            increase_percent: 50,
+            max_fee_rate: TxFeeRate::from_sat_per_vbyte(250),
+            urgency: 6,
+        }
+    }
+}
+
+impl Policy {
+    /// The fee rate to bump a transaction to, if it should be bumped at all.
+    ///
+    /// `fee_rate` is the current fee rate of the transaction including all its children,
+    /// `unconfirmed_for` the number of blocks since it was published or last bumped and
+    /// `blocks_to_deadline` the number of blocks until it has to be confirmed, if any.
+    pub fn next_fee_rate(
+        &self,
+        fee_rate: TxFeeRate,
+        unconfirmed_for: u32,
+        blocks_to_deadline: Option<u32>,
+    ) -> Option<TxFeeRate> {
+        let current = fee_rate.to_u32();
+        let max = self.max_fee_rate.to_u32();
+
+        if current >= max {
+            return None;
+        }
+
+        let target = match blocks_to_deadline {
+            Some(remaining) if remaining <= self.urgency => max,
+            remaining => {
+                // Wait less the closer the deadline, so that several bumps fit before it
+                let patience = remaining.map_or(self.patience, |remaining| {
+                    ((remaining - self.urgency) / 4).clamp(1, self.patience)
+                });
+
+                if unconfirmed_for < patience {
+                    return None;
+                }
+
+                let increase = (current * self.increase_percent + 99) / 100;
+
+                (current + increase).min(max)
+            }
+        };
+
+        NonZeroU32::new(target).map(TxFeeRate::new)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_no_deadline_then_bump_after_patience_by_increase() {
+        let policy = Policy::default();
+
+        assert_eq!(
+            policy.next_fee_rate(TxFeeRate::from_sat_per_vbyte(10), 5, None),
+            None
+        );
+        assert_eq!(
+            policy.next_fee_rate(TxFeeRate::from_sat_per_vbyte(10), 6, None),
+            Some(TxFeeRate::from_sat_per_vbyte(15))
+        );
+        assert_eq!(
+            policy.next_fee_rate(TxFeeRate::from_sat_per_vbyte(1), 6, None),
+            Some(TxFeeRate::from_sat_per_vbyte(2))
+        );
+    }
+
+    #[test]
+    fn given_deadline_near_then_bump_sooner_and_up_to_max() {
+        let policy = Policy::default();
+
+        assert_eq!(
+            policy.next_fee_rate(TxFeeRate::from_sat_per_vbyte(10), 1, Some(100)),
+            None
+        );
+        assert_eq!(
+            policy.next_fee_rate(TxFeeRate::from_sat_per_vbyte(10), 2, Some(14)),
+            Some(TxFeeRate::from_sat_per_vbyte(15))
+        );
+        assert_eq!(
+            policy.next_fee_rate(TxFeeRate::from_sat_per_vbyte(10), 0, Some(6)),
+            Some(policy.max_fee_rate)
+        );
+    }
+
+    #[test]
+    fn given_max_fee_rate_then_no_bump() {
+        let policy = Policy::default();
+
+        assert_eq!(
+            policy.next_fee_rate(TxFeeRate::from_sat_per_vbyte(240), 6, None),
+            Some(TxFeeRate::from_sat_per_vbyte(250))
+        );
+        assert_eq!(
+            policy.next_fee_rate(TxFeeRate::from_sat_per_vbyte(250), 100, Some(0)),
+            None
+        );
+    }
+}
@@ -6,22 +6,28 @@ use anyhow::Context as _;
 use anyhow::Result;
 use bdk::bitcoin;
 use bdk::bitcoin::Amount;
+use bdk::bitcoin::OutPoint;
 use bdk::FeeRate;
 use connection::ConnectionStatus;
 use libp2p_core::Multiaddr;
-use libp2p_tcp::TokioTcpConfig;
-use maia_core::secp256k1_zkp::XOnlyPublicKey;
 use model::libp2p::PeerId;
 use model::olivia;
 use model::Identity;
 use model::Leverage;
 use model::Order;
 use model::OrderId;
+use model::PositionTriggers;
 use model::Price;
+use model::RecoveredContract;
 use model::Role;
+use model::Timestamp;
 use model::Usd;
 use parse_display::Display;
 use seed::Identities;
+use sqlite_db::event_log::EventLogEntry;
+use sqlite_db::order_requests::OrderRequest;
+use sqlite_db::order_requests::OrderRequestState;
+use std::path::PathBuf;
 use std::time::Duration;
 use time::ext::NumericalDuration;
 use tokio::sync::watch;
@@ -47,17 +53,23 @@ pub use maia_core;
 pub mod archive_closed_cfds;
 pub mod archive_failed_cfds;
 pub mod auto_rollover;
+pub mod backup;
+pub mod blockchain;
 pub mod collab_settlement;
 pub mod command;
 pub mod connection;
+pub mod contract_setup;
+pub mod fee_bump;
 pub mod libp2p_utils;
 pub mod monitor;
 pub mod noise;
 mod online_status;
 pub mod oracle;
 pub mod position_metrics;
+pub mod position_triggers;
 pub mod process_manager;
 pub mod projection;
+pub mod recovery;
 pub mod rollover;
 pub mod seed;
 pub mod setup_contract;
@@ -66,6 +78,7 @@ pub mod setup_contract_deprecated;
 pub mod setup_taker;
 pub mod shared_protocol;
 pub mod taker_cfd;
+pub mod tor;
 mod transaction_ext;
 pub mod version;
 pub mod wallet;
@@ -82,6 +95,12 @@ pub const RESTART_INTERVAL: Duration = Duration::from_secs(5);
 pub const ENDPOINT_CONNECTION_TIMEOUT: Duration = Duration::from_secs(20);
 pub const PING_INTERVAL: Duration = Duration::from_secs(30);
 
+/// How long a repeated order request waits for the outcome of the first one.
+const ORDER_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The protocol version we announce to other peers through the identify protocol.
+pub const IDENTIFY_PROTOCOL_VERSION: &str = "itchysats/1.0.0";
+
 pub const N_PAYOUTS: usize = 200;
 
 pub struct TakerActorSystem<O, W, P> {
@@ -90,12 +109,18 @@ pub struct TakerActorSystem<O, W, P> {
     wallet_actor: Address<W>,
     pub auto_rollover_actor: Address<auto_rollover::Actor>,
     pub price_feed_actor: Address<P>,
+    position_triggers_actor: Address<position_triggers::Actor>,
     executor: command::Executor,
+    db: sqlite_db::Connection,
     _close_cfds_actor: Address<archive_closed_cfds::Actor>,
     _archive_failed_cfds_actor: Address<archive_failed_cfds::Actor>,
     _pong_actor: Address<pong::Actor>,
+    _identify_listener_actor: Address<xtra_libp2p_identify::listener::Actor>,
     _online_status_actor: Address<online_status::Actor>,
 
+    recovery_actor: Address<recovery::requester::Actor>,
+    maker_peer_id: PeerId,
+
     pub maker_online_status_feed_receiver: watch::Receiver<ConnectionStatus>,
 
     _tasks: Tasks,
@@ -112,8 +137,11 @@ where
         + Handler<wallet::Sign, Return = Result<PartiallySignedTransaction>>
         + Handler<wallet::Withdraw, Return = Result<Txid>>
         + Handler<wallet::Sync, Return = ()>
+        + Handler<wallet::ListUtxos, Return = Result<Vec<wallet::Utxo>>>
+        + Handler<wallet::FreezeUtxo, Return = Result<()>>
+        + Handler<wallet::UnfreezeUtxo, Return = Result<()>>
         + Actor<Stop = ()>,
-    P: Handler<xtra_bitmex_price_feed::LatestQuote, Return = Option<xtra_bitmex_price_feed::Quote>>
+    P: Handler<xtra_bitmex_price_feed::LatestQuotes, Return = xtra_bitmex_price_feed::Quotes>
         + Actor<Stop = xtra_bitmex_price_feed::Error>,
 {
     #[instrument(
@@ -130,7 +158,6 @@ where
     pub fn new<M>(
         db: sqlite_db::Connection,
         wallet_actor_addr: Address<W>,
-        oracle_pk: XOnlyPublicKey,
         identity: Identities,
         oracle_constructor: impl FnOnce(command::Executor) -> O,
         monitor_constructor: impl FnOnce(command::Executor) -> Result<M>,
@@ -141,13 +168,17 @@ where
         maker_identity: Identity,
         maker_multiaddr: Multiaddr,
         environment: Environment,
+        backup_path: PathBuf,
+        tor: Option<tor::Config>,
     ) -> Result<Self>
     where
         M: Handler<monitor::StartMonitoring, Return = ()>
             + Handler<monitor::Sync, Return = ()>
             + Handler<monitor::MonitorCollaborativeSettlement, Return = ()>
             + Handler<monitor::MonitorCetFinality, Return = Result<()>>
+            + Handler<monitor::MonitorPunishFinality, Return = Result<()>>
             + Handler<monitor::TryBroadcastTransaction, Return = Result<()>>
+            + Handler<monitor::IsSpent, Return = Result<bool>>
             + Actor<Stop = ()>,
     {
         let (maker_online_status_feed_sender, maker_online_status_feed_receiver) =
@@ -165,6 +196,10 @@ where
             .create(None)
             .spawn(&mut tasks);
 
+        let backup_actor = backup::Actor::new(db.clone(), identity.backup_key.clone(), backup_path)
+            .create(None)
+            .spawn(&mut tasks);
+
         tasks.add(process_manager_ctx.run(process_manager::Actor::new(
             db.clone(),
             Role::Taker,
@@ -173,8 +208,10 @@ where
             monitor_addr.clone().into(),
             monitor_addr.clone().into(),
             monitor_addr.clone().into(),
-            monitor_addr.into(),
+            monitor_addr.clone().into(),
+            monitor_addr.clone().into(),
             oracle_addr.clone().into(),
+            backup_actor.into(),
         )));
 
         let (endpoint_addr, endpoint_context) = Context::new(None);
@@ -192,24 +229,28 @@ where
         });
         tasks.add(collab_settlement_supervisor.run_log_summary());
 
+        let maker_peer_id = PeerId::from(
+            maker_multiaddr
+                .clone()
+                .extract_peer_id()
+                .context("Unable to extract peer id from maker address")?,
+        );
+
         let (connection_actor_addr, connection_actor_ctx) = Context::new(None);
         let cfd_actor_addr = taker_cfd::Actor::new(
             db.clone(),
             wallet_actor_addr.clone(),
-            oracle_pk,
-            projection_actor,
+            projection_actor.clone(),
             process_manager_addr,
+            endpoint_addr.clone(),
             connection_actor_addr.clone(),
             oracle_addr.clone(),
             libp2p_collab_settlement_addr,
             n_payouts,
             maker_identity,
-            PeerId::from(
-                maker_multiaddr
-                    .clone()
-                    .extract_peer_id()
-                    .context("Unable to extract peer id from maker address")?,
-            ),
+            maker_peer_id,
+            (identity.peer_id(), identity.identity_sk.clone()),
+            identity.contracts.clone(),
         )
         .create(None)
         .spawn(&mut tasks);
@@ -221,7 +262,6 @@ where
                 rollover::taker::Actor::new(
                     endpoint_addr.clone(),
                     executor.clone(),
-                    oracle_pk,
                     oracle_addr.clone().into(),
                     n_payouts,
                 )
@@ -271,30 +311,73 @@ where
             let cfd_actor_addr = cfd_actor_addr.clone();
             move || xtra_libp2p_offer::taker::Actor::new(cfd_actor_addr.clone().into())
         });
+        let (deprecated_offers_supervisor, deprecated_libp2p_offer_addr) = Supervisor::new({
+            let cfd_actor_addr = cfd_actor_addr.clone();
+            move || xtra_libp2p_offer::taker::Actor::deprecated(cfd_actor_addr.clone().into())
+        });
 
         let pong_address = pong::Actor.create(None).spawn(&mut tasks);
 
+        let recovery_listener_addr = recovery::listener::Actor::new(db.clone())
+            .create(None)
+            .spawn(&mut tasks);
+        let recovery_actor = recovery::requester::Actor::new(
+            db.clone(),
+            endpoint_addr.clone(),
+            identity.contracts.clone(),
+            monitor_addr.clone().into(),
+            monitor_addr.into(),
+        )
+        .create(None)
+        .spawn(&mut tasks);
+
+        let identify_listener_actor = xtra_libp2p_identify::listener::Actor::new(
+            endpoint_addr.clone(),
+            identity.libp2p.public(),
+            IDENTIFY_PROTOCOL_VERSION.to_owned(),
+            version::version().to_owned(),
+        )
+        .create(None)
+        .spawn(&mut tasks);
+
+        let (supervisor, identify_dialer_actor) = Supervisor::new({
+            let endpoint_addr = endpoint_addr.clone();
+            move || xtra_libp2p_identify::dialer::Actor::new(endpoint_addr.clone())
+        });
+        tasks.add(supervisor.run_log_summary());
+
         let (supervisor, ping_actor) =
             Supervisor::new(move || ping::Actor::new(endpoint_addr.clone(), PING_INTERVAL));
         tasks.add(supervisor.run_log_summary());
 
         let endpoint = Endpoint::new(
-            Box::new(TokioTcpConfig::new),
+            libp2p_utils::endpoint_transport(tor)?,
             identity.libp2p,
             ENDPOINT_CONNECTION_TIMEOUT,
             [
                 (xtra_libp2p_ping::PROTOCOL_NAME, pong_address.clone().into()),
                 (xtra_libp2p_offer::PROTOCOL_NAME, libp2p_offer_addr.into()),
+                (
+                    xtra_libp2p_offer::DEPRECATED_PROTOCOL_NAME,
+                    deprecated_libp2p_offer_addr.into(),
+                ),
+                (recovery::PROTOCOL, recovery_listener_addr.into()),
+                (
+                    xtra_libp2p_identify::PROTOCOL_NAME,
+                    identify_listener_actor.clone().into(),
+                ),
             ],
             endpoint::Subscribers::new(
                 vec![
                     online_status_actor.clone().into(),
                     ping_actor.clone().into(),
+                    identify_dialer_actor.clone().into(),
                 ],
                 vec![
                     dialer_actor.into(),
                     ping_actor.into(),
                     online_status_actor.clone().into(),
+                    identify_dialer_actor.into(),
                 ],
                 vec![],
                 vec![],
@@ -305,6 +388,7 @@ where
 
         tasks.add(dialer_supervisor.run_log_summary());
         tasks.add(offers_supervisor.run_log_summary());
+        tasks.add(deprecated_offers_supervisor.run_log_summary());
 
         let (supervisor, price_feed_actor) =
             Supervisor::<_, xtra_bitmex_price_feed::Error>::with_policy(
@@ -314,10 +398,19 @@ where
 
         tasks.add(supervisor.run_log_summary());
 
+        let position_triggers_actor = position_triggers::Actor::new(
+            db.clone(),
+            price_feed_actor.clone().into(),
+            cfd_actor_addr.clone().into(),
+            projection_actor.into(),
+        )
+        .create(None)
+        .spawn(&mut tasks);
+
         let close_cfds_actor = archive_closed_cfds::Actor::new(db.clone())
             .create(None)
             .spawn(&mut tasks);
-        let archive_failed_cfds_actor = archive_failed_cfds::Actor::new(db)
+        let archive_failed_cfds_actor = archive_failed_cfds::Actor::new(db.clone())
             .create(None)
             .spawn(&mut tasks);
 
@@ -329,13 +422,18 @@ where
             wallet_actor: wallet_actor_addr,
             auto_rollover_actor: auto_rollover_addr,
             price_feed_actor,
+            position_triggers_actor,
             executor,
+            db,
             _close_cfds_actor: close_cfds_actor,
             _archive_failed_cfds_actor: archive_failed_cfds_actor,
             _tasks: tasks,
             maker_online_status_feed_receiver,
             _online_status_actor: online_status_actor,
             _pong_actor: pong_address,
+            _identify_listener_actor: identify_listener_actor,
+            recovery_actor,
+            maker_peer_id,
         })
     }
 
@@ -356,6 +454,100 @@ where
         Ok(())
     }
 
+    /// Take an offer at most once per `idempotency_key`
+    ///
+    /// The outcome is recorded with the request. Repeating a request with the same key and
+    /// parameters does not take the offer again but returns the recorded outcome, waiting for it
+    /// if the first request is still in progress. Reusing a key for different parameters fails
+    /// with [`IdempotencyKeyReused`].
+    ///
+    /// If the offer cannot be taken as requested ([`taker_cfd::InvalidOrder`]), the request is
+    /// forgotten and the client may retry with the same key.
+    #[instrument(skip(self), err)]
+    pub async fn take_offer_idempotent(
+        &self,
+        idempotency_key: String,
+        order_id: OrderId,
+        quantity: Usd,
+        leverage: Leverage,
+    ) -> Result<()> {
+        let request = OrderRequest {
+            idempotency_key,
+            order_id,
+            quantity,
+            leverage,
+            timestamp: Timestamp::now(),
+            state: OrderRequestState::Pending,
+        };
+
+        if !self.db.try_insert_order_request(&request).await? {
+            let placed = self
+                .db
+                .load_order_request(&request.idempotency_key)
+                .await?
+                .context("Order request for idempotency key not found")?;
+
+            if (placed.order_id, placed.quantity, placed.leverage) != (order_id, quantity, leverage)
+            {
+                return Err(IdempotencyKeyReused(request.idempotency_key).into());
+            }
+
+            tracing::debug!(%order_id, "Offer was already taken with this idempotency key");
+
+            return self.order_request_outcome(&request.idempotency_key).await;
+        }
+
+        let state = match self.take_offer(order_id, quantity, leverage).await {
+            Ok(()) => OrderRequestState::Placed,
+            Err(e) if e.is::<taker_cfd::InvalidOrder>() => {
+                // Nothing was taken, so the client may retry with the same key
+                self.db
+                    .delete_order_request(&request.idempotency_key)
+                    .await?;
+
+                return Err(e);
+            }
+            Err(e) => OrderRequestState::Failed {
+                reason: format!("{e:#}"),
+            },
+        };
+
+        self.db
+            .update_order_request_state(&request.idempotency_key, state.clone())
+            .await?;
+
+        match state {
+            OrderRequestState::Failed { reason } => Err(OrderRequestFailed(reason).into()),
+            OrderRequestState::Pending | OrderRequestState::Placed => Ok(()),
+        }
+    }
+
+    /// Wait for the outcome of an order request placed before.
+    async fn order_request_outcome(&self, idempotency_key: &str) -> Result<()> {
+        let started = std::time::Instant::now();
+
+        loop {
+            let request = self
+                .db
+                .load_order_request(idempotency_key)
+                .await?
+                .context("Order request for idempotency key not found")?;
+
+            match request.state {
+                OrderRequestState::Placed => return Ok(()),
+                OrderRequestState::Failed { reason } => {
+                    return Err(OrderRequestFailed(reason).into())
+                }
+                OrderRequestState::Pending if started.elapsed() >= ORDER_REQUEST_TIMEOUT => {
+                    return Err(OrderRequestPending(idempotency_key.to_owned()).into())
+                }
+                OrderRequestState::Pending => {
+                    tokio_extras::time::sleep(Duration::from_millis(200)).await
+                }
+            }
+        }
+    }
+
     #[instrument(skip(self), err)]
     pub async fn commit(&self, order_id: OrderId) -> Result<()> {
         self.executor
@@ -367,12 +559,19 @@ where
 
     #[instrument(skip(self), err)]
     pub async fn propose_settlement(&self, order_id: OrderId) -> Result<()> {
-        let latest_quote = self
+        let trading_pair = self
+            .db
+            .load_open_cfd::<model::Cfd>(order_id, ())
+            .await?
+            .trading_pair();
+
+        let latest_quote = *self
             .price_feed_actor
-            .send(xtra_bitmex_price_feed::LatestQuote)
+            .send(xtra_bitmex_price_feed::LatestQuotes)
             .await
             .context("Price feed not available")?
-            .context("No quote available")?;
+            .get(trading_pair.bitmex_instrument())
+            .with_context(|| format!("No quote available for {trading_pair}"))?;
 
         let quote_timestamp = latest_quote
             .timestamp
@@ -398,18 +597,34 @@ where
             .await?
     }
 
+    /// Set the stop-loss and take-profit of an open position
+    ///
+    /// Replaces previously set triggers, an empty set of triggers removes them.
+    #[instrument(skip(self), err)]
+    pub async fn set_position_triggers(
+        &self,
+        order_id: OrderId,
+        triggers: PositionTriggers,
+    ) -> Result<()> {
+        self.position_triggers_actor
+            .send(position_triggers::SetTriggers { order_id, triggers })
+            .await?
+    }
+
     #[instrument(skip(self), err)]
     pub async fn withdraw(
         &self,
         amount: Option<Amount>,
         address: bitcoin::Address,
         fee_rate: FeeRate,
+        utxos: Vec<OutPoint>,
     ) -> Result<Txid> {
         self.wallet_actor
             .send(wallet::Withdraw {
                 amount,
                 address,
                 fee: Some(fee_rate),
+                utxos,
             })
             .await?
     }
@@ -419,8 +634,55 @@ where
         self.wallet_actor.send(wallet::Sync).await?;
         Ok(())
     }
+
+    #[instrument(skip(self), err)]
+    pub async fn list_utxos(&self) -> Result<Vec<wallet::Utxo>> {
+        self.wallet_actor.send(wallet::ListUtxos).await?
+    }
+
+    #[instrument(skip(self), err)]
+    pub async fn freeze_utxo(&self, outpoint: OutPoint) -> Result<()> {
+        self.wallet_actor.send(wallet::FreezeUtxo(outpoint)).await?
+    }
+
+    #[instrument(skip(self), err)]
+    pub async fn unfreeze_utxo(&self, outpoint: OutPoint) -> Result<()> {
+        self.wallet_actor
+            .send(wallet::UnfreezeUtxo(outpoint))
+            .await?
+    }
+
+    /// Names and times of all events of the CFD, oldest first
+    pub async fn event_log(&self, order_id: OrderId) -> Result<Vec<EventLogEntry>> {
+        self.db.load_event_log(order_id).await
+    }
+
+    /// Recover the contracts we have open with the maker after losing the database
+    ///
+    /// The refund transactions of the recovered contracts are published once their timelock
+    /// expired.
+    #[instrument(skip(self), err)]
+    pub async fn recover(&self) -> Result<Vec<RecoveredContract>> {
+        self.recovery_actor
+            .send(recovery::requester::Recover {
+                counterparty: self.maker_peer_id,
+            })
+            .await?
+    }
 }
 
+#[derive(thiserror::Error, Debug, Clone)]
+#[error("Idempotency key {0} was already used for a different order")]
+pub struct IdempotencyKeyReused(pub String);
+
+#[derive(thiserror::Error, Debug, Clone)]
+#[error("Order request with idempotency key {0} is still in progress")]
+pub struct OrderRequestPending(pub String);
+
+#[derive(thiserror::Error, Debug, Clone)]
+#[error("Order request failed: {0}")]
+pub struct OrderRequestFailed(pub String);
+
 #[derive(Debug, Copy, Clone, Display)]
 pub enum Environment {
     Umbrel,
@@ -1,22 +1,91 @@
-use anyhow::ensure;
+use crate::tor;
+use anyhow::bail;
 use anyhow::Context;
 use anyhow::Result;
 use std::net::IpAddr;
 use std::net::SocketAddr;
 
+use libp2p_core::multiaddr::Protocol;
+use libp2p_core::transport::OptionalTransport;
+use libp2p_core::transport::OrTransport;
 use libp2p_core::Multiaddr;
 use libp2p_core::PeerId;
+use libp2p_dns::TokioDnsConfig;
+use libp2p_tcp::TokioTcpConfig;
+
+pub type EndpointTransport = OrTransport<
+    OptionalTransport<tor::TorTransport>,
+    OptionalTransport<TokioDnsConfig<TokioTcpConfig>>,
+>;
+
+/// The transport to construct the libp2p endpoint with.
+///
+/// Plain TCP resolves `/dns/`, `/dns4/` and `/dns6/` addresses with the DNS configuration of the
+/// system. If we dial through Tor, plain TCP is disabled so that we never connect to the
+/// counterparty directly.
+pub fn endpoint_transport(
+    tor: Option<tor::Config>,
+) -> Result<Box<dyn Fn() -> EndpointTransport + Send + 'static>> {
+    // Fail on startup instead of on every connection attempt
+    TokioDnsConfig::system(TokioTcpConfig::new())
+        .context("Failed to load DNS configuration of the system")?;
+
+    let tcp_enabled = tor.map_or(true, |tor| tor.socks_proxy.is_none());
+
+    Ok(Box::new(move || {
+        let tor = match tor {
+            Some(config) => OptionalTransport::some(tor::TorTransport::new(config)),
+            None => OptionalTransport::none(),
+        };
+        let tcp = if tcp_enabled {
+            OptionalTransport::some(
+                TokioDnsConfig::system(TokioTcpConfig::new())
+                    .expect("DNS configuration to be loaded on startup"),
+            )
+        } else {
+            OptionalTransport::none()
+        };
+
+        OrTransport::new(tor, tcp)
+    }))
+}
 
 /// Creates MultiAddr from SocketAddr and PeerId
 pub fn create_connect_tcp_multiaddr(
     socket_addr: &SocketAddr,
     peer_id: PeerId,
 ) -> Result<Multiaddr> {
-    let ip = socket_addr.ip();
-    let port = socket_addr.port();
-    ensure!(socket_addr.is_ipv4(), "only ipv4 is supported");
+    let tcp = create_listen_tcp_multiaddr(&socket_addr.ip(), socket_addr.port())?;
 
-    format!("/ip4/{ip}/tcp/{port}/p2p/{peer_id}")
+    format!("{tcp}/p2p/{peer_id}")
+        .parse::<Multiaddr>()
+        .with_context(|| "failed to construct multiaddr")
+}
+
+/// Creates MultiAddr to dial a peer from its host name or IP address
+///
+/// Hosts ending in `.onion` become `/onion3/` addresses. Other names are kept as `/dns/`, so that
+/// they are resolved when dialing, by Tor if we connect through it.
+pub fn create_connect_host_multiaddr(host: &str, port: u16, peer_id: PeerId) -> Result<Multiaddr> {
+    let addr = match (host.strip_suffix(".onion"), host.parse::<IpAddr>()) {
+        (Some(onion), _) => format!("/onion3/{onion}:{port}"),
+        (None, Ok(IpAddr::V4(ip))) => format!("/ip4/{ip}/tcp/{port}"),
+        (None, Ok(IpAddr::V6(ip))) => format!("/ip6/{ip}/tcp/{port}"),
+        (None, Err(_)) => format!("/dns/{host}/tcp/{port}"),
+    };
+
+    format!("{addr}/p2p/{peer_id}")
+        .parse::<Multiaddr>()
+        .with_context(|| "failed to construct multiaddr")
+}
+
+/// Creates MultiAddr of our hidden service from its `.onion` address
+pub fn create_listen_onion_multiaddr(onion_address: &str, port: u16) -> Result<Multiaddr> {
+    let onion = onion_address
+        .strip_suffix(".onion")
+        .unwrap_or(onion_address);
+
+    format!("/onion3/{onion}:{port}")
         .parse::<Multiaddr>()
         .with_context(|| "failed to construct multiaddr")
 }
@@ -34,13 +103,54 @@ pub fn create_connect_multiaddr(
 
 /// Creates MultiAddr from SocketAddr
 pub fn create_listen_tcp_multiaddr(ip: &IpAddr, port: u16) -> Result<Multiaddr> {
-    ensure!(ip.is_ipv4(), "only ipv4 is supported");
+    let addr = match ip {
+        IpAddr::V4(ip) => format!("/ip4/{ip}/tcp/{port}"),
+        IpAddr::V6(ip) => format!("/ip6/{ip}/tcp/{port}"),
+    };
 
-    format!("/ip4/{ip}/tcp/{port}")
-        .parse::<Multiaddr>()
+    addr.parse::<Multiaddr>()
         .with_context(|| "failed to construct multiaddr")
 }
 
+/// Resolve a `/dns/`, `/dns4/` or `/dns6/` address to listen on into the IP addresses it points to
+///
+/// We can only listen on IP addresses, other addresses are returned as they are.
+pub async fn resolve_listen_multiaddr(addr: Multiaddr) -> Result<Vec<Multiaddr>> {
+    let mut protocols = addr.iter();
+
+    let (name, ipv4, ipv6) = match protocols.next() {
+        Some(Protocol::Dns(name)) => (name, true, true),
+        Some(Protocol::Dns4(name)) => (name, true, false),
+        Some(Protocol::Dns6(name)) => (name, false, true),
+        _ => return Ok(vec![addr]),
+    };
+    let port = match protocols.next() {
+        Some(Protocol::Tcp(port)) => port,
+        _ => bail!("Cannot listen on {addr}, expected a TCP port after the DNS name"),
+    };
+    if protocols.next().is_some() {
+        bail!("Cannot listen on {addr}, expected nothing after the TCP port");
+    }
+
+    let resolved = tokio::net::lookup_host((name.as_ref(), port))
+        .await
+        .with_context(|| format!("Failed to resolve {name}"))?
+        .filter(|socket_addr| (socket_addr.is_ipv4() && ipv4) || (socket_addr.is_ipv6() && ipv6))
+        .map(|socket_addr| create_listen_tcp_multiaddr(&socket_addr.ip(), socket_addr.port()))
+        .collect::<Result<Vec<_>>>()?;
+
+    if resolved.is_empty() {
+        bail!("{addr} did not resolve to any address to listen on");
+    }
+
+    tracing::debug!(
+        "Resolved {addr} to [{}]",
+        itertools::join(resolved.iter(), ",")
+    );
+
+    Ok(resolved)
+}
+
 /// By convention we increment the port by 1 for libp2p-based connections.
 ///
 /// The obvious drawback is that when doing blue/green deployment, we need to
@@ -56,3 +166,54 @@ pub fn can_use_libp2p(cfd: &model::Cfd) -> bool {
     // can't dial in to them using libp2p.
     cfd.counterparty_peer_id().is_some()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PEER_ID: &str = "12D3KooWEsK2X8Tp24XtyWh7DM65VfwXtNH2cmfs2JsWmkmwKbV1";
+
+    #[test]
+    fn ipv6_socket_addr_becomes_ip6_multiaddr() {
+        let socket_addr = "[2001:db8::1]:10000".parse::<SocketAddr>().unwrap();
+        let peer_id = PEER_ID.parse().unwrap();
+
+        let addr = create_connect_tcp_multiaddr(&socket_addr, peer_id).unwrap();
+
+        assert_eq!(
+            addr.to_string(),
+            format!("/ip6/2001:db8::1/tcp/10000/p2p/{PEER_ID}")
+        );
+    }
+
+    #[test]
+    fn host_name_is_kept_for_dialing() {
+        let peer_id = PEER_ID.parse().unwrap();
+
+        let dns =
+            create_connect_host_multiaddr("mainnet.itchysats.network", 10000, peer_id).unwrap();
+        let ip6 = create_connect_host_multiaddr("::1", 10000, peer_id).unwrap();
+
+        assert_eq!(
+            dns.to_string(),
+            format!("/dns/mainnet.itchysats.network/tcp/10000/p2p/{PEER_ID}")
+        );
+        assert_eq!(ip6.to_string(), format!("/ip6/::1/tcp/10000/p2p/{PEER_ID}"));
+    }
+
+    #[tokio::test]
+    async fn ip_addresses_to_listen_on_are_not_resolved() {
+        let addr = "/ip6/::/tcp/10000".parse::<Multiaddr>().unwrap();
+
+        let resolved = resolve_listen_multiaddr(addr.clone()).await.unwrap();
+
+        assert_eq!(resolved, vec![addr]);
+    }
+
+    #[tokio::test]
+    async fn dns_name_without_port_cannot_be_listened_on() {
+        let addr = "/dns4/localhost".parse::<Multiaddr>().unwrap();
+
+        assert!(resolve_listen_multiaddr(addr).await.is_err());
+    }
+}
@@ -1,31 +1,35 @@
 use crate::bitcoin::consensus::encode::serialize_hex;
 use crate::bitcoin::Transaction;
+use crate::blockchain;
 use crate::command;
+use crate::fee_bump;
+use crate::wallet;
 use crate::wallet::RpcErrorCode;
 use anyhow::Context;
 use anyhow::Result;
 use async_trait::async_trait;
+use bdk::bitcoin::Amount;
+use bdk::bitcoin::OutPoint;
 use bdk::bitcoin::PublicKey;
 use bdk::bitcoin::Script;
 use bdk::bitcoin::Txid;
 use bdk::descriptor::Descriptor;
-use bdk::electrum_client;
-use bdk::electrum_client::ElectrumApi;
 use bdk::miniscript::DescriptorTrait;
 use btsieve::ScriptStatus;
 use btsieve::State;
-use btsieve::TxStatus;
 use futures::StreamExt;
 use model::CfdEvent;
 use model::Dlc;
 use model::EventKind;
 use model::OrderId;
+use model::TxFeeRate;
 use model::CET_TIMELOCK;
-use serde_json::Value;
 use sqlite_db;
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::time::Duration;
 use tracing::Instrument;
+use xtra::prelude::MessageChannel;
 use xtra_productivity::xtra_productivity;
 use xtras::SendInterval;
 
@@ -34,6 +38,7 @@ const CLOSE_FINALITY_CONFIRMATIONS: u32 = 3;
 const COMMIT_FINALITY_CONFIRMATIONS: u32 = 1;
 const CET_FINALITY_CONFIRMATIONS: u32 = 3;
 const REFUND_FINALITY_CONFIRMATIONS: u32 = 3;
+const PUNISH_FINALITY_CONFIRMATIONS: u32 = 3;
 
 pub struct StartMonitoring {
     pub id: OrderId,
@@ -50,6 +55,18 @@ pub struct MonitorCetFinality {
     pub cet: Transaction,
 }
 
+pub struct MonitorPunishFinality {
+    pub order_id: OrderId,
+    pub punish_tx: Transaction,
+}
+
+/// Check whether `outpoint`, paying to `script`, was spent by a transaction in the blockchain or
+/// the mempool.
+pub struct IsSpent {
+    pub outpoint: OutPoint,
+    pub script: Script,
+}
+
 // TODO: The design of this struct causes a lot of marshalling und unmarshelling that is quite
 // unnecessary. Should be taken apart so we can handle all cases individually!
 #[derive(Clone)]
@@ -61,17 +78,21 @@ pub struct MonitorParams {
 }
 
 pub struct TryBroadcastTransaction {
+    pub order_id: OrderId,
     pub tx: Transaction,
     pub kind: TransactionKind,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq)]
 pub enum TransactionKind {
     Lock,
     Commit,
     Refund,
     CollaborativeClose,
     Cet,
+    Punish,
+    /// Child transaction paying for a transaction of one of the other kinds
+    Cpfp,
 }
 
 impl TransactionKind {
@@ -82,27 +103,41 @@ impl TransactionKind {
             TransactionKind::Refund => "refund",
             TransactionKind::CollaborativeClose => "collaborative-close",
             TransactionKind::Cet => "contract-execution",
+            TransactionKind::Punish => "punish",
+            TransactionKind::Cpfp => "cpfp",
         }
     }
-}
-
-fn parse_rpc_protocol_error(error_value: &Value) -> Result<RpcError> {
-    let json = error_value
-        .as_str()
-        .context("Not a string")?
-        .split_terminator("RPC error: ")
-        .nth(1)
-        .context("Unknown error code format")?;
-
-    let error = serde_json::from_str::<RpcError>(json).context("Error has unexpected format")?;
 
-    Ok(error)
+    /// Whether the fee of the transaction is bumped if it does not confirm in time.
+    ///
+    /// Children are bumped by replacing them through their parent instead.
+    fn can_bump_fee(&self) -> bool {
+        match self {
+            TransactionKind::Lock
+            | TransactionKind::Commit
+            | TransactionKind::Refund
+            | TransactionKind::CollaborativeClose
+            | TransactionKind::Cet
+            | TransactionKind::Punish => true,
+            TransactionKind::Cpfp => false,
+        }
+    }
 }
 
-#[derive(serde::Deserialize)]
-struct RpcError {
-    code: i64,
-    message: String,
+/// A transaction we published whose fee we bump if it does not confirm in time.
+#[derive(Clone)]
+struct Unconfirmed {
+    order_id: OrderId,
+    tx: Transaction,
+    kind: TransactionKind,
+    /// Height at which the transaction was published or its fee last bumped
+    since: u32,
+    /// Fee paid by the transaction itself, once known
+    fee: Option<Amount>,
+    /// Fee rate of the transaction and its latest child together, once known
+    fee_rate: Option<TxFeeRate>,
+    /// The latest child paying for the transaction, replaced by the next bump
+    child: Option<Transaction>,
 }
 
 #[derive(Clone, Copy)]
@@ -113,9 +148,15 @@ pub struct Sync;
 pub struct Actor {
     cfds: HashMap<OrderId, MonitorParams>,
     executor: command::Executor,
-    client: bdk::electrum_client::Client,
+    client: blockchain::Client,
     state: State<Event>,
     db: sqlite_db::Connection,
+    block_height: u32,
+    unconfirmed: HashMap<Txid, Unconfirmed>,
+    /// Revoked commit transactions we found but could not fetch yet, retried on every sync.
+    pending_revoked_transactions: HashSet<(OrderId, Txid)>,
+    fee_bump_policy: fee_bump::Policy,
+    bump_fee: MessageChannel<wallet::BumpFee, Result<Transaction>>,
 }
 
 /// Read-model of the CFD for the monitoring actor.
@@ -134,11 +175,15 @@ struct Cfd {
     // Ideally, all of the above would be like this.
     monitor_collaborative_settlement_finality: Option<(Txid, Script)>,
     monitor_cet_finality: Option<(Txid, Script)>,
+    monitor_punish_finality: Option<(Txid, Script)>,
 
     // Rebroadcast transactions upon startup
     lock_tx: Option<Transaction>,
     cet: Option<Transaction>,
     commit_tx: Option<Transaction>,
+    refund_tx: Option<Transaction>,
+    punish_tx: Option<Transaction>,
+    cpfp_tx: Option<Transaction>,
 
     version: u32,
 }
@@ -158,9 +203,13 @@ impl sqlite_db::CfdAggregate for Cfd {
             monitor_revoked_commit_transactions: false,
             monitor_collaborative_settlement_finality: None,
             monitor_cet_finality: None,
+            monitor_punish_finality: None,
             lock_tx: None,
             cet: None,
             commit_tx: None,
+            refund_tx: None,
+            punish_tx: None,
+            cpfp_tx: None,
             version: 0,
         }
     }
@@ -254,20 +303,26 @@ impl Cfd {
                 ..self
             },
             // final states, don't monitor anything
-            CetConfirmed | RefundConfirmed | CollaborativeSettlementConfirmed => Self {
-                monitor_lock_finality: false,
-                monitor_commit_finality: false,
-                monitor_cet_timelock: false,
-                monitor_refund_timelock: false,
-                monitor_refund_finality: false,
-                monitor_revoked_commit_transactions: false,
-                monitor_collaborative_settlement_finality: None,
-                monitor_cet_finality: None,
-                lock_tx: None,
-                cet: None,
-                commit_tx: None,
-                ..self
-            },
+            CetConfirmed | RefundConfirmed | CollaborativeSettlementConfirmed | PunishConfirmed => {
+                Self {
+                    monitor_lock_finality: false,
+                    monitor_commit_finality: false,
+                    monitor_cet_timelock: false,
+                    monitor_refund_timelock: false,
+                    monitor_refund_finality: false,
+                    monitor_revoked_commit_transactions: false,
+                    monitor_collaborative_settlement_finality: None,
+                    monitor_cet_finality: None,
+                    monitor_punish_finality: None,
+                    lock_tx: None,
+                    cet: None,
+                    commit_tx: None,
+                    refund_tx: None,
+                    punish_tx: None,
+                    cpfp_tx: None,
+                    ..self
+                }
+            }
             CetTimelockExpiredPriorOracleAttestation => Self {
                 monitor_cet_timelock: false,
                 ..self
@@ -278,8 +333,9 @@ impl Cfd {
                 monitor_cet_timelock: false,
                 ..self
             },
-            RefundTimelockExpired { .. } => Self {
+            RefundTimelockExpired { refund_tx } => Self {
                 monitor_refund_timelock: false,
+                refund_tx: Some(refund_tx),
                 ..self
             },
             OracleAttestedPostCetTimelock { cet, .. } => Self {
@@ -295,11 +351,22 @@ impl Cfd {
             | CollaborativeSettlementStarted { .. }
             | CollaborativeSettlementRejected
             | CollaborativeSettlementFailed
-            | CollaborativeSettlementProposalAccepted => self,
-            RevokeConfirmed => {
-                // TODO: Implement revoked logic
-                self
-            }
+            | CollaborativeSettlementProposalAccepted
+            | PolicyDecided { .. } => self,
+            RevokeConfirmed => Self {
+                monitor_revoked_commit_transactions: false,
+                ..self
+            },
+            PunishPublished { punish_tx } => Self {
+                monitor_revoked_commit_transactions: false,
+                monitor_punish_finality: punish_txid_and_script(&punish_tx),
+                punish_tx: Some(punish_tx),
+                ..self
+            },
+            FeeBumped { cpfp_tx, .. } => Self {
+                cpfp_tx: Some(cpfp_tx),
+                ..self
+            },
         }
     }
 }
@@ -314,29 +381,37 @@ fn cet_txid_and_script(cet: Transaction) -> Option<(Txid, Script)> {
     }
 }
 
+fn punish_txid_and_script(punish_tx: &Transaction) -> Option<(Txid, Script)> {
+    match punish_tx.output.first() {
+        Some(output) => Some((punish_tx.txid(), output.script_pubkey.clone())),
+        None => {
+            tracing::error!("Failed to monitor punish transaction because it has no TxOut's");
+            None
+        }
+    }
+}
+
 impl Actor {
     pub fn new(
         db: sqlite_db::Connection,
-        electrum_rpc_url: String,
+        backend: blockchain::Backend,
+        bump_fee: MessageChannel<wallet::BumpFee, Result<Transaction>>,
         executor: command::Executor,
     ) -> Result<Self> {
-        let client = bdk::electrum_client::Client::new(&electrum_rpc_url)
-            .context("Failed to initialize Electrum RPC client")?;
-
-        // Initially fetch the latest block for storing the height.
-        // We do not act on this subscription after this call.
-        let latest_block = client
-            .block_headers_subscribe()
-            .context("Failed to subscribe to header notifications")?
-            .height
-            .into();
+        let client = backend.client()?;
 
         Ok(Self {
             cfds: HashMap::new(),
             client,
             executor,
-            state: State::new(latest_block),
+            // The latest block height is fetched once the actor is started
+            state: State::new(0.into()),
             db,
+            block_height: 0,
+            unconfirmed: HashMap::new(),
+            pending_revoked_transactions: HashSet::new(),
+            fee_bump_policy: fee_bump::Policy::default(),
+            bump_fee,
         })
     }
 }
@@ -378,6 +453,15 @@ impl Actor {
         );
     }
 
+    fn monitor_punish_finality(&mut self, punish_params: (Txid, Script), order_id: OrderId) {
+        self.state.monitor(
+            punish_params.0,
+            punish_params.1,
+            ScriptStatus::with_confirmations(PUNISH_FINALITY_CONFIRMATIONS),
+            Event::PunishFinality(order_id),
+        );
+    }
+
     fn monitor_commit_cet_timelock(&mut self, params: &MonitorParams, order_id: OrderId) {
         self.state.monitor(
             params.commit.0,
@@ -411,47 +495,27 @@ impl Actor {
                 revoked_commit_tx.0,
                 revoked_commit_tx.1.clone(),
                 ScriptStatus::InMempool,
-                Event::RevokedTransactionFound(order_id),
+                Event::RevokedTransactionFound(order_id, revoked_commit_tx.0),
             )
         }
     }
 
     #[tracing::instrument("Sync monitor", skip_all, err)]
     async fn sync(&mut self) -> Result<()> {
-        // Fetch the latest block for storing the height.
-        // We do not act on this subscription after this call, as we cannot rely on
-        // subscription push notifications because eventually the Electrum server will
-        // close the connection and subscriptions are not automatically renewed
-        // upon renewing the connection.
-        let latest_block_height = self
-            .client
-            .block_headers_subscribe()
-            .context("Failed to subscribe to header notifications")?
-            .height
-            .into();
+        let latest_block_height = self.client.block_height().await?;
+        self.block_height = latest_block_height;
 
         let num_transactions = self.state.num_monitoring();
 
         tracing::trace!("Updating status of {num_transactions} transactions",);
 
-        let histories = self
-            .client
-            .batch_script_get_history(self.state.monitoring_scripts())
-            .context("Failed to get script histories")?;
-
-        let mut ready_events = self.state.update(
-            latest_block_height,
-            histories
-                .into_iter()
-                .map(|list| {
-                    list.into_iter()
-                        .map(|response| TxStatus {
-                            height: response.height,
-                            tx_hash: response.tx_hash,
-                        })
-                        .collect()
-                })
-                .collect(),
+        let histories = self.client.histories(self.state.monitoring()).await?;
+
+        let mut ready_events = self.state.update(latest_block_height.into(), histories);
+        ready_events.extend(
+            self.pending_revoked_transactions
+                .drain()
+                .map(|(id, txid)| Event::RevokedTransactionFound(id, txid)),
         );
 
         while let Some(event) = ready_events.pop() {
@@ -482,8 +546,23 @@ impl Actor {
                     self.invoke_cfd_command(id, |cfd| Ok(Some(cfd.handle_refund_confirmed())))
                         .await
                 }
-                Event::RevokedTransactionFound(id) => {
-                    self.invoke_cfd_command(id, |cfd| Ok(Some(cfd.handle_revoke_confirmed())))
+                Event::RevokedTransactionFound(id, txid) => {
+                    let revoked_commit_tx = match self.client.transaction(txid).await {
+                        Ok(tx) => tx,
+                        Err(e) => {
+                            tracing::warn!(order_id = %id, %txid, "Failed to fetch revoked commit transaction, retrying on next sync: {e:#}");
+                            self.pending_revoked_transactions.insert((id, txid));
+                            continue;
+                        }
+                    };
+
+                    self.invoke_cfd_command(id, |cfd| {
+                        cfd.handle_revoke_confirmed(revoked_commit_tx)
+                    })
+                    .await
+                }
+                Event::PunishFinality(id) => {
+                    self.invoke_cfd_command(id, |cfd| Ok(Some(cfd.handle_punish_confirmed())))
                         .await
                 }
                 Event::RefundTimelockExpired(id) => {
@@ -493,9 +572,192 @@ impl Actor {
             }
         }
 
+        let txids = self.unconfirmed.keys().copied().collect::<Vec<_>>();
+        for txid in txids {
+            if let Err(e) = self.bump_fee(txid).await {
+                tracing::warn!(%txid, "Failed to bump fee of transaction: {e:#}");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Publish a child transaction paying for `txid` if the fee bump policy says so.
+    async fn bump_fee(&mut self, txid: Txid) -> Result<()> {
+        let unconfirmed = match self.unconfirmed.get(&txid) {
+            Some(unconfirmed) => unconfirmed.clone(),
+            None => return Ok(()),
+        };
+        let order_id = unconfirmed.order_id;
+
+        let script = &unconfirmed
+            .tx
+            .output
+            .first()
+            .context("Transaction has no outputs")?
+            .script_pubkey;
+
+        match self.client.transaction_height(txid, script).await? {
+            Some(height) if height > 0 => {
+                self.unconfirmed.remove(&txid);
+                return Ok(());
+            }
+            Some(_) => {}
+            None => {
+                // Without the parent in the mempool a child cannot help
+                tracing::debug!(%txid, "Transaction not in mempool, not bumping its fee");
+                self.unconfirmed.remove(&txid);
+                return Ok(());
+            }
+        }
+
+        let blocks_to_deadline = match unconfirmed.kind {
+            // The counterparty can publish the refund transaction once its timelock expires
+            TransactionKind::Cet => self.blocks_to_refund_timelock(order_id).await?,
+            _ => None,
+        };
+
+        let fee = match unconfirmed.fee {
+            Some(fee) => fee,
+            None => self.transaction_fee(&unconfirmed.tx).await?,
+        };
+        let fee_rate = unconfirmed
+            .fee_rate
+            .unwrap_or_else(|| fee_rate_of(fee, &unconfirmed.tx));
+
+        let target = self.fee_bump_policy.next_fee_rate(
+            fee_rate,
+            self.block_height.saturating_sub(unconfirmed.since),
+            blocks_to_deadline,
+        );
+
+        let target = match target {
+            Some(target) => target,
+            None => {
+                self.unconfirmed.insert(
+                    txid,
+                    Unconfirmed {
+                        fee: Some(fee),
+                        fee_rate: Some(fee_rate),
+                        ..unconfirmed
+                    },
+                );
+                return Ok(());
+            }
+        };
+
+        let cpfp_tx = match self
+            .bump_fee
+            .send(wallet::BumpFee {
+                parent: unconfirmed.tx.clone(),
+                parent_fee: fee,
+                fee_rate: target,
+                replaces: unconfirmed.child.clone(),
+            })
+            .await
+            .context("Wallet actor is disconnected")?
+        {
+            Ok(cpfp_tx) => cpfp_tx,
+            Err(e) if e.is::<wallet::NoOutputToSpend>() => {
+                if unconfirmed.kind == TransactionKind::Commit {
+                    // The commit transaction only has an output of ours if the contract gives us
+                    // one; its fee is fixed otherwise. Keep watching it so that it is reported
+                    // again after the next period of patience.
+                    tracing::warn!(%txid, fee_rate = %fee_rate, "Commit transaction is stuck without an output of ours to bump its fee with");
+                    self.unconfirmed.insert(
+                        txid,
+                        Unconfirmed {
+                            since: self.block_height,
+                            fee: Some(fee),
+                            fee_rate: Some(fee_rate),
+                            ..unconfirmed
+                        },
+                    );
+                    return Ok(());
+                }
+
+                tracing::debug!(%txid, kind = %unconfirmed.kind.name(), "Cannot bump fee of transaction without an output of ours");
+                self.unconfirmed.remove(&txid);
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        };
+
+        self.executor
+            .execute(order_id, |cfd| cfd.bump_fee(txid, cpfp_tx.clone(), target))
+            .await?;
+
+        self.unconfirmed.insert(
+            txid,
+            Unconfirmed {
+                since: self.block_height,
+                fee: Some(fee),
+                fee_rate: Some(target),
+                child: Some(cpfp_tx),
+                ..unconfirmed
+            },
+        );
+
         Ok(())
     }
 
+    async fn blocks_to_refund_timelock(&self, order_id: OrderId) -> Result<Option<u32>> {
+        let params = match self.cfds.get(&order_id) {
+            Some(params) => params,
+            None => return Ok(None),
+        };
+
+        let (commit_txid, commit_descriptor) = &params.commit;
+        let refund_timelock = params.refund.2;
+
+        let blocks = match self
+            .client
+            .transaction_height(*commit_txid, &commit_descriptor.script_pubkey())
+            .await?
+        {
+            Some(height) if height > 0 => {
+                Some((height as u32 + refund_timelock).saturating_sub(self.block_height))
+            }
+            _ => None,
+        };
+
+        Ok(blocks)
+    }
+
+    /// Fee paid by `tx`, looking up the outputs it spends.
+    async fn transaction_fee(&self, tx: &Transaction) -> Result<Amount> {
+        let prev_txids = tx
+            .input
+            .iter()
+            .map(|input| input.previous_output.txid)
+            .collect::<Vec<_>>();
+        let prev_txs = self
+            .client
+            .transactions(&prev_txids)
+            .await
+            .context("Failed to get spent transactions")?;
+
+        let input_value = tx
+            .input
+            .iter()
+            .zip(prev_txs.iter())
+            .map(|(input, prev_tx)| {
+                prev_tx
+                    .output
+                    .get(input.previous_output.vout as usize)
+                    .map(|output| output.value)
+                    .context("Spent output does not exist")
+            })
+            .sum::<Result<u64>>()?;
+        let output_value = tx.output.iter().map(|output| output.value).sum::<u64>();
+
+        let fee = input_value
+            .checked_sub(output_value)
+            .context("Transaction spends more than its inputs")?;
+
+        Ok(Amount::from_sat(fee))
+    }
+
     async fn invoke_cfd_command(
         &self,
         id: OrderId,
@@ -510,6 +772,13 @@ impl Actor {
     }
 }
 
+fn fee_rate_of(fee: Amount, tx: &Transaction) -> TxFeeRate {
+    let vbytes = ((tx.weight() + 3) / 4) as u64;
+    let sat_per_vbyte = (fee.as_sat() / vbytes.max(1)).max(1) as u32;
+
+    TxFeeRate::from_sat_per_vbyte(sat_per_vbyte)
+}
+
 #[derive(Debug, Clone, PartialEq, Copy)]
 enum Event {
     LockFinality(OrderId),
@@ -519,7 +788,8 @@ enum Event {
     CetFinality(OrderId),
     RefundTimelockExpired(OrderId),
     RefundFinality(OrderId),
-    RevokedTransactionFound(OrderId),
+    RevokedTransactionFound(OrderId, Txid),
+    PunishFinality(OrderId),
 }
 
 impl MonitorParams {
@@ -546,6 +816,14 @@ impl MonitorParams {
 impl xtra::Actor for Actor {
     type Stop = ();
     async fn started(&mut self, ctx: &mut xtra::Context<Self>) {
+        match self.client.block_height().await {
+            Ok(block_height) => {
+                self.block_height = block_height;
+                self.state = State::new(block_height.into());
+            }
+            Err(e) => tracing::warn!("Failed to fetch latest block height: {e:#}"),
+        }
+
         let this = ctx.address().expect("we are alive");
         tokio_extras::spawn(
             &this,
@@ -569,6 +847,9 @@ impl xtra::Actor for Actor {
                             cet,
                             commit_tx,
                             lock_tx,
+                            refund_tx,
+                            punish_tx,
+                            cpfp_tx,
                             id,
                             params,
                             monitor_lock_finality,
@@ -579,6 +860,7 @@ impl xtra::Actor for Actor {
                             monitor_revoked_commit_transactions,
                             monitor_collaborative_settlement_finality,
                             monitor_cet_finality,
+                            monitor_punish_finality,
                             ..
                         } = match cfd {
                             Ok(cfd) => cfd,
@@ -591,6 +873,7 @@ impl xtra::Actor for Actor {
                             let span = tracing::debug_span!("Broadcast commit TX", order_id = %id);
                             if let Err(e) = this
                                 .send(TryBroadcastTransaction {
+                                    order_id: id,
                                     tx,
                                     kind: TransactionKind::Commit,
                                 })
@@ -605,6 +888,7 @@ impl xtra::Actor for Actor {
                             let span = tracing::debug_span!("Broadcast CET", order_id = %id);
                             if let Err(e) = this
                                 .send(TryBroadcastTransaction {
+                                    order_id: id,
                                     tx,
                                     kind: TransactionKind::Cet,
                                 })
@@ -619,6 +903,7 @@ impl xtra::Actor for Actor {
                             let span = tracing::debug_span!("Broadcast lock TX", order_id = %id);
                             if let Err(e) = this
                                 .send(TryBroadcastTransaction {
+                                    order_id: id,
                                     tx,
                                     kind: TransactionKind::Lock,
                                 })
@@ -629,6 +914,52 @@ impl xtra::Actor for Actor {
                             }
                         }
 
+                        if let Some(tx) = punish_tx {
+                            let span = tracing::debug_span!("Broadcast punish TX", order_id = %id);
+                            if let Err(e) = this
+                                .send(TryBroadcastTransaction {
+                                    order_id: id,
+                                    tx,
+                                    kind: TransactionKind::Punish,
+                                })
+                                .instrument(span)
+                                .await?
+                            {
+                                tracing::warn!("{e:#}")
+                            }
+                        }
+
+                        if let Some(tx) = refund_tx {
+                            let span = tracing::debug_span!("Broadcast refund TX", order_id = %id);
+                            if let Err(e) = this
+                                .send(TryBroadcastTransaction {
+                                    order_id: id,
+                                    tx,
+                                    kind: TransactionKind::Refund,
+                                })
+                                .instrument(span)
+                                .await?
+                            {
+                                tracing::warn!("{e:#}")
+                            }
+                        }
+
+                        // The child can only be accepted once its parent is back in the mempool
+                        if let Some(tx) = cpfp_tx {
+                            let span = tracing::debug_span!("Broadcast CPFP TX", order_id = %id);
+                            if let Err(e) = this
+                                .send(TryBroadcastTransaction {
+                                    order_id: id,
+                                    tx,
+                                    kind: TransactionKind::Cpfp,
+                                })
+                                .instrument(span)
+                                .await?
+                            {
+                                tracing::warn!("{e:#}")
+                            }
+                        }
+
                         let params = match params {
                             None => continue,
                             Some(params) => params,
@@ -645,6 +976,7 @@ impl xtra::Actor for Actor {
                             monitor_revoked_commit_transactions,
                             monitor_collaborative_settlement_finality,
                             monitor_cet_finality,
+                            monitor_punish_finality,
                         })
                         .await?;
                     }
@@ -688,15 +1020,15 @@ impl Actor {
         );
     }
 
-    async fn handle_try_broadcast_transaction(&self, msg: TryBroadcastTransaction) -> Result<()> {
-        let TryBroadcastTransaction { tx, kind } = msg;
-
-        let result = self.client.transaction_broadcast(&tx);
+    async fn handle_try_broadcast_transaction(
+        &mut self,
+        msg: TryBroadcastTransaction,
+    ) -> Result<()> {
+        let TryBroadcastTransaction { order_id, tx, kind } = msg;
 
-        if let Err(electrum_client::Error::Protocol(ref value)) = result {
-            let rpc_error = parse_rpc_protocol_error(value)
-                .with_context(|| format!("Failed to parse electrum error response '{value:?}'"))?;
+        let result = self.client.broadcast(&tx).await;
 
+        if let Ok(Err(ref rpc_error)) = result {
             if rpc_error.code == i64::from(RpcErrorCode::RpcVerifyAlreadyInChain) {
                 let txid = tx.txid();
                 tracing::trace!(
@@ -711,7 +1043,7 @@ impl Actor {
             if rpc_error.code == i64::from(RpcErrorCode::RpcVerifyError)
                 && rpc_error.message == "bad-txns-inputs-missingorspent"
             {
-                if let Ok(tx) = self.client.transaction_get(&tx.txid()) {
+                if let Ok(tx) = self.client.transaction(tx.txid()).await {
                     let txid = tx.txid();
                     tracing::trace!(
                         %txid, kind = %kind.name(), "Attempted to broadcast transaction that was already on-chain",
@@ -722,7 +1054,7 @@ impl Actor {
         }
         let txid = tx.txid();
 
-        result.with_context(|| {
+        result.and_then(|result| Ok(result?)).with_context(|| {
             let tx_hex = serialize_hex(&tx);
 
             format!("Failed to broadcast transaction. Txid: {txid}. Kind: {}. Raw transaction: {tx_hex}", kind.name())
@@ -734,6 +1066,27 @@ impl Actor {
             .with(&HashMap::from([(KIND_LABEL, kind.name())]))
             .inc();
 
+        if kind.can_bump_fee() {
+            let block_height = self.block_height;
+
+            self.unconfirmed.entry(txid).or_insert(Unconfirmed {
+                order_id,
+                tx,
+                kind,
+                since: block_height,
+                fee: None,
+                fee_rate: None,
+                child: None,
+            });
+        } else if kind == TransactionKind::Cpfp {
+            // Remember children published on startup, so that the next bump replaces them
+            for input in tx.input.iter() {
+                if let Some(parent) = self.unconfirmed.get_mut(&input.previous_output.txid) {
+                    parent.child = Some(tx.clone());
+                }
+            }
+        }
+
         Ok(())
     }
 
@@ -749,6 +1102,7 @@ impl Actor {
             monitor_revoked_commit_transactions,
             monitor_collaborative_settlement_finality,
             monitor_cet_finality,
+            monitor_punish_finality,
         } = msg;
 
         self.cfds.insert(id, params.clone());
@@ -784,6 +1138,10 @@ impl Actor {
         if let Some(params) = monitor_cet_finality {
             self.monitor_cet_finality(params, id);
         }
+
+        if let Some(params) = monitor_punish_finality {
+            self.monitor_punish_finality(params, id);
+        }
     }
 
     async fn handle_monitor_cet_finality(&mut self, msg: MonitorCetFinality) -> Result<()> {
@@ -800,6 +1158,15 @@ impl Actor {
 
         Ok(())
     }
+
+    async fn handle_monitor_punish_finality(&mut self, msg: MonitorPunishFinality) -> Result<()> {
+        let params = punish_txid_and_script(&msg.punish_tx)
+            .context("Failed to monitor punish transaction using script pubkey")?;
+
+        self.monitor_punish_finality(params, msg.order_id);
+
+        Ok(())
+    }
 }
 
 // TODO: Re-model this by tearing apart `MonitorParams`.
@@ -818,6 +1185,7 @@ struct ReinitMonitoring {
     // Ideally, all of the above would be like this.
     monitor_collaborative_settlement_finality: Option<(Txid, Script)>,
     monitor_cet_finality: Option<(Txid, Script)>,
+    monitor_punish_finality: Option<(Txid, Script)>,
 }
 
 #[xtra_productivity]
@@ -827,6 +1195,12 @@ impl Actor {
             tracing::warn!("Sync failed: {:#}", e);
         }
     }
+
+    async fn handle(&mut self, msg: IsSpent) -> Result<bool> {
+        let IsSpent { outpoint, script } = msg;
+
+        self.client.is_spent(outpoint, &script).await
+    }
 }
 
 const KIND_LABEL: &str = "kind";
@@ -9,6 +9,7 @@ use model::olivia::next_announcement_after;
 use model::olivia::BitMexPriceEventId;
 use model::CfdEvent;
 use model::EventKind;
+use model::TradingPair;
 use sqlite_db;
 use std::collections::HashMap;
 use std::collections::HashSet;
@@ -18,6 +19,20 @@ use tracing::Instrument;
 use xtra_productivity::xtra_productivity;
 use xtras::SendInterval;
 
+const KIND_LABEL: &str = "kind";
+const KIND_ANNOUNCEMENT: &str = "announcement";
+const KIND_ATTESTATION: &str = "attestation";
+
+static REJECTED_ORACLE_RESPONSES_COUNTER: conquer_once::Lazy<prometheus::IntCounterVec> =
+    conquer_once::Lazy::new(|| {
+        prometheus::register_int_counter_vec!(
+            "oracle_responses_rejected_total",
+            "The number of oracle responses rejected because they failed verification.",
+            &[KIND_LABEL]
+        )
+        .unwrap()
+    });
+
 /// Timout to be passed into the reqwest client for doing http requests against the oracle.
 ///
 /// 10 seconds was chosen arbitrarily. It should be plenty to fetch from the oracle and does not let
@@ -32,9 +47,13 @@ const SYNC_ANNOUNCEMENTS_INTERVAL: core::time::Duration = std::time::Duration::f
 /// We want to sync attestations fast but don't spam our internal actor. Hence, we chose 30 seconds.
 const SYNC_ATTESTATIONS_INTERVAL: core::time::Duration = std::time::Duration::from_secs(30);
 
+/// Identifies an event of a specific oracle.
+type OracleEvent = (XOnlyPublicKey, BitMexPriceEventId);
+
 pub struct Actor {
-    announcements: HashMap<BitMexPriceEventId, (OffsetDateTime, Vec<XOnlyPublicKey>)>,
-    pending_attestations: HashSet<BitMexPriceEventId>,
+    oracles: Vec<olivia::Oracle>,
+    announcements: HashMap<OracleEvent, (OffsetDateTime, Vec<XOnlyPublicKey>)>,
+    pending_attestations: HashSet<OracleEvent>,
     executor: command::Executor,
     db: sqlite_db::Connection,
     client: reqwest::Client,
@@ -67,21 +86,25 @@ pub struct SyncAttestations;
 
 #[derive(Clone, Copy)]
 pub struct MonitorAttestation {
+    pub oracle_pk: XOnlyPublicKey,
     pub event_id: BitMexPriceEventId,
 }
 
 #[derive(Clone)]
 struct MonitorAttestations {
-    pub event_ids: Vec<BitMexPriceEventId>,
+    pub events: Vec<OracleEvent>,
 }
 
 /// Message used to request the `Announcement` from the
 /// `oracle::Actor`'s local state.
 ///
-/// The `Announcement` corresponds to the [`BitMexPriceEventId`] included in
-/// the message.
+/// The `Announcement` corresponds to the [`BitMexPriceEventId`] of the oracle identified by
+/// `oracle_pk`.
 #[derive(Clone, Copy)]
-pub struct GetAnnouncement(pub BitMexPriceEventId);
+pub struct GetAnnouncement {
+    pub oracle_pk: XOnlyPublicKey,
+    pub event_id: BitMexPriceEventId,
+}
 
 #[derive(Debug, Clone)]
 pub struct Attestation(olivia::Attestation);
@@ -89,6 +112,7 @@ pub struct Attestation(olivia::Attestation);
 /// A module-private message to allow parallelization of fetching announcements.
 #[derive(Debug)]
 struct NewAnnouncementFetched {
+    oracle_pk: XOnlyPublicKey,
     id: BitMexPriceEventId,
     expected_outcome_time: OffsetDateTime,
     nonce_pks: Vec<XOnlyPublicKey>,
@@ -97,13 +121,16 @@ struct NewAnnouncementFetched {
 /// A module-private message to allow parallelization of fetching attestations.
 #[derive(Debug)]
 struct NewAttestationFetched {
+    oracle_pk: XOnlyPublicKey,
     id: BitMexPriceEventId,
     attestation: Attestation,
+    /// The nonces announced alongside the attestation.
+    nonce_pks: Vec<XOnlyPublicKey>,
 }
 
 #[derive(Default, Clone)]
 struct Cfd {
-    pending_attestation: Option<BitMexPriceEventId>,
+    pending_attestation: Option<OracleEvent>,
     version: u32,
 }
 
@@ -111,11 +138,15 @@ impl Cfd {
     fn apply(mut self, event: CfdEvent) -> Self {
         self.version += 1;
 
-        let settlement_event_id = match event.event {
+        let settlement_event = match event.event {
             EventKind::ContractSetupCompleted { dlc: None, .. } => return self,
-            EventKind::ContractSetupCompleted { dlc: Some(dlc), .. } => dlc.settlement_event_id,
+            EventKind::ContractSetupCompleted { dlc: Some(dlc), .. } => {
+                (dlc.oracle_pk, dlc.settlement_event_id)
+            }
             EventKind::RolloverCompleted { dlc: None, .. } => return self,
-            EventKind::RolloverCompleted { dlc: Some(dlc), .. } => dlc.settlement_event_id,
+            EventKind::RolloverCompleted { dlc: Some(dlc), .. } => {
+                (dlc.oracle_pk, dlc.settlement_event_id)
+            }
             // TODO: There might be a few cases where we do not need to monitor the attestation,
             // e.g. when we already agreed to collab. settle. Ignoring it for now
             // because I don't want to think about it and it doesn't cause much harm to do the
@@ -126,7 +157,7 @@ impl Cfd {
         // we can comfortably overwrite what was there because events are processed in order, thus
         // old attestations don't matter.
         Self {
-            pending_attestation: Some(settlement_event_id),
+            pending_attestation: Some(settlement_event),
             ..self
         }
     }
@@ -149,8 +180,13 @@ impl sqlite_db::CfdAggregate for Cfd {
 }
 
 impl Actor {
-    pub fn new(db: sqlite_db::Connection, executor: command::Executor) -> Self {
+    pub fn new(
+        oracles: Vec<olivia::Oracle>,
+        db: sqlite_db::Connection,
+        executor: command::Executor,
+    ) -> Self {
         Self {
+            oracles,
             announcements: HashMap::new(),
             pending_attestations: HashSet::new(),
             executor,
@@ -160,73 +196,117 @@ impl Actor {
     }
 
     fn ensure_having_announcements(&mut self, ctx: &mut xtra::Context<Self>) {
-        for hour in 1..ANNOUNCEMENT_LOOKAHEAD.whole_hours() {
-            let event_id =
-                next_announcement_after(OffsetDateTime::now_utc() + Duration::hours(hour));
+        let now = OffsetDateTime::now_utc();
+        let event_ids = TradingPair::ALL
+            .into_iter()
+            .flat_map(|trading_pair| {
+                (1..ANNOUNCEMENT_LOOKAHEAD.whole_hours()).map(move |hour| {
+                    next_announcement_after(trading_pair, now + Duration::hours(hour))
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for oracle in self.oracles.iter() {
+            for event_id in event_ids.iter().copied() {
+                if self
+                    .announcements
+                    .contains_key(&(oracle.public_key, event_id))
+                {
+                    continue;
+                }
 
-            if self.announcements.get(&event_id).is_some() {
-                continue;
+                self.fetch_announcement(ctx, oracle.clone(), event_id);
             }
-            let this = ctx.address().expect("self to be alive");
-            let client = self.client.clone();
+        }
+    }
 
-            let this_clone = this.clone();
-            let task = async move {
-                let url = event_id.to_olivia_url();
+    fn fetch_announcement(
+        &self,
+        ctx: &mut xtra::Context<Self>,
+        oracle: olivia::Oracle,
+        event_id: BitMexPriceEventId,
+    ) {
+        let this = ctx.address().expect("self to be alive");
+        let client = self.client.clone();
 
-                tracing::debug!(event_id = %event_id, "Fetching announcement");
+        let this_clone = this.clone();
+        let task = async move {
+            let url = oracle.event_url(event_id);
 
-                let response = client
-                    .get(url.clone())
-                    .send()
-                    .await
-                    .with_context(|| format!("Failed to GET {url}"))?;
+            tracing::debug!(event_id = %event_id, oracle = %oracle.url, "Fetching announcement");
 
-                let code = response.status();
-                if !code.is_success() {
-                    anyhow::bail!("GET {url} responded with {code}");
-                }
+            let response = client
+                .get(url.clone())
+                .send()
+                .await
+                .with_context(|| format!("Failed to GET {url}"))?;
 
-                let announcement = response
-                    .json::<olivia::Announcement>()
-                    .await
-                    .context("Failed to deserialize as Announcement")?;
+            let code = response.status();
+            if !code.is_success() {
+                anyhow::bail!("GET {url} responded with {code}");
+            }
 
-                this.send(NewAnnouncementFetched {
-                    id: event_id,
-                    nonce_pks: announcement.nonce_pks,
-                    expected_outcome_time: announcement.expected_outcome_time,
-                })
-                .await?;
+            let announcement = response
+                .json::<olivia::Announcement>()
+                .await
+                .context("Failed to deserialize as Announcement")?;
+
+            if announcement.id != event_id {
+                reject_response(KIND_ANNOUNCEMENT);
+                anyhow::bail!(
+                    "Requested announcement for {event_id} but got {}",
+                    announcement.id
+                );
+            }
 
-                Ok(())
-            };
+            this.send(NewAnnouncementFetched {
+                oracle_pk: oracle.public_key,
+                id: event_id,
+                nonce_pks: announcement.nonce_pks,
+                expected_outcome_time: announcement.expected_outcome_time,
+            })
+            .await?;
 
-            tokio_extras::spawn_fallible(
-                &this_clone,
-                task.instrument(tracing::debug_span!("Fetch announcement")),
-                |e| async move {
-                    tracing::debug!("Failed to fetch announcement: {:#}", e);
-                },
-            );
-        }
+            Ok(())
+        };
+
+        tokio_extras::spawn_fallible(
+            &this_clone,
+            task.instrument(tracing::debug_span!("Fetch announcement")),
+            |e| async move {
+                tracing::debug!("Failed to fetch announcement: {:#}", e);
+            },
+        );
     }
 
     fn update_pending_attestations(&mut self, ctx: &mut xtra::Context<Self>) {
-        for event_id in self.pending_attestations.iter().copied() {
+        for (oracle_pk, event_id) in self.pending_attestations.iter().copied() {
             if !event_id.has_likely_occurred() {
                 tracing::trace!("Skipping {event_id} because it likely hasn't occurred yet");
 
                 continue;
             }
 
+            let oracle = match self
+                .oracles
+                .iter()
+                .find(|oracle| oracle.public_key == oracle_pk)
+            {
+                Some(oracle) => oracle.clone(),
+                None => {
+                    tracing::warn!(%oracle_pk, "Unable to fetch attestation for {event_id} from unknown oracle");
+
+                    continue;
+                }
+            };
+
             let this = ctx.address().expect("self to be alive");
             let client = self.client.clone();
 
             tokio_extras::spawn_fallible(
                 &this.clone(),
                 async move {
-                    let url = event_id.to_olivia_url();
+                    let url = oracle.event_url(event_id);
 
                     tracing::debug!("Fetching attestation for {event_id}");
 
@@ -242,14 +322,28 @@ impl Actor {
                         anyhow::bail!("GET {url} responded with {code}");
                     }
 
-                    let attestation = response
-                        .json::<olivia::Attestation>()
+                    let body = response
+                        .text()
                         .await
+                        .with_context(|| format!("Failed to read response of {url}"))?;
+                    let attestation = serde_json::from_str::<olivia::Attestation>(&body)
                         .context("Failed to deserialize as Attestation")?;
+                    let announcement = serde_json::from_str::<olivia::Announcement>(&body)
+                        .context("Failed to deserialize as Announcement")?;
+
+                    if attestation.id != event_id {
+                        reject_response(KIND_ATTESTATION);
+                        anyhow::bail!(
+                            "Requested attestation for {event_id} but got {}",
+                            attestation.id
+                        );
+                    }
 
                     this.send(NewAttestationFetched {
+                        oracle_pk,
                         id: event_id,
                         attestation: Attestation(attestation),
+                        nonce_pks: announcement.nonce_pks,
                     })
                     .await??;
 
@@ -262,8 +356,8 @@ impl Actor {
         }
     }
 
-    fn add_pending_attestation(&mut self, event_id: BitMexPriceEventId) {
-        if !self.pending_attestations.insert(event_id) {
+    fn add_pending_attestation(&mut self, oracle_pk: XOnlyPublicKey, event_id: BitMexPriceEventId) {
+        if !self.pending_attestations.insert((oracle_pk, event_id)) {
             tracing::trace!("Attestation for {event_id} already being monitored");
         }
     }
@@ -272,12 +366,12 @@ impl Actor {
 #[xtra_productivity]
 impl Actor {
     fn handle_monitor_attestation(&mut self, msg: MonitorAttestation) {
-        self.add_pending_attestation(msg.event_id)
+        self.add_pending_attestation(msg.oracle_pk, msg.event_id)
     }
 
     fn handle_monitor_attestations(&mut self, msg: MonitorAttestations) {
-        for id in msg.event_ids.into_iter() {
-            self.add_pending_attestation(id);
+        for (oracle_pk, event_id) in msg.events.into_iter() {
+            self.add_pending_attestation(oracle_pk, event_id);
         }
     }
 
@@ -286,18 +380,20 @@ impl Actor {
         msg: GetAnnouncement,
     ) -> Result<olivia::Announcement, NoAnnouncement> {
         self.announcements
-            .get_key_value(&msg.0)
-            .map(|(id, (time, nonce_pks))| olivia::Announcement {
-                id: *id,
+            .get(&(msg.oracle_pk, msg.event_id))
+            .map(|(time, nonce_pks)| olivia::Announcement {
+                id: msg.event_id,
                 expected_outcome_time: *time,
                 nonce_pks: nonce_pks.clone(),
             })
-            .ok_or(NoAnnouncement(msg.0))
+            .ok_or(NoAnnouncement(msg.event_id))
     }
 
     fn handle_new_announcement_fetched(&mut self, msg: NewAnnouncementFetched) {
-        self.announcements
-            .insert(msg.id, (msg.expected_outcome_time, msg.nonce_pks));
+        self.announcements.insert(
+            (msg.oracle_pk, msg.id),
+            (msg.expected_outcome_time, msg.nonce_pks),
+        );
     }
 
     fn handle_sync_announcements(&mut self, _: SyncAnnouncements, ctx: &mut xtra::Context<Self>) {
@@ -309,26 +405,59 @@ impl Actor {
     }
 
     async fn handle_new_attestation_fetched(&mut self, msg: NewAttestationFetched) -> Result<()> {
-        let NewAttestationFetched { id, attestation } = msg;
+        let NewAttestationFetched {
+            oracle_pk,
+            id,
+            attestation,
+            nonce_pks,
+        } = msg;
+
+        // Prefer the nonces we fetched with the announcement earlier, those are what our CETs were
+        // built with. The oracle must not be able to swap them out together with the attestation.
+        let nonce_pks = match self.announcements.get(&(oracle_pk, id)) {
+            Some((_, known_nonce_pks)) if known_nonce_pks != &nonce_pks => {
+                reject_response(KIND_ATTESTATION);
+                tracing::error!(
+                    "Nonces for {id} differ from the ones announced before, ignoring attestation"
+                );
+
+                return Ok(());
+            }
+            Some((_, known_nonce_pks)) => known_nonce_pks.clone(),
+            None => nonce_pks,
+        };
+
+        if let Err(e) = attestation.0.verify(&oracle_pk, &nonce_pks) {
+            reject_response(KIND_ATTESTATION);
+            tracing::error!("Ignoring invalid attestation for {id}: {e:#}");
+
+            return Ok(());
+        }
 
         tracing::info!("Fetched new attestation for {id}");
 
         for id in self.db.load_open_cfd_ids().await? {
             if let Err(err) = self
                 .executor
-                .execute(id, |cfd| cfd.decrypt_cet(&attestation.0))
+                .execute(id, |cfd| cfd.decrypt_cet(&oracle_pk, &attestation.0))
                 .await
             {
                 tracing::warn!(order_id = %id, "Failed to decrypt CET using attestation: {err:#}")
             }
         }
 
-        self.pending_attestations.remove(&id);
+        self.pending_attestations.remove(&(oracle_pk, id));
 
         Ok(())
     }
 }
 
+fn reject_response(kind: &str) {
+    REJECTED_ORACLE_RESPONSES_COUNTER
+        .with(&HashMap::from([(KIND_LABEL, kind)]))
+        .inc();
+}
+
 #[derive(Debug, Clone, thiserror::Error, Copy)]
 #[error("Announcement {0} not found")]
 pub struct NoAnnouncement(pub BitMexPriceEventId);
@@ -371,7 +500,7 @@ impl xtra::Actor for Actor {
 
                 let _: Result<(), xtra::Error> = this
                     .send(MonitorAttestations {
-                        event_ids: pending_attestations,
+                        events: pending_attestations,
                     })
                     .instrument(span)
                     .await;
@@ -1,4 +1,3 @@
-use crate::position_metrics::metrics::BTCUSD_LABEL;
 use anyhow::Context;
 use anyhow::Result;
 use async_trait::async_trait;
@@ -11,11 +10,11 @@ use model::EventKind;
 use model::FailedCfd;
 use model::FailedKind;
 use model::Identity;
-use model::Leverage;
 use model::OrderId;
 use model::Position;
 use model::Role;
 use model::Settlement;
+use model::TradingPair;
 use model::Usd;
 use rust_decimal::Decimal;
 use sqlite_db;
@@ -72,10 +71,11 @@ impl Actor {
         }
 
         self.state.cfds = Some(cfds);
-        metrics::update_position_metrics(
-            self.state.cfds.clone().expect("We've initialized it above"),
-            // TODO: once we have multiple trading pairs, this needs to come from the positions
-            BTCUSD_LABEL,
+        metrics::update_position_metrics_per_trading_pair(
+            self.state
+                .cfds
+                .as_ref()
+                .expect("We've initialized it above"),
         );
     }
 
@@ -85,13 +85,11 @@ impl Actor {
             return;
         };
 
-        metrics::update_position_metrics(
+        metrics::update_position_metrics_per_trading_pair(
             self.state
                 .cfds
-                .clone()
+                .as_ref()
                 .expect("updating metrics failed. Internal list has not been initialized yet"),
-            // TODO: once we have multiple trading pairs, this needs to come from the positions
-            BTCUSD_LABEL,
         );
     }
 }
@@ -126,6 +124,7 @@ pub struct CfdChanged(pub OrderId);
 #[derive(Clone, Copy)]
 pub struct Cfd {
     id: OrderId,
+    trading_pair: TradingPair,
     position: Position,
     quantity_usd: Usd,
     margin: Amount,
@@ -153,16 +152,26 @@ impl sqlite_db::CfdAggregate for Cfd {
 
     fn new(_: Self::CtorArgs, cfd: sqlite_db::Cfd) -> Self {
         let (our_leverage, counterparty_leverage) = match cfd.role {
-            Role::Maker => (Leverage::ONE, cfd.taker_leverage),
-            Role::Taker => (cfd.taker_leverage, Leverage::ONE),
+            Role::Maker => (cfd.maker_leverage, cfd.taker_leverage),
+            Role::Taker => (cfd.taker_leverage, cfd.maker_leverage),
         };
 
-        let margin = calculate_margin(cfd.initial_price, cfd.quantity_usd, our_leverage);
-        let margin_counterparty =
-            calculate_margin(cfd.initial_price, cfd.quantity_usd, counterparty_leverage);
+        let margin = calculate_margin(
+            cfd.trading_pair,
+            cfd.initial_price,
+            cfd.quantity_usd,
+            our_leverage,
+        );
+        let margin_counterparty = calculate_margin(
+            cfd.trading_pair,
+            cfd.initial_price,
+            cfd.quantity_usd,
+            counterparty_leverage,
+        );
 
         Self {
             id: cfd.id,
+            trading_pair: cfd.trading_pair,
             position: cfd.position,
             quantity_usd: cfd.quantity_usd,
             margin,
@@ -222,6 +231,14 @@ impl Cfd {
                 state: AggregatedState::Closed,
                 ..self
             },
+            FeeBumped { .. } => Self {
+                // paying more fees does not change the position
+                ..self
+            },
+            PolicyDecided { .. } => Self {
+                // the decision is carried out through the events of the protocol it concerns
+                ..self
+            },
             ManualCommit { .. } | CommitConfirmed => Self {
                 // we don't know yet if the position will be closed immediately (e.g. through
                 // punishing) or a bit later after the oracle has attested to the price
@@ -235,8 +252,8 @@ impl Cfd {
                 state: AggregatedState::Refunded,
                 ..self
             },
-            RevokeConfirmed => Self {
-                // the other party was punished, we are done here!
+            RevokeConfirmed | PunishPublished { .. } | PunishConfirmed => Self {
+                // the other party published a revoked commit transaction, we are done here!
                 state: AggregatedState::Closed,
                 ..self
             },
@@ -276,12 +293,14 @@ impl sqlite_db::ClosedCfdAggregate for Cfd {
     fn new_closed(_: Self::CtorArgs, closed_cfd: ClosedCfd) -> Self {
         let ClosedCfd {
             id,
+            trading_pair,
             position,
             n_contracts,
             settlement,
             counterparty_network_identity,
             role,
             taker_leverage,
+            maker_leverage,
             initial_price,
             ..
         } = closed_cfd;
@@ -289,21 +308,28 @@ impl sqlite_db::ClosedCfdAggregate for Cfd {
         let quantity_usd = Usd::new(Decimal::from(u64::from(n_contracts)));
 
         let state = match settlement {
-            Settlement::Collaborative { .. } | Settlement::Cet { .. } => AggregatedState::Closed,
+            Settlement::Collaborative { .. }
+            | Settlement::Cet { .. }
+            | Settlement::Punish { .. } => AggregatedState::Closed,
             Settlement::Refund { .. } => AggregatedState::Refunded,
         };
 
         let (our_leverage, counterparty_leverage) = match role {
-            Role::Maker => (Leverage::ONE, taker_leverage),
-            Role::Taker => (taker_leverage, Leverage::ONE),
+            Role::Maker => (maker_leverage, taker_leverage),
+            Role::Taker => (taker_leverage, maker_leverage),
         };
 
-        let margin = calculate_margin(initial_price, quantity_usd, our_leverage);
-        let margin_counterparty =
-            calculate_margin(initial_price, quantity_usd, counterparty_leverage);
+        let margin = calculate_margin(trading_pair, initial_price, quantity_usd, our_leverage);
+        let margin_counterparty = calculate_margin(
+            trading_pair,
+            initial_price,
+            quantity_usd,
+            counterparty_leverage,
+        );
 
         Self {
             id,
+            trading_pair,
             position,
             quantity_usd,
             margin,
@@ -319,12 +345,14 @@ impl sqlite_db::FailedCfdAggregate for Cfd {
     fn new_failed(_: Self::CtorArgs, cfd: FailedCfd) -> Self {
         let FailedCfd {
             id,
+            trading_pair,
             position,
             n_contracts,
             kind,
             counterparty_network_identity,
             role,
             taker_leverage,
+            maker_leverage,
             initial_price,
             ..
         } = cfd;
@@ -337,16 +365,21 @@ impl sqlite_db::FailedCfdAggregate for Cfd {
         };
 
         let (our_leverage, counterparty_leverage) = match role {
-            Role::Maker => (Leverage::ONE, taker_leverage),
-            Role::Taker => (taker_leverage, Leverage::ONE),
+            Role::Maker => (maker_leverage, taker_leverage),
+            Role::Taker => (taker_leverage, maker_leverage),
         };
 
-        let margin = calculate_margin(initial_price, quantity_usd, our_leverage);
-        let margin_counterparty =
-            calculate_margin(initial_price, quantity_usd, counterparty_leverage);
+        let margin = calculate_margin(trading_pair, initial_price, quantity_usd, our_leverage);
+        let margin_counterparty = calculate_margin(
+            trading_pair,
+            initial_price,
+            quantity_usd,
+            counterparty_leverage,
+        );
 
         Self {
             id,
+            trading_pair,
             position,
             quantity_usd,
             margin,
@@ -365,6 +398,7 @@ mod metrics {
     use itertools::Itertools;
     use model::OrderId;
     use model::Position;
+    use model::TradingPair;
     use model::Usd;
     use rust_decimal::prelude::ToPrimitive;
     use std::collections::HashMap;
@@ -383,7 +417,6 @@ mod metrics {
     const STATUS_REFUNDED_LABEL: &str = "refunded";
 
     const SYMBOL_LABEL: &str = "symbol";
-    pub const BTCUSD_LABEL: &str = "BTCUSD";
 
     static POSITION_QUANTITY_GAUGE: conquer_once::Lazy<prometheus::GaugeVec> =
         conquer_once::Lazy::new(|| {
@@ -435,6 +468,19 @@ mod metrics {
             .unwrap()
         });
 
+    /// Update the position metrics of every trading pair, labelled by its symbol.
+    pub fn update_position_metrics_per_trading_pair(cfds: &HashMap<OrderId, Cfd>) {
+        for trading_pair in TradingPair::ALL {
+            let cfds = cfds
+                .iter()
+                .filter(|(_, cfd)| cfd.trading_pair == trading_pair)
+                .map(|(id, cfd)| (*id, *cfd))
+                .collect();
+
+            update_position_metrics(cfds, trading_pair.symbol());
+        }
+    }
+
     pub fn update_position_metrics(cfds: HashMap<OrderId, Cfd>, symbol: &str) {
         let cfds = cfds.into_iter().map(|(_, cfd)| cfd).collect::<Vec<_>>();
 
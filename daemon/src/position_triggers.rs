@@ -0,0 +1,242 @@
+use crate::projection;
+use crate::taker_cfd;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use model::market_closing_price;
+use model::OrderId;
+use model::PositionTriggers;
+use model::Price;
+use model::Role;
+use sqlite_db::position_triggers::PositionTriggersState;
+use std::time::Duration;
+use time::ext::NumericalDuration;
+use xtra::prelude::MessageChannel;
+use xtra_bitmex_price_feed::LatestQuotes;
+use xtra_bitmex_price_feed::Quotes;
+use xtra_bitmex_price_feed::QUOTE_INTERVAL_MINUTES;
+use xtra_productivity::xtra_productivity;
+use xtras::SendInterval;
+
+/// How often the stop-loss and take-profit triggers are evaluated against the latest quote
+const CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Closes positions once their stop-loss or take-profit price is reached
+///
+/// A triggered position is closed by proposing a collaborative settlement to the maker. If the
+/// maker does not settle, the failure is recorded against the triggers so that it can be reported
+/// to the user.
+pub struct Actor {
+    db: sqlite_db::Connection,
+    latest_quotes: MessageChannel<LatestQuotes, Quotes>,
+    propose_settlement: MessageChannel<taker_cfd::ProposeSettlement, Result<()>>,
+    projection: MessageChannel<projection::CfdChanged, ()>,
+}
+
+impl Actor {
+    pub fn new(
+        db: sqlite_db::Connection,
+        latest_quotes: MessageChannel<LatestQuotes, Quotes>,
+        propose_settlement: MessageChannel<taker_cfd::ProposeSettlement, Result<()>>,
+        projection: MessageChannel<projection::CfdChanged, ()>,
+    ) -> Self {
+        Self {
+            db,
+            latest_quotes,
+            propose_settlement,
+            projection,
+        }
+    }
+}
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, msg: SetTriggers) -> Result<()> {
+        let SetTriggers { order_id, triggers } = msg;
+
+        let cfd = self.db.load_open_cfd::<model::Cfd>(order_id, ()).await?;
+
+        if triggers.is_empty() {
+            self.db.delete_position_triggers(order_id).await?;
+        } else {
+            triggers.validate(cfd.position())?;
+            self.db.upsert_position_triggers(order_id, triggers).await?;
+        }
+
+        self.notify_projection(order_id).await;
+
+        Ok(())
+    }
+
+    async fn handle(&mut self, _: CheckTriggers) {
+        tracing::trace!("Checking position triggers");
+
+        // Invoked periodically by `send_interval()`, which does not handle errors
+        if let Err(e) = self.check_triggers().await {
+            tracing::error!("Failed to check position triggers: {e:#}");
+        }
+    }
+}
+
+impl Actor {
+    async fn check_triggers(&mut self) -> Result<()> {
+        let all_position_triggers = self.db.load_all_position_triggers().await?;
+
+        if all_position_triggers.is_empty() {
+            return Ok(());
+        }
+
+        let quotes = self
+            .latest_quotes
+            .send(LatestQuotes)
+            .await
+            .context("Price feed not available")?;
+
+        for position_triggers in all_position_triggers {
+            let order_id = position_triggers.order_id;
+
+            if let Err(e) = self.check_position(position_triggers, &quotes).await {
+                tracing::warn!(%order_id, "Failed to check position triggers: {e:#}");
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn check_position(
+        &mut self,
+        PositionTriggersState {
+            order_id,
+            triggers,
+            fired,
+            failure,
+        }: PositionTriggersState,
+        quotes: &Quotes,
+    ) -> Result<()> {
+        let cfd = match self.db.load_open_cfd::<model::Cfd>(order_id, ()).await {
+            Ok(cfd) => cfd,
+            Err(sqlite_db::Error::OpenCfdNotFound) => {
+                tracing::debug!(%order_id, "Removing triggers of position that is no longer open");
+                self.db.delete_position_triggers(order_id).await?;
+                return Ok(());
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        if failure.is_some() || !cfd.can_settle_collaboratively() {
+            return Ok(());
+        }
+
+        if let Some(trigger) = fired {
+            // Proposing the settlement moves the CFD into collaborative settlement, so if it is
+            // not settling anymore the maker rejected the proposal or the protocol failed
+            if !cfd.is_in_collaborative_settlement() {
+                self.fail(
+                    order_id,
+                    format!("Position was not closed after {trigger} was triggered"),
+                )
+                .await?;
+            }
+
+            return Ok(());
+        }
+
+        let trading_pair = cfd.trading_pair();
+        let quote = quotes
+            .get(trading_pair.bitmex_instrument())
+            .with_context(|| format!("No quote available for {trading_pair}"))?;
+
+        if quote.is_older_than(QUOTE_INTERVAL_MINUTES.minutes() * 2) {
+            tracing::debug!(%order_id, "Not evaluating position triggers against outdated quote");
+            return Ok(());
+        }
+
+        let bid = Price::new(quote.bid())?;
+        let ask = Price::new(quote.ask())?;
+        let closing_price = market_closing_price(bid, ask, Role::Taker, cfd.position());
+
+        let trigger = match triggers.triggered(cfd.position(), closing_price) {
+            Some(trigger) => trigger,
+            None => return Ok(()),
+        };
+
+        tracing::info!(%order_id, %trigger, %closing_price, "Position trigger fired, closing position");
+
+        self.db
+            .mark_position_trigger_fired(order_id, trigger)
+            .await?;
+
+        let quote_timestamp = quote
+            .timestamp
+            .format(&time::format_description::well_known::Rfc3339)
+            .context("Failed to format timestamp")?;
+
+        let proposal = self
+            .propose_settlement
+            .send(taker_cfd::ProposeSettlement {
+                order_id,
+                bid,
+                ask,
+                quote_timestamp,
+            })
+            .await
+            .context("CFD actor not available");
+
+        match proposal.and_then(|result| result) {
+            Ok(()) => self.notify_projection(order_id).await,
+            Err(e) => {
+                self.fail(
+                    order_id,
+                    format!("Failed to close position after {trigger} was triggered: {e:#}"),
+                )
+                .await?
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn fail(&mut self, order_id: OrderId, reason: String) -> Result<()> {
+        tracing::warn!(%order_id, %reason, "Position trigger failed");
+
+        self.db
+            .mark_position_trigger_failed(order_id, reason)
+            .await?;
+        self.notify_projection(order_id).await;
+
+        Ok(())
+    }
+
+    async fn notify_projection(&mut self, order_id: OrderId) {
+        if let Err(e) = self.projection.send(projection::CfdChanged(order_id)).await {
+            tracing::warn!(%order_id, "Failed to notify projection: {e:#}");
+        }
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn started(&mut self, ctx: &mut xtra::Context<Self>) {
+        let this = ctx.address().expect("we are alive");
+        tokio_extras::spawn(
+            &this.clone(),
+            this.send_interval(CHECK_INTERVAL, || CheckTriggers, xtras::IncludeSpan::Always),
+        );
+    }
+
+    async fn stopped(self) -> Self::Stop {}
+}
+
+/// Set the stop-loss and take-profit of an open position
+///
+/// Replaces previously set triggers, an empty set of triggers removes them.
+pub struct SetTriggers {
+    pub order_id: OrderId,
+    pub triggers: PositionTriggers,
+}
+
+/// Message sent to ourselves at an interval to evaluate the triggers of all open positions.
+#[derive(Clone, Copy)]
+pub struct CheckTriggers;
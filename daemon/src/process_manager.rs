@@ -1,6 +1,8 @@
+use crate::backup;
 use crate::monitor::MonitorCetFinality;
 use crate::monitor::MonitorCollaborativeSettlement;
 use crate::monitor::MonitorParams;
+use crate::monitor::MonitorPunishFinality;
 use crate::monitor::StartMonitoring;
 use crate::monitor::TransactionKind;
 use crate::monitor::TryBroadcastTransaction;
@@ -26,8 +28,10 @@ pub struct Actor {
     try_broadcast_transaction: MessageChannel<TryBroadcastTransaction, Result<()>>,
     start_monitoring: MessageChannel<StartMonitoring, ()>,
     monitor_cet_finality: MessageChannel<MonitorCetFinality, Result<()>>,
+    monitor_punish_finality: MessageChannel<MonitorPunishFinality, Result<()>>,
     monitor_collaborative_settlement: MessageChannel<MonitorCollaborativeSettlement, ()>,
     monitor_attestation: MessageChannel<oracle::MonitorAttestation, ()>,
+    dlc_changed: MessageChannel<backup::DlcChanged, ()>,
 }
 
 pub struct Event(CfdEvent);
@@ -48,8 +52,10 @@ impl Actor {
         try_broadcast_transaction: MessageChannel<TryBroadcastTransaction, Result<()>>,
         start_monitoring: MessageChannel<StartMonitoring, ()>,
         monitor_cet_finality: MessageChannel<MonitorCetFinality, Result<()>>,
+        monitor_punish_finality: MessageChannel<MonitorPunishFinality, Result<()>>,
         monitor_collaborative_settlement: MessageChannel<MonitorCollaborativeSettlement, ()>,
         monitor_attestation: MessageChannel<oracle::MonitorAttestation, ()>,
+        dlc_changed: MessageChannel<backup::DlcChanged, ()>,
     ) -> Self {
         Self {
             db,
@@ -59,8 +65,10 @@ impl Actor {
             try_broadcast_transaction,
             start_monitoring,
             monitor_cet_finality,
+            monitor_punish_finality,
             monitor_collaborative_settlement,
             monitor_attestation,
+            dlc_changed,
         }
     }
 }
@@ -82,6 +90,7 @@ impl Actor {
                 let span = tracing::debug_span!("Broadcast lock TX", order_id = %event.id);
                 self.try_broadcast_transaction
                     .send_async_safe(TryBroadcastTransaction {
+                        order_id: event.id,
                         tx: lock_tx,
                         kind: TransactionKind::Lock,
                     })
@@ -97,9 +106,12 @@ impl Actor {
 
                 self.monitor_attestation
                     .send_async_safe(oracle::MonitorAttestation {
+                        oracle_pk: dlc.oracle_pk,
                         event_id: dlc.settlement_event_id,
                     })
                     .await?;
+
+                self.dlc_changed.send_async_safe(backup::DlcChanged).await?;
             }
             CollaborativeSettlementCompleted {
                 spend_tx, script, ..
@@ -114,6 +126,7 @@ impl Actor {
                         );
                         self.try_broadcast_transaction
                             .send_async_safe(TryBroadcastTransaction {
+                                order_id: event.id,
                                 tx: spend_tx,
                                 kind: TransactionKind::CollaborativeClose,
                             })
@@ -145,6 +158,7 @@ impl Actor {
                 let span = tracing::debug_span!("Broadcast CET", order_id = %event.id);
                 self.try_broadcast_transaction
                     .send_async_safe(TryBroadcastTransaction {
+                        order_id: event.id,
                         tx: cet,
                         kind: TransactionKind::Cet,
                     })
@@ -159,6 +173,7 @@ impl Actor {
                 let span = tracing::debug_span!("Broadcast commit TX", order_id = %event.id);
                 self.try_broadcast_transaction
                     .send_async_safe(TryBroadcastTransaction {
+                        order_id: event.id,
                         tx,
                         kind: TransactionKind::Commit,
                     })
@@ -188,27 +203,66 @@ impl Actor {
 
                 self.monitor_attestation
                     .send_async_safe(oracle::MonitorAttestation {
+                        oracle_pk: dlc.oracle_pk,
                         event_id: dlc.settlement_event_id,
                     })
                     .await?;
+
+                self.dlc_changed.send_async_safe(backup::DlcChanged).await?;
             }
             RefundTimelockExpired { refund_tx: tx } => {
                 let span = tracing::debug_span!("Broadcast refund TX", order_id = %event.id);
                 self.try_broadcast_transaction
                     .send_async_safe(TryBroadcastTransaction {
+                        order_id: event.id,
                         tx,
                         kind: TransactionKind::Refund,
                     })
                     .instrument(span)
                     .await?;
             }
+            PunishPublished { punish_tx } => {
+                let _ = self
+                    .monitor_punish_finality
+                    .send_async_safe(MonitorPunishFinality {
+                        order_id: event.id,
+                        punish_tx: punish_tx.clone(),
+                    })
+                    .await?;
+                let span = tracing::debug_span!("Broadcast punish TX", order_id = %event.id);
+                self.try_broadcast_transaction
+                    .send_async_safe(TryBroadcastTransaction {
+                        order_id: event.id,
+                        tx: punish_tx,
+                        kind: TransactionKind::Punish,
+                    })
+                    .instrument(span)
+                    .await?;
+            }
+            FeeBumped { cpfp_tx, .. } => {
+                let span = tracing::debug_span!("Broadcast CPFP TX", order_id = %event.id);
+                self.try_broadcast_transaction
+                    .send_async_safe(TryBroadcastTransaction {
+                        order_id: event.id,
+                        tx: cpfp_tx,
+                        kind: TransactionKind::Cpfp,
+                    })
+                    .instrument(span)
+                    .await?;
+            }
+            CollaborativeSettlementConfirmed
+            | CetConfirmed
+            | RefundConfirmed
+            | PunishConfirmed
+            | ContractSetupFailed
+            | OfferRejected => {
+                // The CFD no longer needs to be restorable, drop it from the backup
+                self.dlc_changed.send_async_safe(backup::DlcChanged).await?;
+            }
             ContractSetupCompleted { dlc: None, .. }
             | RolloverCompleted { dlc: None, .. }
-            | RefundConfirmed
             | CollaborativeSettlementStarted { .. }
             | ContractSetupStarted
-            | ContractSetupFailed
-            | OfferRejected
             | RolloverStarted
             | RolloverAccepted
             | RolloverRejected
@@ -217,12 +271,11 @@ impl Actor {
             | LockConfirmed
             | LockConfirmedAfterFinality
             | CommitConfirmed
-            | CetConfirmed
             | RevokeConfirmed
-            | CollaborativeSettlementConfirmed
             | CollaborativeSettlementRejected
             | CollaborativeSettlementFailed
-            | CetTimelockExpiredPriorOracleAttestation => {}
+            | CetTimelockExpiredPriorOracleAttestation
+            | PolicyDecided { .. } => {}
         }
 
         // 3. Update UI
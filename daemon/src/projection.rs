@@ -77,12 +77,14 @@ pub struct Actor {
     tx: Tx,
     state: State,
     price_feed:
-        MessageChannel<xtra_bitmex_price_feed::LatestQuote, Option<xtra_bitmex_price_feed::Quote>>,
+        MessageChannel<xtra_bitmex_price_feed::LatestQuotes, xtra_bitmex_price_feed::Quotes>,
 }
 
 pub struct Feeds {
-    pub quote: watch::Receiver<Option<Quote>>,
-    pub offers: watch::Receiver<MakerOffers>,
+    /// The latest quote of every trading pair
+    pub quotes: watch::Receiver<Vec<Quote>>,
+    /// The maker's offers, one entry per trading pair
+    pub offers: watch::Receiver<Vec<MakerOffers>>,
     pub connected_takers: watch::Receiver<Vec<model::Identity>>,
     pub cfds: watch::Receiver<Option<Vec<Cfd>>>,
 }
@@ -92,16 +94,13 @@ impl Actor {
         db: sqlite_db::Connection,
         network: Network,
         price_feed: MessageChannel<
-            xtra_bitmex_price_feed::LatestQuote,
-            Option<xtra_bitmex_price_feed::Quote>,
+            xtra_bitmex_price_feed::LatestQuotes,
+            xtra_bitmex_price_feed::Quotes,
         >,
     ) -> (Self, Feeds) {
         let (tx_cfds, rx_cfds) = watch::channel(None);
-        let (tx_order, rx_order) = watch::channel(MakerOffers {
-            long: None,
-            short: None,
-        });
-        let (tx_quote, rx_quote) = watch::channel(None);
+        let (tx_order, rx_order) = watch::channel(Vec::new());
+        let (tx_quotes, rx_quotes) = watch::channel(Vec::new());
         let (tx_connected_takers, rx_connected_takers) = watch::channel(Vec::new());
 
         let actor = Self {
@@ -109,7 +108,7 @@ impl Actor {
             tx: Tx {
                 cfds: tx_cfds,
                 order: tx_order,
-                quote: tx_quote,
+                quotes: tx_quotes,
                 connected_takers: tx_connected_takers,
             },
             state: State::new(network),
@@ -118,7 +117,7 @@ impl Actor {
         let feeds = Feeds {
             cfds: rx_cfds,
             offers: rx_order,
-            quote: rx_quote,
+            quotes: rx_quotes,
             connected_takers: rx_connected_takers,
         };
 
@@ -142,6 +141,8 @@ pub struct Cfd {
     /// The taker leverage
     #[serde(rename = "leverage")]
     pub leverage_taker: Leverage,
+    /// The maker leverage
+    pub leverage_maker: Leverage,
     pub trading_pair: TradingPair,
     pub position: Position,
     #[serde(with = "round_to_two_dp")]
@@ -189,6 +190,13 @@ pub struct Cfd {
     #[serde(with = "round_to_two_dp::opt")]
     pub pending_settlement_proposal_price: Option<Price>,
 
+    #[serde(with = "round_to_two_dp::opt")]
+    pub stop_loss: Option<Price>,
+    #[serde(with = "round_to_two_dp::opt")]
+    pub take_profit: Option<Price>,
+    /// Why the position was not closed after its stop-loss or take-profit was triggered
+    pub position_trigger_failure: Option<String>,
+
     #[serde(skip)]
     #[derivative(PartialEq = "ignore")]
     aggregated: Aggregated,
@@ -217,6 +225,10 @@ pub struct Aggregated {
     cet: Option<Transaction>,
     /// If this is present, it should have been published.
     refund_tx: Option<Transaction>,
+    /// If this is present, it should have been published.
+    punish_tx: Option<Transaction>,
+    /// The latest child transaction paying for one of the above, if any.
+    cpfp_tx: Option<Transaction>,
 
     /// If this is present the cet has not been published
     timelocked_cet: Option<Transaction>,
@@ -245,6 +257,8 @@ impl Aggregated {
             collab_settlement_tx: None,
             cet: None,
             refund_tx: None,
+            punish_tx: None,
+            cpfp_tx: None,
             timelocked_cet: None,
             commit_published: false,
             refund_published: false,
@@ -266,6 +280,11 @@ impl Aggregated {
             return Some(extract_payout_amount(tx, script));
         }
 
+        if let Some(tx) = self.punish_tx {
+            let script = self.latest_dlc?.script_pubkey_for(role);
+            return Some(extract_payout_amount(tx, script));
+        }
+
         let tx = self.cet.or(self.timelocked_cet)?;
         let script = self.latest_dlc?.script_pubkey_for(role);
 
@@ -330,9 +349,11 @@ impl Cfd {
     fn new(
         sqlite_db::Cfd {
             id,
+            trading_pair,
             position,
             initial_price,
             taker_leverage,
+            maker_leverage,
             quantity_usd,
             counterparty_network_identity,
             role,
@@ -343,23 +364,32 @@ impl Cfd {
         network: Network,
     ) -> Self {
         let (our_leverage, counterparty_leverage) = match role {
-            Role::Maker => (Leverage::ONE, taker_leverage),
-            Role::Taker => (taker_leverage, Leverage::ONE),
+            Role::Maker => (maker_leverage, taker_leverage),
+            Role::Taker => (taker_leverage, maker_leverage),
         };
 
-        let margin = calculate_margin(initial_price, quantity_usd, our_leverage);
-        let margin_counterparty =
-            calculate_margin(initial_price, quantity_usd, counterparty_leverage);
+        let margin = calculate_margin(trading_pair, initial_price, quantity_usd, our_leverage);
+        let margin_counterparty = calculate_margin(
+            trading_pair,
+            initial_price,
+            quantity_usd,
+            counterparty_leverage,
+        );
 
         let liquidation_price = match position {
-            Position::Long => calculate_long_liquidation_price(our_leverage, initial_price),
-            Position::Short => calculate_short_liquidation_price(our_leverage, initial_price),
+            Position::Long => {
+                calculate_long_liquidation_price(trading_pair, our_leverage, initial_price)
+            }
+            Position::Short => {
+                calculate_short_liquidation_price(trading_pair, our_leverage, initial_price)
+            }
         };
 
         let (long_leverage, short_leverage) =
-            long_and_short_leverage(taker_leverage, role, position);
+            long_and_short_leverage(taker_leverage, maker_leverage, role, position);
 
         let initial_funding_fee = FundingFee::calculate(
+            trading_pair,
             initial_price,
             quantity_usd,
             long_leverage,
@@ -384,7 +414,8 @@ impl Cfd {
             initial_price,
             accumulated_fees: fee_account.balance(),
             leverage_taker: taker_leverage,
-            trading_pair: TradingPair::BtcUsd,
+            leverage_maker: maker_leverage,
+            trading_pair,
             position,
             liquidation_price,
             quantity_usd,
@@ -405,6 +436,9 @@ impl Cfd {
             expiry_timestamp: None,
             counterparty: counterparty_network_identity,
             pending_settlement_proposal_price: None,
+            stop_loss: None,
+            take_profit: None,
+            position_trigger_failure: None,
             aggregated: Aggregated::new(fee_account),
             network,
         }
@@ -551,9 +585,21 @@ impl Cfd {
                 self.aggregated.state = CfdState::PendingCommit;
             }
             RevokeConfirmed => {
-                // TODO: Implement revoked logic
+                // We failed to punish the counterparty
                 self.aggregated.state = CfdState::OpenCommitted;
             }
+            PunishPublished { punish_tx } => {
+                self.aggregated.punish_tx = Some(punish_tx);
+
+                self.aggregated.state = CfdState::PendingClose;
+            }
+            PunishConfirmed => {
+                self.aggregated.state = CfdState::Closed;
+            }
+            FeeBumped { cpfp_tx, .. } => {
+                self.aggregated.cpfp_tx = Some(cpfp_tx);
+            }
+            PolicyDecided { .. } => {}
             RolloverStarted { .. } => {
                 self.aggregated.rollover_state = Some(ProtocolNegotiationState::Started);
             }
@@ -580,6 +626,12 @@ impl Cfd {
         if let Some(cet_url) = self.cet_url(self.network) {
             self.details.tx_url_list.insert(cet_url);
         }
+        if let Some(punish_tx_url) = self.punish_tx_url(self.network) {
+            self.details.tx_url_list.insert(punish_tx_url);
+        }
+        if let Some(cpfp_tx_url) = self.cpfp_tx_url(self.network) {
+            self.details.tx_url_list.insert(cpfp_tx_url);
+        }
 
         self.aggregated.version += 1;
 
@@ -647,10 +699,15 @@ impl Cfd {
 
         let closing_price = market_closing_price(bid, ask, self.role, self.position);
 
-        let (long_leverage, short_leverage) =
-            long_and_short_leverage(self.leverage_taker, self.role, self.position);
+        let (long_leverage, short_leverage) = long_and_short_leverage(
+            self.leverage_taker,
+            self.leverage_maker,
+            self.role,
+            self.position,
+        );
 
         let (profit_btc, profit_percent, payout) = match calculate_profit_at_price(
+            self.trading_pair,
             self.initial_price,
             closing_price,
             self.quantity_usd,
@@ -744,6 +801,15 @@ impl Cfd {
         &self.aggregated
     }
 
+    fn with_position_triggers(
+        &mut self,
+        state: sqlite_db::position_triggers::PositionTriggersState,
+    ) {
+        self.stop_loss = state.triggers.stop_loss;
+        self.take_profit = state.triggers.take_profit;
+        self.position_trigger_failure = state.failure;
+    }
+
     fn collab_settlement_tx_url(&self, network: Network) -> Option<TxUrl> {
         let (tx, script) = self.aggregated.collab_settlement_tx.as_ref()?;
         let url = TxUrl::from_transaction(tx, script, network, TxLabel::Collaborative);
@@ -777,13 +843,33 @@ impl Cfd {
 
         Some(url)
     }
+
+    fn punish_tx_url(&self, network: Network) -> Option<TxUrl> {
+        let tx = self.aggregated.punish_tx.as_ref()?;
+        let dlc = self.aggregated.latest_dlc.as_ref()?;
+
+        let url = TxUrl::from_transaction(
+            tx,
+            &dlc.script_pubkey_for(self.role),
+            network,
+            TxLabel::Punish,
+        );
+
+        Some(url)
+    }
+
+    fn cpfp_tx_url(&self, network: Network) -> Option<TxUrl> {
+        let tx = self.aggregated.cpfp_tx.as_ref()?;
+
+        Some(TxUrl::new(tx.txid(), network, TxLabel::Cpfp))
+    }
 }
 
 /// Internal struct to keep all the senders around in one place
 struct Tx {
     cfds: watch::Sender<Option<Vec<Cfd>>>,
-    pub order: watch::Sender<MakerOffers>,
-    pub quote: watch::Sender<Option<Quote>>,
+    pub order: watch::Sender<Vec<MakerOffers>>,
+    pub quotes: watch::Sender<Vec<Quote>>,
     // TODO: Use this channel to communicate maker status as well with generic
     // ID of connected counterparties
     pub connected_takers: watch::Sender<Vec<model::Identity>>,
@@ -793,11 +879,14 @@ impl Tx {
     fn send_cfds_update(
         &self,
         cfds: HashMap<OrderId, Cfd>,
-        quote: Option<xtra_bitmex_price_feed::Quote>,
+        quotes: &xtra_bitmex_price_feed::Quotes,
     ) {
         let cfds_with_quote = cfds
             .into_iter()
-            .map(|(_, cfd)| cfd.with_current_quote(quote))
+            .map(|(_, cfd)| {
+                let quote = quotes.get(cfd.trading_pair.bitmex_instrument()).copied();
+                cfd.with_current_quote(quote)
+            })
             .sorted_by(|a, b| {
                 Ord::cmp(
                     &b.aggregated.creation_timestamp,
@@ -809,14 +898,24 @@ impl Tx {
         let _ = self.cfds.send(Some(cfds_with_quote));
     }
 
-    fn send_quote_update(&self, quote: Option<xtra_bitmex_price_feed::Quote>) {
-        let _ = self.quote.send(quote.map(|q| q.into()));
+    /// Publish the quote of every trading pair we have a quote for.
+    fn send_quote_update(&self, quotes: &xtra_bitmex_price_feed::Quotes) {
+        let quotes = TradingPair::ALL
+            .into_iter()
+            .filter_map(|trading_pair| {
+                let quote = quotes.get(trading_pair.bitmex_instrument())?;
+
+                Some(Quote::new(trading_pair, *quote))
+            })
+            .collect();
+
+        let _ = self.quotes.send(quotes);
     }
 
-    fn send_order_update(&self, offers: Option<model::MakerOffers>) {
-        let (long, short) = match offers {
-            None => (None, None),
-            Some(offers) => {
+    fn send_order_update(&self, offers: Vec<model::MakerOffers>) {
+        let projection_offers = offers
+            .into_iter()
+            .map(|offers| {
                 let projection_long =
                     offers
                         .long
@@ -839,11 +938,13 @@ impl Tx {
                             }
                         });
 
-                (projection_long, projection_short)
-            }
-        };
-
-        let projection_offers = MakerOffers { long, short };
+                MakerOffers {
+                    trading_pair: offers.trading_pair,
+                    long: projection_long,
+                    short: projection_short,
+                }
+            })
+            .collect();
 
         let _ = self.order.send(projection_offers);
     }
@@ -852,7 +953,7 @@ impl Tx {
 /// Internal struct to keep state in one place
 struct State {
     network: Network,
-    quote: Option<xtra_bitmex_price_feed::Quote>,
+    quotes: xtra_bitmex_price_feed::Quotes,
     /// All hydrated CFDs.
     cfds: Option<HashMap<OrderId, Cfd>>,
 }
@@ -877,9 +978,11 @@ impl sqlite_db::ClosedCfdAggregate for Cfd {
     fn new_closed(network: Self::CtorArgs, closed_cfd: ClosedCfd) -> Self {
         let ClosedCfd {
             id,
+            trading_pair,
             position,
             initial_price,
             taker_leverage,
+            maker_leverage,
             n_contracts,
             counterparty_network_identity,
             role,
@@ -894,17 +997,25 @@ impl sqlite_db::ClosedCfdAggregate for Cfd {
         let quantity_usd = Usd::new(Decimal::from(u64::from(n_contracts)));
 
         let (our_leverage, counterparty_leverage) = match role {
-            Role::Maker => (Leverage::ONE, taker_leverage),
-            Role::Taker => (taker_leverage, Leverage::ONE),
+            Role::Maker => (maker_leverage, taker_leverage),
+            Role::Taker => (taker_leverage, maker_leverage),
         };
 
-        let margin = calculate_margin(initial_price, quantity_usd, our_leverage);
-        let margin_counterparty =
-            calculate_margin(initial_price, quantity_usd, counterparty_leverage);
+        let margin = calculate_margin(trading_pair, initial_price, quantity_usd, our_leverage);
+        let margin_counterparty = calculate_margin(
+            trading_pair,
+            initial_price,
+            quantity_usd,
+            counterparty_leverage,
+        );
 
         let liquidation_price = match position {
-            Position::Long => calculate_long_liquidation_price(our_leverage, initial_price),
-            Position::Short => calculate_short_liquidation_price(our_leverage, initial_price),
+            Position::Long => {
+                calculate_long_liquidation_price(trading_pair, our_leverage, initial_price)
+            }
+            Position::Short => {
+                calculate_short_liquidation_price(trading_pair, our_leverage, initial_price)
+            }
         };
 
         let (details, closing_price, payout, state) = {
@@ -959,6 +1070,21 @@ impl sqlite_db::ClosedCfdAggregate for Cfd {
                     );
                     (None, payout, CfdState::Refunded)
                 }
+                Settlement::Punish {
+                    commit_txid,
+                    txid,
+                    vout,
+                    payout,
+                } => {
+                    tx_url_list.insert(
+                        TxUrl::new(commit_txid, network, TxLabel::Commit).with_output_index(0),
+                    );
+
+                    tx_url_list.insert(
+                        TxUrl::new(txid, network, TxLabel::Punish).with_output_index(vout.into()),
+                    );
+                    (None, payout, CfdState::Closed)
+                }
             };
 
             (
@@ -988,7 +1114,8 @@ impl sqlite_db::ClosedCfdAggregate for Cfd {
             initial_price,
             accumulated_fees: fees.into(),
             leverage_taker: taker_leverage,
-            trading_pair: TradingPair::BtcUsd,
+            leverage_maker: maker_leverage,
+            trading_pair,
             position,
             liquidation_price,
             quantity_usd,
@@ -1007,6 +1134,9 @@ impl sqlite_db::ClosedCfdAggregate for Cfd {
             expiry_timestamp: Some(expiry_timestamp),
             counterparty: counterparty_network_identity,
             pending_settlement_proposal_price: None,
+            stop_loss: None,
+            take_profit: None,
+            position_trigger_failure: None,
             aggregated,
             network,
         }
@@ -1017,9 +1147,11 @@ impl sqlite_db::FailedCfdAggregate for Cfd {
     fn new_failed(network: Self::CtorArgs, failed_cfd: FailedCfd) -> Self {
         let FailedCfd {
             id,
+            trading_pair,
             position,
             initial_price,
             taker_leverage,
+            maker_leverage,
             n_contracts,
             counterparty_network_identity,
             role,
@@ -1038,17 +1170,25 @@ impl sqlite_db::FailedCfdAggregate for Cfd {
             Usd::new(Decimal::from_u64(u64::from(n_contracts)).expect("u64 to fit into Decimal"));
 
         let (our_leverage, counterparty_leverage) = match role {
-            Role::Maker => (Leverage::ONE, taker_leverage),
-            Role::Taker => (taker_leverage, Leverage::ONE),
+            Role::Maker => (maker_leverage, taker_leverage),
+            Role::Taker => (taker_leverage, maker_leverage),
         };
 
-        let margin = calculate_margin(initial_price, quantity_usd, our_leverage);
-        let margin_counterparty =
-            calculate_margin(initial_price, quantity_usd, counterparty_leverage);
+        let margin = calculate_margin(trading_pair, initial_price, quantity_usd, our_leverage);
+        let margin_counterparty = calculate_margin(
+            trading_pair,
+            initial_price,
+            quantity_usd,
+            counterparty_leverage,
+        );
 
         let liquidation_price = match position {
-            Position::Long => calculate_long_liquidation_price(our_leverage, initial_price),
-            Position::Short => calculate_short_liquidation_price(our_leverage, initial_price),
+            Position::Long => {
+                calculate_long_liquidation_price(trading_pair, our_leverage, initial_price)
+            }
+            Position::Short => {
+                calculate_short_liquidation_price(trading_pair, our_leverage, initial_price)
+            }
         };
 
         // there are no events to apply at this stage for failed CFDs,
@@ -1063,7 +1203,8 @@ impl sqlite_db::FailedCfdAggregate for Cfd {
             initial_price,
             accumulated_fees: fees.into(),
             leverage_taker: taker_leverage,
-            trading_pair: TradingPair::BtcUsd,
+            leverage_maker: maker_leverage,
+            trading_pair,
             position,
             liquidation_price,
             quantity_usd,
@@ -1084,6 +1225,9 @@ impl sqlite_db::FailedCfdAggregate for Cfd {
             expiry_timestamp: None,
             counterparty: counterparty_network_identity,
             pending_settlement_proposal_price: None,
+            stop_loss: None,
+            take_profit: None,
+            position_trigger_failure: None,
             aggregated,
             network,
         }
@@ -1094,13 +1238,17 @@ impl State {
     fn new(network: Network) -> Self {
         Self {
             network,
-            quote: None,
+            quotes: xtra_bitmex_price_feed::Quotes::new(),
             cfds: None,
         }
     }
 
     async fn update_cfd(&mut self, db: sqlite_db::Connection, id: OrderId) -> Result<()> {
-        let cfd = db.load_open_cfd(id, self.network).await?;
+        let mut cfd: Cfd = db.load_open_cfd(id, self.network).await?;
+
+        if let Some(position_triggers) = db.load_position_triggers(id).await? {
+            cfd.with_position_triggers(position_triggers);
+        }
 
         let cfds = self
             .cfds
@@ -1112,8 +1260,8 @@ impl State {
         Ok(())
     }
 
-    fn update_quote(&mut self, quote: Option<xtra_bitmex_price_feed::Quote>) {
-        self.quote = quote;
+    fn update_quotes(&mut self, quotes: xtra_bitmex_price_feed::Quotes) {
+        self.quotes = quotes;
     }
 }
 
@@ -1136,6 +1284,17 @@ impl Actor {
             cfds.insert(cfd.order_id, cfd);
         }
 
+        match self.db.load_all_position_triggers().await {
+            Ok(all_position_triggers) => {
+                for position_triggers in all_position_triggers {
+                    if let Some(cfd) = cfds.get_mut(&position_triggers.order_id) {
+                        cfd.with_position_triggers(position_triggers);
+                    }
+                }
+            }
+            Err(e) => tracing::error!("Failed to load position triggers: {e:#}"),
+        }
+
         self.state.cfds = Some(cfds);
 
         self.tx.send_cfds_update(
@@ -1143,7 +1302,7 @@ impl Actor {
                 .cfds
                 .clone()
                 .expect("we initialized the state above; qed"),
-            self.state.quote,
+            &self.state.quotes,
         );
     }
 
@@ -1158,17 +1317,17 @@ impl Actor {
                 .cfds
                 .clone()
                 .expect("update_cfd fails if the CFDs have not been initialized yet"),
-            self.state.quote,
+            &self.state.quotes,
         );
     }
 
-    fn handle(&mut self, msg: Update<Option<model::MakerOffers>>) {
+    fn handle(&mut self, msg: Update<Vec<model::MakerOffers>>) {
         self.tx.send_order_update(msg.0);
     }
 
-    fn handle(&mut self, msg: Update<Option<xtra_bitmex_price_feed::Quote>>) {
-        self.state.update_quote(msg.0);
-        self.tx.send_quote_update(msg.0);
+    fn handle(&mut self, msg: Update<xtra_bitmex_price_feed::Quotes>) {
+        self.tx.send_quote_update(&msg.0);
+        self.state.update_quotes(msg.0);
 
         let hydrated_cfds = match self.state.cfds.clone() {
             None => {
@@ -1178,7 +1337,7 @@ impl Actor {
             Some(cfds) => cfds,
         };
 
-        self.tx.send_cfds_update(hydrated_cfds, msg.0);
+        self.tx.send_cfds_update(hydrated_cfds, &self.state.quotes);
     }
 
     fn handle(&mut self, msg: Update<Vec<model::Identity>>) {
@@ -1201,13 +1360,13 @@ impl xtra::Actor for Actor {
                     {
                         let span = info_span!("Update projection with latest quote");
                         let latest = price_feed
-                            .send(xtra_bitmex_price_feed::LatestQuote)
+                            .send(xtra_bitmex_price_feed::LatestQuotes)
                             .instrument(span.clone())
                             .await;
 
                         match latest {
-                            Ok(quote) => {
-                                let _ = this.send(Update(quote)).instrument(span).await;
+                            Ok(quotes) => {
+                                let _ = this.send(Update(quotes)).instrument(span).await;
                             }
                             Err(_) => {
                                 span.in_scope(|| {
@@ -1228,6 +1387,7 @@ impl xtra::Actor for Actor {
 
 #[derive(Debug, Clone, Copy, Serialize)]
 pub struct Quote {
+    pub trading_pair: TradingPair,
     #[serde(with = "round_to_two_dp")]
     bid: Decimal,
     #[serde(with = "round_to_two_dp")]
@@ -1235,9 +1395,10 @@ pub struct Quote {
     last_updated_at: Timestamp,
 }
 
-impl From<xtra_bitmex_price_feed::Quote> for Quote {
-    fn from(quote: xtra_bitmex_price_feed::Quote) -> Self {
+impl Quote {
+    fn new(trading_pair: TradingPair, quote: xtra_bitmex_price_feed::Quote) -> Self {
         Quote {
+            trading_pair,
             bid: quote.bid,
             ask: quote.ask,
             last_updated_at: Timestamp::new(quote.timestamp.unix_timestamp()),
@@ -1248,6 +1409,7 @@ impl From<xtra_bitmex_price_feed::Quote> for Quote {
 /// Maker offers represents the offers as created by the maker
 #[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct MakerOffers {
+    pub trading_pair: TradingPair,
     /// The offer where the maker's position is long
     pub long: Option<CfdOrder>,
     /// The offer where the maker's position is short
@@ -1343,16 +1505,24 @@ impl TryFrom<Order> for CfdOrder {
             .iter()
             .map(|leverage| {
                 let liquidation_price = match own_position {
-                    Position::Long => calculate_long_liquidation_price(*leverage, order.price),
-                    Position::Short => calculate_short_liquidation_price(*leverage, order.price),
+                    Position::Long => {
+                        calculate_long_liquidation_price(order.trading_pair, *leverage, order.price)
+                    }
+                    Position::Short => calculate_short_liquidation_price(
+                        order.trading_pair,
+                        *leverage,
+                        order.price,
+                    ),
                 };
                 // Margin per lot price is dependent on one's own leverage
-                let margin_per_lot = calculate_margin(order.price, lot_size, *leverage);
+                let margin_per_lot =
+                    calculate_margin(order.trading_pair, order.price, lot_size, *leverage);
 
                 let (long_leverage, short_leverage) =
-                    long_and_short_leverage(*leverage, role, own_position);
+                    long_and_short_leverage(*leverage, order.leverage_maker, role, own_position);
 
                 let initial_funding_fee_per_lot = FundingFee::calculate(
+                    order.trading_pair,
                     order.price,
                     lot_size,
                     long_leverage,
@@ -1400,7 +1570,7 @@ impl TryFrom<Order> for CfdOrder {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Display, FromStr, Serialize)]
 pub enum CfdState {
     PendingSetup,
     ContractSetup,
@@ -1581,6 +1751,8 @@ pub enum TxLabel {
     Cet,
     Refund,
     Collaborative,
+    Punish,
+    Cpfp,
 }
 
 struct AnnualisedFundingPercent(Decimal);
@@ -1661,9 +1833,11 @@ mod tests {
     pub fn dummy_cfd() -> model::Cfd {
         model::Cfd::new(
             OrderId::default(),
+            TradingPair::BtcUsd,
             Position::Long,
             Price::new(dec!(60_000)).unwrap(),
             Leverage::TWO,
+            Leverage::ONE,
             time::Duration::hours(24),
             Role::Taker,
             Usd::new(dec!(1_000)),
@@ -1699,9 +1873,11 @@ mod tests {
         let order_id = OrderId::default();
         let cfd = model::Cfd::new(
             order_id,
+            TradingPair::BtcUsd,
             Position::Long,
             Price::new(dec!(41_772.8325)).unwrap(),
             Leverage::TWO,
+            Leverage::ONE,
             time::Duration::hours(24),
             Role::Taker,
             Usd::new(dec!(100)),
@@ -0,0 +1,15 @@
+//! Recovery of open contracts after losing the database.
+//!
+//! The counterparty shares the contracts it has open with us, which allows us to publish the
+//! refund transactions with identity keys derived from the seed once the counterparty committed
+//! them to the blockchain.
+//!
+//! Only contracts set up since identity keys are derived from the seed can be recovered. The
+//! identity keys of older contracts were random and are lost with the database, so the
+//! counterparty's copies of these contracts fail verification and are ignored.
+
+pub mod listener;
+pub mod protocol;
+pub mod requester;
+
+pub const PROTOCOL: &str = "/itchysats/recovery/1.0.0";
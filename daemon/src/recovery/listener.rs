@@ -0,0 +1,104 @@
+use crate::recovery::protocol::*;
+use anyhow::Context;
+use async_trait::async_trait;
+use asynchronous_codec::Framed;
+use asynchronous_codec::JsonCodec;
+use futures::SinkExt;
+use futures::StreamExt;
+use model::libp2p::PeerId;
+use model::Cfd;
+use model::RecoveredContract;
+use xtra_libp2p::NewInboundSubstream;
+use xtra_productivity::xtra_productivity;
+
+/// Permanent actor to handle incoming substreams for the `/itchysats/recovery/1.0.0` protocol.
+///
+/// Shares all open contracts with the peer that set them up with us without changing them. The
+/// peer can only publish the refund transaction of a contract once it was committed to the
+/// blockchain, which requires the operator to commit the CFD.
+pub struct Actor {
+    db: sqlite_db::Connection,
+}
+
+impl Actor {
+    pub fn new(db: sqlite_db::Connection) -> Self {
+        Self { db }
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn stopped(self) -> Self::Stop {}
+}
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, msg: NewInboundSubstream, ctx: &mut xtra::Context<Self>) {
+        let NewInboundSubstream { peer, stream } = msg;
+        let address = ctx.address().expect("we are alive");
+
+        tokio_extras::spawn_fallible(
+            &address,
+            {
+                let db = self.db.clone();
+                async move {
+                    let mut framed =
+                        Framed::new(stream, JsonCodec::<ListenerMessage, DialerMessage>::new());
+
+                    let DialerMessage::Recover = framed
+                        .next()
+                        .await
+                        .context("End of stream while receiving Recover")?
+                        .context("Failed to decode Recover")?;
+
+                    let contracts = recover(&db, peer.into()).await?;
+
+                    tracing::info!(%peer, n = %contracts.len(), "Sharing open contracts for recovery");
+
+                    framed
+                        .send(ListenerMessage::Contracts(contracts))
+                        .await
+                        .context("Failed to send contracts")?;
+
+                    anyhow::Ok(())
+                }
+            },
+            move |e| async move {
+                tracing::warn!(%peer, "Failed to handle incoming recovery request: {e:#}")
+            },
+        );
+    }
+}
+
+/// Collect the open contracts set up with `peer`, reading them from the database only.
+async fn recover(
+    db: &sqlite_db::Connection,
+    peer: PeerId,
+) -> anyhow::Result<Vec<RecoveredContract>> {
+    let mut contracts = Vec::new();
+
+    for id in db.load_open_cfd_ids().await? {
+        let cfd = db.load_open_cfd::<Cfd>(id, ()).await?;
+
+        match cfd.recover_counterparty(&peer) {
+            Ok(contract) => {
+                if !cfd.is_in_force_close() {
+                    tracing::warn!(
+                        order_id = %id,
+                        %peer,
+                        "Counterparty lost its database, commit the CFD to let it publish the refund transaction"
+                    );
+                }
+
+                contracts.push(contract)
+            }
+            Err(e) => {
+                tracing::trace!(order_id = %id, %peer, "Not sharing contract for recovery: {e:#}")
+            }
+        }
+    }
+
+    Ok(contracts)
+}
@@ -0,0 +1,63 @@
+use crate::recovery::PROTOCOL;
+use anyhow::Context;
+use anyhow::Result;
+use futures::SinkExt;
+use futures::StreamExt;
+use libp2p_core::PeerId;
+use model::RecoveredContract;
+use serde::Deserialize;
+use serde::Serialize;
+use std::time::Duration;
+use tokio_extras::FutureExt;
+use xtra::Address;
+use xtra_libp2p::Endpoint;
+use xtra_libp2p::OpenSubstream;
+
+/// How long we wait for the counterparty to collect the contracts it has open with us.
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Serialize, Deserialize)]
+pub enum DialerMessage {
+    Recover,
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum ListenerMessage {
+    Contracts(Vec<RecoveredContract>),
+}
+
+/// Ask `counterparty` for all the contracts it has open with us.
+#[tracing::instrument(skip(endpoint))]
+pub async fn dialer(
+    endpoint: Address<Endpoint>,
+    counterparty: PeerId,
+) -> Result<Vec<RecoveredContract>> {
+    let substream = endpoint
+        .send(OpenSubstream::single_protocol(counterparty, PROTOCOL))
+        .await
+        .context("Endpoint is disconnected")?
+        .context("No connection to peer")?
+        .await
+        .context("Failed to open substream")?;
+    let mut framed = asynchronous_codec::Framed::new(
+        substream,
+        asynchronous_codec::JsonCodec::<DialerMessage, ListenerMessage>::new(),
+    );
+
+    framed
+        .send(DialerMessage::Recover)
+        .await
+        .context("Failed to send Recover")?;
+
+    let ListenerMessage::Contracts(contracts) = framed
+        .next()
+        .timeout(RESPONSE_TIMEOUT, || {
+            tracing::debug_span!("receive contracts")
+        })
+        .await
+        .context("Timeout while waiting for contracts")?
+        .context("End of stream while receiving contracts")?
+        .context("Failed to decode contracts")?;
+
+    Ok(contracts)
+}
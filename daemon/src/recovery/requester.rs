@@ -0,0 +1,184 @@
+use crate::monitor;
+use crate::monitor::TransactionKind;
+use crate::recovery::protocol;
+use crate::seed::ContractIdentities;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use bdk::bitcoin::OutPoint;
+use bdk::bitcoin::Script;
+use model::libp2p::PeerId;
+use model::RecoveredContract;
+use std::time::Duration;
+use xtra::prelude::MessageChannel;
+use xtra::Address;
+use xtra_libp2p::Endpoint;
+use xtra_productivity::xtra_productivity;
+
+/// How often we try to publish the refund transactions of recovered contracts.
+const REFUND_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Recovers open contracts from a counterparty after we lost our database.
+///
+/// Recovered contracts are stored in the database until their refund transaction was published,
+/// or until a CET spent the output of the commit transaction, which rules out the refund.
+pub struct Actor {
+    db: sqlite_db::Connection,
+    endpoint: Address<Endpoint>,
+    contract_identities: ContractIdentities,
+    try_broadcast_transaction: MessageChannel<monitor::TryBroadcastTransaction, Result<()>>,
+    is_spent: MessageChannel<monitor::IsSpent, Result<bool>>,
+}
+
+impl Actor {
+    pub fn new(
+        db: sqlite_db::Connection,
+        endpoint: Address<Endpoint>,
+        contract_identities: ContractIdentities,
+        try_broadcast_transaction: MessageChannel<monitor::TryBroadcastTransaction, Result<()>>,
+        is_spent: MessageChannel<monitor::IsSpent, Result<bool>>,
+    ) -> Self {
+        Self {
+            db,
+            endpoint,
+            contract_identities,
+            try_broadcast_transaction,
+            is_spent,
+        }
+    }
+
+    async fn is_spent(&self, (outpoint, script): (OutPoint, Script)) -> Result<bool> {
+        self.is_spent
+            .send(monitor::IsSpent { outpoint, script })
+            .await
+            .context("Monitor actor is disconnected")?
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn started(&mut self, ctx: &mut xtra::Context<Self>) {
+        let this = ctx.address().expect("self to be alive");
+        tokio_extras::spawn(
+            &this.clone(),
+            this.send_interval(
+                REFUND_INTERVAL,
+                || PublishRefunds,
+                xtras::IncludeSpan::Always,
+            ),
+        );
+    }
+
+    async fn stopped(self) -> Self::Stop {}
+}
+
+#[xtra_productivity]
+impl Actor {
+    pub async fn handle(&mut self, msg: Recover) -> Result<Vec<RecoveredContract>> {
+        let Recover { counterparty } = msg;
+
+        let contracts = protocol::dialer(self.endpoint.clone(), counterparty.inner())
+            .await
+            .with_context(|| format!("Failed to recover contracts from {counterparty}"))?;
+
+        let mut recovered = Vec::new();
+        for contract in contracts {
+            let order_id = contract.order_id;
+            let (_, identity_pk) = self.contract_identities.derive(order_id);
+
+            if let Err(e) = contract.verify(identity_pk) {
+                tracing::warn!(%order_id, %counterparty, "Ignoring contract shared for recovery: {e:#}");
+                continue;
+            }
+
+            self.db
+                .insert_recovered_contract(&contract)
+                .await
+                .context("Failed to store recovered contract")?;
+            recovered.push(contract);
+        }
+
+        tracing::info!(
+            %counterparty,
+            order_ids = ?recovered.iter().map(|c| c.order_id).collect::<Vec<_>>(),
+            "Recovered contracts"
+        );
+
+        Ok(recovered)
+    }
+
+    async fn handle(&mut self, _: PublishRefunds) {
+        let contracts = match self.db.load_recovered_contracts().await {
+            Ok(contracts) => contracts,
+            Err(e) => {
+                tracing::error!("Failed to load recovered contracts: {e:#}");
+                return;
+            }
+        };
+
+        for contract in contracts {
+            let order_id = contract.order_id;
+            let (identity_sk, _) = self.contract_identities.derive(order_id);
+
+            let tx = match contract.signed_refund_tx(&identity_sk) {
+                Ok(tx) => tx,
+                Err(e) => {
+                    tracing::error!(%order_id, "Failed to sign refund transaction: {e:#}");
+                    continue;
+                }
+            };
+
+            match self
+                .try_broadcast_transaction
+                .send(monitor::TryBroadcastTransaction {
+                    order_id,
+                    tx,
+                    kind: TransactionKind::Refund,
+                })
+                .await
+            {
+                Ok(Ok(())) => {
+                    tracing::info!(%order_id, "Published refund transaction of recovered contract");
+
+                    if let Err(e) = self.db.delete_recovered_contract(order_id).await {
+                        tracing::error!(%order_id, "Failed to delete recovered contract: {e:#}");
+                    }
+                }
+                Ok(Err(e)) => match self.is_spent(contract.commit_output()).await {
+                    // The refund transaction was rejected although the commit output is spent,
+                    // so it was spent by a CET
+                    Ok(true) => {
+                        tracing::warn!(%order_id, "Commit output was spent by a CET, giving up on refund of recovered contract");
+
+                        if let Err(e) = self.db.delete_recovered_contract(order_id).await {
+                            tracing::error!(%order_id, "Failed to delete recovered contract: {e:#}");
+                        }
+                    }
+                    // Expected until the commit transaction was published and the refund
+                    // timelock expired
+                    Ok(false) => {
+                        tracing::debug!(%order_id, "Failed to publish refund transaction: {e:#}")
+                    }
+                    Err(e) => {
+                        tracing::warn!(%order_id, "Failed to check commit output of recovered contract: {e:#}")
+                    }
+                },
+                Err(_) => {
+                    tracing::warn!("Monitor actor is disconnected");
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Ask the counterparty for the contracts we have open with it and store them until their refund
+/// transaction can be published.
+#[derive(Clone, Copy)]
+pub struct Recover {
+    pub counterparty: PeerId,
+}
+
+struct PublishRefunds;
@@ -12,7 +12,6 @@ use bdk_ext::keypair;
 use futures::SinkExt;
 use futures::StreamExt;
 use libp2p_core::PeerId;
-use maia_core::secp256k1_zkp::XOnlyPublicKey;
 use model::olivia;
 use model::BaseDlcParams;
 use model::Dlc;
@@ -45,7 +44,6 @@ type ListenerConnection = (
 /// task whenever we interact with a substream to not block the execution of other connections.
 pub struct Actor {
     protocol_tasks: HashMap<OrderId, Tasks>,
-    oracle_pk: XOnlyPublicKey,
     get_announcement:
         MessageChannel<oracle::GetAnnouncement, Result<olivia::Announcement, NoAnnouncement>>,
     n_payouts: usize,
@@ -56,7 +54,6 @@ pub struct Actor {
 impl Actor {
     pub fn new(
         executor: command::Executor,
-        oracle_pk: XOnlyPublicKey,
         get_announcement: MessageChannel<
             oracle::GetAnnouncement,
             Result<olivia::Announcement, NoAnnouncement>,
@@ -65,7 +62,6 @@ impl Actor {
     ) -> Self {
         Self {
             protocol_tasks: HashMap::default(),
-            oracle_pk,
             get_announcement,
             n_payouts,
             pending_protocols: HashMap::default(),
@@ -177,7 +173,6 @@ impl Actor {
             {
                 let executor = self.executor.clone();
                 let get_announcement = self.get_announcement.clone();
-                let oracle_pk = self.oracle_pk;
                 let n_payouts = self.n_payouts;
                 async move {
                     let (rollover_params, dlc, position, oracle_event_id, funding_rate) = executor
@@ -215,8 +210,12 @@ impl Actor {
                         .await
                         .context("Failed to send rollover confirmation message")?;
 
+                    let oracle_pk = dlc.oracle_pk;
                     let announcement = get_announcement
-                        .send(oracle::GetAnnouncement(oracle_event_id))
+                        .send(oracle::GetAnnouncement {
+                            oracle_pk,
+                            event_id: oracle_event_id,
+                        })
                         .await
                         .context("Oracle actor disconnected")?
                         .context("Failed to get announcement")?;
@@ -346,6 +345,7 @@ impl Actor {
                         revoked_commit: revoked_commits,
                         settlement_event_id: announcement.id,
                         refund_timelock: rollover_params.refund_timelock,
+                        oracle_pk,
                     };
 
                     emit_completed(order_id, dlc, funding_fee, complete_fee, &executor).await;
@@ -375,6 +375,7 @@ pub(crate) async fn build_own_cfd_transactions(
             nonce_pks: announcement.nonce_pks.clone(),
         },
         calculate_payouts(
+            announcement.id.trading_pair(),
             our_position,
             punish_params.own_role,
             rollover_params.price,
@@ -10,7 +10,6 @@ use async_trait::async_trait;
 use bdk_ext::keypair;
 use futures::SinkExt;
 use futures::StreamExt;
-use maia_core::secp256k1_zkp::XOnlyPublicKey;
 use model::libp2p::PeerId;
 use model::olivia;
 use model::olivia::BitMexPriceEventId;
@@ -36,7 +35,6 @@ const DECISION_TIMEOUT: Duration = Duration::from_secs(30);
 /// One actor to rule all the rollovers
 pub struct Actor {
     endpoint: Address<Endpoint>,
-    oracle_pk: XOnlyPublicKey,
     get_announcement:
         MessageChannel<oracle::GetAnnouncement, Result<olivia::Announcement, NoAnnouncement>>,
     n_payouts: usize,
@@ -62,7 +60,6 @@ impl Actor {
     pub fn new(
         endpoint: Address<Endpoint>,
         executor: command::Executor,
-        oracle_pk: XOnlyPublicKey,
         get_announcement: MessageChannel<
             oracle::GetAnnouncement,
             Result<olivia::Announcement, NoAnnouncement>,
@@ -73,7 +70,6 @@ impl Actor {
             endpoint,
             executor,
             get_announcement,
-            oracle_pk,
             n_payouts,
         }
     }
@@ -122,7 +118,6 @@ impl Actor {
             {
                 let executor = self.executor.clone();
                 let get_announcement = self.get_announcement.clone();
-                let oracle_pk = self.oracle_pk;
                 let n_payouts = self.n_payouts;
                 async move {
                     let mut framed = asynchronous_codec::Framed::new(
@@ -176,8 +171,12 @@ impl Actor {
                                 })
                                 .await?;
 
+                            let oracle_pk = dlc.oracle_pk;
                             let announcement = get_announcement
-                                .send(oracle::GetAnnouncement(oracle_event_id))
+                                .send(oracle::GetAnnouncement {
+                                    oracle_pk,
+                                    event_id: oracle_event_id,
+                                })
                                 .await
                                 .context("Oracle actor disconnected")?
                                 .context("Failed to get announcement")?;
@@ -322,6 +321,7 @@ impl Actor {
                                 revoked_commit: revoked_commits,
                                 settlement_event_id: announcement.id,
                                 refund_timelock: rollover_params.refund_timelock,
+                                oracle_pk,
                             };
 
                             emit_completed(
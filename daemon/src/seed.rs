@@ -1,17 +1,65 @@
+use crate::backup::BackupKey;
 use anyhow::anyhow;
+use anyhow::bail;
+use anyhow::Context;
 use anyhow::Result;
+use argon2::Algorithm;
+use argon2::Argon2;
+use argon2::Params;
+use argon2::Version;
+use bdk::bitcoin;
+use bdk::bitcoin::secp256k1;
 use bdk::bitcoin::util::bip32::ExtendedPrivKey;
 use bdk::bitcoin::Network;
+use bdk_ext::SecretKeyExt;
+use chacha20poly1305::aead::Aead;
+use chacha20poly1305::aead::NewAead;
+use chacha20poly1305::aead::Payload;
+use chacha20poly1305::Key;
+use chacha20poly1305::XChaCha20Poly1305;
+use chacha20poly1305::XNonce;
 use hkdf::Hkdf;
 use libp2p_core::identity::ed25519;
 use libp2p_core::identity::Keypair;
 use model::libp2p::PeerId;
+use model::OrderId;
 use rand::Rng;
 use sha2::Sha256;
 use std::convert::TryInto;
 use std::fmt;
 use std::fmt::Debug;
 use std::path::Path;
+use std::str::FromStr;
+use zeroize::Zeroize;
+use zeroize::Zeroizing;
+
+/// Prefix of seed files encrypted with a passphrase.
+const ENCRYPTED_SEED_MAGIC: &[u8] = b"itchysats-encrypted-seed";
+/// Version of the layout of encrypted seed files, stored right after the magic.
+const ENCRYPTED_SEED_VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+/// Memory cost in KiB of deriving the key of new encrypted seed files.
+const ARGON2_M_COST: u32 = 19 * 1024;
+/// Number of passes of deriving the key of new encrypted seed files.
+const ARGON2_T_COST: u32 = 2;
+/// Degree of parallelism of deriving the key of new encrypted seed files.
+const ARGON2_P_COST: u32 = 1;
+/// Upper bound on the memory cost read from a seed file, so that a corrupted header cannot
+/// exhaust our memory.
+const ARGON2_MAX_M_COST: u32 = 1024 * 1024;
+/// Length of the header of encrypted seed files: magic, version, Argon2 parameters, salt and
+/// nonce.
+const ENCRYPTED_SEED_HEADER_LEN: usize =
+    ENCRYPTED_SEED_MAGIC.len() + 1 + 3 * 4 + SALT_LEN + NONCE_LEN;
+/// Number of words of the mnemonic of a [`MnemonicSeed`], the most BIP39 supports.
+const MNEMONIC_WORD_COUNT: usize = 24;
+/// Number of bytes encoded by a mnemonic of 24 words.
+const MNEMONIC_ENTROPY_LEN: usize = 32;
+/// Number of bytes of a [`RandomSeed`].
+const RANDOM_SEED_LEN: usize = 256;
+/// Number of mnemonics of 24 words a [`RandomSeed`] is backed up as.
+const RANDOM_SEED_MNEMONIC_COUNT: usize = RANDOM_SEED_LEN / MNEMONIC_ENTROPY_LEN;
 
 /// Struct containing keys for both legacy and libp2p connections.
 ///
@@ -21,6 +69,8 @@ pub struct Identities {
     pub identity_sk: x25519_dalek::StaticSecret,
     pub identity_pk: x25519_dalek::PublicKey,
     pub libp2p: Keypair,
+    pub contracts: ContractIdentities,
+    pub backup_key: BackupKey,
 }
 
 impl Identities {
@@ -29,8 +79,37 @@ impl Identities {
     }
 }
 
+/// Source of the identity keys used in the contracts we set up.
+///
+/// Deriving the identity key of a contract from the seed allows us to spend the refund
+/// transaction of the contract even if we lost the database.
+#[derive(Clone)]
+pub struct ContractIdentities([u8; 32]);
+
+impl ContractIdentities {
+    pub fn derive(&self, order_id: OrderId) -> (secp256k1::SecretKey, bitcoin::PublicKey) {
+        let mut secret = [0u8; 32];
+
+        Hkdf::<Sha256>::new(Some(order_id.to_string().as_bytes()), &self.0)
+            .expand(b"CONTRACT_IDENTITY", &mut secret)
+            .expect("okm array is of correct length");
+
+        let sk = secp256k1::SecretKey::from_slice(&secret)
+            .expect("HKDF output is a valid secret key with overwhelming probability");
+        let pk = bitcoin::PublicKey::new(sk.to_public_key());
+
+        (sk, pk)
+    }
+}
+
+impl Debug for ContractIdentities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ContractIdentities").field(&"...").finish()
+    }
+}
+
 pub trait Seed {
-    fn seed(&self) -> Vec<u8>;
+    fn seed(&self) -> Zeroizing<Vec<u8>>;
 
     fn derive_extended_priv_key(&self, network: Network) -> Result<ExtendedPrivKey> {
         let mut ext_priv_key_seed = [0u8; 64];
@@ -78,6 +157,26 @@ pub trait Seed {
         )
     }
 
+    fn derive_contract_identities(&self) -> ContractIdentities {
+        let mut secret = [0u8; 32];
+
+        Hkdf::<Sha256>::new(None, &self.seed())
+            .expand(b"CONTRACT_IDENTITIES", &mut secret)
+            .expect("okm array is of correct length");
+
+        ContractIdentities(secret)
+    }
+
+    fn derive_backup_key(&self) -> BackupKey {
+        let mut key = [0u8; 32];
+
+        Hkdf::<Sha256>::new(None, &self.seed())
+            .expand(b"CFD_BACKUP_KEY", &mut key)
+            .expect("okm array is of correct length");
+
+        BackupKey::from(key)
+    }
+
     fn derive_identities(&self) -> Identities {
         let (identity_pk, identity_sk) = self.derive_identity();
         let keypair_libp2p = self.derive_ed25519_keypair();
@@ -86,16 +185,24 @@ pub trait Seed {
             identity_sk,
             identity_pk,
             libp2p: Keypair::Ed25519(keypair_libp2p),
+            contracts: self.derive_contract_identities(),
+            backup_key: self.derive_backup_key(),
         }
     }
 }
 
-#[derive(Copy, Clone)]
-pub struct RandomSeed([u8; 256]);
+#[derive(Clone)]
+pub struct RandomSeed([u8; RANDOM_SEED_LEN]);
 
 impl Seed for RandomSeed {
-    fn seed(&self) -> Vec<u8> {
-        self.0.to_vec()
+    fn seed(&self) -> Zeroizing<Vec<u8>> {
+        Zeroizing::new(self.0.to_vec())
+    }
+}
+
+impl Drop for RandomSeed {
+    fn drop(&mut self) {
+        self.0[..].zeroize();
     }
 }
 
@@ -105,58 +212,200 @@ impl Debug for RandomSeed {
     }
 }
 
-impl RandomSeed {
+impl Default for RandomSeed {
+    fn default() -> Self {
+        let mut seed = [0u8; RANDOM_SEED_LEN];
+        rand::thread_rng().fill(&mut seed);
+
+        Self(seed)
+    }
+}
+
+/// The seed stored in the seed file of a data directory.
+///
+/// Seed files created before mnemonic backups were supported hold a [`RandomSeed`] of 256 bytes,
+/// newer ones the 32 bytes of entropy of a [`MnemonicSeed`].
+#[derive(Clone, Debug)]
+pub enum FileSeed {
+    Random(RandomSeed),
+    Mnemonic(MnemonicSeed),
+}
+
+impl Seed for FileSeed {
+    fn seed(&self) -> Zeroizing<Vec<u8>> {
+        match self {
+            FileSeed::Random(seed) => seed.seed(),
+            FileSeed::Mnemonic(seed) => seed.seed(),
+        }
+    }
+}
+
+impl FileSeed {
     /// Initialize a [`Seed`] from a path.
     /// Generates new seed if there was no seed found in the given path
-    pub async fn initialize(seed_file: &Path) -> Result<RandomSeed> {
-        let seed = if !seed_file.exists() {
+    ///
+    /// The passphrase is required if the seed file is encrypted. A new seed is encrypted with
+    /// the passphrase, if given.
+    pub async fn initialize(seed_file: &Path, passphrase: Option<Passphrase>) -> Result<FileSeed> {
+        if !seed_file.exists() {
             tracing::info!("No seed found. Generating new seed");
-            let seed = RandomSeed::default();
-            seed.write_to(seed_file).await?;
-            seed
+            let seed = FileSeed::Mnemonic(MnemonicSeed::default());
+            seed.write_to(seed_file, passphrase.as_ref()).await?;
+
+            return Ok(seed);
+        }
+
+        FileSeed::read_from(seed_file, passphrase.as_ref()).await
+    }
+
+    /// Load the seed from an existing seed file at `path`.
+    ///
+    /// The passphrase is required if the seed file is encrypted.
+    pub async fn read_from(path: &Path, passphrase: Option<&Passphrase>) -> Result<FileSeed> {
+        if !path.exists() {
+            let path = path.display();
+            bail!("No seed file at {path}")
+        }
+
+        let bytes = Zeroizing::new(tokio::fs::read(path).await?);
+        let bytes = if bytes.starts_with(ENCRYPTED_SEED_MAGIC) {
+            let passphrase =
+                passphrase.context("Seed file is encrypted but no passphrase was given")?;
+
+            decrypt(&bytes, passphrase)?
         } else {
-            RandomSeed::read_from(seed_file).await?
+            bytes
         };
-        Ok(seed)
+
+        FileSeed::from_bytes(&bytes)
     }
 
-    async fn read_from(path: &Path) -> Result<Self> {
+    /// Whether the seed file at `path` is encrypted with a passphrase.
+    ///
+    /// Returns `false` if there is no seed file.
+    pub async fn is_encrypted(path: &Path) -> Result<bool> {
+        if !path.exists() {
+            return Ok(false);
+        }
+
         let bytes = tokio::fs::read(path).await?;
 
-        let bytes = bytes
-            .try_into()
-            .map_err(|_| anyhow!("Bytes from seed file don't fit into array"))?;
+        Ok(bytes.starts_with(ENCRYPTED_SEED_MAGIC))
+    }
+
+    /// Replace the plaintext seed file at `path` with one encrypted with `passphrase`.
+    pub async fn encrypt(path: &Path, passphrase: &Passphrase) -> Result<()> {
+        if FileSeed::is_encrypted(path).await? {
+            let path = path.display();
+            bail!("Seed file at {path} is already encrypted")
+        }
+
+        let seed = FileSeed::read_from(path, None).await?;
+
+        // Write to a separate file first so that the seed is never lost halfway through
+        let encrypted_path = path.with_extension("encrypted");
+        seed.write_to(&encrypted_path, Some(passphrase)).await?;
+        tokio::fs::rename(&encrypted_path, path).await?;
+
+        tracing::info!(path = %path.display(), "Encrypted seed file");
 
-        Ok(RandomSeed(bytes))
+        Ok(())
     }
 
-    async fn write_to(&self, path: &Path) -> Result<()> {
+    /// Restore the seed file at `path` from the mnemonics of a backup, see
+    /// [`FileSeed::mnemonics`].
+    ///
+    /// The seed is encrypted with the passphrase, if given.
+    pub async fn restore(
+        path: &Path,
+        mnemonics: &[MnemonicSeed],
+        passphrase: Option<Passphrase>,
+    ) -> Result<FileSeed> {
+        let seed = FileSeed::from_mnemonics(mnemonics)?;
+        seed.write_to(path, passphrase.as_ref()).await?;
+
+        tracing::info!(path = %path.display(), "Restored seed file from mnemonic");
+
+        Ok(seed)
+    }
+
+    /// The mnemonics to back up the seed with.
+    ///
+    /// A [`MnemonicSeed`] is backed up as its single mnemonic. A [`RandomSeed`] holds more
+    /// entropy than 24 words can encode and is backed up as 8 mnemonics instead, each encoding 32
+    /// consecutive bytes of the seed.
+    pub fn mnemonics(&self) -> Vec<MnemonicSeed> {
+        match self {
+            FileSeed::Mnemonic(mnemonic) => vec![mnemonic.clone()],
+            FileSeed::Random(seed) => seed
+                .0
+                .chunks(MNEMONIC_ENTROPY_LEN)
+                .map(|chunk| {
+                    MnemonicSeed::from_entropy(chunk).expect("32 bytes to be valid entropy")
+                })
+                .collect(),
+        }
+    }
+
+    /// Reassemble the seed from the mnemonics of [`FileSeed::mnemonics`], in the same order.
+    pub fn from_mnemonics(mnemonics: &[MnemonicSeed]) -> Result<Self> {
+        if mnemonics.len() != 1 && mnemonics.len() != RANDOM_SEED_MNEMONIC_COUNT {
+            bail!(
+                "Expected 1 or {RANDOM_SEED_MNEMONIC_COUNT} mnemonics of {MNEMONIC_WORD_COUNT} words but got {}",
+                mnemonics.len()
+            )
+        }
+
+        let mut bytes = Zeroizing::new(Vec::with_capacity(mnemonics.len() * MNEMONIC_ENTROPY_LEN));
+        for mnemonic in mnemonics {
+            bytes.extend_from_slice(&mnemonic.seed());
+        }
+
+        FileSeed::from_bytes(&bytes)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let seed = match bytes.len() {
+            RANDOM_SEED_LEN => FileSeed::Random(RandomSeed(
+                bytes.try_into().expect("256 bytes to fit into array"),
+            )),
+            MNEMONIC_ENTROPY_LEN => FileSeed::Mnemonic(MnemonicSeed::from_entropy(bytes)?),
+            len => bail!("Seed file of {len} bytes is neither a random nor a mnemonic seed"),
+        };
+
+        Ok(seed)
+    }
+
+    /// Write the bytes the keys are derived from to `path`, encrypted with the passphrase if
+    /// given.
+    async fn write_to(&self, path: &Path, passphrase: Option<&Passphrase>) -> Result<()> {
         if path.exists() {
             let path = path.display();
             anyhow::bail!("Refusing to overwrite file at {path}")
         }
 
-        tokio::fs::write(path, &self.0).await?;
+        let bytes = self.seed();
+        match passphrase {
+            Some(passphrase) => tokio::fs::write(path, encrypt(&bytes, passphrase)?).await?,
+            None => tokio::fs::write(path, bytes.as_slice()).await?,
+        }
 
         Ok(())
     }
 }
 
-impl Default for RandomSeed {
-    fn default() -> Self {
-        let mut seed = [0u8; 256];
-        rand::thread_rng().fill(&mut seed);
+#[derive(Clone)]
+pub struct UmbrelSeed([u8; 32]);
 
-        Self(seed)
+impl Seed for UmbrelSeed {
+    fn seed(&self) -> Zeroizing<Vec<u8>> {
+        Zeroizing::new(self.0.to_vec())
     }
 }
 
-#[derive(Copy, Clone)]
-pub struct UmbrelSeed([u8; 32]);
-
-impl Seed for UmbrelSeed {
-    fn seed(&self) -> Vec<u8> {
-        self.0.to_vec()
+impl Drop for UmbrelSeed {
+    fn drop(&mut self) {
+        self.0[..].zeroize();
     }
 }
 
@@ -165,3 +414,396 @@ impl From<[u8; 32]> for UmbrelSeed {
         Self(bytes)
     }
 }
+
+/// Seed backed by a BIP39 mnemonic of 24 words for a human-readable backup.
+///
+/// All keys are derived from the 32 bytes of entropy of the mnemonic along the same paths as for
+/// the other seeds, so the mnemonic of an [`UmbrelSeed`] reproduces its identities and wallet
+/// addresses. A [`RandomSeed`] holds more entropy than 24 words can encode and is backed up as
+/// several mnemonics, see [`FileSeed::mnemonics`].
+#[derive(Clone, PartialEq)]
+pub struct MnemonicSeed(bip39::Mnemonic);
+
+impl MnemonicSeed {
+    /// Parse the words of one or more mnemonics of 24 words, in order.
+    pub fn parse_all(words: &str) -> Result<Vec<Self>> {
+        let words = words.split_whitespace().collect::<Vec<_>>();
+        if words.is_empty() || words.len() % MNEMONIC_WORD_COUNT != 0 {
+            bail!(
+                "Expected a multiple of {MNEMONIC_WORD_COUNT} words but got {} words",
+                words.len()
+            )
+        }
+
+        words
+            .chunks(MNEMONIC_WORD_COUNT)
+            .map(|chunk| chunk.join(" ").parse())
+            .collect()
+    }
+
+    fn from_entropy(entropy: &[u8]) -> Result<Self> {
+        let mnemonic = bip39::Mnemonic::from_entropy(entropy)
+            .map_err(|e| anyhow!("Invalid mnemonic entropy: {e}"))?;
+
+        Ok(Self(mnemonic))
+    }
+}
+
+impl Default for MnemonicSeed {
+    fn default() -> Self {
+        let mut entropy = Zeroizing::new([0u8; MNEMONIC_ENTROPY_LEN]);
+        rand::thread_rng().fill(entropy.as_mut_slice());
+
+        Self::from_entropy(entropy.as_slice()).expect("32 bytes to be valid entropy")
+    }
+}
+
+impl Seed for MnemonicSeed {
+    fn seed(&self) -> Zeroizing<Vec<u8>> {
+        let (mut entropy, len) = self.0.to_entropy_array();
+        let seed = Zeroizing::new(entropy[..len].to_vec());
+        entropy[..].zeroize();
+
+        seed
+    }
+}
+
+impl From<&UmbrelSeed> for MnemonicSeed {
+    fn from(seed: &UmbrelSeed) -> Self {
+        Self::from_entropy(&seed.0).expect("32 bytes to be valid entropy")
+    }
+}
+
+impl FromStr for MnemonicSeed {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let words = s.split_whitespace().collect::<Vec<_>>();
+        if words.len() != MNEMONIC_WORD_COUNT {
+            bail!(
+                "Expected {MNEMONIC_WORD_COUNT} words but got {} words",
+                words.len()
+            )
+        }
+
+        let mnemonic = bip39::Mnemonic::parse_normalized(&words.join(" "))
+            .map_err(|e| anyhow!("Invalid mnemonic: {e}"))?;
+
+        Ok(Self(mnemonic))
+    }
+}
+
+impl fmt::Display for MnemonicSeed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Debug for MnemonicSeed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("MnemonicSeed").field(&"...").finish()
+    }
+}
+
+/// Passphrase to encrypt a seed file with.
+///
+/// The passphrase is wiped from memory when dropped.
+#[derive(Clone, PartialEq)]
+pub struct Passphrase(Zeroizing<String>);
+
+impl Passphrase {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<String> for Passphrase {
+    fn from(passphrase: String) -> Self {
+        Self(Zeroizing::new(passphrase))
+    }
+}
+
+impl Debug for Passphrase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Passphrase").field(&"...").finish()
+    }
+}
+
+/// Encrypt `seed` with a key derived from `passphrase` using Argon2id.
+///
+/// The encrypted seed is laid out as magic, version, the Argon2 memory cost, number of passes
+/// and degree of parallelism as little-endian `u32`s, salt, nonce and the XChaCha20-Poly1305
+/// ciphertext. Everything before the ciphertext is authenticated as associated data.
+fn encrypt(seed: &[u8], passphrase: &Passphrase) -> Result<Vec<u8>> {
+    let mut salt = [0u8; SALT_LEN];
+    rand::thread_rng().fill(&mut salt);
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::thread_rng().fill(&mut nonce);
+
+    let header = [
+        ENCRYPTED_SEED_MAGIC,
+        &[ENCRYPTED_SEED_VERSION],
+        &ARGON2_M_COST.to_le_bytes(),
+        &ARGON2_T_COST.to_le_bytes(),
+        &ARGON2_P_COST.to_le_bytes(),
+        &salt,
+        &nonce,
+    ]
+    .concat();
+
+    let key = derive_key(
+        passphrase,
+        &salt,
+        (ARGON2_M_COST, ARGON2_T_COST, ARGON2_P_COST),
+    )?;
+    let cipher = XChaCha20Poly1305::new(Key::from_slice(key.as_slice()));
+    let ciphertext = cipher
+        .encrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: seed,
+                aad: &header,
+            },
+        )
+        .map_err(|_| anyhow!("Failed to encrypt seed"))?;
+
+    Ok([header, ciphertext].concat())
+}
+
+fn decrypt(bytes: &[u8], passphrase: &Passphrase) -> Result<Zeroizing<Vec<u8>>> {
+    if !bytes.starts_with(ENCRYPTED_SEED_MAGIC) {
+        bail!("Seed is not encrypted")
+    }
+    if bytes.len() < ENCRYPTED_SEED_HEADER_LEN {
+        bail!("Encrypted seed is truncated")
+    }
+    let (header, ciphertext) = bytes.split_at(ENCRYPTED_SEED_HEADER_LEN);
+
+    let (version, rest) = header[ENCRYPTED_SEED_MAGIC.len()..]
+        .split_first()
+        .expect("header to contain the version");
+    if *version != ENCRYPTED_SEED_VERSION {
+        bail!("Unsupported version {version} of encrypted seed file")
+    }
+
+    let (params, rest) = rest.split_at(3 * 4);
+    let param = |i: usize| {
+        u32::from_le_bytes(
+            params[i * 4..(i + 1) * 4]
+                .try_into()
+                .expect("4 bytes to fit into u32"),
+        )
+    };
+    let (m_cost, t_cost, p_cost) = (param(0), param(1), param(2));
+    if m_cost > ARGON2_MAX_M_COST {
+        bail!("Memory cost of {m_cost} KiB in encrypted seed file exceeds {ARGON2_MAX_M_COST} KiB")
+    }
+
+    let (salt, nonce) = rest.split_at(SALT_LEN);
+
+    let key = derive_key(passphrase, salt, (m_cost, t_cost, p_cost))?;
+    let cipher = XChaCha20Poly1305::new(Key::from_slice(key.as_slice()));
+    let seed = cipher
+        .decrypt(
+            XNonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: header,
+            },
+        )
+        .map_err(|_| anyhow!("Failed to decrypt seed, wrong passphrase or corrupted seed file"))?;
+
+    Ok(Zeroizing::new(seed))
+}
+
+/// Derive the key to encrypt a seed with from `passphrase` using Argon2id with the given memory
+/// cost, number of passes and degree of parallelism.
+fn derive_key(
+    passphrase: &Passphrase,
+    salt: &[u8],
+    (m_cost, t_cost, p_cost): (u32, u32, u32),
+) -> Result<Zeroizing<[u8; 32]>> {
+    let mut key = Zeroizing::new([0u8; 32]);
+
+    let params = Params::new(m_cost, t_cost, p_cost, Some(32))
+        .map_err(|e| anyhow!("Invalid Argon2 parameters: {e}"))?;
+
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.0.as_bytes(), salt, &mut *key)
+        .map_err(|e| anyhow!("Failed to derive key from passphrase: {e}"))?;
+
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encrypted_seed_roundtrip() {
+        let seed = RandomSeed::default();
+        let passphrase = Passphrase::from("correct horse battery staple".to_string());
+
+        let encrypted = encrypt(&seed.0, &passphrase).unwrap();
+        let decrypted = decrypt(&encrypted, &passphrase).unwrap();
+
+        assert!(encrypted.starts_with(ENCRYPTED_SEED_MAGIC));
+        assert_eq!(
+            encrypted[ENCRYPTED_SEED_MAGIC.len()],
+            ENCRYPTED_SEED_VERSION
+        );
+        assert_eq!(decrypted, seed.seed());
+    }
+
+    #[test]
+    fn encrypted_seed_with_tampered_header_is_rejected() {
+        let seed = RandomSeed::default();
+        let passphrase = Passphrase::from("passphrase".to_string());
+
+        let mut encrypted = encrypt(&seed.0, &passphrase).unwrap();
+        // Lower the number of passes
+        encrypted[ENCRYPTED_SEED_MAGIC.len() + 1 + 4] = 1;
+
+        decrypt(&encrypted, &passphrase).expect_err("tampered header to be rejected");
+    }
+
+    #[test]
+    fn encrypted_seed_cannot_be_decrypted_with_wrong_passphrase() {
+        let seed = RandomSeed::default();
+
+        let encrypted = encrypt(&seed.0, &Passphrase::from("passphrase".to_string())).unwrap();
+
+        decrypt(
+            &encrypted,
+            &Passphrase::from("wrong passphrase".to_string()),
+        )
+        .expect_err("wrong passphrase to be rejected");
+    }
+
+    #[test]
+    fn mnemonic_of_file_seed_reproduces_keys() {
+        let seed = FileSeed::Mnemonic(MnemonicSeed::default());
+
+        let mnemonics = seed.mnemonics();
+        let mnemonic = mnemonics[0].to_string();
+        let restored =
+            FileSeed::from_mnemonics(&MnemonicSeed::parse_all(&mnemonic).unwrap()).unwrap();
+
+        assert_eq!(mnemonics.len(), 1);
+        assert_eq!(mnemonic.split(' ').count(), 24);
+        assert!(matches!(restored, FileSeed::Mnemonic(_)));
+        assert_eq!(restored.seed(), seed.seed());
+        assert_eq!(
+            restored.derive_extended_priv_key(Network::Testnet).unwrap(),
+            seed.derive_extended_priv_key(Network::Testnet).unwrap()
+        );
+        assert_eq!(
+            restored.derive_identities().peer_id(),
+            seed.derive_identities().peer_id()
+        );
+    }
+
+    #[test]
+    fn mnemonic_of_umbrel_seed_reproduces_keys() {
+        let seed = UmbrelSeed::from([42u8; 32]);
+
+        let mnemonic = MnemonicSeed::from(&seed);
+
+        assert_eq!(mnemonic.to_string().split(' ').count(), 24);
+        assert_eq!(
+            mnemonic.derive_identities().peer_id(),
+            seed.derive_identities().peer_id()
+        );
+    }
+
+    #[test]
+    fn mnemonics_of_random_seed_reproduce_keys() {
+        let seed = FileSeed::Random(RandomSeed::default());
+
+        let words = seed
+            .mnemonics()
+            .iter()
+            .map(|mnemonic| mnemonic.to_string())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let restored = FileSeed::from_mnemonics(&MnemonicSeed::parse_all(&words).unwrap()).unwrap();
+
+        assert_eq!(words.split(' ').count(), 8 * 24);
+        assert!(matches!(restored, FileSeed::Random(_)));
+        assert_eq!(restored.seed(), seed.seed());
+        assert_eq!(
+            restored.derive_extended_priv_key(Network::Testnet).unwrap(),
+            seed.derive_extended_priv_key(Network::Testnet).unwrap()
+        );
+        assert_eq!(
+            restored.derive_identities().peer_id(),
+            seed.derive_identities().peer_id()
+        );
+    }
+
+    #[test]
+    fn incomplete_mnemonics_of_random_seed_are_rejected() {
+        let seed = FileSeed::Random(RandomSeed::default());
+        let mut mnemonics = seed.mnemonics();
+        mnemonics.pop();
+
+        FileSeed::from_mnemonics(&mnemonics).unwrap_err();
+    }
+
+    #[test]
+    fn contract_identity_is_derived_per_order() {
+        let seed = RandomSeed::default();
+        let order_id = OrderId::default();
+
+        let (_, pk) = seed.derive_identities().contracts.derive(order_id);
+        let (_, same_pk) = seed.derive_identities().contracts.derive(order_id);
+        let (_, other_pk) = seed
+            .derive_identities()
+            .contracts
+            .derive(OrderId::default());
+
+        assert_eq!(pk, same_pk);
+        assert_ne!(pk, other_pk);
+    }
+
+    #[test]
+    fn mnemonic_with_typo_is_rejected() {
+        let mnemonic = MnemonicSeed::from(&UmbrelSeed::from([42u8; 32])).to_string();
+        let mut words = mnemonic.split(' ').collect::<Vec<_>>();
+        words[3] = "itchysats";
+
+        words.join(" ").parse::<MnemonicSeed>().unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn random_seed_file_is_still_read() {
+        let path = std::env::temp_dir().join(format!("seed-{}", uuid::Uuid::new_v4()));
+        let seed = RandomSeed::default();
+        tokio::fs::write(&path, &seed.0).await.unwrap();
+
+        let read = FileSeed::read_from(&path, None).await.unwrap();
+
+        assert!(matches!(read, FileSeed::Random(_)));
+        assert_eq!(read.seed(), seed.seed());
+
+        tokio::fs::remove_file(&path).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn encrypting_seed_file_keeps_seed() {
+        let path = std::env::temp_dir().join(format!("seed-{}", uuid::Uuid::new_v4()));
+        let passphrase = Passphrase::from("passphrase".to_string());
+
+        let seed = FileSeed::initialize(&path, None).await.unwrap();
+        FileSeed::encrypt(&path, &passphrase).await.unwrap();
+
+        assert!(FileSeed::is_encrypted(&path).await.unwrap());
+        FileSeed::initialize(&path, None)
+            .await
+            .expect_err("encrypted seed to require passphrase");
+        let unlocked = FileSeed::initialize(&path, Some(passphrase)).await.unwrap();
+        assert_eq!(unlocked.seed(), seed.seed());
+
+        tokio::fs::remove_file(&path).await.unwrap();
+    }
+}
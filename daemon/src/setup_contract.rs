@@ -11,8 +11,10 @@ use crate::wire::Msg3;
 use crate::wire::SetupMsg;
 use anyhow::Context;
 use anyhow::Result;
+use bdk::bitcoin::secp256k1::SecretKey;
 use bdk::bitcoin::util::psbt::PartiallySignedTransaction;
 use bdk::bitcoin::Amount;
+use bdk::bitcoin::PublicKey;
 use bdk_ext::keypair;
 use futures::Sink;
 use futures::SinkExt;
@@ -66,11 +68,11 @@ pub async fn new(
     role: Role,
     position: Position,
     n_payouts: usize,
+    (sk, pk): (SecretKey, PublicKey),
 ) -> Result<Dlc> {
     tracing::debug!(?setup_params, ?role, ?position, ?n_payouts);
     tracing::trace!(?oracle_pk, ?announcement);
 
-    let (sk, pk) = keypair::new(&mut rand::thread_rng());
     let (rev_sk, rev_pk) = keypair::new(&mut rand::thread_rng());
     let (publish_sk, publish_pk) = keypair::new(&mut rand::thread_rng());
 
@@ -133,6 +135,7 @@ pub async fn new(
     let payouts = HashMap::from_iter([(
         announcement.into(),
         calculate_payouts(
+            settlement_event_id.trading_pair(),
             position,
             role,
             setup_params.price,
@@ -376,6 +379,7 @@ pub async fn new(
         revoked_commit: Vec::new(),
         settlement_event_id,
         refund_timelock: setup_params.refund_timelock,
+        oracle_pk,
     })
 }
 
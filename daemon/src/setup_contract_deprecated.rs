@@ -13,9 +13,11 @@ use crate::wire::SetupMsg;
 use anyhow::Context;
 use anyhow::Result;
 use bdk::bitcoin::secp256k1::ecdsa::Signature;
+use bdk::bitcoin::secp256k1::SecretKey;
 use bdk::bitcoin::secp256k1::SECP256K1;
 use bdk::bitcoin::util::psbt::PartiallySignedTransaction;
 use bdk::bitcoin::Amount;
+use bdk::bitcoin::PublicKey;
 use bdk::bitcoin::Transaction;
 use bdk::descriptor::Descriptor;
 use bdk::miniscript::DescriptorTrait;
@@ -89,11 +91,11 @@ pub async fn new(
     role: Role,
     position: Position,
     n_payouts: usize,
+    (sk, pk): (SecretKey, PublicKey),
 ) -> Result<Dlc> {
     tracing::debug!(?setup_params, ?role, ?position, ?n_payouts);
     tracing::trace!(?oracle_pk, ?announcement);
 
-    let (sk, pk) = keypair::new(&mut rand::thread_rng());
     let (rev_sk, rev_pk) = keypair::new(&mut rand::thread_rng());
     let (publish_sk, publish_pk) = keypair::new(&mut rand::thread_rng());
 
@@ -153,6 +155,7 @@ pub async fn new(
     let payouts = HashMap::from_iter([(
         announcement.into(),
         calculate_payouts(
+            settlement_event_id.trading_pair(),
             position,
             role,
             setup_params.price,
@@ -392,6 +395,7 @@ pub async fn new(
         revoked_commit: Vec::new(),
         settlement_event_id,
         refund_timelock: setup_params.refund_timelock,
+        oracle_pk,
     })
 }
 
@@ -442,6 +446,7 @@ pub async fn roll_over(
             nonce_pks: announcement.nonce_pks.clone(),
         },
         calculate_payouts(
+            announcement.id.trading_pair(),
             our_position,
             our_role,
             rollover_params.price,
@@ -683,6 +688,7 @@ pub async fn roll_over(
         settlement_event_id: None,
         complete_fee: None,
         revocation_sk_ours: None,
+        commit_descriptor: Some(dlc.commit.2.clone()),
     });
 
     // TODO: Remove send- and receiving ACK messages once we are able to handle incomplete DLC
@@ -715,6 +721,7 @@ pub async fn roll_over(
         revoked_commit,
         settlement_event_id: announcement.id,
         refund_timelock: rollover_params.refund_timelock,
+        oracle_pk,
     })
 }
 
@@ -1,7 +1,15 @@
 use crate::bitcoin::util::psbt::PartiallySignedTransaction;
 use crate::command;
 use crate::connection;
+use crate::contract_setup;
+use crate::contract_setup::protocol::dialer_setup_channels;
+use crate::contract_setup::protocol::prove_identity;
+use crate::contract_setup::protocol::Decision;
+use crate::contract_setup::protocol::DialerMessage;
+use crate::contract_setup::protocol::ListenerMessage;
+use crate::contract_setup::protocol::TakeOrder;
 use crate::process_manager;
+use crate::setup_contract;
 use crate::setup_contract_deprecated;
 use crate::wallet;
 use crate::wire;
@@ -9,27 +17,43 @@ use anyhow::anyhow;
 use anyhow::Context;
 use anyhow::Result;
 use async_trait::async_trait;
+use asynchronous_codec::Framed;
+use asynchronous_codec::JsonCodec;
+use bdk::bitcoin::secp256k1::SecretKey;
+use bdk::bitcoin::PublicKey;
 use futures::channel::mpsc;
 use futures::channel::mpsc::UnboundedSender;
 use futures::future;
 use futures::SinkExt;
+use futures::StreamExt;
 use maia_core::secp256k1_zkp::XOnlyPublicKey;
 use maia_core::PartyParams;
+use model::libp2p::PeerId;
 use model::olivia::Announcement;
 use model::Dlc;
+use model::Identity;
 use model::Leverage;
 use model::OrderId;
 use model::Role;
 use model::Usd;
 use sqlite_db;
 use std::time::Duration;
+use tokio_extras::FutureExt;
 use tracing::Instrument;
 use xtra::message_channel::MessageChannel;
+use xtra::Address;
+use xtra_libp2p::Endpoint;
+use xtra_libp2p::OpenSubstream;
+use xtra_libp2p::Substream;
 use xtra_productivity::xtra_productivity;
 
 /// The maximum amount of time we give the maker to send us a response.
 const MAKER_RESPONSE_TIMEOUT: Duration = Duration::from_secs(30);
 
+/// Takes an order of the maker over the `/itchysats/contract-setup/1.0.0` protocol and sets up
+/// the contract once the maker accepted.
+///
+/// Makers which do not support the protocol yet are taken over the legacy connection instead.
 pub struct Actor {
     order_id: OrderId,
     quantity: Usd,
@@ -39,9 +63,14 @@ pub struct Actor {
     announcement: Announcement,
     build_party_params: MessageChannel<wallet::BuildPartyParams, Result<PartyParams>>,
     sign: MessageChannel<wallet::Sign, Result<PartiallySignedTransaction>>,
+    endpoint: Address<Endpoint>,
+    maker_peer_id: PeerId,
+    taker_peer_id: PeerId,
+    taker_identity_sk: x25519_dalek::StaticSecret,
     maker: xtra::Address<connection::Actor>,
     setup_msg_sender: Option<UnboundedSender<wire::SetupMsg>>,
     executor: command::Executor,
+    identity: (SecretKey, PublicKey),
 }
 
 impl Actor {
@@ -53,7 +82,10 @@ impl Actor {
         (oracle_pk, announcement): (XOnlyPublicKey, Announcement),
         build_party_params: MessageChannel<wallet::BuildPartyParams, Result<PartyParams>>,
         sign: MessageChannel<wallet::Sign, Result<PartiallySignedTransaction>>,
+        (endpoint, maker_peer_id, taker_peer_id): (Address<Endpoint>, PeerId, PeerId),
+        taker_identity_sk: x25519_dalek::StaticSecret,
         maker: xtra::Address<connection::Actor>,
+        identity: (SecretKey, PublicKey),
     ) -> Self {
         Self {
             order_id,
@@ -64,13 +96,18 @@ impl Actor {
             announcement,
             build_party_params,
             sign,
+            endpoint,
+            maker_peer_id,
+            taker_peer_id,
+            taker_identity_sk,
             maker,
             setup_msg_sender: None,
             executor: command::Executor::new(db, process_manager),
+            identity,
         }
     }
 
-    /// Returns whether the maker has accepted our setup proposal.
+    /// Returns whether the maker has accepted our setup proposal on the legacy connection.
     fn is_accepted(&self) -> bool {
         self.setup_msg_sender.is_some()
     }
@@ -86,8 +123,85 @@ impl Actor {
     }
 }
 
+/// Open a substream to the maker for contract setup
+///
+/// Returns `None` if the maker does not support [`contract_setup::PROTOCOL`] yet.
+async fn open_substream(
+    endpoint: Address<Endpoint>,
+    maker_peer_id: PeerId,
+) -> Result<Option<Substream>> {
+    let substream = endpoint
+        .send(OpenSubstream::single_protocol(
+            maker_peer_id.inner(),
+            contract_setup::PROTOCOL,
+        ))
+        .await
+        .context("Endpoint is disconnected")?
+        .context("No connection to maker")?
+        .await;
+
+    match substream {
+        Ok(substream) => Ok(Some(substream)),
+        Err(xtra_libp2p::Error::NegotiationFailed(_)) => Ok(None),
+        Err(e) => Err(e).context("Failed to open substream"),
+    }
+}
+
 #[xtra_productivity]
 impl Actor {
+    fn handle(&mut self, _: TakeOrderOverLegacyConnection, ctx: &mut xtra::Context<Self>) {
+        let address = ctx
+            .address()
+            .expect("actor to be able to give address to itself");
+        let this = address.clone();
+
+        let res = self
+            .maker
+            .send(connection::TakeOrder {
+                order_id: self.order_id,
+                quantity: self.quantity,
+                leverage: self.leverage,
+                address,
+            })
+            .await
+            .context("Connection actor is disconnected")
+            .and_then(|res| res);
+
+        if let Err(e) = res {
+            tracing::warn!(id = %self.order_id, "Stopping setup_taker actor: {e:#}");
+
+            if let Err(e) = self
+                .executor
+                .execute(self.order_id, |cfd| Ok(cfd.fail_contract_setup(e)))
+                .await
+            {
+                tracing::warn!("Failed to execute `fail_contract_setup` command: {e:#}");
+            }
+
+            ctx.stop_self();
+            return;
+        }
+
+        let maker_response_timeout = {
+            let this = ctx.address().expect("self to be alive");
+            async move {
+                tokio_extras::time::sleep(MAKER_RESPONSE_TIMEOUT).await;
+
+                let _ = this
+                    .send(MakerResponseTimeoutReached {
+                        timeout: MAKER_RESPONSE_TIMEOUT,
+                    })
+                    .await;
+            }
+        };
+
+        tokio_extras::spawn(
+            &this,
+            maker_response_timeout
+                .instrument(tracing::debug_span!("Wait for maker response timeout")),
+        );
+    }
+
     fn handle(&mut self, _: Accepted, ctx: &mut xtra::Context<Self>) {
         let order_id = self.order_id;
         tracing::info!(%order_id, "Order got accepted");
@@ -122,6 +236,7 @@ impl Actor {
             Role::Taker,
             position,
             self.n_payouts,
+            self.identity,
         );
 
         let this = ctx.address().expect("self to be alive");
@@ -217,58 +332,131 @@ impl Actor {
 impl xtra::Actor for Actor {
     type Stop = ();
     async fn started(&mut self, ctx: &mut xtra::Context<Self>) {
-        let address = ctx
-            .address()
-            .expect("actor to be able to give address to itself");
-        let this = address.clone();
-
-        let res = self
-            .maker
-            .send(connection::TakeOrder {
-                order_id: self.order_id,
-                quantity: self.quantity,
-                leverage: self.leverage,
-                address,
-            })
-            .await;
-
-        if let Err(e) = res {
-            tracing::warn!(id = %self.order_id, "Stopping setup_taker actor: {e}");
-            ctx.stop_self();
-        }
-
-        let maker_response_timeout = {
-            let this = ctx.address().expect("self to be alive");
-            async move {
-                tokio_extras::time::sleep(MAKER_RESPONSE_TIMEOUT).await;
+        let this = ctx.address().expect("self to be alive");
+        let order_id = self.order_id;
 
-                let _ = this
-                    .send(MakerResponseTimeoutReached {
-                        timeout: MAKER_RESPONSE_TIMEOUT,
-                    })
-                    .await;
-            }
+        let take_order = TakeOrder {
+            order_id,
+            quantity: self.quantity,
+            leverage: self.leverage,
+            taker_id: Identity::new(x25519_dalek::PublicKey::from(&self.taker_identity_sk)),
         };
 
-        tokio_extras::spawn(
-            &this,
-            maker_response_timeout
-                .instrument(tracing::debug_span!("Wait for maker response timeout")),
+        let endpoint = self.endpoint.clone();
+        let maker_peer_id = self.maker_peer_id;
+        let taker_peer_id = self.taker_peer_id;
+        let taker_identity_sk = self.taker_identity_sk.clone();
+        let executor = self.executor.clone();
+        let announcement = (self.oracle_pk, self.announcement.clone());
+        let build_party_params = self.build_party_params.clone();
+        let sign = self.sign.clone();
+        let n_payouts = self.n_payouts;
+        let identity = self.identity;
+
+        tokio_extras::spawn_fallible(
+            &this.clone(),
+            {
+                let this = this.clone();
+                async move {
+                    let substream = match open_substream(endpoint, maker_peer_id).await? {
+                        Some(substream) => substream,
+                        None => {
+                            tracing::info!(
+                                %order_id,
+                                "Maker does not support {}, taking order over legacy connection",
+                                contract_setup::PROTOCOL
+                            );
+                            this.send(TakeOrderOverLegacyConnection).await?;
+                            return Ok(());
+                        }
+                    };
+                    let mut framed = Framed::new(
+                        substream,
+                        JsonCodec::<DialerMessage, ListenerMessage>::new(),
+                    );
+
+                    framed
+                        .send(DialerMessage::TakeOrder(take_order))
+                        .await
+                        .context("Failed to send take order request")?;
+
+                    prove_identity(&mut framed, &taker_identity_sk, taker_peer_id).await?;
+
+                    let decision = framed
+                        .next()
+                        .timeout(MAKER_RESPONSE_TIMEOUT, || {
+                            tracing::debug_span!("receive decision")
+                        })
+                        .await
+                        .with_context(|| {
+                            format!(
+                                "Maker did not respond within {} seconds",
+                                MAKER_RESPONSE_TIMEOUT.as_secs()
+                            )
+                        })?
+                        .context("End of stream while receiving decision from maker")?
+                        .context("Failed to decode decision from maker")?
+                        .into_decision()?;
+
+                    match decision {
+                        Decision::Accept => {}
+                        Decision::Reject => {
+                            this.send(Rejected::without_reason()).await?;
+                            return Ok(());
+                        }
+                        Decision::InvalidOrderId => {
+                            this.send(Rejected::invalid_order_id()).await?;
+                            return Ok(());
+                        }
+                    }
+
+                    tracing::info!(%order_id, "Order got accepted");
+
+                    let (setup_params, position) = executor
+                        .execute(order_id, |cfd| cfd.start_contract_setup())
+                        .await?;
+
+                    let (sink, stream) = dialer_setup_channels(framed);
+                    let dlc = setup_contract::new(
+                        sink,
+                        stream,
+                        announcement,
+                        setup_params,
+                        build_party_params,
+                        sign,
+                        Role::Taker,
+                        position,
+                        n_payouts,
+                        identity,
+                    )
+                    .await?;
+
+                    this.send(SetupSucceeded { dlc }).await?;
+
+                    anyhow::Ok(())
+                }
+            },
+            move |error| async move {
+                let _ = this.send(SetupFailed { error }).await;
+            },
         );
     }
 
     async fn stopped(self) -> Self::Stop {}
 }
 
+/// Message sent from the spawned task to `setup_taker::Actor` if the
+/// maker does not support the libp2p contract setup protocol.
+struct TakeOrderOverLegacyConnection;
+
 /// Message sent from the `connection::Actor` to the
 /// `setup_taker::Actor` to notify that the order taken was accepted
 /// by the maker.
 #[derive(Clone, Copy)]
 pub struct Accepted;
 
-/// Message sent from the `connection::Actor` to the
-/// `setup_taker::Actor` to notify that the order taken was rejected
-/// by the maker.
+/// Message sent to the `setup_taker::Actor` to notify that the order
+/// taken was rejected by the maker.
 #[derive(Clone, Copy)]
 pub struct Rejected {
     /// Used to indicate whether the rejection stems from the order ID
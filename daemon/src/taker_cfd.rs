@@ -5,13 +5,12 @@ use crate::connection;
 use crate::oracle;
 use crate::process_manager;
 use crate::projection;
+use crate::seed::ContractIdentities;
 use crate::setup_taker;
 use crate::wallet;
-use anyhow::bail;
 use anyhow::Context;
 use anyhow::Result;
 use async_trait::async_trait;
-use maia_core::secp256k1_zkp::XOnlyPublicKey;
 use maia_core::PartyParams;
 use model::libp2p::PeerId;
 use model::market_closing_price;
@@ -28,6 +27,7 @@ use model::Usd;
 use sqlite_db;
 use time::OffsetDateTime;
 use xtra::Actor as _;
+use xtra_libp2p::Endpoint;
 use xtra_productivity::xtra_productivity;
 use xtras::AddressMap;
 
@@ -41,6 +41,19 @@ pub struct TakeOffer {
     pub leverage: Leverage,
 }
 
+/// The offer cannot be taken as requested, nothing was taken.
+#[derive(thiserror::Error, Debug, Clone, Copy)]
+pub enum InvalidOrder {
+    #[error("Contract setup for order {0} is already in progress")]
+    SetupInProgress(OrderId),
+    #[error("No maker offers available to take")]
+    NoOffers,
+    #[error("Order to take could not be found in current maker offers, you might have an outdated offer")]
+    UnknownOffer,
+    #[error("The maker's offer appears to be outdated, refusing to take offer")]
+    OutdatedOffer,
+}
+
 #[derive(Clone)]
 pub struct ProposeSettlement {
     pub order_id: OrderId,
@@ -52,17 +65,21 @@ pub struct ProposeSettlement {
 pub struct Actor<O, W> {
     db: sqlite_db::Connection,
     wallet: xtra::Address<W>,
-    oracle_pk: XOnlyPublicKey,
     projection_actor: xtra::Address<projection::Actor>,
     process_manager_actor: xtra::Address<process_manager::Actor>,
+    endpoint: xtra::Address<Endpoint>,
     conn_actor: xtra::Address<connection::Actor>,
     setup_actors: AddressMap<OrderId, setup_taker::Actor>,
     libp2p_collab_settlement_actor: xtra::Address<collab_settlement::taker::Actor>,
     oracle_actor: xtra::Address<O>,
     n_payouts: usize,
-    current_maker_offers: Option<MakerOffers>,
+    /// The maker's offers, one entry per trading pair.
+    current_maker_offers: Vec<MakerOffers>,
     maker_identity: Identity,
     maker_peer_id: PeerId,
+    taker_peer_id: PeerId,
+    taker_identity_sk: x25519_dalek::StaticSecret,
+    contract_identities: ContractIdentities,
 }
 
 impl<O, W> Actor<O, W>
@@ -73,30 +90,35 @@ where
     pub fn new(
         db: sqlite_db::Connection,
         wallet: xtra::Address<W>,
-        oracle_pk: XOnlyPublicKey,
         projection_actor: xtra::Address<projection::Actor>,
         process_manager_actor: xtra::Address<process_manager::Actor>,
+        endpoint: xtra::Address<Endpoint>,
         conn_actor: xtra::Address<connection::Actor>,
         oracle_actor: xtra::Address<O>,
         libp2p_collab_settlement_actor: xtra::Address<collab_settlement::taker::Actor>,
         n_payouts: usize,
         maker_identity: Identity,
         maker_peer_id: PeerId,
+        (taker_peer_id, taker_identity_sk): (PeerId, x25519_dalek::StaticSecret),
+        contract_identities: ContractIdentities,
     ) -> Self {
         Self {
             db,
             wallet,
-            oracle_pk,
             projection_actor,
             process_manager_actor,
+            endpoint,
             conn_actor,
             oracle_actor,
             libp2p_collab_settlement_actor,
             n_payouts,
             setup_actors: AddressMap::default(),
-            current_maker_offers: None,
+            current_maker_offers: Vec::new(),
             maker_identity,
             maker_peer_id,
+            taker_peer_id,
+            taker_identity_sk,
+            contract_identities,
         }
     }
 }
@@ -104,18 +126,22 @@ where
 #[xtra_productivity]
 impl<O, W> Actor<O, W> {
     async fn handle_current_offers(&mut self, msg: xtra_libp2p_offer::taker::LatestMakerOffers) {
-        let takers_perspective_of_maker_offers = msg.0.map(|mut maker_offers| {
-            maker_offers.long = maker_offers.long.map(|mut long| {
-                long.origin = Origin::Theirs;
-                long
-            });
-            maker_offers.short = maker_offers.short.map(|mut short| {
-                short.origin = Origin::Theirs;
-                short
-            });
-
-            maker_offers
-        });
+        let takers_perspective_of_maker_offers = msg
+            .0
+            .into_iter()
+            .map(|mut maker_offers| {
+                maker_offers.long = maker_offers.long.map(|mut long| {
+                    long.origin = Origin::Theirs;
+                    long
+                });
+                maker_offers.short = maker_offers.short.map(|mut short| {
+                    short.origin = Origin::Theirs;
+                    short
+                });
+
+                maker_offers
+            })
+            .collect::<Vec<_>>();
 
         self.current_maker_offers = takers_perspective_of_maker_offers.clone();
         tracing::trace!("new maker offers {:?}", takers_perspective_of_maker_offers);
@@ -187,30 +213,35 @@ where
         let disconnected = self
             .setup_actors
             .get_disconnected(order_id)
-            .with_context(|| {
-                format!("Contract setup for order {order_id} is already in progress")
-            })?;
+            .map_err(|_| InvalidOrder::SetupInProgress(order_id))?;
 
-        let (order_to_take, maker_offers) = self
-            .current_maker_offers
-            .clone()
-            .context("No maker offers available to take")?
-            .take_order(order_id);
-
-        let order_to_take = order_to_take.context("Order to take could not be found in current maker offers, you might have an outdated offer")?;
+        if self.current_maker_offers.is_empty() {
+            return Err(InvalidOrder::NoOffers.into());
+        }
 
         // The offer we are instructed to take is removed from the
         // set of available offers immediately so that we don't attempt
         // to take it more than once
+        let order_to_take = self
+            .current_maker_offers
+            .iter_mut()
+            .find_map(|maker_offers| {
+                let (order_to_take, remaining_offers) = maker_offers.clone().take_order(order_id);
+                let order_to_take = order_to_take?;
+                *maker_offers = remaining_offers;
+
+                Some(order_to_take)
+            })
+            .ok_or(InvalidOrder::UnknownOffer)?;
+
         {
-            self.current_maker_offers.replace(maker_offers);
             self.projection_actor
                 .send(projection::Update(self.current_maker_offers.clone()))
                 .await?;
         }
 
         if !order_to_take.is_safe_to_take(OffsetDateTime::now_utc()) {
-            bail!("The maker's offer appears to be outdated, refusing to take offer",);
+            return Err(InvalidOrder::OutdatedOffer.into());
         }
 
         tracing::info!("Taking current order: {:?}", &order_to_take);
@@ -235,7 +266,10 @@ where
         let price_event_id = order_to_take.oracle_event_id;
         let announcement = self
             .oracle_actor
-            .send(oracle::GetAnnouncement(price_event_id))
+            .send(oracle::GetAnnouncement {
+                oracle_pk: order_to_take.oracle_pk,
+                event_id: price_event_id,
+            })
             .await?
             .with_context(|| format!("Announcement {price_event_id} not found"))?;
 
@@ -248,10 +282,17 @@ where
                 cfd.taker_leverage(),
                 self.n_payouts,
             ),
-            (self.oracle_pk, announcement),
+            (order_to_take.oracle_pk, announcement),
             self.wallet.clone().into(),
             self.wallet.clone().into(),
+            (
+                self.endpoint.clone(),
+                self.maker_peer_id,
+                self.taker_peer_id,
+            ),
+            self.taker_identity_sk.clone(),
             self.conn_actor.clone(),
+            self.contract_identities.derive(cfd.id()),
         )
         .create(None)
         .run();
@@ -0,0 +1,219 @@
+//! Transport for libp2p connections over Tor.
+//!
+//! Outgoing connections are dialed through the SOCKS5 proxy of a local Tor daemon, so the
+//! counterparty never learns our IP address. Incoming connections arrive through a hidden service
+//! which the Tor daemon forwards to a local port, so we can be reached without a public IP address.
+
+use data_encoding::BASE32;
+use futures::future;
+use futures::future::BoxFuture;
+use futures::future::Ready;
+use futures::stream::BoxStream;
+use futures::FutureExt;
+use futures::StreamExt;
+use libp2p_core::multiaddr::Protocol;
+use libp2p_core::transport::ListenerEvent;
+use libp2p_core::transport::TransportError;
+use libp2p_core::Multiaddr;
+use libp2p_core::Transport;
+use std::io;
+use std::net::Ipv4Addr;
+use std::net::SocketAddr;
+use tokio::net::TcpListener;
+use tokio::net::TcpStream;
+use tokio_socks::tcp::Socks5Stream;
+use tokio_util::compat::Compat;
+use tokio_util::compat::TokioAsyncReadCompatExt;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Config {
+    /// Address of the SOCKS5 proxy of the Tor daemon, required to dial.
+    pub socks_proxy: Option<SocketAddr>,
+    /// Local port the Tor daemon forwards connections to our hidden service to, required to
+    /// listen on an `/onion3/` address.
+    pub hidden_service_port: Option<u16>,
+}
+
+/// Dials `/onion3/`, `/dns/`, `/ip4/` and `/ip6/` addresses through Tor and listens on
+/// `/onion3/` addresses of our hidden service.
+///
+/// Addresses that cannot be handled with the given [`Config`] are reported as not supported.
+#[derive(Clone, Copy, Debug)]
+pub struct TorTransport {
+    config: Config,
+}
+
+impl TorTransport {
+    pub fn new(config: Config) -> Self {
+        Self { config }
+    }
+}
+
+impl Transport for TorTransport {
+    type Output = Compat<TcpStream>;
+    type Error = io::Error;
+    type Listener =
+        BoxStream<'static, Result<ListenerEvent<Self::ListenerUpgrade, Self::Error>, Self::Error>>;
+    type ListenerUpgrade = Ready<Result<Self::Output, Self::Error>>;
+    type Dial = BoxFuture<'static, Result<Self::Output, Self::Error>>;
+
+    fn listen_on(
+        &mut self,
+        addr: Multiaddr,
+    ) -> Result<Self::Listener, TransportError<Self::Error>> {
+        let port = match (self.config.hidden_service_port, is_onion(&addr)) {
+            (Some(port), true) => port,
+            _ => return Err(TransportError::MultiaddrNotSupported(addr)),
+        };
+        let local = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+
+        let listener = async_stream::try_stream! {
+            let listener = TcpListener::bind(local).await?;
+            tracing::info!(%addr, %local, "Listening for connections to hidden service");
+
+            yield ListenerEvent::NewAddress(addr.clone());
+
+            loop {
+                let (stream, remote) = listener.accept().await?;
+
+                yield ListenerEvent::Upgrade {
+                    upgrade: future::ok(stream.compat()),
+                    local_addr: addr.clone(),
+                    remote_addr: Multiaddr::from(remote.ip()).with(Protocol::Tcp(remote.port())),
+                };
+            }
+        };
+
+        Ok(listener.boxed())
+    }
+
+    fn dial(&mut self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
+        let (socks_proxy, target) = match (self.config.socks_proxy, socks_target(&addr)) {
+            (Some(socks_proxy), Some(target)) => (socks_proxy, target),
+            _ => return Err(TransportError::MultiaddrNotSupported(addr)),
+        };
+
+        let dial = async move {
+            let stream = Socks5Stream::connect(socks_proxy, target)
+                .await
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+
+            Ok(stream.into_inner().compat())
+        };
+
+        Ok(dial.boxed())
+    }
+
+    fn dial_as_listener(
+        &mut self,
+        addr: Multiaddr,
+    ) -> Result<Self::Dial, TransportError<Self::Error>> {
+        self.dial(addr)
+    }
+
+    fn address_translation(&self, _: &Multiaddr, _: &Multiaddr) -> Option<Multiaddr> {
+        None
+    }
+}
+
+fn is_onion(addr: &Multiaddr) -> bool {
+    let mut protocols = addr.iter();
+
+    matches!(
+        (protocols.next(), protocols.next()),
+        (Some(Protocol::Onion3(_)), None)
+    )
+}
+
+/// The host and port to ask the SOCKS5 proxy to connect to.
+///
+/// Names are passed on as they are, so that they are resolved by Tor instead of locally.
+fn socks_target(addr: &Multiaddr) -> Option<(String, u16)> {
+    let mut protocols = addr
+        .iter()
+        .filter(|protocol| !matches!(protocol, Protocol::P2p(_)));
+
+    let target = match protocols.next()? {
+        Protocol::Onion3(onion) => {
+            let host = format!("{}.onion", BASE32.encode(onion.hash()).to_lowercase());
+            (host, onion.port())
+        }
+        host => {
+            let host = match host {
+                Protocol::Ip4(ip) => ip.to_string(),
+                Protocol::Ip6(ip) => ip.to_string(),
+                Protocol::Dns(name) | Protocol::Dns4(name) | Protocol::Dns6(name) => {
+                    name.to_string()
+                }
+                _ => return None,
+            };
+
+            match protocols.next()? {
+                Protocol::Tcp(port) => (host, port),
+                _ => return None,
+            }
+        }
+    };
+
+    if protocols.next().is_some() {
+        return None;
+    }
+
+    Some(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ONION: &str = "vww6ybal4bd7szmgncyruucpgfkqahzddi37ktceo3ah7ngmcopnpyyd";
+    const PEER_ID: &str = "12D3KooWEsK2X8Tp24XtyWh7DM65VfwXtNH2cmfs2JsWmkmwKbV1";
+
+    #[test]
+    fn onion_address_is_dialed_by_name() {
+        let addr = format!("/onion3/{ONION}:10000/p2p/{PEER_ID}")
+            .parse::<Multiaddr>()
+            .unwrap();
+
+        assert_eq!(socks_target(&addr), Some((format!("{ONION}.onion"), 10000)));
+    }
+
+    #[test]
+    fn dns_name_is_not_resolved_locally() {
+        let addr = format!("/dns/mainnet.itchysats.network/tcp/10000/p2p/{PEER_ID}")
+            .parse::<Multiaddr>()
+            .unwrap();
+
+        assert_eq!(
+            socks_target(&addr),
+            Some(("mainnet.itchysats.network".to_owned(), 10000))
+        );
+    }
+
+    #[test]
+    fn ip_addresses_are_dialed_through_proxy() {
+        let ip4 = "/ip4/1.2.3.4/tcp/10000".parse::<Multiaddr>().unwrap();
+        let ip6 = "/ip6/2001:db8::1/tcp/10000".parse::<Multiaddr>().unwrap();
+
+        assert_eq!(socks_target(&ip4), Some(("1.2.3.4".to_owned(), 10000)));
+        assert_eq!(socks_target(&ip6), Some(("2001:db8::1".to_owned(), 10000)));
+    }
+
+    #[test]
+    fn udp_address_is_not_supported() {
+        let addr = "/ip4/1.2.3.4/udp/10000".parse::<Multiaddr>().unwrap();
+
+        assert_eq!(socks_target(&addr), None);
+    }
+
+    #[test]
+    fn only_onion_addresses_can_be_listened_on() {
+        let onion = format!("/onion3/{ONION}:10000")
+            .parse::<Multiaddr>()
+            .unwrap();
+        let tcp = "/ip4/0.0.0.0/tcp/10000".parse::<Multiaddr>().unwrap();
+
+        assert!(is_onion(&onion));
+        assert!(!is_onion(&tcp));
+    }
+}
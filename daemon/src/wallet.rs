@@ -1,17 +1,23 @@
+use crate::blockchain;
 use anyhow::bail;
+use anyhow::ensure;
 use anyhow::Context;
 use anyhow::Result;
 use async_trait::async_trait;
+use bdk::bitcoin::secp256k1::Secp256k1;
 use bdk::bitcoin::util::bip32::ExtendedPrivKey;
 use bdk::bitcoin::util::psbt::PartiallySignedTransaction;
 use bdk::bitcoin::Address;
 use bdk::bitcoin::Amount;
 use bdk::bitcoin::OutPoint;
 use bdk::bitcoin::PublicKey;
+use bdk::bitcoin::Transaction;
 use bdk::bitcoin::Txid;
+use bdk::blockchain::AnyBlockchain;
 use bdk::blockchain::Blockchain;
-use bdk::blockchain::ElectrumBlockchain;
 use bdk::database::BatchDatabase;
+use bdk::database::MemoryDatabase;
+use bdk::descriptor::IntoWalletDescriptor;
 use bdk::sled;
 use bdk::wallet::tx_builder::TxOrdering;
 use bdk::wallet::AddressIndex;
@@ -36,6 +42,13 @@ use xtra_productivity::xtra_productivity;
 use xtras::SendInterval;
 
 const SYNC_INTERVAL: Duration = Duration::from_secs(3 * 60);
+/// Number of blocks within which withdrawals without an explicit fee rate should confirm.
+const WITHDRAW_CONFIRMATION_TARGET: usize = 6;
+/// Number of blocks within which sweeps to the watch-only descriptor should confirm.
+const SWEEP_CONFIRMATION_TARGET: usize = 12;
+/// Funds exceeding the amount to keep in the hot wallet are only swept once they reach this
+/// amount, to avoid sweeping dust after every sync.
+const MIN_SWEEP_AMOUNT: Amount = Amount::from_sat(100_000);
 pub const MAKER_WALLET_ID: &str = "maker-wallet";
 pub const TAKER_WALLET_ID: &str = "taker-wallet";
 
@@ -92,6 +105,8 @@ pub struct Actor<B, DB> {
     wallet: bdk::Wallet<DB>,
     blockchain_client: B,
     used_utxos: LockedUtxos,
+    frozen_utxos: FrozenUtxos,
+    sweep: Option<Sweep>,
     sender: watch::Sender<Option<WalletInfo>>,
 }
 
@@ -99,18 +114,31 @@ pub struct Actor<B, DB> {
 #[error("The transaction is already in the blockchain")]
 pub struct TransactionAlreadyInBlockchain;
 
-impl Actor<ElectrumBlockchain, sled::Tree> {
+#[derive(thiserror::Error, Debug, Clone, Copy)]
+#[error("No output of transaction {0} belongs to the wallet")]
+pub struct NoOutputToSpend(pub Txid);
+
+impl Actor<AnyBlockchain, sled::Tree> {
     pub fn spawn(
-        electrum_rpc_url: &str,
+        backend: &blockchain::Backend,
         ext_priv_key: ExtendedPrivKey,
         db_path: PathBuf,
         wallet_name: String,
+        sweep: Option<SweepConfig>,
     ) -> Result<(xtra::Address<Self>, watch::Receiver<Option<WalletInfo>>)> {
-        let client = bdk::electrum_client::Client::new(electrum_rpc_url)
-            .context("Failed to initialize Electrum RPC client")?;
+        let blockchain_client = backend.wallet_blockchain(ext_priv_key.network, &wallet_name)?;
 
         // Create a database (using default sled type) to store wallet data
         let db = sled::open(db_path)?;
+        let frozen_utxos = FrozenUtxos::load(db.open_tree(format!("{wallet_name}-frozen-utxos"))?)?;
+        let sweep = match sweep {
+            Some(config) => Some(Sweep::load(
+                config,
+                ext_priv_key.network,
+                db.open_tree(format!("{wallet_name}-sweep"))?,
+            )?),
+            None => None,
+        };
         let db = db.open_tree(wallet_name)?;
 
         let wallet = bdk::Wallet::new(
@@ -133,7 +161,9 @@ impl Actor<ElectrumBlockchain, sled::Tree> {
             wallet,
             sender,
             used_utxos: LockedUtxos::new(time_to_lock),
-            blockchain_client: ElectrumBlockchain::from(client),
+            frozen_utxos,
+            sweep,
+            blockchain_client,
         };
 
         let (addr, fut) = actor.create(None).run();
@@ -144,7 +174,7 @@ impl Actor<ElectrumBlockchain, sled::Tree> {
     }
 }
 
-impl<DB> Actor<ElectrumBlockchain, DB>
+impl<DB> Actor<AnyBlockchain, DB>
 where
     DB: BatchDatabase,
 {
@@ -191,25 +221,22 @@ where
         tracing::trace!(target : "wallet", sync_time_sec = %now.elapsed().as_secs(), "Wallet sync done");
         Ok(wallet_info)
     }
-}
 
-#[xtra_productivity]
-impl<DB> Actor<ElectrumBlockchain, DB>
-where
-    DB: BatchDatabase,
-{
-    pub fn handle_sync(&mut self, _msg: Sync) {
-        let wallet_info_update = match self.sync_internal() {
-            Ok(wallet_info) => Some(wallet_info),
-            Err(e) => {
-                tracing::warn!("Syncing failed: {:#}", e);
-                None
-            }
-        };
-        let _ = self.sender.send(wallet_info_update);
+    fn estimate_fee_rate(&self, target_blocks: usize) -> Result<FeeRate> {
+        let fee_rate = self
+            .blockchain_client
+            .estimate_fee(target_blocks)
+            .context("Failed to estimate fee rate")?;
+
+        // Electrum servers report -1 if they have no estimate for the target
+        if fee_rate.as_sat_vb() <= 0.0 {
+            bail!("No fee rate estimate available for {target_blocks} blocks")
+        }
+
+        Ok(fee_rate)
     }
 
-    pub fn handle_withdraw(&mut self, msg: Withdraw) -> Result<Txid> {
+    fn build_withdraw_psbt(&mut self, msg: Withdraw) -> Result<PartiallySignedTransaction> {
         self.sync_internal()?;
 
         if msg.address.network != self.wallet.network() {
@@ -220,17 +247,40 @@ where
             )
         }
 
-        let fee_rate = msg.fee.unwrap_or_else(FeeRate::default_min_relay_fee);
+        let fee_rate = match msg.fee {
+            Some(fee_rate) => fee_rate,
+            None => self
+                .estimate_fee_rate(WITHDRAW_CONFIRMATION_TARGET)
+                .unwrap_or_else(|e| {
+                    tracing::warn!("Falling back to minimum relay fee for withdrawal: {e:#}");
+                    FeeRate::default_min_relay_fee()
+                }),
+        };
         let address = msg.address;
 
-        let mut psbt = {
+        let (psbt, _) = {
             let mut tx_builder = self.wallet.build_tx();
 
             tx_builder
                 .fee_rate(fee_rate)
+                .unspendable(self.frozen_utxos.list())
                 // Turn on RBF signaling
                 .enable_rbf();
 
+            if !msg.utxos.is_empty() {
+                if let Some(frozen) = msg
+                    .utxos
+                    .iter()
+                    .find(|utxo| self.frozen_utxos.contains(utxo))
+                {
+                    bail!("Cannot spend frozen output {frozen}")
+                }
+
+                tracing::info!(utxos = ?msg.utxos, "Spending only selected outputs");
+
+                tx_builder.add_utxos(&msg.utxos)?.manually_selected_only();
+            }
+
             match msg.amount {
                 Some(amount) => {
                     tracing::info!(%amount, %address, "Withdrawing from wallet");
@@ -240,14 +290,118 @@ where
                 None => {
                     tracing::info!(%address, "Draining wallet");
 
-                    tx_builder.drain_wallet().drain_to(address.script_pubkey());
+                    if msg.utxos.is_empty() {
+                        tx_builder.drain_wallet();
+                    }
+
+                    tx_builder.drain_to(address.script_pubkey());
                 }
             }
 
-            let (psbt, _) = tx_builder.finish()?;
+            tx_builder.finish()?
+        };
+
+        Ok(psbt)
+    }
+
+    /// Sweep the spendable funds exceeding the amount to keep to the watch-only descriptor, if
+    /// one is configured.
+    fn sweep_excess(&mut self) -> Result<()> {
+        let keep = match &self.sweep {
+            Some(sweep) => sweep.keep,
+            None => return Ok(()),
+        };
+
+        let unspendable = self.unspendable_utxos();
+        let spendable = self
+            .wallet
+            .list_unspent()?
+            .into_iter()
+            .filter(|utxo| !unspendable.contains(&utxo.outpoint))
+            .map(|utxo| utxo.txout.value)
+            .sum::<u64>();
+        let excess = Amount::from_sat(spendable.saturating_sub(keep.as_sat()));
+
+        if excess < MIN_SWEEP_AMOUNT {
+            return Ok(());
+        }
+
+        let address = match &self.sweep {
+            Some(sweep) => sweep.address()?,
+            None => return Ok(()),
+        };
+
+        let fee_rate = self
+            .estimate_fee_rate(SWEEP_CONFIRMATION_TARGET)
+            .unwrap_or_else(|e| {
+                tracing::warn!("Falling back to minimum relay fee for sweep: {e:#}");
+                FeeRate::default_min_relay_fee()
+            });
+
+        let (mut psbt, _) = {
+            let mut tx_builder = self.wallet.build_tx();
+
+            tx_builder
+                .fee_rate(fee_rate)
+                .unspendable(unspendable)
+                .enable_rbf();
+
+            // Without funds to keep, the fee can only be paid out of the swept amount
+            if keep == Amount::ZERO {
+                tx_builder.drain_wallet().drain_to(address.script_pubkey());
+            } else {
+                tx_builder.add_recipient(address.script_pubkey(), excess.as_sat());
+            }
+
+            tx_builder.finish()?
+        };
+
+        self.wallet.sign(&mut psbt, SignOptions::default())?;
+
+        let tx = psbt.extract_tx();
+        let txid = tx.txid();
+        self.blockchain_client.broadcast(&tx)?;
+
+        if let Some(sweep) = &mut self.sweep {
+            sweep.advance()?;
+        }
+
+        tracing::info!(%txid, amount = %excess, %address, "Swept funds to watch-only descriptor");
+
+        Ok(())
+    }
+}
+
+#[xtra_productivity]
+impl<DB> Actor<AnyBlockchain, DB>
+where
+    DB: BatchDatabase,
+{
+    pub fn handle_sync(&mut self, _msg: Sync) {
+        let wallet_info_update = match self.sync_internal() {
+            Ok(wallet_info) => {
+                if let Err(e) = self.sweep_excess() {
+                    tracing::warn!("Sweeping to watch-only descriptor failed: {:#}", e);
+                }
 
-            psbt
+                Some(wallet_info)
+            }
+            Err(e) => {
+                tracing::warn!("Syncing failed: {:#}", e);
+                None
+            }
         };
+        let _ = self.sender.send(wallet_info_update);
+    }
+
+    pub fn handle_estimate_fee_rate(&mut self, msg: EstimateFeeRate) -> Result<TxFeeRate> {
+        let fee_rate = self.estimate_fee_rate(msg.target_blocks)?;
+
+        Ok(tx_fee_rate(fee_rate))
+    }
+
+    pub fn handle_withdraw(&mut self, msg: Withdraw) -> Result<Txid> {
+        let mut psbt = self.build_withdraw_psbt(msg)?;
 
         self.wallet.sign(&mut psbt, SignOptions::default())?;
 
@@ -259,6 +413,72 @@ where
 
         Ok(txid)
     }
+
+    pub fn handle_build_withdraw_psbt(
+        &mut self,
+        msg: BuildWithdrawPsbt,
+    ) -> Result<PartiallySignedTransaction> {
+        let BuildWithdrawPsbt(withdraw) = msg;
+
+        let psbt = self.build_withdraw_psbt(withdraw)?;
+
+        // Keep the inputs from being used for lock transactions while the PSBT is being signed
+        self.used_utxos.extend(
+            psbt.unsigned_tx
+                .input
+                .iter()
+                .map(|input| input.previous_output),
+        );
+
+        tracing::info!(txid = %psbt.unsigned_tx.txid(), "Built unsigned withdrawal PSBT");
+
+        Ok(psbt)
+    }
+
+    pub fn handle_broadcast_psbt(&mut self, msg: BroadcastPsbt) -> Result<Txid> {
+        let mut psbt = msg.psbt;
+
+        let is_finalized = self
+            .wallet
+            .finalize_psbt(&mut psbt, SignOptions::default())
+            .context("Failed to finalize PSBT")?;
+        if !is_finalized {
+            bail!("PSBT is not fully signed")
+        }
+
+        let tx = psbt.extract_tx();
+        let txid = tx.txid();
+        self.blockchain_client.broadcast(&tx)?;
+
+        tracing::info!(%txid, "Broadcast signed PSBT");
+
+        Ok(txid)
+    }
+
+    pub fn handle_bump_fee(&mut self, msg: BumpFee) -> Result<Transaction> {
+        // The outputs of the parent are only known to the wallet once it saw the parent in the
+        // mempool
+        self.sync_internal()?;
+
+        let mut psbt = match &msg.replaces {
+            Some(previous) => self.wallet.build_cpfp_replacement(
+                &msg.parent,
+                msg.parent_fee,
+                msg.fee_rate.into(),
+                previous,
+            )?,
+            None => self.wallet.build_cpfp_tx(
+                &msg.parent,
+                msg.parent_fee,
+                msg.fee_rate.into(),
+                self.unspendable_utxos(),
+            )?,
+        };
+
+        self.wallet.sign(&mut psbt, SignOptions::default())?;
+
+        Ok(psbt.extract_tx())
+    }
 }
 
 #[xtra_productivity]
@@ -283,6 +503,59 @@ where
         Ok(psbt)
     }
 
+    pub fn handle_list_utxos(&mut self, _msg: ListUtxos) -> Result<Vec<Utxo>> {
+        let locked = self.used_utxos.list();
+
+        let mut utxos = Vec::new();
+        for utxo in self.wallet.list_unspent()? {
+            let confirmation_height = self
+                .wallet
+                .get_tx(&utxo.outpoint.txid, false)?
+                .and_then(|details| details.confirmation_time)
+                .map(|confirmation_time| confirmation_time.height);
+
+            utxos.push(Utxo {
+                outpoint: utxo.outpoint,
+                amount: Amount::from_sat(utxo.txout.value),
+                change: utxo.keychain == KeychainKind::Internal,
+                locked: locked.contains(&utxo.outpoint),
+                frozen: self.frozen_utxos.contains(&utxo.outpoint),
+                confirmation_height,
+            });
+        }
+
+        Ok(utxos)
+    }
+
+    pub fn handle_freeze_utxo(&mut self, msg: FreezeUtxo) -> Result<()> {
+        let FreezeUtxo(outpoint) = msg;
+
+        let is_unspent = self
+            .wallet
+            .list_unspent()?
+            .iter()
+            .any(|utxo| utxo.outpoint == outpoint);
+        if !is_unspent {
+            bail!("Output {outpoint} is not an unspent output of the wallet")
+        }
+
+        self.frozen_utxos.freeze(outpoint)?;
+
+        tracing::info!(%outpoint, "Froze output");
+
+        Ok(())
+    }
+
+    pub fn handle_unfreeze_utxo(&mut self, msg: UnfreezeUtxo) -> Result<()> {
+        let UnfreezeUtxo(outpoint) = msg;
+
+        self.frozen_utxos.unfreeze(outpoint)?;
+
+        tracing::info!(%outpoint, "Unfroze output");
+
+        Ok(())
+    }
+
     pub fn build_party_params(
         &mut self,
         BuildPartyParams {
@@ -291,9 +564,12 @@ where
             fee_rate,
         }: BuildPartyParams,
     ) -> Result<PartyParams> {
-        let psbt = self
-            .wallet
-            .build_lock_tx(amount, &mut self.used_utxos, fee_rate.into())?;
+        let psbt = self.wallet.build_lock_tx(
+            amount,
+            &mut self.used_utxos,
+            &self.frozen_utxos,
+            fee_rate.into(),
+        )?;
 
         Ok(PartyParams {
             lock_psbt: psbt,
@@ -304,8 +580,19 @@ where
     }
 }
 
+impl<B, DB> Actor<B, DB> {
+    /// Outputs that must not be spent by new transactions: the ones locked for contracts being
+    /// set up and the frozen ones.
+    fn unspendable_utxos(&mut self) -> Vec<OutPoint> {
+        let mut unspendable = self.used_utxos.list();
+        unspendable.extend(self.frozen_utxos.list());
+
+        unspendable
+    }
+}
+
 #[async_trait]
-impl<DB: 'static> xtra::Actor for Actor<ElectrumBlockchain, DB>
+impl<DB: 'static> xtra::Actor for Actor<AnyBlockchain, DB>
 where
     DB: BatchDatabase + Send,
 {
@@ -333,14 +620,83 @@ pub struct BuildPartyParams {
 #[derive(Clone, Copy)]
 pub struct Sync;
 
+/// Estimate the fee rate for a transaction to confirm within `target_blocks` blocks.
+#[derive(Clone, Copy)]
+pub struct EstimateFeeRate {
+    pub target_blocks: usize,
+}
+
 pub struct Sign {
     pub psbt: PartiallySignedTransaction,
 }
 
+/// List the unspent outputs of the wallet as of the last sync.
+#[derive(Clone, Copy)]
+pub struct ListUtxos;
+
+/// Exclude an unspent output from being spent until it is unfrozen again.
+///
+/// Frozen outputs are neither used to fund contracts nor withdrawals.
+#[derive(Clone, Copy)]
+pub struct FreezeUtxo(pub OutPoint);
+
+#[derive(Clone, Copy)]
+pub struct UnfreezeUtxo(pub OutPoint);
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+pub struct Utxo {
+    pub outpoint: OutPoint,
+    #[serde(with = "::bdk::bitcoin::util::amount::serde::as_sat")]
+    pub amount: Amount,
+    /// Whether the output belongs to the change keychain.
+    pub change: bool,
+    /// Whether the output was recently selected for a lock transaction and is not used for new
+    /// ones.
+    pub locked: bool,
+    /// Whether the output was frozen and is not spent until it is unfrozen.
+    pub frozen: bool,
+    /// Height of the block that confirmed the output, `None` if it is unconfirmed.
+    pub confirmation_height: Option<u32>,
+}
+
+/// Build and sign a child transaction spending our outputs of `parent`.
+///
+/// The child pays enough fee for the parent and the child together to reach `fee_rate`. It
+/// signals RBF so that it can be replaced by a child paying even more.
+pub struct BumpFee {
+    pub parent: Transaction,
+    pub parent_fee: Amount,
+    pub fee_rate: TxFeeRate,
+    /// The child published by the previous bump, if any.
+    ///
+    /// The new child replaces it, otherwise both would spend the same output of the parent.
+    pub replaces: Option<Transaction>,
+}
+
 pub struct Withdraw {
     pub amount: Option<Amount>,
     pub fee: Option<FeeRate>,
     pub address: Address,
+    /// Outputs to spend; if empty, outputs are chosen by coin selection.
+    pub utxos: Vec<OutPoint>,
+}
+
+/// Build an unsigned withdrawal PSBT to be signed externally instead of signing and
+/// broadcasting it with the hot wallet.
+pub struct BuildWithdrawPsbt(pub Withdraw);
+
+/// Finalize and broadcast an externally signed PSBT.
+pub struct BroadcastPsbt {
+    pub psbt: PartiallySignedTransaction,
+}
+
+/// Sweep funds exceeding `keep` from the hot wallet to a watch-only descriptor.
+#[derive(Debug, Clone)]
+pub struct SweepConfig {
+    /// Output descriptor with a wildcard derivation path, without private keys.
+    pub descriptor: String,
+    /// Amount to keep in the hot wallet as trading margin.
+    pub keep: Amount,
 }
 
 /// Bitcoin error codes: <https://github.com/bitcoin/bitcoin/blob/97d3500601c1d28642347d014a6de1e38f53ae4e/src/rpc/protocol.h#L23>
@@ -370,6 +726,7 @@ trait BuildLockTx {
         &mut self,
         amount: Amount,
         used_utxos: &mut LockedUtxos,
+        frozen_utxos: &FrozenUtxos,
         fee_rate: FeeRate,
     ) -> Result<PartiallySignedTransaction>;
 }
@@ -382,14 +739,18 @@ where
         &mut self,
         amount: Amount,
         used_utxos: &mut LockedUtxos,
+        frozen_utxos: &FrozenUtxos,
         fee_rate: FeeRate,
     ) -> Result<PartiallySignedTransaction> {
+        let mut unspendable = used_utxos.list();
+        unspendable.extend(frozen_utxos.list());
+
         let mut builder = self.build_tx();
 
         builder
             .ordering(TxOrdering::Bip69Lexicographic) // TODO: I think this is pointless but we did this in maia.
             .fee_rate(fee_rate)
-            .unspendable(used_utxos.list())
+            .unspendable(unspendable)
             .add_2of2_multisig_recipient(amount);
 
         let (psbt, _) = builder.finish()?;
@@ -405,6 +766,139 @@ where
     }
 }
 
+/// Module private trait to faciliate testing.
+trait BuildCpfpTx {
+    fn build_cpfp_tx(
+        &self,
+        parent: &Transaction,
+        parent_fee: Amount,
+        fee_rate: FeeRate,
+        unspendable: Vec<OutPoint>,
+    ) -> Result<PartiallySignedTransaction>;
+
+    /// Build a child replacing `previous`, a child of `parent` built before.
+    fn build_cpfp_replacement(
+        &self,
+        parent: &Transaction,
+        parent_fee: Amount,
+        fee_rate: FeeRate,
+        previous: &Transaction,
+    ) -> Result<PartiallySignedTransaction>;
+}
+
+impl<D> BuildCpfpTx for bdk::Wallet<D>
+where
+    D: BatchDatabase,
+{
+    fn build_cpfp_tx(
+        &self,
+        parent: &Transaction,
+        parent_fee: Amount,
+        fee_rate: FeeRate,
+        unspendable: Vec<OutPoint>,
+    ) -> Result<PartiallySignedTransaction> {
+        let parent_txid = parent.txid();
+
+        let mut outpoints = Vec::new();
+        for (vout, output) in parent.output.iter().enumerate() {
+            if self.is_mine(&output.script_pubkey)? {
+                outpoints.push(OutPoint::new(parent_txid, vout as u32));
+            }
+        }
+
+        if outpoints.is_empty() {
+            return Err(NoOutputToSpend(parent_txid).into());
+        }
+
+        // Draining to a change address lets the wallet reduce the change when replacing the child
+        let drain_script = self
+            .get_internal_address(AddressIndex::New)?
+            .script_pubkey();
+
+        let build = |fee_absolute: Option<u64>| -> Result<(PartiallySignedTransaction, u64)> {
+            let mut builder = self.build_tx();
+
+            builder
+                .add_utxos(&outpoints)?
+                .unspendable(unspendable.clone())
+                .drain_to(drain_script.clone())
+                .enable_rbf();
+
+            match fee_absolute {
+                Some(fee) => builder.fee_absolute(fee),
+                None => builder.fee_rate(fee_rate),
+            };
+
+            let (psbt, details) = builder.finish()?;
+            let fee = details.fee.context("Fee of child transaction is unknown")?;
+
+            Ok((psbt, fee))
+        };
+
+        // The first attempt only serves to learn the size of the child
+        let (_, child_fee) = build(None)?;
+
+        let parent_vbytes = (parent.weight() + 3) / 4;
+        let child_vbytes = (child_fee as f32 / fee_rate.as_sat_vb()).ceil() as usize;
+        let package_fee = fee_rate.fee_vb(parent_vbytes + child_vbytes);
+
+        let (psbt, _) = build(Some(
+            package_fee
+                .saturating_sub(parent_fee.as_sat())
+                .max(child_fee),
+        ))?;
+
+        Ok(psbt)
+    }
+
+    fn build_cpfp_replacement(
+        &self,
+        parent: &Transaction,
+        parent_fee: Amount,
+        fee_rate: FeeRate,
+        previous: &Transaction,
+    ) -> Result<PartiallySignedTransaction> {
+        let previous_txid = previous.txid();
+        let previous_fee = self
+            .get_tx(&previous_txid, false)?
+            .and_then(|details| details.fee)
+            .with_context(|| format!("Fee of child transaction {previous_txid} is unknown"))?;
+
+        // The replacement spends the same outputs as the previous child and is the same size
+        let fee = cpfp_replacement_fee(
+            (parent.weight() + 3) / 4,
+            parent_fee.as_sat(),
+            (previous.weight() + 3) / 4,
+            previous_fee,
+            fee_rate,
+        );
+
+        let mut builder = self.build_fee_bump(previous_txid)?;
+        builder.fee_absolute(fee).enable_rbf();
+
+        let (psbt, _) = builder.finish()?;
+
+        Ok(psbt)
+    }
+}
+
+/// Fee of a child replacing one that paid `previous_fee`, so that the parent and the child
+/// together reach `fee_rate`.
+///
+/// A replacement has to pay for its own relay on top of the fee of the child it replaces.
+fn cpfp_replacement_fee(
+    parent_vbytes: usize,
+    parent_fee: u64,
+    child_vbytes: usize,
+    previous_fee: u64,
+    fee_rate: FeeRate,
+) -> u64 {
+    let package_fee = fee_rate.fee_vb(parent_vbytes + child_vbytes);
+    let min_fee = previous_fee + FeeRate::default_min_relay_fee().fee_vb(child_vbytes);
+
+    package_fee.saturating_sub(parent_fee).max(min_fee)
+}
+
 struct LockedUtxos {
     inner: HashSet<(Instant, OutPoint)>,
     time_to_lock: Duration,
@@ -448,11 +942,154 @@ impl LockedUtxos {
     }
 }
 
+/// Outputs the user excluded from being spent.
+///
+/// Persisted in a database tree, if given, so that outputs stay frozen across restarts.
+struct FrozenUtxos {
+    inner: HashSet<OutPoint>,
+    tree: Option<sled::Tree>,
+}
+
+impl FrozenUtxos {
+    fn load(tree: sled::Tree) -> Result<Self> {
+        let mut inner = HashSet::new();
+        for entry in tree.iter() {
+            let (key, _) = entry?;
+            let outpoint = std::str::from_utf8(&key)?
+                .parse()
+                .context("Failed to parse frozen outpoint")?;
+
+            inner.insert(outpoint);
+        }
+
+        Ok(Self {
+            inner,
+            tree: Some(tree),
+        })
+    }
+
+    #[cfg(test)]
+    fn in_memory() -> Self {
+        Self {
+            inner: HashSet::new(),
+            tree: None,
+        }
+    }
+
+    fn freeze(&mut self, outpoint: OutPoint) -> Result<()> {
+        if let Some(tree) = &self.tree {
+            tree.insert(outpoint.to_string(), sled::IVec::default())?;
+            tree.flush()?;
+        }
+
+        self.inner.insert(outpoint);
+
+        Ok(())
+    }
+
+    fn unfreeze(&mut self, outpoint: OutPoint) -> Result<()> {
+        if let Some(tree) = &self.tree {
+            tree.remove(outpoint.to_string())?;
+            tree.flush()?;
+        }
+
+        self.inner.remove(&outpoint);
+
+        Ok(())
+    }
+
+    fn contains(&self, outpoint: &OutPoint) -> bool {
+        self.inner.contains(outpoint)
+    }
+
+    fn list(&self) -> Vec<OutPoint> {
+        self.inner.iter().copied().collect()
+    }
+}
+
+/// Round a fee rate up to whole satoshis per vbyte.
+fn tx_fee_rate(fee_rate: FeeRate) -> TxFeeRate {
+    let sat_per_vbyte = fee_rate.as_sat_vb().ceil() as u32;
+
+    TxFeeRate::from_sat_per_vbyte(sat_per_vbyte)
+}
+
+/// Watch-only descriptor to sweep excess funds to.
+struct Sweep {
+    wallet: bdk::Wallet<MemoryDatabase>,
+    keep: Amount,
+    /// Derivation index of the address of the next sweep.
+    ///
+    /// Persisted so that addresses are not reused across restarts. Only advanced after a
+    /// successful sweep so that failed attempts do not leave gaps in the used addresses.
+    next_index: u32,
+    tree: sled::Tree,
+}
+
+impl Sweep {
+    const NEXT_INDEX_KEY: &'static str = "next_index";
+
+    fn load(config: SweepConfig, network: bdk::bitcoin::Network, tree: sled::Tree) -> Result<Self> {
+        let (_, keymap) = config
+            .descriptor
+            .as_str()
+            .into_wallet_descriptor(&Secp256k1::new(), network)
+            .context("Invalid sweep descriptor")?;
+        ensure!(
+            keymap.is_empty(),
+            "Sweep descriptor must not contain private keys"
+        );
+
+        let wallet = bdk::Wallet::new(
+            config.descriptor.as_str(),
+            None,
+            network,
+            MemoryDatabase::new(),
+        )?;
+
+        let next_index = match tree.get(Self::NEXT_INDEX_KEY)? {
+            Some(bytes) => u32::from_be_bytes(
+                bytes[..]
+                    .try_into()
+                    .context("Failed to parse sweep address index")?,
+            ),
+            None => 0,
+        };
+
+        Ok(Self {
+            wallet,
+            keep: config.keep,
+            next_index,
+            tree,
+        })
+    }
+
+    fn address(&self) -> Result<Address> {
+        let address = self
+            .wallet
+            .get_address(AddressIndex::Peek(self.next_index))?
+            .address;
+
+        Ok(address)
+    }
+
+    fn advance(&mut self) -> Result<()> {
+        self.next_index += 1;
+
+        self.tree
+            .insert(Self::NEXT_INDEX_KEY, self.next_index.to_be_bytes().to_vec())?;
+        self.tree.flush()?;
+
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use bdk_ext::keypair;
     use bdk_ext::new_test_wallet;
+    use bdk_ext::AddressExt;
     use itertools::Itertools;
     use rand::thread_rng;
     use std::collections::HashSet;
@@ -475,6 +1112,8 @@ mod tests {
                     inner: HashSet::default(),
                     time_to_lock,
                 },
+                frozen_utxos: FrozenUtxos::in_memory(),
+                sweep: None,
                 blockchain_client: (),
             })
         }
@@ -502,6 +1141,7 @@ mod tests {
             .build_lock_tx(
                 Amount::from_sat(2500),
                 &mut used_utxos,
+                &FrozenUtxos::in_memory(),
                 FeeRate::default_min_relay_fee(),
             )
             .unwrap();
@@ -509,6 +1149,7 @@ mod tests {
             .build_lock_tx(
                 Amount::from_sat(2500),
                 &mut used_utxos,
+                &FrozenUtxos::in_memory(),
                 FeeRate::default_min_relay_fee(),
             )
             .unwrap();
@@ -541,6 +1182,107 @@ mod tests {
         );
     }
 
+    #[test]
+    fn lock_transaction_does_not_spend_frozen_utxos() {
+        let mut wallet = new_test_wallet(&mut thread_rng(), Amount::from_sat(1000), 10).unwrap();
+        let mut used_utxos = LockedUtxos::new(Duration::from_secs(120));
+        let mut frozen_utxos = FrozenUtxos::in_memory();
+
+        for utxo in wallet.list_unspent().unwrap().into_iter().take(5) {
+            frozen_utxos.freeze(utxo.outpoint).unwrap();
+        }
+
+        let lock_tx = wallet
+            .build_lock_tx(
+                Amount::from_sat(2500),
+                &mut used_utxos,
+                &frozen_utxos,
+                FeeRate::default_min_relay_fee(),
+            )
+            .unwrap();
+
+        assert!(lock_tx
+            .unsigned_tx
+            .input
+            .iter()
+            .all(|input| !frozen_utxos.contains(&input.previous_output)));
+
+        wallet
+            .build_lock_tx(
+                Amount::from_sat(4000),
+                &mut used_utxos,
+                &frozen_utxos,
+                FeeRate::default_min_relay_fee(),
+            )
+            .expect_err("only two unfrozen and unlocked UTXOs to remain");
+    }
+
+    #[test]
+    fn cpfp_transaction_spends_our_output_and_pays_for_parent() {
+        let wallet = new_test_wallet(&mut thread_rng(), Amount::from_sat(100_000), 1).unwrap();
+        let utxo = wallet.list_unspent().unwrap().remove(0);
+        let parent = wallet
+            .get_tx(&utxo.outpoint.txid, true)
+            .unwrap()
+            .and_then(|details| details.transaction)
+            .unwrap();
+        let parent_fee = Amount::from_sat(100);
+        let fee_rate = FeeRate::from_sat_per_vb(10.0);
+
+        let child = wallet
+            .build_cpfp_tx(&parent, parent_fee, fee_rate, Vec::new())
+            .unwrap()
+            .unsigned_tx;
+
+        let child_fee = utxo.txout.value - child.output.iter().map(|o| o.value).sum::<u64>();
+        let package_vbytes = (parent.weight() + child.weight() + 3) / 4;
+
+        assert_eq!(child.input.len(), 1);
+        assert_eq!(child.input[0].previous_output, utxo.outpoint);
+        assert!(child_fee + parent_fee.as_sat() >= fee_rate.fee_vb(package_vbytes));
+    }
+
+    #[test]
+    fn cpfp_replacement_pays_for_package_and_its_own_relay() {
+        let fee_rate = FeeRate::from_sat_per_vb(10.0);
+
+        // The package fee rate dominates
+        assert_eq!(
+            cpfp_replacement_fee(200, 200, 100, 1_000, fee_rate),
+            3_000 - 200
+        );
+        // The previous child already paid almost enough
+        assert_eq!(
+            cpfp_replacement_fee(200, 200, 100, 2_750, fee_rate),
+            2_750 + 100
+        );
+    }
+
+    #[test]
+    fn cannot_build_cpfp_transaction_without_own_output() {
+        let wallet = new_test_wallet(&mut thread_rng(), Amount::from_sat(100_000), 1).unwrap();
+        let parent = Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![],
+            output: vec![bdk::bitcoin::TxOut {
+                value: 100_000,
+                script_pubkey: Address::random().script_pubkey(),
+            }],
+        };
+
+        let error = wallet
+            .build_cpfp_tx(
+                &parent,
+                Amount::ZERO,
+                FeeRate::default_min_relay_fee(),
+                Vec::new(),
+            )
+            .unwrap_err();
+
+        assert!(error.is::<NoOutputToSpend>());
+    }
+
     #[tokio::test]
     async fn utxo_is_locked_after_building_party_params() {
         let mut tasks = Tasks::default();
@@ -630,4 +1372,68 @@ mod tests {
             .unwrap()
             .expect("single UTXO to be available after unlocking it");
     }
+
+    #[test]
+    fn estimated_fee_rate_is_rounded_up_to_whole_sats() {
+        assert_eq!(tx_fee_rate(FeeRate::from_sat_per_vb(4.2)).to_u32(), 5);
+        assert_eq!(tx_fee_rate(FeeRate::from_sat_per_vb(7.0)).to_u32(), 7);
+        assert_eq!(tx_fee_rate(FeeRate::from_sat_per_vb(0.5)).to_u32(), 1);
+    }
+
+    #[test]
+    fn sweep_addresses_are_not_reused_across_restarts() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let (xpub_descriptor, _) = test_descriptors();
+
+        let mut sweep = Sweep::load(
+            sweep_config(&xpub_descriptor),
+            bdk::bitcoin::Network::Testnet,
+            db.open_tree("sweep").unwrap(),
+        )
+        .unwrap();
+        let first_address = sweep.address().unwrap();
+
+        assert_eq!(sweep.address().unwrap(), first_address);
+
+        sweep.advance().unwrap();
+        let second_address = sweep.address().unwrap();
+        assert_ne!(second_address, first_address);
+
+        let sweep = Sweep::load(
+            sweep_config(&xpub_descriptor),
+            bdk::bitcoin::Network::Testnet,
+            db.open_tree("sweep").unwrap(),
+        )
+        .unwrap();
+        assert_eq!(sweep.address().unwrap(), second_address);
+    }
+
+    #[test]
+    fn sweep_descriptor_with_private_keys_is_rejected() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let (_, xprv_descriptor) = test_descriptors();
+
+        let result = Sweep::load(
+            sweep_config(&xprv_descriptor),
+            bdk::bitcoin::Network::Testnet,
+            db.open_tree("sweep").unwrap(),
+        );
+
+        assert!(result.is_err());
+    }
+
+    /// Watch-only and private descriptors of the same key.
+    fn test_descriptors() -> (String, String) {
+        let xprv = ExtendedPrivKey::new_master(bdk::bitcoin::Network::Testnet, &[7u8; 32]).unwrap();
+        let xpub = bdk::bitcoin::util::bip32::ExtendedPubKey::from_priv(&Secp256k1::new(), &xprv);
+
+        (format!("wpkh({xpub}/0/*)"), format!("wpkh({xprv}/0/*)"))
+    }
+
+    fn sweep_config(descriptor: &str) -> SweepConfig {
+        SweepConfig {
+            descriptor: descriptor.to_string(),
+            keep: Amount::from_btc(0.1).unwrap(),
+        }
+    }
 }
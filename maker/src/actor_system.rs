@@ -1,47 +1,69 @@
 use crate::cfd;
 use crate::connection;
+use crate::exposure;
+use crate::exposure::RiskLimits;
+use crate::fee_estimation::FeeEstimation;
 use crate::metrics::time_to_first_position;
+use crate::policy;
+use crate::quoting;
 use anyhow::Result;
 use bdk::bitcoin;
 use bdk::bitcoin::util::psbt::PartiallySignedTransaction;
 use bdk::bitcoin::Amount;
+use bdk::bitcoin::OutPoint;
 use bdk::bitcoin::Txid;
 use daemon::archive_closed_cfds;
 use daemon::archive_failed_cfds;
+use daemon::backup;
 use daemon::collab_settlement;
 use daemon::command;
+use daemon::libp2p_utils;
 use daemon::monitor;
 use daemon::oracle;
 use daemon::oracle::NoAnnouncement;
 use daemon::position_metrics;
 use daemon::process_manager;
 use daemon::projection;
+use daemon::recovery;
 use daemon::rollover;
 use daemon::seed::Identities;
+use daemon::tor;
 use daemon::wallet;
-use libp2p_tcp::TokioTcpConfig;
 use maia_core::secp256k1_zkp::XOnlyPublicKey;
 use maia_core::PartyParams;
+use model::libp2p::PeerId;
 use model::olivia::Announcement;
 use model::FundingRate;
 use model::Leverage;
 use model::OpeningFee;
 use model::OrderId;
 use model::Price;
+use model::RecoveredContract;
 use model::Role;
+use model::TradingPair;
 use model::TxFeeRate;
 use model::Usd;
+use std::collections::HashMap;
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::time::Duration;
+use tokio::sync::watch;
 use tokio_extras::Tasks;
+use xtra::prelude::MessageChannel;
 use xtra::Actor;
 use xtra::Address;
 use xtra::Context;
 use xtra::Handler;
+use xtra_bitmex_price_feed::LatestQuotes;
+use xtra_bitmex_price_feed::Quotes;
 use xtra_libp2p::endpoint;
 use xtra_libp2p::libp2p::Multiaddr;
 use xtra_libp2p::listener;
+use xtra_libp2p::BanPeer;
+use xtra_libp2p::ConnectionGating;
 use xtra_libp2p::Endpoint;
+use xtra_libp2p::UnbanPeer;
+use xtra_libp2p_identify::PeerInfo;
 use xtra_libp2p_ping::ping;
 use xtra_libp2p_ping::pong;
 use xtras::supervisor::always_restart_after;
@@ -57,12 +79,19 @@ pub const RESTART_INTERVAL: Duration = Duration::from_secs(5);
 
 pub struct ActorSystem<O: 'static, W: 'static> {
     pub cfd_actor: Address<cfd::Actor<O, connection::Actor, W>>,
+    quoting_actor: Address<quoting::Actor>,
+    policy_actor: Address<policy::Actor>,
     wallet_actor: Address<W>,
     _archive_closed_cfds_actor: Address<archive_closed_cfds::Actor>,
     _archive_failed_cfds_actor: Address<archive_failed_cfds::Actor>,
     executor: command::Executor,
+    db: sqlite_db::Connection,
+    recovery_actor: Address<recovery::requester::Actor>,
     _tasks: Tasks,
     _pong_actor: Address<pong::Actor>,
+    _identify_listener_actor: Address<xtra_libp2p_identify::listener::Actor>,
+    identify_dialer_actor: Address<xtra_libp2p_identify::dialer::Actor>,
+    endpoint_actor: Address<Endpoint>,
 }
 
 impl<O, W> ActorSystem<O, W>
@@ -74,6 +103,10 @@ where
         + Handler<wallet::Sign, Return = Result<PartiallySignedTransaction>>
         + Handler<wallet::Withdraw, Return = Result<Txid>>
         + Handler<wallet::Sync, Return = ()>
+        + Handler<wallet::EstimateFeeRate, Return = Result<TxFeeRate>>
+        + Handler<wallet::ListUtxos, Return = Result<Vec<wallet::Utxo>>>
+        + Handler<wallet::FreezeUtxo, Return = Result<()>>
+        + Handler<wallet::UnfreezeUtxo, Return = Result<()>>
         + Actor<Stop = ()>,
 {
     #[allow(clippy::too_many_arguments)]
@@ -86,10 +119,15 @@ where
         settlement_interval: time::Duration,
         n_payouts: usize,
         projection_actor: Address<projection::Actor>,
+        cfds_feed: watch::Receiver<Option<Vec<projection::Cfd>>>,
+        price_feed: MessageChannel<LatestQuotes, Quotes>,
         identity: Identities,
         heartbeat_interval: Duration,
         p2p_socket: SocketAddr,
-        listen_multiaddr: Multiaddr,
+        listen_multiaddrs: Vec<Multiaddr>,
+        backup_path: PathBuf,
+        tor: Option<tor::Config>,
+        connection_gating: ConnectionGating,
     ) -> Result<Self>
     where
         M: Handler<monitor::StartMonitoring, Return = ()>
@@ -97,6 +135,8 @@ where
             + Handler<monitor::MonitorCollaborativeSettlement, Return = ()>
             + Handler<monitor::TryBroadcastTransaction, Return = Result<()>>
             + Handler<monitor::MonitorCetFinality, Return = Result<()>>
+            + Handler<monitor::MonitorPunishFinality, Return = Result<()>>
+            + Handler<monitor::IsSpent, Return = Result<bool>>
             + Actor<Stop = ()>,
     {
         let (monitor_addr, monitor_ctx) = Context::new(None);
@@ -113,6 +153,10 @@ where
             .create(None)
             .spawn(&mut tasks);
 
+        let backup_actor = backup::Actor::new(db.clone(), identity.backup_key.clone(), backup_path)
+            .create(None)
+            .spawn(&mut tasks);
+
         tasks.add(process_manager_ctx.run(process_manager::Actor::new(
             db.clone(),
             Role::Maker,
@@ -121,8 +165,10 @@ where
             monitor_addr.clone().into(),
             monitor_addr.clone().into(),
             monitor_addr.clone().into(),
-            monitor_addr.into(),
+            monitor_addr.clone().into(),
+            monitor_addr.clone().into(),
             oracle_addr.clone().into(),
+            backup_actor.into(),
         )));
 
         let (collab_settlement_supervisor, libp2p_collab_settlement_addr) = Supervisor::new({
@@ -135,12 +181,7 @@ where
             let executor = executor.clone();
             let oracle_addr = oracle_addr.clone();
             move || {
-                rollover::maker::Actor::new(
-                    executor.clone(),
-                    oracle_pk,
-                    oracle_addr.clone().into(),
-                    n_payouts,
-                )
+                rollover::maker::Actor::new(executor.clone(), oracle_addr.clone().into(), n_payouts)
             }
         });
         tasks.add(rollover_supervisor.run_log_summary());
@@ -167,6 +208,42 @@ where
             libp2p_rollover_addr.clone(),
             libp2p_collab_settlement_addr.clone(),
             maker_offer_address.clone(),
+            cfds_feed.clone(),
+            identity.contracts.clone(),
+        )
+        .create(None)
+        .spawn(&mut tasks);
+
+        tasks.add(exposure::update_metrics(cfds_feed.clone()));
+
+        let quoting_actor = quoting::Actor::new(price_feed.clone(), cfd_actor_addr.clone().into())
+            .create(None)
+            .spawn(&mut tasks);
+
+        let policy_actor = policy::Actor::new(
+            db.clone(),
+            executor.clone(),
+            cfds_feed,
+            price_feed,
+            cfd_actor_addr.clone().into(),
+            cfd_actor_addr.clone().into(),
+            cfd_actor_addr.clone().into(),
+            cfd_actor_addr.clone().into(),
+            cfd_actor_addr.clone().into(),
+            cfd_actor_addr.clone().into(),
+        )
+        .create(None)
+        .spawn(&mut tasks);
+
+        let recovery_listener_addr = recovery::listener::Actor::new(db.clone())
+            .create(None)
+            .spawn(&mut tasks);
+        let recovery_actor = recovery::requester::Actor::new(
+            db.clone(),
+            endpoint_addr.clone(),
+            identity.contracts.clone(),
+            monitor_addr.clone().into(),
+            monitor_addr.into(),
         )
         .create(None)
         .spawn(&mut tasks);
@@ -176,15 +253,35 @@ where
             move || ping::Actor::new(endpoint_addr.clone(), PING_INTERVAL)
         });
 
-        let (listener_supervisor, listener_actor) = Supervisor::<_, listener::Error>::with_policy(
-            move || listener::Actor::new(endpoint_addr.clone(), listen_multiaddr.clone()),
-            always_restart_after(RESTART_INTERVAL),
-        );
+        let (listener_supervisors, listener_actors): (Vec<_>, Vec<_>) = listen_multiaddrs
+            .into_iter()
+            .map(|listen_multiaddr| {
+                let endpoint_addr = endpoint_addr.clone();
+                Supervisor::<_, listener::Error>::with_policy(
+                    move || listener::Actor::new(endpoint_addr.clone(), listen_multiaddr.clone()),
+                    always_restart_after(RESTART_INTERVAL),
+                )
+            })
+            .unzip();
 
         let pong_address = pong::Actor.create(None).spawn(&mut tasks);
 
+        let identify_listener_actor = xtra_libp2p_identify::listener::Actor::new(
+            endpoint_addr.clone(),
+            identity.libp2p.public(),
+            daemon::IDENTIFY_PROTOCOL_VERSION.to_owned(),
+            daemon::version::version().to_owned(),
+        )
+        .create(None)
+        .spawn(&mut tasks);
+
+        let (identify_dialer_supervisor, identify_dialer_actor) = Supervisor::new({
+            let endpoint_addr = endpoint_addr.clone();
+            move || xtra_libp2p_identify::dialer::Actor::new(endpoint_addr.clone())
+        });
+
         let endpoint = Endpoint::new(
-            Box::new(TokioTcpConfig::new),
+            libp2p_utils::endpoint_transport(tor)?,
             identity.libp2p,
             ENDPOINT_CONNECTION_TIMEOUT,
             [
@@ -194,22 +291,43 @@ where
                     libp2p_collab_settlement_addr.into(),
                 ),
                 (xtra_libp2p_ping::PROTOCOL_NAME, pong_address.clone().into()),
+                (recovery::PROTOCOL, recovery_listener_addr.into()),
+                (
+                    daemon::contract_setup::PROTOCOL,
+                    cfd_actor_addr.clone().into(),
+                ),
+                (
+                    xtra_libp2p_identify::PROTOCOL_NAME,
+                    identify_listener_actor.clone().into(),
+                ),
             ],
             endpoint::Subscribers::new(
                 vec![
                     ping_address.clone().into(),
                     maker_offer_address.clone().into(),
+                    identify_dialer_actor.clone().into(),
+                ],
+                vec![
+                    ping_address.into(),
+                    maker_offer_address.into(),
+                    identify_dialer_actor.clone().into(),
                 ],
-                vec![ping_address.into(), maker_offer_address.into()],
                 vec![],
-                vec![listener_actor.into()],
+                listener_actors
+                    .into_iter()
+                    .map(|listener_actor| listener_actor.into())
+                    .collect(),
             ),
-        );
+        )
+        .with_connection_gating(connection_gating);
 
         tasks.add(endpoint_context.run(endpoint));
 
-        tasks.add(listener_supervisor.run_log_summary());
+        for listener_supervisor in listener_supervisors {
+            tasks.add(listener_supervisor.run_log_summary());
+        }
         tasks.add(ping_supervisor.run_log_summary());
+        tasks.add(identify_dialer_supervisor.run_log_summary());
 
         tasks.add(
             inc_conn_ctx
@@ -236,18 +354,25 @@ where
             .create(None)
             .spawn(&mut tasks);
 
-        tasks.add(time_to_first_position_ctx.run(time_to_first_position::Actor::new(db)));
+        tasks.add(time_to_first_position_ctx.run(time_to_first_position::Actor::new(db.clone())));
 
         tracing::debug!("Maker actor system ready");
 
         Ok(Self {
             cfd_actor: cfd_actor_addr,
+            quoting_actor,
+            policy_actor,
             wallet_actor: wallet_addr,
             _archive_closed_cfds_actor: archive_closed_cfds_actor,
             _archive_failed_cfds_actor: archive_failed_cfds_actor,
             executor,
+            db,
+            recovery_actor,
             _tasks: tasks,
             _pong_actor: pong_address,
+            _identify_listener_actor: identify_listener_actor,
+            identify_dialer_actor,
+            endpoint_actor: endpoint_addr,
         })
     }
 
@@ -257,6 +382,7 @@ where
     #[allow(clippy::too_many_arguments)]
     pub async fn set_offer_params(
         &self,
+        trading_pair: TradingPair,
         price_long: Option<Price>,
         price_short: Option<Price>,
         min_quantity: Usd,
@@ -266,24 +392,121 @@ where
         funding_rate_short: FundingRate,
         opening_fee: OpeningFee,
         leverage_choices: Vec<Leverage>,
+        leverage_maker: Leverage,
     ) -> Result<()> {
         self.cfd_actor
-            .send(cfd::OfferParams {
-                price_long,
-                price_short,
-                min_quantity,
-                max_quantity,
-                tx_fee_rate,
-                funding_rate_long,
-                funding_rate_short,
-                opening_fee,
-                leverage_choices,
+            .send(cfd::NewOffers {
+                params: cfd::OfferParams {
+                    trading_pair,
+                    price_long,
+                    price_short,
+                    min_quantity,
+                    max_quantity,
+                    tx_fee_rate,
+                    funding_rate_long,
+                    funding_rate_short,
+                    opening_fee,
+                    leverage_choices,
+                    leverage_maker,
+                },
             })
             .await??;
 
         Ok(())
     }
 
+    /// Set the limits on our exposure that orders of takers are checked against.
+    ///
+    /// Orders that would exceed any of the limits are rejected. The limits are stored and survive
+    /// a restart.
+    pub async fn set_risk_limits(&self, risk_limits: RiskLimits) -> Result<()> {
+        self.cfd_actor
+            .send(cfd::SetRiskLimits(risk_limits))
+            .await??;
+        Ok(())
+    }
+
+    /// Estimate the fee rate of offers and rollovers, or use the fee rate of the offer parameters
+    /// if `None`.
+    pub async fn set_fee_estimation(&self, fee_estimation: Option<FeeEstimation>) -> Result<()> {
+        self.cfd_actor
+            .send(cfd::SetFeeEstimation(fee_estimation))
+            .await??;
+        Ok(())
+    }
+
+    /// Automatically create offers based on the latest price from the price feed.
+    ///
+    /// Offers are updated periodically until quoting is disabled again. Offers set through
+    /// [`ActorSystem::set_offer_params`] are overwritten on the next update.
+    pub async fn enable_quoting(&self, params: quoting::QuotingParams) -> Result<()> {
+        self.quoting_actor.send(quoting::Enable(params)).await??;
+        Ok(())
+    }
+
+    /// Stop creating offers automatically and pull the current offers.
+    pub async fn disable_quoting(&self) -> Result<()> {
+        self.quoting_actor.send(quoting::Disable).await??;
+        Ok(())
+    }
+
+    /// Decide on requests of takers automatically according to the given policy.
+    ///
+    /// Every decision is recorded alongside its reason, see [`ActorSystem::policy_decisions`].
+    pub async fn enable_policy(&self, params: policy::PolicyParams) -> Result<()> {
+        self.policy_actor.send(policy::Enable(params)).await??;
+        Ok(())
+    }
+
+    /// Leave all decisions on requests of takers to the operator.
+    pub async fn disable_policy(&self) -> Result<()> {
+        self.policy_actor.send(policy::Disable).await?;
+        Ok(())
+    }
+
+    /// What the connected takers told us about themselves through the identify protocol.
+    ///
+    /// Takers which have not been identified (yet) are not included.
+    pub async fn identified_takers(&self) -> Result<HashMap<PeerId, PeerInfo>> {
+        let peer_infos = self
+            .identify_dialer_actor
+            .send(xtra_libp2p_identify::dialer::GetPeerInfos)
+            .await?;
+
+        Ok(peer_infos
+            .into_iter()
+            .map(|(peer, peer_info)| (PeerId::from(peer), peer_info))
+            .collect())
+    }
+
+    /// Disconnect the taker and reject its libp2p connections until the taker is unbanned.
+    ///
+    /// The ban is stored in the database and applied again when the maker restarts.
+    pub async fn ban_taker(&self, peer_id: PeerId) -> Result<()> {
+        self.db.insert_banned_peer(peer_id).await?;
+        self.endpoint_actor.send(BanPeer(peer_id.inner())).await?;
+        Ok(())
+    }
+
+    /// Accept libp2p connections of the taker again.
+    ///
+    /// Takers given via `--deny-peer` are rejected again once the maker restarts.
+    pub async fn unban_taker(&self, peer_id: PeerId) -> Result<()> {
+        self.db.delete_banned_peer(peer_id).await?;
+        self.endpoint_actor.send(UnbanPeer(peer_id.inner())).await?;
+        Ok(())
+    }
+
+    /// Load the policy decisions on requests of an open CFD, oldest first.
+    pub async fn policy_decisions(&self, order_id: OrderId) -> Result<Vec<policy::PolicyDecision>> {
+        let decisions = self
+            .db
+            .load_open_cfd::<policy::PolicyDecisions>(order_id, ())
+            .await?;
+
+        Ok(decisions.decisions)
+    }
+
     pub async fn accept_order(&self, order_id: OrderId) -> Result<()> {
         self.cfd_actor.send(cfd::AcceptOrder { order_id }).await??;
         Ok(())
@@ -335,18 +558,46 @@ where
         amount: Option<Amount>,
         address: bitcoin::Address,
         fee: f32,
+        utxos: Vec<OutPoint>,
     ) -> Result<Txid> {
         self.wallet_actor
             .send(wallet::Withdraw {
                 amount,
                 address,
                 fee: Some(bdk::FeeRate::from_sat_per_vb(fee)),
+                utxos,
             })
             .await?
     }
 
+    pub async fn list_utxos(&self) -> Result<Vec<wallet::Utxo>> {
+        self.wallet_actor.send(wallet::ListUtxos).await?
+    }
+
+    pub async fn freeze_utxo(&self, outpoint: OutPoint) -> Result<()> {
+        self.wallet_actor.send(wallet::FreezeUtxo(outpoint)).await?
+    }
+
+    pub async fn unfreeze_utxo(&self, outpoint: OutPoint) -> Result<()> {
+        self.wallet_actor
+            .send(wallet::UnfreezeUtxo(outpoint))
+            .await?
+    }
+
     pub async fn sync_wallet(&self) -> Result<()> {
         self.wallet_actor.send(wallet::Sync).await?;
         Ok(())
     }
+
+    /// Recover the contracts we have open with a taker after losing the database
+    ///
+    /// The refund transactions of the recovered contracts are published once their timelock
+    /// expired.
+    pub async fn recover(&self, taker: PeerId) -> Result<Vec<RecoveredContract>> {
+        self.recovery_actor
+            .send(recovery::requester::Recover {
+                counterparty: taker,
+            })
+            .await?
+    }
 }
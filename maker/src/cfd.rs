@@ -2,6 +2,10 @@ use crate::collab_settlement;
 use crate::connection;
 use crate::connection::NoConnection;
 use crate::contract_setup;
+use crate::exposure;
+use crate::exposure::Exposure;
+use crate::exposure::RiskLimits;
+use crate::fee_estimation::FeeEstimation;
 use crate::metrics::time_to_first_position;
 use crate::rollover;
 use anyhow::anyhow;
@@ -11,11 +15,14 @@ use anyhow::Result;
 use async_trait::async_trait;
 use bdk::bitcoin::util::psbt::PartiallySignedTransaction;
 use daemon::command;
+use daemon::contract_setup::protocol::Listener;
+use daemon::contract_setup::protocol::TakeOrder;
 use daemon::libp2p_utils::can_use_libp2p;
 use daemon::oracle;
 use daemon::oracle::NoAnnouncement;
 use daemon::process_manager;
 use daemon::projection;
+use daemon::seed::ContractIdentities;
 use daemon::wallet;
 use daemon::wire;
 use maia_core::secp256k1_zkp::XOnlyPublicKey;
@@ -39,14 +46,18 @@ use model::Role;
 use model::RolloverVersion;
 use model::SettlementProposal;
 use model::Timestamp;
+use model::TradingPair;
 use model::TxFeeRate;
 use model::Usd;
 use sqlite_db;
+use std::collections::HashMap;
 use std::collections::HashSet;
 use time::Duration;
+use tokio::sync::watch;
 use tokio_extras::FutureExt;
 use tracing::instrument;
 use xtra::Actor as _;
+use xtra_libp2p::NewInboundSubstream;
 use xtra_productivity::xtra_productivity;
 use xtras::address_map::NotConnected;
 use xtras::AddressMap;
@@ -90,6 +101,24 @@ pub struct RejectRollover {
     pub order_id: OrderId,
 }
 
+/// Take order request received over `/itchysats/contract-setup/1.0.0`
+///
+/// The taker already proved that it owns the identity of the request.
+struct TakeOrderReceived {
+    peer: PeerId,
+    take_order: TakeOrder,
+    listener: Listener,
+}
+
+/// Replace the limits on the exposure of the maker that apply to new orders.
+#[derive(Clone, Copy)]
+pub struct SetRiskLimits(pub RiskLimits);
+
+/// Estimate the fee rate of offers and rollovers, or use the fee rate of the offer parameters if
+/// `None`.
+#[derive(Clone, Copy)]
+pub struct SetFeeEstimation(pub Option<FeeEstimation>);
+
 #[derive(Clone, Copy)]
 pub struct TakerConnected {
     pub id: Identity,
@@ -102,6 +131,7 @@ pub struct TakerDisconnected {
 
 #[derive(Clone)]
 pub struct OfferParams {
+    pub trading_pair: TradingPair,
     pub price_long: Option<Price>,
     pub price_short: Option<Price>,
     pub min_quantity: Usd,
@@ -111,14 +141,22 @@ pub struct OfferParams {
     pub funding_rate_short: FundingRate,
     pub opening_fee: OpeningFee,
     pub leverage_choices: Vec<Leverage>,
+    pub leverage_maker: Leverage,
 }
 
 impl OfferParams {
-    fn pick_oracle_event_id(settlement_interval: Duration) -> BitMexPriceEventId {
-        olivia::next_announcement_after(time::OffsetDateTime::now_utc() + settlement_interval)
+    fn pick_oracle_event_id(&self, settlement_interval: Duration) -> BitMexPriceEventId {
+        olivia::next_announcement_after(
+            self.trading_pair,
+            time::OffsetDateTime::now_utc() + settlement_interval,
+        )
     }
 
-    pub fn create_long_order(&self, settlement_interval: Duration) -> Option<Order> {
+    pub fn create_long_order(
+        &self,
+        settlement_interval: Duration,
+        oracle_pk: XOnlyPublicKey,
+    ) -> Option<Order> {
         self.price_long.map(|price_long| {
             Order::new(
                 Position::Long,
@@ -126,17 +164,23 @@ impl OfferParams {
                 self.min_quantity,
                 self.max_quantity,
                 Origin::Ours,
-                Self::pick_oracle_event_id(settlement_interval),
+                self.pick_oracle_event_id(settlement_interval),
+                oracle_pk,
                 settlement_interval,
                 self.tx_fee_rate,
                 self.funding_rate_long,
                 self.opening_fee,
                 self.leverage_choices.clone(),
+                self.leverage_maker,
             )
         })
     }
 
-    pub fn create_short_order(&self, settlement_interval: Duration) -> Option<Order> {
+    pub fn create_short_order(
+        &self,
+        settlement_interval: Duration,
+        oracle_pk: XOnlyPublicKey,
+    ) -> Option<Order> {
         self.price_short.map(|price_short| {
             Order::new(
                 Position::Short,
@@ -144,21 +188,28 @@ impl OfferParams {
                 self.min_quantity,
                 self.max_quantity,
                 Origin::Ours,
-                Self::pick_oracle_event_id(settlement_interval),
+                self.pick_oracle_event_id(settlement_interval),
+                oracle_pk,
                 settlement_interval,
                 self.tx_fee_rate,
                 self.funding_rate_short,
                 self.opening_fee,
                 self.leverage_choices.clone(),
+                self.leverage_maker,
             )
         })
     }
 }
 
-fn create_maker_offers(offer_params: OfferParams, settlement_interval: Duration) -> MakerOffers {
+fn create_maker_offers(
+    offer_params: OfferParams,
+    settlement_interval: Duration,
+    oracle_pk: XOnlyPublicKey,
+) -> MakerOffers {
     MakerOffers {
-        long: offer_params.create_long_order(settlement_interval),
-        short: offer_params.create_short_order(settlement_interval),
+        trading_pair: offer_params.trading_pair,
+        long: offer_params.create_long_order(settlement_interval, oracle_pk),
+        short: offer_params.create_short_order(settlement_interval, oracle_pk),
         tx_fee_rate: offer_params.tx_fee_rate,
         funding_rate_long: offer_params.funding_rate_long,
         funding_rate_short: offer_params.funding_rate_short,
@@ -188,7 +239,7 @@ pub struct Actor<O: 'static, T: 'static, W: 'static> {
     executor: command::Executor,
     rollover_actors: AddressMap<OrderId, rollover::Actor>,
     takers: xtra::Address<T>,
-    current_offers: Option<MakerOffers>,
+    current_offers: HashMap<TradingPair, MakerOffers>,
     setup_actors: AddressMap<OrderId, contract_setup::Actor>,
     settlement_actors: AddressMap<OrderId, collab_settlement::Actor>,
     oracle: xtra::Address<O>,
@@ -198,6 +249,13 @@ pub struct Actor<O: 'static, T: 'static, W: 'static> {
     libp2p_rollover: xtra::Address<daemon::rollover::maker::Actor>,
     libp2p_collab_settlement: xtra::Address<daemon::collab_settlement::maker::Actor>,
     libp2p_offer: xtra::Address<xtra_libp2p_offer::maker::Actor>,
+    cfds: watch::Receiver<Option<Vec<projection::Cfd>>>,
+    risk_limits: RiskLimits,
+    fee_estimation: Option<FeeEstimation>,
+    /// The fee rate of the offer parameters per trading pair, used if fee estimation is disabled
+    /// or fails.
+    offer_fee_rates: HashMap<TradingPair, TxFeeRate>,
+    contract_identities: ContractIdentities,
 }
 
 impl<O, T, W> Actor<O, T, W> {
@@ -216,6 +274,8 @@ impl<O, T, W> Actor<O, T, W> {
         libp2p_rollover: xtra::Address<daemon::rollover::maker::Actor>,
         libp2p_collab_settlement: xtra::Address<daemon::collab_settlement::maker::Actor>,
         libp2p_offer: xtra::Address<xtra_libp2p_offer::maker::Actor>,
+        cfds: watch::Receiver<Option<Vec<projection::Cfd>>>,
+        contract_identities: ContractIdentities,
     ) -> Self {
         Self {
             db: db.clone(),
@@ -227,7 +287,7 @@ impl<O, T, W> Actor<O, T, W> {
             executor: command::Executor::new(db, process_manager),
             rollover_actors: AddressMap::default(),
             takers,
-            current_offers: None,
+            current_offers: HashMap::default(),
             setup_actors: AddressMap::default(),
             oracle,
             time_to_first_position,
@@ -237,9 +297,31 @@ impl<O, T, W> Actor<O, T, W> {
             libp2p_rollover,
             libp2p_collab_settlement,
             libp2p_offer,
+            cfds,
+            risk_limits: RiskLimits::default(),
+            fee_estimation: None,
+            offer_fee_rates: HashMap::default(),
+            contract_identities,
         }
     }
 
+    /// The offers we broadcast to takers and show in the UI, one entry per trading pair.
+    fn broadcast_offers(&self) -> Vec<MakerOffers> {
+        TradingPair::ALL
+            .iter()
+            .filter_map(|trading_pair| self.current_offers.get(trading_pair))
+            .cloned()
+            .collect()
+    }
+
+    /// The offers we send over the legacy connection.
+    ///
+    /// Takers on the legacy connection only understand a single set of offers, hence only the
+    /// BTC/USD offers are sent.
+    fn legacy_offers(&self) -> Option<MakerOffers> {
+        self.current_offers.get(&TradingPair::BtcUsd).cloned()
+    }
+
     #[tracing::instrument(skip(self))]
     async fn update_connected_takers(&mut self) -> Result<()> {
         self.projection
@@ -252,6 +334,23 @@ impl<O, T, W> Actor<O, T, W> {
             .await?;
         Ok(())
     }
+
+    /// Check whether taking `cfd` keeps our exposure within the configured limits.
+    fn check_risk_limits(&self, cfd: &Cfd) -> Result<()> {
+        if self.risk_limits.is_empty() {
+            return Ok(());
+        }
+
+        let cfds = self.cfds.borrow();
+        let cfds = cfds
+            .as_deref()
+            .context("Cannot check risk limits before CFDs are loaded")?;
+
+        let exposure = Exposure::new(cfds);
+        let taker_quantity = exposure::taker_quantity(cfds, cfd.counterparty_network_identity());
+
+        self.risk_limits.check(&exposure, taker_quantity, cfd)
+    }
 }
 
 impl<O, T, W> Actor<O, T, W>
@@ -262,7 +361,7 @@ where
         self.takers
             .send_async_safe(connection::TakerMessage {
                 taker_id,
-                msg: wire::MakerToTaker::CurrentOffers(self.current_offers.clone()),
+                msg: wire::MakerToTaker::CurrentOffers(self.legacy_offers()),
             })
             .await?;
 
@@ -306,7 +405,6 @@ where
             self.n_payouts,
             self.takers.clone().into(),
             taker_id,
-            self.oracle_pk,
             self.oracle.clone().into(),
             self.process_manager.clone(),
             self.takers.clone().into(),
@@ -332,13 +430,16 @@ where
         + xtra::Handler<connection::TakerMessage, Return = Result<(), NoConnection>>
         + xtra::Handler<connection::BroadcastOffers, Return = ()>,
     W: xtra::Handler<wallet::Sign, Return = Result<PartiallySignedTransaction>>
-        + xtra::Handler<wallet::BuildPartyParams, Return = Result<PartyParams>>,
+        + xtra::Handler<wallet::BuildPartyParams, Return = Result<PartyParams>>
+        + xtra::Handler<wallet::EstimateFeeRate, Return = Result<TxFeeRate>>,
 {
-    #[instrument(skip(self, taker_id, this), err)]
+    #[allow(clippy::too_many_arguments)]
+    #[instrument(skip(self, taker_id, taker, this), err)]
     async fn handle_take_order(
         &mut self,
         taker_id: Identity,
         taker_peer_id: Option<PeerId>,
+        mut taker: contract_setup::Taker,
         order_id: OrderId,
         quantity: Usd,
         leverage: Leverage,
@@ -356,8 +457,8 @@ where
         // 1. Validate if order is still valid
         let order_to_take = self
             .current_offers
-            .as_ref()
-            .and_then(|offers| offers.pick_order_to_take(order_id));
+            .values()
+            .find_map(|offers| offers.pick_order_to_take(order_id));
 
         let order_to_take = if let Some(order_to_take) = order_to_take {
             order_to_take
@@ -368,12 +469,7 @@ where
             // a warning to be sure we don't trigger this code path frequently.
             tracing::warn!("Taker tried to take order with outdated id {order_id}");
 
-            self.takers
-                .send(connection::TakerMessage {
-                    taker_id,
-                    msg: wire::MakerToTaker::InvalidOrderId(order_id),
-                })
-                .await??;
+            taker.reject_invalid_order_id(taker_id, order_id).await?;
 
             return Ok(());
         };
@@ -387,33 +483,31 @@ where
             leverage,
         );
 
-        // 2. Replicate the orders in the offers with new ones to allow other takers to use
-        // the same offer
-        if let Some(offers) = &self.current_offers {
-            self.current_offers = Some(offers.replicate());
-        }
+        // 2. Reject the order right away if it would exceed our risk limits. The offer was not
+        // used up, so other takers can still take it.
+        if let Err(reason) = self.check_risk_limits(&cfd) {
+            tracing::info!(%order_id, "Order rejected: {reason:#}");
 
-        if let Err(e) = self
-            .takers
-            .send_async_safe(connection::BroadcastOffers(self.current_offers.clone()))
-            .await
-        {
-            tracing::warn!("{e:#}");
-        }
+            self.db.insert_cfd(&cfd).await?;
+            self.projection
+                .send(projection::CfdChanged(cfd.id()))
+                .await?;
 
-        if let Err(e) = self
-            .libp2p_offer
-            .send_async_safe(xtra_libp2p_offer::maker::NewOffers::new(
-                self.current_offers.clone(),
-            ))
-            .await
-        {
-            tracing::warn!("{e:#}");
+            if let Err(e) = taker.reject(taker_id, order_id).await {
+                tracing::warn!(%order_id, "Failed to notify taker about rejected order: {e:#}");
+            }
+
+            self.executor
+                .execute(order_id, |cfd| cfd.reject_contract_setup(reason))
+                .await?;
+
+            return Ok(());
         }
 
-        self.projection
-            .send(projection::Update(self.current_offers.clone()))
-            .await?;
+        // 3. Replicate the orders in the offers with new ones to allow other takers to use
+        // the same offer
+        self.replicate_offers(order_to_take.trading_pair).await;
+        self.publish_offers().await?;
 
         self.db.insert_cfd(&cfd).await?;
         self.projection
@@ -424,7 +518,10 @@ where
         // state
         let announcement = self
             .oracle
-            .send(oracle::GetAnnouncement(order_to_take.oracle_event_id))
+            .send(oracle::GetAnnouncement {
+                oracle_pk: order_to_take.oracle_pk,
+                event_id: order_to_take.oracle_event_id,
+            })
             .await??;
 
         // 5. Start up contract setup actor
@@ -432,15 +529,12 @@ where
             self.db.clone(),
             self.process_manager.clone(),
             (order_to_take.clone(), cfd.quantity(), self.n_payouts),
-            (self.oracle_pk, announcement),
+            (order_to_take.oracle_pk, announcement),
             self.wallet.clone().into(),
             self.wallet.clone().into(),
-            (
-                self.takers.clone().into(),
-                self.takers.clone().into(),
-                taker_id,
-            ),
+            (taker, taker_id),
             self.time_to_first_position.clone(),
+            self.contract_identities.derive(cfd.id()),
         )
         .create(None)
         .run();
@@ -453,8 +547,106 @@ where
     }
 }
 
+impl<O, T, W> Actor<O, T, W>
+where
+    W: xtra::Handler<wallet::EstimateFeeRate, Return = Result<TxFeeRate>>,
+{
+    /// The fee rate for new contracts and rollovers, estimated if fee estimation is enabled.
+    ///
+    /// Falls back to `fee_rate` if fee estimation is disabled or fails.
+    async fn tx_fee_rate(&self, fee_rate: TxFeeRate) -> TxFeeRate {
+        let fee_estimation = match self.fee_estimation {
+            Some(fee_estimation) => fee_estimation,
+            None => return fee_rate,
+        };
+
+        let estimate = self
+            .wallet
+            .send(wallet::EstimateFeeRate {
+                target_blocks: fee_estimation.target_blocks,
+            })
+            .await
+            .context("Wallet actor disconnected");
+
+        match estimate {
+            Ok(Ok(estimate)) => fee_estimation.bound(estimate),
+            Ok(Err(e)) | Err(e) => {
+                tracing::warn!("Failed to estimate fee rate, using {fee_rate} sat/vbyte: {e:#}");
+                fee_rate
+            }
+        }
+    }
+
+    /// Replace the offers for `trading_pair` with new ones at the current fee rate.
+    async fn replicate_offers(&mut self, trading_pair: TradingPair) {
+        let offers = match self.current_offers.get(&trading_pair) {
+            Some(offers) => offers.clone(),
+            None => return,
+        };
+
+        let fee_rate = self
+            .offer_fee_rates
+            .get(&trading_pair)
+            .copied()
+            .unwrap_or(offers.tx_fee_rate);
+        let tx_fee_rate = self.tx_fee_rate(fee_rate).await;
+
+        self.current_offers
+            .insert(trading_pair, offers.replicate(tx_fee_rate));
+    }
+}
+
+impl<O, T, W> Actor<O, T, W>
+where
+    T: xtra::Handler<connection::BroadcastOffers, Return = ()>,
+{
+    /// Show the current offers in the UI and send them to connected takers.
+    async fn publish_offers(&self) -> Result<()> {
+        self.projection
+            .send(projection::Update(self.broadcast_offers()))
+            .await?;
+
+        if let Err(e) = self
+            .takers
+            .send_async_safe(connection::BroadcastOffers(self.legacy_offers()))
+            .await
+        {
+            tracing::warn!("{e:#}");
+        }
+
+        if let Err(e) = self
+            .libp2p_offer
+            .send_async_safe(xtra_libp2p_offer::maker::NewOffers::new(
+                self.broadcast_offers(),
+            ))
+            .await
+        {
+            tracing::warn!("{e:#}");
+        }
+
+        Ok(())
+    }
+}
+
 #[xtra_productivity]
-impl<O, T, W> Actor<O, T, W> {
+impl<O, T, W> Actor<O, T, W>
+where
+    W: xtra::Handler<wallet::EstimateFeeRate, Return = Result<TxFeeRate>>,
+{
+    async fn handle_set_risk_limits(&mut self, msg: SetRiskLimits) -> Result<()> {
+        let SetRiskLimits(risk_limits) = msg;
+
+        tracing::info!(?risk_limits, "Updating risk limits");
+
+        self.db
+            .upsert_risk_limits(risk_limits.into())
+            .await
+            .context("Failed to store risk limits")?;
+        self.risk_limits = risk_limits;
+
+        Ok(())
+    }
+
     async fn handle_accept_order(&mut self, msg: AcceptOrder) -> Result<()> {
         let AcceptOrder { order_id } = msg;
 
@@ -600,12 +792,15 @@ impl<O, T, W> Actor<O, T, W> {
     }
 
     async fn handle_accept_rollover(&mut self, msg: AcceptRollover) -> Result<()> {
+        let order_id = msg.order_id;
+
+        let cfd = self.db.load_open_cfd::<Cfd>(order_id, ()).await?;
         let current_offers = self
             .current_offers
-            .as_ref()
+            .get(&cfd.trading_pair())
+            .cloned()
             .context("Cannot accept rollover without current offer, as we need up-to-date fees")?;
-
-        let order_id = msg.order_id;
+        let tx_fee_rate = self.tx_fee_rate(current_offers.tx_fee_rate).await;
 
         // We try to dispatch to libp2p rollover first
         // Using send here is fine because we dispatch to a task internally
@@ -613,7 +808,7 @@ impl<O, T, W> Actor<O, T, W> {
             .libp2p_rollover
             .send(daemon::rollover::maker::Accept {
                 order_id,
-                tx_fee_rate: current_offers.tx_fee_rate,
+                tx_fee_rate,
                 long_funding_rate: current_offers.funding_rate_long,
                 short_funding_rate: current_offers.funding_rate_short,
             })
@@ -622,7 +817,6 @@ impl<O, T, W> Actor<O, T, W> {
             // Return early if dispatch to libp2p rollover worked
             Ok(Ok(())) => return Ok(()),
             Ok(Err(error)) => {
-                let cfd = self.db.load_open_cfd::<Cfd>(order_id, ()).await?;
                 if can_use_libp2p(&cfd) {
                     tracing::error!("Failed to accept rollover for libp2p: {error:#}");
                     return Err(error);
@@ -631,7 +825,6 @@ impl<O, T, W> Actor<O, T, W> {
                 tracing::debug!("Try fallback to legacy rollover because unable to handle accept via libp2p: {error:#}");
             }
             Err(error) => {
-                let cfd = self.db.load_open_cfd::<Cfd>(order_id, ()).await?;
                 if can_use_libp2p(&cfd) {
                     tracing::error!(
                         "Failed to dispatch accept to libp2p rollover actor: {error:#}"
@@ -651,7 +844,7 @@ impl<O, T, W> Actor<O, T, W> {
             .send_async(
                 &order_id,
                 rollover::AcceptRollover {
-                    tx_fee_rate: current_offers.tx_fee_rate,
+                    tx_fee_rate,
                     long_funding_rate: current_offers.funding_rate_long,
                     short_funding_rate: current_offers.funding_rate_short,
                 },
@@ -773,38 +966,43 @@ where
         + xtra::Handler<connection::settlement::Response, Return = Result<()>>
         + xtra::Handler<connection::RegisterRollover, Return = ()>,
     W: xtra::Handler<wallet::Sign, Return = Result<PartiallySignedTransaction>>
-        + xtra::Handler<wallet::BuildPartyParams, Return = Result<PartyParams>>,
+        + xtra::Handler<wallet::BuildPartyParams, Return = Result<PartyParams>>
+        + xtra::Handler<wallet::EstimateFeeRate, Return = Result<TxFeeRate>>,
 {
-    async fn handle_offer_params(&mut self, msg: OfferParams) -> Result<()> {
+    async fn handle_new_offers(&mut self, msg: NewOffers) -> Result<()> {
+        let mut params = msg.params;
+        self.offer_fee_rates
+            .insert(params.trading_pair, params.tx_fee_rate);
+        params.tx_fee_rate = self.tx_fee_rate(params.tx_fee_rate).await;
+
         // 1. Update actor state to current order
-        self.current_offers
-            .replace(create_maker_offers(msg, self.settlement_interval));
+        self.current_offers.insert(
+            params.trading_pair,
+            create_maker_offers(params, self.settlement_interval, self.oracle_pk),
+        );
 
-        // 2. Notify UI via feed
-        self.projection
-            .send(projection::Update(self.current_offers.clone()))
-            .await?;
+        // 2. Notify UI via feed and inform connected takers
+        self.publish_offers().await
+    }
 
-        // 3. Inform connected takers
-        if let Err(e) = self
-            .takers
-            .send_async_safe(connection::BroadcastOffers(self.current_offers.clone()))
-            .await
-        {
-            tracing::warn!("{e:#}");
+    /// Update the fee estimation and republish the current offers at the resulting fee rate.
+    async fn handle_set_fee_estimation(&mut self, msg: SetFeeEstimation) -> Result<()> {
+        let SetFeeEstimation(fee_estimation) = msg;
+
+        tracing::info!(?fee_estimation, "Updating fee estimation");
+
+        self.fee_estimation = fee_estimation;
+
+        let trading_pairs = self.current_offers.keys().copied().collect::<Vec<_>>();
+        if trading_pairs.is_empty() {
+            return Ok(());
         }
 
-        if let Err(e) = self
-            .libp2p_offer
-            .send_async_safe(xtra_libp2p_offer::maker::NewOffers::new(
-                self.current_offers.clone(),
-            ))
-            .await
-        {
-            tracing::warn!("{e:#}");
+        for trading_pair in trading_pairs {
+            self.replicate_offers(trading_pair).await;
         }
 
-        Ok(())
+        self.publish_offers().await
     }
 
     async fn handle(&mut self, msg: TakerConnected) -> Result<()> {
@@ -815,6 +1013,58 @@ where
         self.handle_taker_disconnected(msg.id).await
     }
 
+    async fn handle(&mut self, msg: NewInboundSubstream, ctx: &mut xtra::Context<Self>) {
+        let NewInboundSubstream { peer, stream } = msg;
+        let address = ctx.address().expect("we are alive");
+
+        tokio_extras::spawn_fallible(
+            &address.clone(),
+            async move {
+                let (take_order, mut listener) = Listener::receive_take_order(stream).await?;
+                listener
+                    .authenticate_taker(take_order.taker_id, PeerId::from(peer))
+                    .await?;
+
+                address
+                    .send(TakeOrderReceived {
+                        peer: PeerId::from(peer),
+                        take_order,
+                        listener,
+                    })
+                    .await?;
+
+                anyhow::Ok(())
+            },
+            move |e| async move {
+                tracing::warn!(%peer, "Failed to handle incoming contract setup protocol: {e:#}")
+            },
+        );
+    }
+
+    async fn handle(&mut self, msg: TakeOrderReceived, ctx: &mut xtra::Context<Self>) {
+        let TakeOrderReceived {
+            peer,
+            take_order,
+            listener,
+        } = msg;
+        let this = ctx.address().expect("self to be alive");
+
+        if let Err(e) = self
+            .handle_take_order(
+                take_order.taker_id,
+                Some(peer),
+                contract_setup::Taker::libp2p(listener),
+                take_order.order_id,
+                take_order.quantity,
+                take_order.leverage,
+                &this,
+            )
+            .await
+        {
+            tracing::error!("Error when handling order take request: {:#}", e)
+        }
+    }
+
     async fn handle(
         &mut self,
         FromTaker {
@@ -833,7 +1083,15 @@ where
                 let leverage = Leverage::TWO;
 
                 if let Err(e) = self
-                    .handle_take_order(taker_id, peer_id, order_id, quantity, leverage, &this)
+                    .handle_take_order(
+                        taker_id,
+                        peer_id,
+                        contract_setup::Taker::legacy(&self.takers),
+                        order_id,
+                        quantity,
+                        leverage,
+                        &this,
+                    )
                     .await
                 {
                     tracing::error!("Error when handling order take request: {:#}", e)
@@ -845,7 +1103,15 @@ where
                 leverage,
             } => {
                 if let Err(e) = self
-                    .handle_take_order(taker_id, peer_id, order_id, quantity, leverage, &this)
+                    .handle_take_order(
+                        taker_id,
+                        peer_id,
+                        contract_setup::Taker::legacy(&self.takers),
+                        order_id,
+                        quantity,
+                        leverage,
+                        &this,
+                    )
                     .await
                 {
                     tracing::error!("Error when handling order take request: {:#}", e)
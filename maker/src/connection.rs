@@ -176,6 +176,7 @@ impl Connection {
                                 creation_timestamp: order.creation_timestamp_maker,
                                 settlement_interval: order.settlement_interval,
                                 liquidation_price: model::calculate_long_liquidation_price(
+                                    order.trading_pair,
                                     leverage,
                                     order.price,
                                 ),
@@ -4,15 +4,21 @@ use crate::metrics::time_to_first_position;
 use anyhow::Context;
 use anyhow::Result;
 use async_trait::async_trait;
+use bdk::bitcoin::secp256k1::SecretKey;
 use bdk::bitcoin::util::psbt::PartiallySignedTransaction;
+use bdk::bitcoin::PublicKey;
 use daemon::command;
+use daemon::contract_setup::protocol::Decision;
+use daemon::contract_setup::protocol::Listener;
 use daemon::process_manager;
+use daemon::setup_contract;
 use daemon::setup_contract_deprecated;
 use daemon::wallet;
 use daemon::wire;
 use futures::channel::mpsc;
 use futures::channel::mpsc::UnboundedSender;
 use futures::sink;
+use futures::Future;
 use futures::SinkExt;
 use maia_core::secp256k1_zkp::XOnlyPublicKey;
 use maia_core::PartyParams;
@@ -20,12 +26,96 @@ use model::olivia::Announcement;
 use model::Dlc;
 use model::Identity;
 use model::Order;
+use model::OrderId;
 use model::Role;
 use model::Usd;
 use xtra::prelude::MessageChannel;
+use xtra::Address;
 use xtra_productivity::xtra_productivity;
 use xtras::SendAsyncSafe;
 
+/// How the maker talks to the taker that took an order
+pub enum Taker {
+    /// The taker took the order over the legacy connection
+    Legacy {
+        taker: MessageChannel<connection::TakerMessage, Result<(), NoConnection>>,
+        confirm_order: MessageChannel<connection::ConfirmOrder, Result<()>>,
+    },
+    /// The taker took the order over `/itchysats/contract-setup/1.0.0`
+    ///
+    /// The listener is taken out once the maker decided on the order.
+    Libp2p(Option<Listener>),
+}
+
+impl Taker {
+    pub fn legacy<T>(takers: &Address<T>) -> Self
+    where
+        T: xtra::Handler<connection::TakerMessage, Return = Result<(), NoConnection>>
+            + xtra::Handler<connection::ConfirmOrder, Return = Result<()>>,
+    {
+        Taker::Legacy {
+            taker: takers.clone().into(),
+            confirm_order: takers.clone().into(),
+        }
+    }
+
+    pub fn libp2p(listener: Listener) -> Self {
+        Taker::Libp2p(Some(listener))
+    }
+
+    /// Let the taker know that we are not going ahead with the order
+    pub async fn reject(&mut self, taker_id: Identity, order_id: OrderId) -> Result<()> {
+        self.send_rejection(
+            taker_id,
+            wire::MakerToTaker::RejectOrder(order_id),
+            Decision::Reject,
+        )
+        .await
+    }
+
+    /// Let the taker know that the order it tried to take is not offered
+    pub async fn reject_invalid_order_id(
+        &mut self,
+        taker_id: Identity,
+        order_id: OrderId,
+    ) -> Result<()> {
+        self.send_rejection(
+            taker_id,
+            wire::MakerToTaker::InvalidOrderId(order_id),
+            Decision::InvalidOrderId,
+        )
+        .await
+    }
+
+    async fn send_rejection(
+        &mut self,
+        taker_id: Identity,
+        legacy_msg: wire::MakerToTaker,
+        decision: Decision,
+    ) -> Result<()> {
+        match self {
+            Taker::Legacy { taker, .. } => {
+                taker
+                    .send(connection::TakerMessage {
+                        taker_id,
+                        msg: legacy_msg,
+                    })
+                    .await
+                    .context("Failed to deliver rejection")??;
+            }
+            Taker::Libp2p(listener) => {
+                listener
+                    .take()
+                    .context("Decision was already sent to taker")?
+                    .send_decision(decision)
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
 pub struct Actor {
     order: Order,
     quantity: Usd,
@@ -34,12 +124,12 @@ pub struct Actor {
     announcement: Announcement,
     build_party_params: MessageChannel<wallet::BuildPartyParams, Result<PartyParams>>,
     sign: MessageChannel<wallet::Sign, Result<PartiallySignedTransaction>>,
-    taker: MessageChannel<connection::TakerMessage, Result<(), NoConnection>>,
-    confirm_order: MessageChannel<connection::ConfirmOrder, Result<()>>,
+    taker: Taker,
     taker_id: Identity,
     setup_msg_sender: Option<UnboundedSender<wire::SetupMsg>>,
     executor: command::Executor,
     time_to_first_position: xtra::Address<time_to_first_position::Actor>,
+    identity: (SecretKey, PublicKey),
 }
 
 impl Actor {
@@ -51,12 +141,9 @@ impl Actor {
         (oracle_pk, announcement): (XOnlyPublicKey, Announcement),
         build_party_params: MessageChannel<wallet::BuildPartyParams, Result<PartyParams>>,
         sign: MessageChannel<wallet::Sign, Result<PartiallySignedTransaction>>,
-        (taker, confirm_order, taker_id): (
-            MessageChannel<connection::TakerMessage, Result<(), NoConnection>>,
-            MessageChannel<connection::ConfirmOrder, Result<()>>,
-            Identity,
-        ),
+        (taker, taker_id): (Taker, Identity),
         time_to_first_position: xtra::Address<time_to_first_position::Actor>,
+        identity: (SecretKey, PublicKey),
     ) -> Self {
         Self {
             executor: command::Executor::new(db, process_manager),
@@ -68,62 +155,98 @@ impl Actor {
             build_party_params,
             sign,
             taker,
-            confirm_order,
             taker_id,
             setup_msg_sender: None,
             time_to_first_position,
+            identity,
         }
     }
 
     async fn contract_setup(&mut self, this: xtra::Address<Self>) -> Result<()> {
         let order_id = self.order.id;
 
-        let (sender, receiver) = mpsc::unbounded();
-        // store the writing end to forward messages from the taker to
-        // the spawned contract setup task
-        self.setup_msg_sender = Some(sender);
+        match &mut self.taker {
+            Taker::Legacy { taker, .. } => {
+                let (sender, receiver) = mpsc::unbounded();
+                // store the writing end to forward messages from the taker to
+                // the spawned contract setup task
+                self.setup_msg_sender = Some(sender);
 
-        let (setup_params, position) = self
-            .executor
-            .execute(order_id, |cfd| cfd.start_contract_setup())
-            .await?;
+                let (setup_params, position) = self
+                    .executor
+                    .execute(order_id, |cfd| cfd.start_contract_setup())
+                    .await?;
 
-        let taker_id = setup_params.counterparty_identity();
-        let taker = self.taker.clone();
-
-        let contract_future = setup_contract_deprecated::new(
-            sink::unfold((), move |_, msg| {
+                let taker_id = setup_params.counterparty_identity();
                 let taker = taker.clone();
-                async move {
-                    let msg = connection::TakerMessage {
-                        taker_id,
-                        msg: wire::MakerToTaker::Protocol { order_id, msg },
-                    };
-
-                    let _ = taker.send(msg).split_receiver().await;
-                    Ok(())
-                }
-            }),
-            receiver,
-            (self.oracle_pk, self.announcement.clone()),
-            setup_params,
-            self.build_party_params.clone(),
-            self.sign.clone(),
-            Role::Maker,
-            position,
-            self.n_payouts,
-        );
-
-        tokio_extras::spawn(&this.clone(), async move {
-            let _: Result<(), xtra::Error> = match contract_future.await {
-                Ok(dlc) => this.send(SetupSucceeded { dlc }).await,
-                Err(error) => this.send(SetupFailed { error }).await,
-            };
-        });
+
+                let contract_future = setup_contract_deprecated::new(
+                    sink::unfold((), move |_, msg| {
+                        let taker = taker.clone();
+                        async move {
+                            let msg = connection::TakerMessage {
+                                taker_id,
+                                msg: wire::MakerToTaker::Protocol { order_id, msg },
+                            };
+
+                            let _ = taker.send(msg).split_receiver().await;
+                            Ok(())
+                        }
+                    }),
+                    receiver,
+                    (self.oracle_pk, self.announcement.clone()),
+                    setup_params,
+                    self.build_party_params.clone(),
+                    self.sign.clone(),
+                    Role::Maker,
+                    position,
+                    self.n_payouts,
+                    self.identity,
+                );
+
+                spawn_contract_setup(this, contract_future);
+            }
+            Taker::Libp2p(listener) => {
+                let mut listener = listener
+                    .take()
+                    .context("Decision was already sent to taker")?;
+                listener.send_decision(Decision::Accept).await?;
+
+                let (setup_params, position) = self
+                    .executor
+                    .execute(order_id, |cfd| cfd.start_contract_setup())
+                    .await?;
+
+                let (sink, stream) = listener.into_setup_channels();
+
+                let contract_future = setup_contract::new(
+                    sink,
+                    stream,
+                    (self.oracle_pk, self.announcement.clone()),
+                    setup_params,
+                    self.build_party_params.clone(),
+                    self.sign.clone(),
+                    Role::Maker,
+                    position,
+                    self.n_payouts,
+                    self.identity,
+                );
+
+                spawn_contract_setup(this, contract_future);
+            }
+        }
 
         Ok(())
     }
 
+    /// Whether we already accepted the order and started contract setup
+    fn is_setup_active(&self) -> bool {
+        match &self.taker {
+            Taker::Legacy { .. } => self.setup_msg_sender.is_some(),
+            Taker::Libp2p(listener) => listener.is_none(),
+        }
+    }
+
     async fn emit_complete(&mut self, dlc: Dlc, ctx: &mut xtra::Context<Self>) {
         if let Err(e) = self
             .executor
@@ -184,7 +307,7 @@ impl Actor {
     fn handle(&mut self, _msg: Accepted, ctx: &mut xtra::Context<Self>) {
         let order_id = self.order.id;
 
-        if self.setup_msg_sender.is_some() {
+        if self.is_setup_active() {
             tracing::warn!(%order_id, "Contract setup already active");
             return;
         }
@@ -196,14 +319,16 @@ impl Actor {
             .expect("actor to be able to give address to itself");
 
         let fut = async {
-            self.confirm_order
-                .send(connection::ConfirmOrder {
-                    taker_id: self.taker_id,
-                    order_id,
-                    address: this.clone(),
-                })
-                .await
-                .context("Failed to deliver order confirmation")??;
+            if let Taker::Legacy { confirm_order, .. } = &self.taker {
+                confirm_order
+                    .send(connection::ConfirmOrder {
+                        taker_id: self.taker_id,
+                        order_id,
+                        address: this.clone(),
+                    })
+                    .await
+                    .context("Failed to deliver order confirmation")??;
+            }
 
             self.contract_setup(this)
                 .await
@@ -222,13 +347,11 @@ impl Actor {
     }
 
     fn handle(&mut self, _msg: Rejected, ctx: &mut xtra::Context<Self>) {
-        let _ = self
-            .taker
-            .send(connection::TakerMessage {
-                taker_id: self.taker_id,
-                msg: wire::MakerToTaker::RejectOrder(self.order.id),
-            })
-            .await;
+        let order_id = self.order.id;
+
+        if let Err(e) = self.taker.reject(self.taker_id, order_id).await {
+            tracing::warn!(%order_id, "Failed to notify taker about rejected order: {e:#}");
+        }
 
         self.emit_reject(anyhow::format_err!("unknown"), ctx).await
     }
@@ -264,13 +387,10 @@ impl xtra::Actor for Actor {
                 format!("Order rejected: quantity {quantity} not in range [{min}, {max}]",);
             tracing::info!("{reason}");
 
-            let _ = self
-                .taker
-                .send(connection::TakerMessage {
-                    taker_id: self.taker_id,
-                    msg: wire::MakerToTaker::RejectOrder(self.order.id),
-                })
-                .await;
+            let order_id = self.order.id;
+            if let Err(e) = self.taker.reject(self.taker_id, order_id).await {
+                tracing::warn!(%order_id, "Failed to notify taker about rejected order: {e:#}");
+            }
 
             self.emit_reject(anyhow::format_err!(reason), ctx).await;
         }
@@ -279,6 +399,18 @@ impl xtra::Actor for Actor {
     async fn stopped(self) -> Self::Stop {}
 }
 
+fn spawn_contract_setup(
+    this: xtra::Address<Actor>,
+    contract_future: impl Future<Output = Result<Dlc>> + Send + 'static,
+) {
+    tokio_extras::spawn(&this.clone(), async move {
+        let _: Result<(), xtra::Error> = match contract_future.await {
+            Ok(dlc) => this.send(SetupSucceeded { dlc }).await,
+            Err(error) => this.send(SetupFailed { error }).await,
+        };
+    });
+}
+
 /// Message sent from the `maker_cfd::Actor` to the
 /// `setup_maker::Actor` to inform that the maker user has accepted
 /// the taker order request from the taker.
@@ -0,0 +1,349 @@
+use anyhow::ensure;
+use anyhow::Result;
+use bdk::bitcoin::Amount;
+use daemon::projection;
+use daemon::projection::CfdState;
+use model::Cfd;
+use model::Identity;
+use model::Position;
+use model::TradingPair;
+use model::Usd;
+use rust_decimal::prelude::ToPrimitive;
+use serde::Serialize;
+use std::collections::HashMap;
+use tokio::sync::watch;
+
+const POSITION_LABEL: &str = "position";
+const POSITION_LONG_LABEL: &str = "long";
+const POSITION_SHORT_LABEL: &str = "short";
+
+const SYMBOL_LABEL: &str = "symbol";
+
+static OPEN_INTEREST_GAUGE: conquer_once::Lazy<prometheus::GaugeVec> =
+    conquer_once::Lazy::new(|| {
+        prometheus::register_gauge_vec!(
+            "maker_open_interest_usd",
+            "Quantity of all open positions of the maker, by the position of the maker.",
+            &[POSITION_LABEL, SYMBOL_LABEL]
+        )
+        .unwrap()
+    });
+
+static LOCKED_MARGIN_GAUGE: conquer_once::Lazy<prometheus::IntGauge> =
+    conquer_once::Lazy::new(|| {
+        prometheus::register_int_gauge!(
+            "maker_locked_margin_satoshis",
+            "Margin of the maker locked in all open positions."
+        )
+        .unwrap()
+    });
+
+/// Open interest of the maker in a single trading pair.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct OpenInterest {
+    pub trading_pair: TradingPair,
+    /// Quantity of all positions in which the maker is long.
+    pub long: Usd,
+    /// Quantity of all positions in which the maker is short.
+    pub short: Usd,
+    /// Long minus short quantity; negative if the maker is net short.
+    pub net: Usd,
+}
+
+impl OpenInterest {
+    fn new(trading_pair: TradingPair, long: Usd, short: Usd) -> Self {
+        Self {
+            trading_pair,
+            long,
+            short,
+            net: long - short,
+        }
+    }
+}
+
+/// Aggregated risk of the maker across all open positions.
+///
+/// Positions that are still being set up count towards the exposure, so that concurrent orders
+/// cannot exceed the limits together.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Exposure {
+    pub open_interest: Vec<OpenInterest>,
+    #[serde(with = "::bdk::bitcoin::util::amount::serde::as_btc")]
+    pub locked_margin: Amount,
+}
+
+impl Exposure {
+    pub fn new(cfds: &[projection::Cfd]) -> Self {
+        let open_interest = TradingPair::ALL
+            .into_iter()
+            .map(|trading_pair| {
+                let (long, short) = cfds
+                    .iter()
+                    .filter(|cfd| is_open(cfd) && cfd.trading_pair == trading_pair)
+                    .fold((Usd::ZERO, Usd::ZERO), |(long, short), cfd| {
+                        match cfd.position {
+                            Position::Long => (long + cfd.quantity_usd, short),
+                            Position::Short => (long, short + cfd.quantity_usd),
+                        }
+                    });
+
+                OpenInterest::new(trading_pair, long, short)
+            })
+            .collect();
+
+        let locked_margin = cfds
+            .iter()
+            .filter(|cfd| is_open(cfd))
+            .fold(Amount::ZERO, |locked_margin, cfd| {
+                locked_margin + cfd.margin
+            });
+
+        Self {
+            open_interest,
+            locked_margin,
+        }
+    }
+
+    pub fn open_interest(&self, trading_pair: TradingPair) -> OpenInterest {
+        self.open_interest
+            .iter()
+            .find(|open_interest| open_interest.trading_pair == trading_pair)
+            .copied()
+            .unwrap_or_else(|| OpenInterest::new(trading_pair, Usd::ZERO, Usd::ZERO))
+    }
+}
+
+/// Quantity of all open positions with `taker`.
+pub fn taker_quantity(cfds: &[projection::Cfd], taker: Identity) -> Usd {
+    cfds.iter()
+        .filter(|cfd| is_open(cfd) && cfd.counterparty == taker)
+        .fold(Usd::ZERO, |quantity, cfd| quantity + cfd.quantity_usd)
+}
+
+/// Whether the CFD is, or may still become, an open position.
+pub fn is_open(cfd: &projection::Cfd) -> bool {
+    !matches!(
+        cfd.state,
+        CfdState::Rejected | CfdState::SetupFailed | CfdState::Closed | CfdState::Refunded
+    )
+}
+
+/// Limits on the exposure of the maker, checked whenever a taker takes an order.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RiskLimits {
+    /// Maximum quantity of all open positions with a single taker, including the order at hand.
+    pub max_taker_quantity: Option<Usd>,
+    /// Maximum net open interest in a single trading pair, long or short.
+    ///
+    /// Orders that reduce the net open interest are always accepted.
+    pub max_net_open_interest: Option<Usd>,
+    /// Maximum margin locked in all open positions, including the order at hand.
+    pub max_locked_margin: Option<Amount>,
+}
+
+impl RiskLimits {
+    pub fn is_empty(&self) -> bool {
+        self == &RiskLimits::default()
+    }
+
+    /// Check whether taking `cfd` keeps the maker within the limits.
+    ///
+    /// `taker_quantity` is the quantity of all other open positions with the taker of `cfd`.
+    pub fn check(&self, exposure: &Exposure, taker_quantity: Usd, cfd: &Cfd) -> Result<()> {
+        let quantity = cfd.quantity();
+
+        if let Some(max_taker_quantity) = self.max_taker_quantity {
+            let taker_quantity = taker_quantity + quantity;
+
+            ensure!(
+                taker_quantity <= max_taker_quantity,
+                "Quantity with taker of {taker_quantity} would exceed maximum of {max_taker_quantity}"
+            );
+        }
+
+        if let Some(max_net_open_interest) = self.max_net_open_interest {
+            let net = exposure.open_interest(cfd.trading_pair()).net;
+            let new_net = match cfd.position() {
+                Position::Long => net + quantity,
+                Position::Short => net - quantity,
+            };
+
+            let net = net.into_decimal().abs();
+            let new_net = new_net.into_decimal().abs();
+
+            ensure!(
+                new_net <= max_net_open_interest.into_decimal() || new_net < net,
+                "Net open interest of {} would exceed maximum of {max_net_open_interest}",
+                new_net.round_dp(2)
+            );
+        }
+
+        if let Some(max_locked_margin) = self.max_locked_margin {
+            let locked_margin = exposure.locked_margin + cfd.margin();
+
+            ensure!(
+                locked_margin <= max_locked_margin,
+                "Locked margin of {locked_margin} would exceed maximum of {max_locked_margin}"
+            );
+        }
+
+        Ok(())
+    }
+}
+
+impl From<sqlite_db::risk_limits::RiskLimits> for RiskLimits {
+    fn from(risk_limits: sqlite_db::risk_limits::RiskLimits) -> Self {
+        Self {
+            max_taker_quantity: risk_limits.max_taker_quantity,
+            max_net_open_interest: risk_limits.max_net_open_interest,
+            max_locked_margin: risk_limits.max_locked_margin,
+        }
+    }
+}
+
+impl From<RiskLimits> for sqlite_db::risk_limits::RiskLimits {
+    fn from(risk_limits: RiskLimits) -> Self {
+        Self {
+            max_taker_quantity: risk_limits.max_taker_quantity,
+            max_net_open_interest: risk_limits.max_net_open_interest,
+            max_locked_margin: risk_limits.max_locked_margin,
+        }
+    }
+}
+
+/// Keep the exposure metrics up to date with the CFD feed.
+pub async fn update_metrics(mut cfds: watch::Receiver<Option<Vec<projection::Cfd>>>) {
+    loop {
+        let exposure = cfds.borrow().as_deref().map(Exposure::new);
+
+        if let Some(exposure) = exposure {
+            set_metrics(&exposure);
+        }
+
+        if cfds.changed().await.is_err() {
+            return;
+        }
+    }
+}
+
+fn set_metrics(exposure: &Exposure) {
+    for open_interest in exposure.open_interest.iter() {
+        let symbol = open_interest.trading_pair.symbol();
+
+        for (label, quantity) in [
+            (POSITION_LONG_LABEL, open_interest.long),
+            (POSITION_SHORT_LABEL, open_interest.short),
+        ] {
+            OPEN_INTEREST_GAUGE
+                .with(&HashMap::from([
+                    (POSITION_LABEL, label),
+                    (SYMBOL_LABEL, symbol),
+                ]))
+                .set(quantity.into_decimal().to_f64().unwrap_or_default());
+        }
+    }
+
+    LOCKED_MARGIN_GAUGE.set(exposure.locked_margin.as_sat() as i64);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use model::Leverage;
+    use model::OpeningFee;
+    use model::Price;
+    use model::Role;
+    use model::TxFeeRate;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn given_no_limits_then_order_is_within_limits() {
+        let exposure = exposure(dec!(1_000_000), dec!(0), Amount::from_btc(100.0).unwrap());
+
+        RiskLimits::default()
+            .check(&exposure, Usd::new(dec!(1_000_000)), &cfd(Position::Long))
+            .unwrap();
+    }
+
+    #[test]
+    fn given_taker_quantity_at_limit_then_order_is_rejected() {
+        let limits = RiskLimits {
+            max_taker_quantity: Some(Usd::new(dec!(1_000))),
+            ..RiskLimits::default()
+        };
+        let exposure = exposure(dec!(0), dec!(0), Amount::ZERO);
+
+        limits
+            .check(&exposure, Usd::new(dec!(900)), &cfd(Position::Long))
+            .unwrap();
+        limits
+            .check(&exposure, Usd::new(dec!(901)), &cfd(Position::Long))
+            .unwrap_err();
+    }
+
+    #[test]
+    fn given_net_open_interest_above_limit_then_only_reducing_orders_are_accepted() {
+        let limits = RiskLimits {
+            max_net_open_interest: Some(Usd::new(dec!(1_000))),
+            ..RiskLimits::default()
+        };
+        let exposure = exposure(dec!(1_500), dec!(0), Amount::ZERO);
+
+        limits
+            .check(&exposure, Usd::ZERO, &cfd(Position::Long))
+            .unwrap_err();
+        limits
+            .check(&exposure, Usd::ZERO, &cfd(Position::Short))
+            .unwrap();
+    }
+
+    #[test]
+    fn given_locked_margin_at_limit_then_order_is_rejected() {
+        let limits = RiskLimits {
+            max_locked_margin: Some(Amount::from_btc(1.0).unwrap()),
+            ..RiskLimits::default()
+        };
+        let exposure = exposure(dec!(0), dec!(0), Amount::from_btc(1.0).unwrap());
+
+        limits
+            .check(&exposure, Usd::ZERO, &cfd(Position::Long))
+            .unwrap_err();
+    }
+
+    fn exposure(
+        long: rust_decimal::Decimal,
+        short: rust_decimal::Decimal,
+        locked_margin: Amount,
+    ) -> Exposure {
+        Exposure {
+            open_interest: vec![OpenInterest::new(
+                TradingPair::BtcUsd,
+                Usd::new(long),
+                Usd::new(short),
+            )],
+            locked_margin,
+        }
+    }
+
+    /// A CFD of 100 USD in which the maker takes `position`.
+    fn cfd(position: Position) -> Cfd {
+        Cfd::new(
+            model::OrderId::default(),
+            TradingPair::BtcUsd,
+            position,
+            Price::new(dec!(20_000)).unwrap(),
+            Leverage::TWO,
+            Leverage::ONE,
+            time::Duration::hours(24),
+            Role::Maker,
+            Usd::new(dec!(100)),
+            "69a42aa90da8b065b9532b62bff940a3ba07dbbb11d4482c7db83a7e049a9f1e"
+                .parse()
+                .unwrap(),
+            None,
+            OpeningFee::default(),
+            model::FundingRate::default(),
+            TxFeeRate::default(),
+        )
+    }
+}
@@ -0,0 +1,53 @@
+use model::TxFeeRate;
+
+/// Estimate the fee rate of new contracts and rollovers instead of using the fixed fee rate of
+/// the offer parameters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FeeEstimation {
+    /// Number of blocks within which the transactions of a contract should confirm.
+    pub target_blocks: usize,
+    pub min_fee_rate: TxFeeRate,
+    pub max_fee_rate: TxFeeRate,
+}
+
+impl FeeEstimation {
+    /// Keep an estimated fee rate within the configured bounds.
+    pub fn bound(&self, estimate: TxFeeRate) -> TxFeeRate {
+        if estimate.to_u32() < self.min_fee_rate.to_u32() {
+            return self.min_fee_rate;
+        }
+
+        if estimate.to_u32() > self.max_fee_rate.to_u32() {
+            return self.max_fee_rate;
+        }
+
+        estimate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimate_is_kept_within_bounds() {
+        let fee_estimation = FeeEstimation {
+            target_blocks: 6,
+            min_fee_rate: TxFeeRate::from_sat_per_vbyte(2),
+            max_fee_rate: TxFeeRate::from_sat_per_vbyte(50),
+        };
+
+        assert_eq!(
+            fee_estimation.bound(TxFeeRate::from_sat_per_vbyte(1)),
+            TxFeeRate::from_sat_per_vbyte(2)
+        );
+        assert_eq!(
+            fee_estimation.bound(TxFeeRate::from_sat_per_vbyte(20)),
+            TxFeeRate::from_sat_per_vbyte(20)
+        );
+        assert_eq!(
+            fee_estimation.bound(TxFeeRate::from_sat_per_vbyte(80)),
+            TxFeeRate::from_sat_per_vbyte(50)
+        );
+    }
+}
@@ -1,11 +1,24 @@
+use anyhow::ensure;
+use anyhow::Result;
 use bdk::bitcoin::util::bip32::ExtendedPrivKey;
+use bdk::bitcoin::Amount;
 use clap::Parser;
 use daemon::bdk;
+use daemon::wallet::SweepConfig;
+use exposure::RiskLimits;
+use fee_estimation::FeeEstimation;
+use model::TxFeeRate;
+use model::Usd;
 use shared_bin::cli::Network;
+use shared_bin::cli::SeedEncryption;
 use shared_bin::logger::LevelFilter;
 use shared_bin::logger::LOCAL_COLLECTOR_ENDPOINT;
+use std::collections::HashSet;
 use std::net::SocketAddr;
 use std::path::PathBuf;
+use xtra_libp2p::libp2p::Multiaddr;
+use xtra_libp2p::libp2p::PeerId;
+use xtra_libp2p::ConnectionGating;
 
 pub use actor_system::ActorSystem;
 
@@ -14,7 +27,11 @@ pub mod cfd;
 mod collab_settlement;
 mod connection;
 mod contract_setup;
+pub mod exposure;
+pub mod fee_estimation;
 mod metrics;
+pub mod policy;
+pub mod quoting;
 mod rollover;
 pub mod routes;
 
@@ -24,14 +41,64 @@ pub struct Opts {
     #[clap(long, default_value = "9999")]
     pub p2p_port: u16,
 
+    /// A multiaddr to listen on for libp2p connections, e.g. `/ip6/::/tcp/10000` or
+    /// `/dns4/maker.example.com/tcp/10000`. Can be given multiple times.
+    ///
+    /// DNS names are resolved on startup. If not specified it defaults to
+    /// `/ip4/0.0.0.0/tcp/<libp2p port>` with the libp2p port being `--p2p-port` + 1, unless
+    /// `--tor-hidden-service` is given.
+    #[clap(long = "listen", multiple_occurrences = true)]
+    pub listen_multiaddrs: Vec<Multiaddr>,
+
     /// The IP address to listen on for the HTTP API.
     #[clap(long, default_value = "127.0.0.1:8001")]
     pub http_address: SocketAddr,
 
+    /// The `.onion` address of a Tor hidden service to accept libp2p connections on, so that no
+    /// public IP address is needed. Only the hidden service is listened on unless `--listen` is
+    /// given as well.
+    ///
+    /// The hidden service is expected to forward the libp2p port (`--p2p-port` + 1) to
+    /// `--tor-hidden-service-port` on localhost.
+    #[clap(long)]
+    pub tor_hidden_service: Option<String>,
+
+    /// The local port the Tor hidden service forwards connections to.
+    ///
+    /// If not specified it defaults to the libp2p port (`--p2p-port` + 1).
+    #[clap(long, requires = "tor_hidden_service")]
+    pub tor_hidden_service_port: Option<u16>,
+
+    /// The libp2p peer id of a taker to accept connections from. Can be given multiple times.
+    ///
+    /// If given, libp2p connections of all other peers are rejected.
+    #[clap(long = "allow-peer", multiple_occurrences = true)]
+    pub allowed_peers: Vec<PeerId>,
+
+    /// The libp2p peer id of a taker to reject connections from. Can be given multiple times.
+    ///
+    /// Takers can also be banned at runtime via `PUT /api/takers/<peer-id>/ban`. These bans are
+    /// stored in the database and rejected in addition to the peers given here.
+    #[clap(long = "deny-peer", multiple_occurrences = true)]
+    pub denied_peers: Vec<PeerId>,
+
+    /// The maximum number of concurrent libp2p connections.
+    #[clap(long, default_value = "1000")]
+    pub max_connections: usize,
+
+    /// The maximum number of libp2p substreams a single taker may open per protocol and second.
+    #[clap(long, default_value = "10")]
+    pub max_inbound_substreams_per_second: u32,
+
     /// Where to permanently store data, defaults to the current working directory.
     #[clap(long)]
     pub data_dir: Option<PathBuf>,
 
+    /// Where to write the encrypted backup of the open CFDs, defaults to `cfds.backup` in the
+    /// data directory.
+    #[clap(long)]
+    pub backup_path: Option<PathBuf>,
+
     /// If enabled logs will be in json format
     #[clap(short, long)]
     pub json: bool,
@@ -79,6 +146,102 @@ pub struct Opts {
     #[clap(short, long, default_value = "Debug")]
     pub log_level: LevelFilter,
 
+    /// If provided, the fee rate of offers and rollovers is estimated for confirmation within
+    /// this many blocks instead of taken from the offer parameters.
+    #[clap(long)]
+    pub fee_estimation_target: Option<usize>,
+
+    /// Lower bound of estimated fee rates in satoshis per vbyte.
+    #[clap(long, default_value = "1")]
+    pub min_fee_rate: TxFeeRate,
+
+    /// Upper bound of estimated fee rates in satoshis per vbyte.
+    #[clap(long, default_value = "100")]
+    pub max_fee_rate: TxFeeRate,
+
+    /// If provided, funds exceeding `--hot-wallet-balance` are swept to addresses of this
+    /// watch-only output descriptor, e.g. "wpkh([fingerprint/84'/0'/0']xpub.../0/*)".
+    #[clap(long)]
+    pub sweep_descriptor: Option<String>,
+
+    /// Amount to keep in the hot wallet as trading margin when sweeping to
+    /// `--sweep-descriptor`. Amount is to be specified with denomination, e.g. "0.5 BTC"
+    #[clap(long, default_value = "1 BTC")]
+    pub hot_wallet_balance: Amount,
+
+    /// Maximum quantity in USD of all open positions with a single taker.
+    ///
+    /// If any risk limit is given, the risk limits set via `PUT /api/risk-limits` before the
+    /// restart are replaced. Otherwise they are kept.
+    #[clap(long)]
+    pub max_taker_quantity: Option<Usd>,
+
+    /// Maximum net open interest in USD per trading pair, long or short.
+    #[clap(long)]
+    pub max_net_open_interest: Option<Usd>,
+
+    /// Maximum margin locked in all open positions. Amount is to be specified with
+    /// denomination, e.g. "2 BTC"
+    #[clap(long)]
+    pub max_locked_margin: Option<Amount>,
+
+    #[clap(flatten)]
+    pub seed_encryption: SeedEncryption,
+
     #[clap(subcommand)]
     pub network: Network,
 }
+
+impl Opts {
+    pub fn fee_estimation(&self) -> Result<Option<FeeEstimation>> {
+        ensure!(
+            self.min_fee_rate.to_u32() <= self.max_fee_rate.to_u32(),
+            "Minimum fee rate {} exceeds maximum fee rate {}",
+            self.min_fee_rate,
+            self.max_fee_rate
+        );
+
+        let fee_estimation = self
+            .fee_estimation_target
+            .map(|target_blocks| FeeEstimation {
+                target_blocks,
+                min_fee_rate: self.min_fee_rate,
+                max_fee_rate: self.max_fee_rate,
+            });
+
+        Ok(fee_estimation)
+    }
+
+    pub fn connection_gating(&self) -> ConnectionGating {
+        let allowlist = if self.allowed_peers.is_empty() {
+            None
+        } else {
+            Some(self.allowed_peers.iter().copied().collect::<HashSet<_>>())
+        };
+
+        ConnectionGating {
+            allowlist,
+            denylist: self.denied_peers.iter().copied().collect(),
+            max_connections: Some(self.max_connections),
+            max_inbound_substreams_per_second: Some(self.max_inbound_substreams_per_second),
+        }
+    }
+
+    /// The risk limits given on the command line, if any.
+    pub fn risk_limits(&self) -> Option<RiskLimits> {
+        let risk_limits = RiskLimits {
+            max_taker_quantity: self.max_taker_quantity,
+            max_net_open_interest: self.max_net_open_interest,
+            max_locked_margin: self.max_locked_margin,
+        };
+
+        (!risk_limits.is_empty()).then(|| risk_limits)
+    }
+
+    pub fn sweep(&self) -> Option<SweepConfig> {
+        self.sweep_descriptor.clone().map(|descriptor| SweepConfig {
+            descriptor,
+            keep: self.hot_wallet_balance,
+        })
+    }
+}
@@ -6,19 +6,21 @@ use daemon::bdk::FeeRate;
 use daemon::monitor;
 use daemon::oracle;
 use daemon::projection;
-use daemon::seed::RandomSeed;
 use daemon::seed::Seed;
+use daemon::tor;
 use daemon::wallet;
 use daemon::wallet::MAKER_WALLET_ID;
 use daemon::HEARTBEAT_INTERVAL;
 use daemon::N_PAYOUTS;
+use maker::exposure::RiskLimits;
 use maker::routes;
 use maker::ActorSystem;
 use maker::Opts;
-use model::olivia;
+use model::TradingPair;
 use model::SETTLEMENT_INTERVAL;
 use shared_bin::catchers::default_catchers;
-use shared_bin::cli::Withdraw;
+use shared_bin::cli::load_seed;
+use shared_bin::cli::Command;
 use shared_bin::fairings;
 use shared_bin::logger;
 use std::net::SocketAddr;
@@ -53,12 +55,25 @@ async fn main() -> Result<()> {
         .unwrap_or_else(|| std::env::current_dir().expect("unable to get cwd"));
 
     let data_dir = opts.network.data_dir(data_dir);
+    let backup_path = opts
+        .backup_path
+        .clone()
+        .unwrap_or_else(|| data_dir.join("cfds.backup"));
 
     if !data_dir.exists() {
         tokio::fs::create_dir_all(&data_dir).await?;
     }
 
-    let seed = RandomSeed::initialize(&data_dir.join("maker_seed")).await?;
+    let seed = match load_seed(
+        &data_dir.join("maker_seed"),
+        &opts.seed_encryption,
+        opts.network.command(),
+    )
+    .await?
+    {
+        Some(seed) => seed,
+        None => return Ok(()),
+    };
 
     let bitcoin_network = opts.network.bitcoin_network();
 
@@ -78,28 +93,54 @@ async fn main() -> Result<()> {
     let mut wallet_dir = data_dir.clone();
 
     wallet_dir.push(MAKER_WALLET_ID);
+    let blockchain = opts.network.blockchain()?;
+    tracing::info!("Using {} as blockchain backend", blockchain.name());
+
     let (wallet, wallet_feed_receiver) = wallet::Actor::spawn(
-        opts.network.electrum(),
+        &blockchain,
         ext_priv_key,
         wallet_dir,
         MAKER_WALLET_ID.to_string(),
+        opts.sweep(),
     )?;
 
-    if let Some(Withdraw::Withdraw {
-        amount,
-        address,
-        fee,
-    }) = opts.network.withdraw()
-    {
-        wallet
-            .send(wallet::Withdraw {
+    match opts.network.command() {
+        Some(Command::Withdraw {
+            amount,
+            address,
+            fee,
+            utxos,
+            psbt,
+        }) => {
+            let withdraw = wallet::Withdraw {
                 amount: *amount,
                 address: address.clone(),
                 fee: fee.map(FeeRate::from_sat_per_vb),
-            })
-            .await??;
+                utxos: utxos.clone(),
+            };
+
+            if *psbt {
+                let psbt = wallet.send(wallet::BuildWithdrawPsbt(withdraw)).await??;
+
+                #[allow(clippy::print_stdout)]
+                {
+                    println!("{psbt}");
+                }
+            } else {
+                wallet.send(withdraw).await??;
+            }
+
+            return Ok(());
+        }
+        Some(Command::BroadcastPsbt { psbt }) => {
+            wallet
+                .send(wallet::BroadcastPsbt { psbt: psbt.clone() })
+                .await??;
 
-        return Ok(());
+            return Ok(());
+        }
+        Some(Command::ExportMnemonic | Command::RestoreMnemonic | Command::ImportBackup { .. })
+        | None => {}
     }
 
     let auth_username = rocket_basicauth::Username("itchysats");
@@ -123,47 +164,123 @@ async fn main() -> Result<()> {
     let db =
         sqlite_db::connect(data_dir.join("maker.sqlite"), opts.ignore_migration_errors).await?;
 
+    if let Some(Command::ImportBackup { file }) = opts.network.command() {
+        let restored = daemon::backup::import(&db, file, &identities.backup_key).await?;
+        tracing::info!(?restored, "Restored CFDs from backup");
+    }
+
+    let mut connection_gating = opts.connection_gating();
+    let banned_peers = db.load_banned_peers().await?;
+    if !banned_peers.is_empty() {
+        tracing::info!(?banned_peers, "Rejecting connections of banned takers");
+    }
+    connection_gating
+        .denylist
+        .extend(banned_peers.into_iter().map(|peer_id| peer_id.inner()));
+
     // Create actors
 
     let (projection_actor, projection_context) = xtra::Context::new(None);
 
     let libp2p_socket = daemon::libp2p_utils::libp2p_socket_from_legacy_networking(&p2p_socket);
-    let endpoint_listen = daemon::libp2p_utils::create_listen_tcp_multiaddr(
-        &libp2p_socket.ip(),
-        libp2p_socket.port(),
-    )
-    .expect("to parse properly");
+    let mut endpoint_listen = Vec::new();
+    for listen_multiaddr in &opts.listen_multiaddrs {
+        endpoint_listen.extend(
+            daemon::libp2p_utils::resolve_listen_multiaddr(listen_multiaddr.clone()).await?,
+        );
+    }
+
+    let tor = match &opts.tor_hidden_service {
+        Some(onion_address) => {
+            endpoint_listen.push(daemon::libp2p_utils::create_listen_onion_multiaddr(
+                onion_address,
+                libp2p_socket.port(),
+            )?);
+
+            Some(tor::Config {
+                socks_proxy: None,
+                hidden_service_port: Some(
+                    opts.tor_hidden_service_port
+                        .unwrap_or_else(|| libp2p_socket.port()),
+                ),
+            })
+        }
+        None => None,
+    };
 
+    if endpoint_listen.is_empty() {
+        endpoint_listen.push(
+            daemon::libp2p_utils::create_listen_tcp_multiaddr(
+                &libp2p_socket.ip(),
+                libp2p_socket.port(),
+            )
+            .expect("to parse properly"),
+        );
+    }
+
+    let bitmex_network = opts.network.bitmex_network();
+    let (supervisor, price_feed) = Supervisor::with_policy(
+        move || {
+            xtra_bitmex_price_feed::Actor::with_symbols(
+                bitmex_network,
+                TradingPair::ALL.map(|pair| pair.bitmex_instrument()),
+            )
+        },
+        always_restart::<xtra_bitmex_price_feed::Error>(),
+    );
+
+    tasks.add(supervisor.run_log_summary());
+
+    let (proj_actor, projection_feeds) =
+        projection::Actor::new(db.clone(), bitcoin_network, price_feed.clone().into());
+    tasks.add(projection_context.run(proj_actor));
+
+    let oracles = opts.network.oracles();
     let maker = ActorSystem::new(
         db.clone(),
         wallet.clone(),
-        *olivia::PUBLIC_KEY,
-        |executor| oracle::Actor::new(db.clone(), executor),
+        oracles[0].public_key,
+        |executor| oracle::Actor::new(oracles, db.clone(), executor),
         {
             |executor| {
-                let electrum = opts.network.electrum().to_string();
-                monitor::Actor::new(db.clone(), electrum, executor)
+                monitor::Actor::new(
+                    db.clone(),
+                    blockchain.clone(),
+                    wallet.clone().into(),
+                    executor,
+                )
             }
         },
         SETTLEMENT_INTERVAL,
         N_PAYOUTS,
         projection_actor.clone(),
+        projection_feeds.cfds.clone(),
+        price_feed.clone().into(),
         identities,
         HEARTBEAT_INTERVAL,
         p2p_socket,
         endpoint_listen,
+        backup_path,
+        tor,
+        connection_gating,
     )?;
 
-    let (supervisor, price_feed) = Supervisor::with_policy(
-        move || xtra_bitmex_price_feed::Actor::new(opts.network.bitmex_network()),
-        always_restart::<xtra_bitmex_price_feed::Error>(),
-    );
-
-    tasks.add(supervisor.run_log_summary());
+    if let Some(fee_estimation) = opts.fee_estimation()? {
+        tracing::info!(
+            ?fee_estimation,
+            "Estimating fee rates of offers and rollovers"
+        );
+        maker.set_fee_estimation(Some(fee_estimation)).await?;
+    }
 
-    let (proj_actor, projection_feeds) =
-        projection::Actor::new(db.clone(), bitcoin_network, price_feed.clone().into());
-    tasks.add(projection_context.run(proj_actor));
+    let risk_limits = match opts.risk_limits() {
+        Some(risk_limits) => Some(risk_limits),
+        None => db.load_risk_limits().await?.map(RiskLimits::from),
+    };
+    if let Some(risk_limits) = risk_limits {
+        tracing::info!(?risk_limits, "Checking orders against risk limits");
+        maker.set_risk_limits(risk_limits).await?;
+    }
 
     let mission_success = rocket::custom(figment)
         .manage(projection_feeds)
@@ -177,12 +294,26 @@ async fn main() -> Result<()> {
             rocket::routes![
                 routes::maker_feed,
                 routes::put_offer_params,
+                routes::put_quoting_params,
+                routes::delete_quoting_params,
+                routes::put_policy_params,
+                routes::delete_policy_params,
+                routes::get_policy_decisions,
+                routes::put_risk_limits,
+                routes::get_exposure,
                 routes::post_cfd_action,
                 routes::get_health_check,
                 routes::get_cfds,
                 routes::get_takers,
+                routes::get_identified_takers,
+                routes::put_taker_ban,
+                routes::delete_taker_ban,
                 routes::get_metrics,
                 routes::put_sync_wallet,
+                routes::get_utxos,
+                routes::put_frozen_utxo,
+                routes::delete_frozen_utxo,
+                routes::post_recover,
                 routes::get_version,
             ],
         )
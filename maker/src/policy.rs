@@ -0,0 +1,655 @@
+use crate::cfd;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use daemon::command;
+use daemon::projection;
+use daemon::projection::CfdAction;
+use model::libp2p::PeerId;
+use model::market_closing_price;
+use model::CfdEvent;
+use model::EventKind;
+use model::OrderId;
+use model::PolicyRequest;
+use model::Position;
+use model::Price;
+use model::Role;
+use model::Timestamp;
+use model::Usd;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use time::ext::NumericalDuration;
+use tokio::sync::watch;
+use xtra::prelude::MessageChannel;
+use xtra_bitmex_price_feed::LatestQuotes;
+use xtra_bitmex_price_feed::Quotes;
+use xtra_bitmex_price_feed::QUOTE_INTERVAL_MINUTES;
+use xtra_productivity::xtra_productivity;
+
+/// Basis points in one unit.
+const BPS: u32 = 10_000;
+
+const REQUEST_LABEL: &str = "request";
+const DECISION_LABEL: &str = "decision";
+
+static POLICY_DECISIONS_COUNTER: conquer_once::Lazy<prometheus::IntCounterVec> =
+    conquer_once::Lazy::new(|| {
+        prometheus::register_int_counter_vec!(
+            "maker_policy_decisions_total",
+            "The number of taker requests decided by the maker policy.",
+            &[REQUEST_LABEL, DECISION_LABEL]
+        )
+        .unwrap()
+    });
+
+/// Rules by which the maker decides on requests of takers without operator involvement.
+///
+/// Requests of a kind that is not decided automatically are left to the operator. Requests of a
+/// kind that is decided automatically are accepted if they pass all configured checks and rejected
+/// otherwise.
+///
+/// Limits on the exposure to a single taker are not part of the policy: orders exceeding the
+/// [`RiskLimits`](crate::exposure::RiskLimits) are rejected before they wait for a decision.
+#[derive(Clone, Debug, Default)]
+pub struct PolicyParams {
+    pub decide_orders: bool,
+    pub decide_settlements: bool,
+    pub decide_rollovers: bool,
+    /// Only accept requests of these peers; all peers are allowed if not set.
+    pub peer_whitelist: Option<Vec<PeerId>>,
+    /// Maximum quantity of a single order.
+    pub max_quantity: Option<Usd>,
+    /// Maximum distance of a proposed settlement price from the market closing price in our
+    /// disfavour, in basis points.
+    ///
+    /// Proposals that are better for us than the market closing price are always within policy.
+    pub max_settlement_price_deviation_bps: Option<u32>,
+}
+
+/// A request of a taker, alongside everything needed to decide on it.
+#[derive(Clone, Copy, Debug)]
+enum Request {
+    Order {
+        quantity: Usd,
+    },
+    Settlement {
+        proposed_price: Price,
+        /// The price we would close at, if a recent quote is available.
+        market_price: Option<Price>,
+        /// Our position in the CFD.
+        position: Position,
+    },
+    Rollover,
+}
+
+impl Request {
+    fn kind(&self) -> PolicyRequest {
+        match self {
+            Request::Order { .. } => PolicyRequest::Order,
+            Request::Settlement { .. } => PolicyRequest::Settlement,
+            Request::Rollover => PolicyRequest::Rollover,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Decision {
+    accepted: bool,
+    reason: String,
+}
+
+impl Decision {
+    fn accept(reason: impl Into<String>) -> Self {
+        Self {
+            accepted: true,
+            reason: reason.into(),
+        }
+    }
+
+    fn reject(reason: impl Into<String>) -> Self {
+        Self {
+            accepted: false,
+            reason: reason.into(),
+        }
+    }
+}
+
+impl PolicyParams {
+    /// Decide on a request of the taker with the given `peer_id`.
+    ///
+    /// Returns `None` if the decision is left to the operator.
+    fn decide(&self, peer_id: Option<PeerId>, request: Request) -> Option<Decision> {
+        let decide = match request {
+            Request::Order { .. } => self.decide_orders,
+            Request::Settlement { .. } => self.decide_settlements,
+            Request::Rollover => self.decide_rollovers,
+        };
+
+        if !decide {
+            return None;
+        }
+
+        if let Some(whitelist) = &self.peer_whitelist {
+            match peer_id {
+                Some(peer_id) if whitelist.contains(&peer_id) => {}
+                Some(peer_id) => {
+                    return Some(Decision::reject(format!(
+                        "Peer {peer_id} is not whitelisted"
+                    )))
+                }
+                None => {
+                    return Some(Decision::reject(
+                        "Peer is unknown and cannot be whitelisted",
+                    ))
+                }
+            }
+        }
+
+        match request {
+            Request::Order { quantity } => {
+                if let Some(max_quantity) = self.max_quantity {
+                    if quantity > max_quantity {
+                        return Some(Decision::reject(format!(
+                            "Quantity {quantity} exceeds maximum of {max_quantity}"
+                        )));
+                    }
+                }
+            }
+            Request::Settlement {
+                proposed_price,
+                market_price,
+                position,
+            } => {
+                if let Some(max_deviation_bps) = self.max_settlement_price_deviation_bps {
+                    // Without a recent quote we cannot judge the price, the operator has to decide
+                    let market_price = market_price?;
+
+                    // A long position gains from a higher price, a short position from a lower one
+                    let disfavour = match position {
+                        Position::Long => {
+                            market_price.into_decimal() - proposed_price.into_decimal()
+                        }
+                        Position::Short => {
+                            proposed_price.into_decimal() - market_price.into_decimal()
+                        }
+                    };
+                    let deviation_bps =
+                        disfavour / market_price.into_decimal() * Decimal::from(BPS);
+
+                    if deviation_bps > Decimal::from(max_deviation_bps) {
+                        return Some(Decision::reject(format!(
+                            "Proposed price {proposed_price} is {} bps worse than market price {market_price}, maximum is {max_deviation_bps} bps",
+                            deviation_bps.round_dp(2)
+                        )));
+                    }
+                }
+            }
+            Request::Rollover => {}
+        }
+
+        Some(Decision::accept("Request is within policy"))
+    }
+}
+
+/// Actor deciding on requests of takers according to the configured [`PolicyParams`].
+///
+/// Watches the CFD feed for requests waiting for the maker's decision. Every decision is recorded
+/// with its reason as [`EventKind::PolicyDecided`] before it is carried out.
+pub struct Actor {
+    db: sqlite_db::Connection,
+    executor: command::Executor,
+    cfds: watch::Receiver<Option<Vec<projection::Cfd>>>,
+    price_feed: MessageChannel<LatestQuotes, Quotes>,
+    accept_order: MessageChannel<cfd::AcceptOrder, Result<()>>,
+    reject_order: MessageChannel<cfd::RejectOrder, Result<()>>,
+    accept_settlement: MessageChannel<cfd::AcceptSettlement, Result<()>>,
+    reject_settlement: MessageChannel<cfd::RejectSettlement, Result<()>>,
+    accept_rollover: MessageChannel<cfd::AcceptRollover, Result<()>>,
+    reject_rollover: MessageChannel<cfd::RejectRollover, Result<()>>,
+    params: Option<PolicyParams>,
+    /// Pending requests we already decided on and whose decision was carried out.
+    decided: HashSet<(OrderId, PolicyRequest)>,
+}
+
+impl Actor {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        db: sqlite_db::Connection,
+        executor: command::Executor,
+        cfds: watch::Receiver<Option<Vec<projection::Cfd>>>,
+        price_feed: MessageChannel<LatestQuotes, Quotes>,
+        accept_order: MessageChannel<cfd::AcceptOrder, Result<()>>,
+        reject_order: MessageChannel<cfd::RejectOrder, Result<()>>,
+        accept_settlement: MessageChannel<cfd::AcceptSettlement, Result<()>>,
+        reject_settlement: MessageChannel<cfd::RejectSettlement, Result<()>>,
+        accept_rollover: MessageChannel<cfd::AcceptRollover, Result<()>>,
+        reject_rollover: MessageChannel<cfd::RejectRollover, Result<()>>,
+    ) -> Self {
+        Self {
+            db,
+            executor,
+            cfds,
+            price_feed,
+            accept_order,
+            reject_order,
+            accept_settlement,
+            reject_settlement,
+            accept_rollover,
+            reject_rollover,
+            params: None,
+            decided: HashSet::new(),
+        }
+    }
+
+    async fn decide_pending_requests(&mut self) -> Result<()> {
+        let cfds = self.cfds.borrow().clone().unwrap_or_default();
+
+        let pending = cfds
+            .iter()
+            .filter_map(|cfd| Some((cfd.order_id, pending_request(cfd)?)))
+            .collect::<HashSet<_>>();
+
+        // Forget about requests that are not pending anymore, the same kind of request can come
+        // in again, e.g. the next rollover
+        self.decided.retain(|request| pending.contains(request));
+
+        let params = match &self.params {
+            Some(params) => params.clone(),
+            None => return Ok(()),
+        };
+
+        let mut quotes = None;
+
+        for cfd in cfds.iter() {
+            let kind = match pending_request(cfd) {
+                Some(kind) if !self.decided.contains(&(cfd.order_id, kind)) => kind,
+                _ => continue,
+            };
+
+            let request = match kind {
+                PolicyRequest::Order => Request::Order {
+                    quantity: cfd.quantity_usd,
+                },
+                PolicyRequest::Settlement => {
+                    let proposed_price = match cfd.pending_settlement_proposal_price {
+                        Some(price) => price,
+                        None => continue,
+                    };
+
+                    if quotes.is_none() {
+                        quotes = Some(
+                            self.price_feed
+                                .send(LatestQuotes)
+                                .await
+                                .context("Price feed not available")?,
+                        );
+                    }
+
+                    Request::Settlement {
+                        proposed_price,
+                        market_price: market_price(quotes.as_ref(), cfd),
+                        position: cfd.position,
+                    }
+                }
+                PolicyRequest::Rollover => Request::Rollover,
+            };
+
+            let order_id = cfd.order_id;
+
+            if let Err(e) = self.decide(&params, order_id, request).await {
+                tracing::warn!(%order_id, ?kind, "Failed to decide on request: {e:#}");
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn decide(
+        &mut self,
+        params: &PolicyParams,
+        order_id: OrderId,
+        request: Request,
+    ) -> Result<()> {
+        let peer_id = self
+            .db
+            .load_open_cfd::<model::Cfd>(order_id, ())
+            .await?
+            .counterparty_peer_id();
+
+        let Decision { accepted, reason } = match params.decide(peer_id, request) {
+            Some(decision) => decision,
+            None => return Ok(()),
+        };
+        let kind = request.kind();
+
+        tracing::info!(%order_id, ?kind, %accepted, %reason, "Policy decided on request");
+
+        self.executor
+            .execute(order_id, |cfd| {
+                cfd.record_policy_decision(kind, accepted, reason)
+            })
+            .await?;
+
+        POLICY_DECISIONS_COUNTER
+            .with(&HashMap::from([
+                (REQUEST_LABEL, format!("{kind:?}").to_lowercase().as_str()),
+                (
+                    DECISION_LABEL,
+                    if accepted { "accepted" } else { "rejected" },
+                ),
+            ]))
+            .inc();
+
+        // Only remember the request once the decision was carried out, otherwise we decide again
+        // on the next update of the CFD feed
+        match (kind, accepted) {
+            (PolicyRequest::Order, true) => {
+                self.accept_order
+                    .send(cfd::AcceptOrder { order_id })
+                    .await?
+            }
+            (PolicyRequest::Order, false) => {
+                self.reject_order
+                    .send(cfd::RejectOrder { order_id })
+                    .await?
+            }
+            (PolicyRequest::Settlement, true) => {
+                self.accept_settlement
+                    .send(cfd::AcceptSettlement { order_id })
+                    .await?
+            }
+            (PolicyRequest::Settlement, false) => {
+                self.reject_settlement
+                    .send(cfd::RejectSettlement { order_id })
+                    .await?
+            }
+            (PolicyRequest::Rollover, true) => {
+                self.accept_rollover
+                    .send(cfd::AcceptRollover { order_id })
+                    .await?
+            }
+            (PolicyRequest::Rollover, false) => {
+                self.reject_rollover
+                    .send(cfd::RejectRollover { order_id })
+                    .await?
+            }
+        }?;
+
+        self.decided.insert((order_id, kind));
+
+        Ok(())
+    }
+}
+
+/// The request of the taker that waits for our decision, if any.
+fn pending_request(cfd: &projection::Cfd) -> Option<PolicyRequest> {
+    if cfd.actions.contains(&CfdAction::AcceptOrder) {
+        Some(PolicyRequest::Order)
+    } else if cfd.actions.contains(&CfdAction::AcceptSettlement) {
+        Some(PolicyRequest::Settlement)
+    } else if cfd.actions.contains(&CfdAction::AcceptRollover) {
+        Some(PolicyRequest::Rollover)
+    } else {
+        None
+    }
+}
+
+fn market_price(quotes: Option<&Quotes>, cfd: &projection::Cfd) -> Option<Price> {
+    let quote = quotes?.get(cfd.trading_pair.bitmex_instrument())?;
+
+    if quote.is_older_than(QUOTE_INTERVAL_MINUTES.minutes() * 2) {
+        return None;
+    }
+
+    let bid = Price::new(quote.bid()).ok()?;
+    let ask = Price::new(quote.ask()).ok()?;
+
+    Some(market_closing_price(bid, ask, Role::Maker, cfd.position))
+}
+
+/// Automated decision of the maker on a request of a taker, and the reason for it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PolicyDecision {
+    pub request: PolicyRequest,
+    pub accepted: bool,
+    pub reason: String,
+    pub timestamp: Timestamp,
+}
+
+/// All policy decisions taken on the requests of a CFD, oldest first.
+#[derive(Debug, Clone, Default)]
+pub struct PolicyDecisions {
+    pub decisions: Vec<PolicyDecision>,
+    version: u32,
+}
+
+impl sqlite_db::CfdAggregate for PolicyDecisions {
+    type CtorArgs = ();
+
+    fn new(_: Self::CtorArgs, _: sqlite_db::Cfd) -> Self {
+        Self::default()
+    }
+
+    fn apply(mut self, event: CfdEvent) -> Self {
+        self.version += 1;
+
+        if let EventKind::PolicyDecided {
+            request,
+            accepted,
+            reason,
+        } = event.event
+        {
+            self.decisions.push(PolicyDecision {
+                request,
+                accepted,
+                reason,
+                timestamp: event.timestamp,
+            });
+        }
+
+        self
+    }
+
+    fn version(&self) -> u32 {
+        self.version
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn started(&mut self, ctx: &mut xtra::Context<Self>) {
+        let this = ctx.address().expect("we are alive");
+        let mut cfds = self.cfds.clone();
+
+        tokio_extras::spawn(&this.clone(), async move {
+            while cfds.changed().await.is_ok() {
+                if this.send(CfdsChanged).await.is_err() {
+                    return;
+                }
+            }
+        });
+    }
+
+    async fn stopped(self) -> Self::Stop {}
+}
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, msg: Enable) -> Result<()> {
+        self.params = Some(msg.0);
+
+        self.decide_pending_requests().await
+    }
+
+    async fn handle(&mut self, _: Disable) {
+        self.params = None;
+    }
+
+    async fn handle(&mut self, _: CfdsChanged) {
+        if let Err(e) = self.decide_pending_requests().await {
+            tracing::warn!("Failed to decide on pending requests: {e:#}");
+        }
+    }
+}
+
+/// Decide on requests of takers automatically with the given parameters.
+///
+/// Replaces the parameters if the policy is already enabled.
+pub struct Enable(pub PolicyParams);
+
+/// Leave all decisions to the operator again.
+pub struct Disable;
+
+struct CfdsChanged;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn given_kind_not_decided_automatically_then_left_to_operator() {
+        let params = PolicyParams {
+            decide_orders: true,
+            ..PolicyParams::default()
+        };
+
+        assert_eq!(params.decide(None, Request::Rollover), None);
+    }
+
+    #[test]
+    fn given_peer_not_whitelisted_then_rejected() {
+        let whitelisted = PeerId::random();
+        let params = PolicyParams {
+            decide_rollovers: true,
+            peer_whitelist: Some(vec![whitelisted]),
+            ..PolicyParams::default()
+        };
+
+        assert!(
+            params
+                .decide(Some(whitelisted), Request::Rollover)
+                .unwrap()
+                .accepted
+        );
+        assert!(
+            !params
+                .decide(Some(PeerId::random()), Request::Rollover)
+                .unwrap()
+                .accepted
+        );
+        assert!(!params.decide(None, Request::Rollover).unwrap().accepted);
+    }
+
+    #[test]
+    fn given_order_exceeding_max_quantity_then_rejected() {
+        let params = PolicyParams {
+            decide_orders: true,
+            max_quantity: Some(usd(dec!(1_000))),
+            ..PolicyParams::default()
+        };
+
+        let within_limits = params.decide(None, order(dec!(1_000))).unwrap();
+        let above_max_quantity = params.decide(None, order(dec!(1_001))).unwrap();
+
+        assert!(within_limits.accepted);
+        assert!(!above_max_quantity.accepted);
+    }
+
+    #[test]
+    fn given_settlement_price_deviation_then_rejected_above_maximum() {
+        let params = PolicyParams {
+            decide_settlements: true,
+            max_settlement_price_deviation_bps: Some(100),
+            ..PolicyParams::default()
+        };
+
+        let within_deviation = params
+            .decide(
+                None,
+                settlement(dec!(19_800), Some(dec!(20_000)), Position::Long),
+            )
+            .unwrap();
+        let above_deviation = params
+            .decide(
+                None,
+                settlement(dec!(19_799), Some(dec!(20_000)), Position::Long),
+            )
+            .unwrap();
+
+        assert!(within_deviation.accepted);
+        assert!(!above_deviation.accepted);
+    }
+
+    #[test]
+    fn given_settlement_price_in_our_favour_then_accepted_regardless_of_deviation() {
+        let params = PolicyParams {
+            decide_settlements: true,
+            max_settlement_price_deviation_bps: Some(100),
+            ..PolicyParams::default()
+        };
+
+        let long = params
+            .decide(
+                None,
+                settlement(dec!(25_000), Some(dec!(20_000)), Position::Long),
+            )
+            .unwrap();
+        let short = params
+            .decide(
+                None,
+                settlement(dec!(15_000), Some(dec!(20_000)), Position::Short),
+            )
+            .unwrap();
+        let short_in_disfavour = params
+            .decide(
+                None,
+                settlement(dec!(20_201), Some(dec!(20_000)), Position::Short),
+            )
+            .unwrap();
+
+        assert!(long.accepted);
+        assert!(short.accepted);
+        assert!(!short_in_disfavour.accepted);
+    }
+
+    #[test]
+    fn given_settlement_without_market_price_then_left_to_operator() {
+        let params = PolicyParams {
+            decide_settlements: true,
+            max_settlement_price_deviation_bps: Some(100),
+            ..PolicyParams::default()
+        };
+
+        assert_eq!(
+            params.decide(None, settlement(dec!(20_000), None, Position::Long)),
+            None
+        );
+    }
+
+    fn order(quantity: Decimal) -> Request {
+        Request::Order {
+            quantity: usd(quantity),
+        }
+    }
+
+    fn settlement(
+        proposed_price: Decimal,
+        market_price: Option<Decimal>,
+        position: Position,
+    ) -> Request {
+        Request::Settlement {
+            proposed_price: Price::new(proposed_price).unwrap(),
+            market_price: market_price.map(|price| Price::new(price).unwrap()),
+            position,
+        }
+    }
+
+    fn usd(value: Decimal) -> Usd {
+        Usd::new(value)
+    }
+}
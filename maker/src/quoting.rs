@@ -0,0 +1,290 @@
+use crate::cfd;
+use crate::cfd::OfferParams;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use model::FundingRate;
+use model::Leverage;
+use model::OpeningFee;
+use model::Price;
+use model::TradingPair;
+use model::TxFeeRate;
+use model::Usd;
+use rust_decimal::Decimal;
+use std::time::Duration;
+use time::ext::NumericalDuration;
+use xtra::prelude::MessageChannel;
+use xtra_bitmex_price_feed::LatestQuotes;
+use xtra_bitmex_price_feed::Quote;
+use xtra_bitmex_price_feed::Quotes;
+use xtra_bitmex_price_feed::QUOTE_INTERVAL_MINUTES;
+use xtra_productivity::xtra_productivity;
+use xtras::SendInterval;
+
+/// Interval at which we re-quote based on the latest price from the feed.
+///
+/// Matches the interval at which the BitMEX price feed publishes new quotes.
+const UPDATE_OFFERS_INTERVAL: Duration = Duration::from_secs(QUOTE_INTERVAL_MINUTES as u64 * 60);
+
+/// Number of feed intervals after which a quote is considered stale.
+///
+/// Once the latest quote is older than this we pull our offers instead of quoting a price that
+/// might not reflect the market anymore.
+const MAX_QUOTE_AGE_INTERVALS: i64 = 2;
+
+/// Basis points in one unit.
+const BPS: u32 = 10_000;
+
+/// Configuration of the automated quoting engine.
+///
+/// All parameters apart from the prices are passed through to the offers as they are. The prices
+/// are derived from the latest quote of the price feed by applying `spread` and `skew`.
+#[derive(Clone, Debug)]
+pub struct QuotingParams {
+    /// The trading pair to quote; prices are taken from the corresponding BitMEX instrument.
+    pub trading_pair: TradingPair,
+    /// Distance of our prices from bid and ask, in basis points.
+    pub spread_bps: u32,
+    /// Shift applied to both our prices, in basis points.
+    ///
+    /// A positive skew moves both prices up: takers going short against the maker's long offer
+    /// sell at a higher price, while takers going long against the maker's short offer buy at a
+    /// higher price. This makes it more attractive for takers to go short and less attractive to
+    /// go long. A negative skew does the opposite.
+    pub skew_bps: i32,
+    pub min_quantity: Usd,
+    pub max_quantity: Usd,
+    pub tx_fee_rate: TxFeeRate,
+    pub funding_rate_long: FundingRate,
+    pub funding_rate_short: FundingRate,
+    pub opening_fee: OpeningFee,
+    pub leverage_choices: Vec<Leverage>,
+    pub leverage_maker: Leverage,
+}
+
+impl QuotingParams {
+    /// Compute the prices for the maker's long and short offer based on the given quote.
+    ///
+    /// The maker's long offer is priced off the bid, the maker's short offer off the ask.
+    fn offer_prices(&self, quote: Quote) -> Result<(Price, Price)> {
+        let spread = Decimal::from(self.spread_bps) / Decimal::from(BPS);
+        let skew = Decimal::from(self.skew_bps) / Decimal::from(BPS);
+
+        let price_long = Price::new(quote.bid() * (Decimal::ONE - spread + skew))
+            .context("Invalid price for long offer")?;
+        let price_short = Price::new(quote.ask() * (Decimal::ONE + spread + skew))
+            .context("Invalid price for short offer")?;
+
+        Ok((price_long, price_short))
+    }
+
+    fn offer_params(&self, prices: Option<(Price, Price)>) -> OfferParams {
+        OfferParams {
+            trading_pair: self.trading_pair,
+            price_long: prices.map(|(long, _)| long),
+            price_short: prices.map(|(_, short)| short),
+            min_quantity: self.min_quantity,
+            max_quantity: self.max_quantity,
+            tx_fee_rate: self.tx_fee_rate,
+            funding_rate_long: self.funding_rate_long,
+            funding_rate_short: self.funding_rate_short,
+            opening_fee: self.opening_fee,
+            leverage_choices: self.leverage_choices.clone(),
+            leverage_maker: self.leverage_maker,
+        }
+    }
+}
+
+/// Actor that automatically publishes offers based on the BitMEX price feed.
+///
+/// Quoting is disabled until the actor receives [`Enable`]. While enabled, new offers are sent to
+/// the `cfd::Actor` on every tick. If no recent quote is available, the offers are pulled until the
+/// price feed recovers.
+pub struct Actor {
+    price_feed: MessageChannel<LatestQuotes, Quotes>,
+    cfd_actor: MessageChannel<cfd::NewOffers, Result<()>>,
+    params: Option<QuotingParams>,
+    offers_pulled: bool,
+}
+
+impl Actor {
+    pub fn new(
+        price_feed: MessageChannel<LatestQuotes, Quotes>,
+        cfd_actor: MessageChannel<cfd::NewOffers, Result<()>>,
+    ) -> Self {
+        Self {
+            price_feed,
+            cfd_actor,
+            params: None,
+            offers_pulled: false,
+        }
+    }
+
+    async fn update_offers(&mut self) -> Result<()> {
+        let params = match &self.params {
+            Some(params) => params.clone(),
+            None => return Ok(()),
+        };
+
+        let latest_quote = self
+            .price_feed
+            .send(LatestQuotes)
+            .await
+            .context("Price feed not available")?
+            .get(params.trading_pair.bitmex_instrument())
+            .copied();
+
+        let threshold = QUOTE_INTERVAL_MINUTES.minutes() * MAX_QUOTE_AGE_INTERVALS;
+
+        match latest_quote {
+            Some(quote) if !quote.is_older_than(threshold) => {
+                let prices = params.offer_prices(quote)?;
+
+                tracing::debug!(
+                    price_long = %prices.0,
+                    price_short = %prices.1,
+                    ?quote,
+                    trading_pair = %params.trading_pair,
+                    "Publishing new offers"
+                );
+
+                self.publish(params.offer_params(Some(prices))).await?;
+                self.offers_pulled = false;
+            }
+            _ if self.offers_pulled => {}
+            _ => {
+                tracing::warn!(
+                    ?latest_quote,
+                    trading_pair = %params.trading_pair,
+                    "No quote younger than {} minutes available, pulling offers",
+                    threshold.whole_minutes()
+                );
+
+                self.publish(params.offer_params(None)).await?;
+                self.offers_pulled = true;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn publish(&self, params: OfferParams) -> Result<()> {
+        self.cfd_actor
+            .send(cfd::NewOffers { params })
+            .await
+            .context("CFD actor not available")?
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn started(&mut self, ctx: &mut xtra::Context<Self>) {
+        let this = ctx.address().expect("we are alive");
+        tokio_extras::spawn(
+            &this.clone(),
+            this.send_interval(
+                UPDATE_OFFERS_INTERVAL,
+                || UpdateOffers,
+                xtras::IncludeSpan::Always,
+            ),
+        );
+    }
+
+    async fn stopped(self) -> Self::Stop {}
+}
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, msg: Enable) -> Result<()> {
+        self.params = Some(msg.0);
+        self.offers_pulled = false;
+
+        self.update_offers().await
+    }
+
+    async fn handle(&mut self, _: Disable) -> Result<()> {
+        if let Some(params) = self.params.take() {
+            self.publish(params.offer_params(None)).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn handle(&mut self, _: UpdateOffers) {
+        if let Err(e) = self.update_offers().await {
+            tracing::warn!("Failed to update offers: {e:#}");
+        }
+    }
+}
+
+/// Start quoting automatically with the given parameters.
+///
+/// Replaces the parameters if quoting is already enabled.
+pub struct Enable(pub QuotingParams);
+
+/// Stop quoting automatically and pull the current offers.
+pub struct Disable;
+
+struct UpdateOffers;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+    use time::OffsetDateTime;
+
+    #[test]
+    fn given_spread_then_long_below_bid_and_short_above_ask() {
+        let params = dummy_quoting_params(100, 0);
+
+        let (price_long, price_short) = params.offer_prices(dummy_quote()).unwrap();
+
+        assert_eq!(price_long, Price::new(dec!(19_800)).unwrap());
+        assert_eq!(price_short, Price::new(dec!(20_402)).unwrap());
+    }
+
+    #[test]
+    fn given_skew_then_both_prices_shifted() {
+        let params = dummy_quoting_params(100, -50);
+
+        let (price_long, price_short) = params.offer_prices(dummy_quote()).unwrap();
+
+        assert_eq!(price_long, Price::new(dec!(19_700)).unwrap());
+        assert_eq!(price_short, Price::new(dec!(20_301)).unwrap());
+    }
+
+    #[test]
+    fn given_spread_of_full_price_then_error() {
+        let params = dummy_quoting_params(BPS, 0);
+
+        let result = params.offer_prices(dummy_quote());
+
+        assert!(result.is_err());
+    }
+
+    fn dummy_quote() -> Quote {
+        Quote {
+            timestamp: OffsetDateTime::now_utc(),
+            bid: dec!(20_000),
+            ask: dec!(20_200),
+        }
+    }
+
+    fn dummy_quoting_params(spread_bps: u32, skew_bps: i32) -> QuotingParams {
+        QuotingParams {
+            trading_pair: TradingPair::BtcUsd,
+            spread_bps,
+            skew_bps,
+            min_quantity: Usd::new(dec!(100)),
+            max_quantity: Usd::new(dec!(1000)),
+            tx_fee_rate: TxFeeRate::default(),
+            funding_rate_long: FundingRate::default(),
+            funding_rate_short: FundingRate::default(),
+            opening_fee: OpeningFee::default(),
+            leverage_choices: vec![Leverage::TWO],
+            leverage_maker: Leverage::ONE,
+        }
+    }
+}
@@ -12,7 +12,6 @@ use futures::channel::mpsc;
 use futures::channel::mpsc::UnboundedSender;
 use futures::sink;
 use futures::SinkExt;
-use maia_core::secp256k1_zkp::XOnlyPublicKey;
 use model::olivia::Announcement;
 use model::Dlc;
 use model::FundingFee;
@@ -58,7 +57,6 @@ pub struct Actor {
     send_to_taker_actor: MessageChannel<connection::TakerMessage, Result<(), NoConnection>>,
     n_payouts: usize,
     taker_id: Identity,
-    oracle_pk: XOnlyPublicKey,
     sent_from_taker: Option<UnboundedSender<wire::RolloverMsg>>,
     oracle_actor: MessageChannel<oracle::GetAnnouncement, Result<Announcement, NoAnnouncement>>,
     register: MessageChannel<connection::RegisterRollover, ()>,
@@ -73,7 +71,6 @@ impl Actor {
         n_payouts: usize,
         send_to_taker_actor: MessageChannel<connection::TakerMessage, Result<(), NoConnection>>,
         taker_id: Identity,
-        oracle_pk: XOnlyPublicKey,
         oracle_actor: MessageChannel<oracle::GetAnnouncement, Result<Announcement, NoAnnouncement>>,
         process_manager: xtra::Address<process_manager::Actor>,
         register: MessageChannel<connection::RegisterRollover, ()>,
@@ -85,7 +82,6 @@ impl Actor {
             n_payouts,
             send_to_taker_actor,
             taker_id,
-            oracle_pk,
             sent_from_taker: None,
             oracle_actor,
             register,
@@ -208,7 +204,10 @@ impl Actor {
 
         let announcement = self
             .oracle_actor
-            .send(oracle::GetAnnouncement(oracle_event_id))
+            .send(oracle::GetAnnouncement {
+                oracle_pk: dlc.oracle_pk,
+                event_id: oracle_event_id,
+            })
             .await
             .context("Oracle actor disconnected")?
             .context("Failed to get announcement")?;
@@ -230,7 +229,7 @@ impl Actor {
                 }
             }),
             receiver,
-            (self.oracle_pk, announcement),
+            (dlc.oracle_pk, announcement),
             rollover_params,
             Role::Maker,
             position,
@@ -1,20 +1,33 @@
 use crate::actor_system::ActorSystem;
+use crate::exposure::Exposure;
+use crate::exposure::RiskLimits;
+use crate::policy::PolicyDecision;
+use crate::policy::PolicyParams;
+use crate::quoting::QuotingParams;
 use anyhow::Result;
+use bdk::bitcoin::Amount;
+use bdk::bitcoin::OutPoint;
 use bdk::sled;
-use daemon::bdk::blockchain::ElectrumBlockchain;
+use daemon::bdk::blockchain::AnyBlockchain;
 use daemon::oracle;
 use daemon::projection::Cfd;
 use daemon::projection::CfdAction;
+use daemon::projection::CfdOrder;
 use daemon::projection::Feeds;
+use daemon::projection::MakerOffers;
+use daemon::projection::Quote;
 use daemon::wallet;
 use http_api_problem::HttpApiProblem;
 use http_api_problem::StatusCode;
+use model::libp2p::PeerId;
 use model::FundingRate;
 use model::Identity;
 use model::Leverage;
 use model::OpeningFee;
 use model::OrderId;
 use model::Price;
+use model::RecoveredContract;
+use model::TradingPair;
 use model::TxFeeRate;
 use model::Usd;
 use model::WalletInfo;
@@ -38,7 +51,7 @@ use tokio::sync::watch;
 use tracing::instrument;
 use uuid::Uuid;
 
-pub type Maker = ActorSystem<oracle::Actor, wallet::Actor<ElectrumBlockchain, sled::Tree>>;
+pub type Maker = ActorSystem<oracle::Actor, wallet::Actor<AnyBlockchain, sled::Tree>>;
 
 #[allow(clippy::too_many_arguments)]
 #[rocket::get("/feed")]
@@ -52,18 +65,18 @@ pub async fn maker_feed(
     let mut rx_cfds = rx.cfds.clone();
     let mut rx_offers = rx.offers.clone();
     let mut rx_wallet = rx_wallet.inner().clone();
-    let mut rx_quote = rx.quote.clone();
+    let mut rx_quotes = rx.quotes.clone();
     let mut rx_connected_takers = rx.connected_takers.clone();
 
     EventStream! {
         let wallet_info = rx_wallet.borrow().clone();
         yield wallet_info.to_sse_event();
 
-        let offers = rx_offers.borrow().clone();
-        yield Event::json(&offers.long).event("long_offer");
-        yield Event::json(&offers.short).event("short_offer");
+        let (long, short) = btc_usd_offers(&rx_offers.borrow());
+        yield Event::json(&long).event("long_offer");
+        yield Event::json(&short).event("short_offer");
 
-        let quote = rx_quote.borrow().clone();
+        let quote = btc_usd_quote(&rx_quotes.borrow());
         yield quote.to_sse_event();
 
         let cfds = rx_cfds.borrow().clone();
@@ -81,9 +94,9 @@ pub async fn maker_feed(
                     yield wallet_info.to_sse_event();
                 },
                 Ok(()) = rx_offers.changed() => {
-                    let offers = rx_offers.borrow().clone();
-                    yield Event::json(&offers.long).event("long_offer");
-                    yield Event::json(&offers.short).event("short_offer");
+                    let (long, short) = btc_usd_offers(&rx_offers.borrow());
+                    yield Event::json(&long).event("long_offer");
+                    yield Event::json(&short).event("short_offer");
                 }
                 Ok(()) = rx_connected_takers.changed() => {
                     let takers = rx_connected_takers.borrow().clone();
@@ -95,8 +108,8 @@ pub async fn maker_feed(
                         yield cfds.to_sse_event()
                     }
                 }
-                Ok(()) = rx_quote.changed() => {
-                    let quote = rx_quote.borrow().clone();
+                Ok(()) = rx_quotes.changed() => {
+                    let quote = btc_usd_quote(&rx_quotes.borrow());
                     yield quote.to_sse_event();
                 }
             }
@@ -104,9 +117,28 @@ pub async fn maker_feed(
     }
 }
 
+/// The long and short offer shown in the maker UI, which only supports BTC/USD.
+fn btc_usd_offers(offers: &[MakerOffers]) -> (Option<CfdOrder>, Option<CfdOrder>) {
+    offers
+        .iter()
+        .find(|offers| offers.trading_pair == TradingPair::BtcUsd)
+        .map(|offers| (offers.long.clone(), offers.short.clone()))
+        .unwrap_or_default()
+}
+
+/// The quote shown in the maker UI, which only supports BTC/USD.
+fn btc_usd_quote(quotes: &[Quote]) -> Option<Quote> {
+    quotes
+        .iter()
+        .find(|quote| quote.trading_pair == TradingPair::BtcUsd)
+        .copied()
+}
+
 /// The maker PUTs this to set the offer params
 #[derive(Debug, Clone, Deserialize)]
 pub struct CfdNewOfferParamsRequest {
+    #[serde(default)]
+    pub trading_pair: TradingPair,
     pub price_long: Option<Price>,
     pub price_short: Option<Price>,
     pub min_quantity: Usd,
@@ -121,12 +153,19 @@ pub struct CfdNewOfferParamsRequest {
     pub opening_fee: OpeningFee,
     #[serde(default = "empty_leverage")]
     pub leverage_choices: Vec<Leverage>,
+    /// The leverage of the maker, unity if not specified
+    #[serde(default = "unity_leverage")]
+    pub leverage_maker: Leverage,
 }
 
 fn empty_leverage() -> Vec<Leverage> {
     vec![Leverage::TWO]
 }
 
+fn unity_leverage() -> Leverage {
+    Leverage::ONE
+}
+
 #[rocket::put("/offer", data = "<offer_params>")]
 #[instrument(name = "PUT /offer", skip(maker, _auth), err)]
 pub async fn put_offer_params(
@@ -136,6 +175,7 @@ pub async fn put_offer_params(
 ) -> Result<(), HttpApiProblem> {
     maker
         .set_offer_params(
+            offer_params.trading_pair,
             offer_params.price_long,
             offer_params.price_short,
             offer_params.min_quantity,
@@ -145,6 +185,7 @@ pub async fn put_offer_params(
             offer_params.daily_funding_rate_short,
             offer_params.opening_fee,
             offer_params.leverage_choices.clone(),
+            offer_params.leverage_maker,
         )
         .await
         .map_err(|e| {
@@ -156,6 +197,211 @@ pub async fn put_offer_params(
     Ok(())
 }
 
+/// The maker PUTs this to enable automated quoting based on the price feed
+#[derive(Debug, Clone, Deserialize)]
+pub struct QuotingParamsRequest {
+    #[serde(default)]
+    pub trading_pair: TradingPair,
+    /// Distance of the offer prices from bid and ask, in basis points
+    pub spread_bps: u32,
+    /// Shift applied to both offer prices, in basis points
+    #[serde(default)]
+    pub skew_bps: i32,
+    pub min_quantity: Usd,
+    pub max_quantity: Usd,
+    /// The current _daily_ funding rate for the maker's long position
+    pub daily_funding_rate_long: FundingRate,
+    /// The current _daily_ funding rate for the maker's short position
+    pub daily_funding_rate_short: FundingRate,
+    pub tx_fee_rate: TxFeeRate,
+    pub opening_fee: OpeningFee,
+    #[serde(default = "empty_leverage")]
+    pub leverage_choices: Vec<Leverage>,
+    /// The leverage of the maker, unity if not specified
+    #[serde(default = "unity_leverage")]
+    pub leverage_maker: Leverage,
+}
+
+#[rocket::put("/quoting", data = "<quoting_params>")]
+#[instrument(name = "PUT /quoting", skip(maker, _auth), err)]
+pub async fn put_quoting_params(
+    quoting_params: Json<QuotingParamsRequest>,
+    maker: &State<Maker>,
+    _auth: Authenticated,
+) -> Result<(), HttpApiProblem> {
+    let quoting_params = quoting_params.into_inner();
+
+    maker
+        .enable_quoting(QuotingParams {
+            trading_pair: quoting_params.trading_pair,
+            spread_bps: quoting_params.spread_bps,
+            skew_bps: quoting_params.skew_bps,
+            min_quantity: quoting_params.min_quantity,
+            max_quantity: quoting_params.max_quantity,
+            tx_fee_rate: quoting_params.tx_fee_rate,
+            funding_rate_long: quoting_params.daily_funding_rate_long,
+            funding_rate_short: quoting_params.daily_funding_rate_short,
+            opening_fee: quoting_params.opening_fee,
+            leverage_choices: quoting_params.leverage_choices,
+            leverage_maker: quoting_params.leverage_maker,
+        })
+        .await
+        .map_err(|e| {
+            HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+                .title("Enabling quoting failed")
+                .detail(format!("{e:#}"))
+        })?;
+
+    Ok(())
+}
+
+#[rocket::delete("/quoting")]
+#[instrument(name = "DELETE /quoting", skip_all, err)]
+pub async fn delete_quoting_params(
+    maker: &State<Maker>,
+    _auth: Authenticated,
+) -> Result<(), HttpApiProblem> {
+    maker.disable_quoting().await.map_err(|e| {
+        HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+            .title("Disabling quoting failed")
+            .detail(format!("{e:#}"))
+    })?;
+
+    Ok(())
+}
+
+/// The maker PUTs this to decide on requests of takers automatically
+#[derive(Debug, Clone, Deserialize)]
+pub struct PolicyParamsRequest {
+    #[serde(default)]
+    pub decide_orders: bool,
+    #[serde(default)]
+    pub decide_settlements: bool,
+    #[serde(default)]
+    pub decide_rollovers: bool,
+    /// Only accept requests of these peers, all peers are accepted if not set
+    pub peer_whitelist: Option<Vec<PeerId>>,
+    pub max_quantity: Option<Usd>,
+    /// Maximum distance of a proposed settlement price from the market price in our disfavour, in
+    /// basis points
+    pub max_settlement_price_deviation_bps: Option<u32>,
+}
+
+#[rocket::put("/policy", data = "<policy_params>")]
+#[instrument(name = "PUT /policy", skip(maker, _auth), err)]
+pub async fn put_policy_params(
+    policy_params: Json<PolicyParamsRequest>,
+    maker: &State<Maker>,
+    _auth: Authenticated,
+) -> Result<(), HttpApiProblem> {
+    let policy_params = policy_params.into_inner();
+
+    maker
+        .enable_policy(PolicyParams {
+            decide_orders: policy_params.decide_orders,
+            decide_settlements: policy_params.decide_settlements,
+            decide_rollovers: policy_params.decide_rollovers,
+            peer_whitelist: policy_params.peer_whitelist,
+            max_quantity: policy_params.max_quantity,
+            max_settlement_price_deviation_bps: policy_params.max_settlement_price_deviation_bps,
+        })
+        .await
+        .map_err(|e| {
+            HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+                .title("Enabling policy failed")
+                .detail(format!("{e:#}"))
+        })?;
+
+    Ok(())
+}
+
+#[rocket::delete("/policy")]
+#[instrument(name = "DELETE /policy", skip_all, err)]
+pub async fn delete_policy_params(
+    maker: &State<Maker>,
+    _auth: Authenticated,
+) -> Result<(), HttpApiProblem> {
+    maker.disable_policy().await.map_err(|e| {
+        HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+            .title("Disabling policy failed")
+            .detail(format!("{e:#}"))
+    })?;
+
+    Ok(())
+}
+
+#[rocket::get("/cfd/<id>/policy-decisions")]
+#[instrument(name = "GET /cfd/<id>/policy-decisions", skip(maker, _auth), err)]
+pub async fn get_policy_decisions(
+    id: Uuid,
+    maker: &State<Maker>,
+    _auth: Authenticated,
+) -> Result<Json<Vec<PolicyDecision>>, HttpApiProblem> {
+    let decisions = maker
+        .policy_decisions(OrderId::from(id))
+        .await
+        .map_err(|e| {
+            HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+                .title("Loading policy decisions failed")
+                .detail(format!("{e:#}"))
+        })?;
+
+    Ok(Json(decisions))
+}
+
+/// The maker PUTs this to limit the exposure new orders can add
+#[derive(Debug, Clone, Deserialize)]
+pub struct RiskLimitsRequest {
+    /// Maximum quantity of all positions with a single taker
+    pub max_taker_quantity: Option<Usd>,
+    /// Maximum net open interest per trading pair, long or short
+    pub max_net_open_interest: Option<Usd>,
+    #[serde(default, with = "::bdk::bitcoin::util::amount::serde::as_btc::opt")]
+    pub max_locked_margin: Option<Amount>,
+}
+
+#[rocket::put("/risk-limits", data = "<risk_limits>")]
+#[instrument(name = "PUT /risk-limits", skip(maker, _auth), err)]
+pub async fn put_risk_limits(
+    risk_limits: Json<RiskLimitsRequest>,
+    maker: &State<Maker>,
+    _auth: Authenticated,
+) -> Result<(), HttpApiProblem> {
+    let risk_limits = risk_limits.into_inner();
+
+    maker
+        .set_risk_limits(RiskLimits {
+            max_taker_quantity: risk_limits.max_taker_quantity,
+            max_net_open_interest: risk_limits.max_net_open_interest,
+            max_locked_margin: risk_limits.max_locked_margin,
+        })
+        .await
+        .map_err(|e| {
+            HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+                .title("Setting risk limits failed")
+                .detail(format!("{e:#}"))
+        })?;
+
+    Ok(())
+}
+
+#[rocket::get("/exposure")]
+#[instrument(name = "GET /exposure", skip_all, err)]
+pub async fn get_exposure(
+    rx: &State<Feeds>,
+    _auth: Authenticated,
+) -> Result<Json<Exposure>, HttpApiProblem> {
+    let rx_cfds = rx.inner().cfds.clone();
+    let cfds = rx_cfds.borrow();
+
+    match cfds.as_deref() {
+        Some(cfds) => Ok(Json(Exposure::new(cfds))),
+        None => Err(HttpApiProblem::new(StatusCode::SERVICE_UNAVAILABLE)
+            .title("Exposure not yet available")
+            .detail("CFDs are still being loaded from the database. Please retry later.")),
+    }
+}
+
 #[rocket::post("/cfd/<id>/<action>")]
 #[instrument(name = "POST /cfd/<id>/<action>", skip(maker, _auth), err)]
 pub async fn post_cfd_action(
@@ -231,6 +477,92 @@ pub async fn put_sync_wallet(
     Ok(())
 }
 
+#[rocket::get("/wallet/utxos")]
+#[instrument(name = "GET /wallet/utxos", skip_all, err)]
+pub async fn get_utxos(
+    maker: &State<Maker>,
+    _auth: Authenticated,
+) -> Result<Json<Vec<wallet::Utxo>>, HttpApiProblem> {
+    let utxos = maker.list_utxos().await.map_err(|e| {
+        HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+            .title("Could not list unspent outputs")
+            .detail(format!("{e:#}"))
+    })?;
+
+    Ok(Json(utxos))
+}
+
+#[rocket::put("/wallet/utxos/<outpoint>/frozen")]
+#[instrument(name = "PUT /wallet/utxos/<outpoint>/frozen", skip(maker, _auth), err)]
+pub async fn put_frozen_utxo(
+    outpoint: &str,
+    maker: &State<Maker>,
+    _auth: Authenticated,
+) -> Result<(), HttpApiProblem> {
+    let outpoint = parse_outpoint(outpoint)?;
+
+    maker.freeze_utxo(outpoint).await.map_err(|e| {
+        HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+            .title("Could not freeze output")
+            .detail(format!("{e:#}"))
+    })?;
+
+    Ok(())
+}
+
+#[rocket::delete("/wallet/utxos/<outpoint>/frozen")]
+#[instrument(
+    name = "DELETE /wallet/utxos/<outpoint>/frozen",
+    skip(maker, _auth),
+    err
+)]
+pub async fn delete_frozen_utxo(
+    outpoint: &str,
+    maker: &State<Maker>,
+    _auth: Authenticated,
+) -> Result<(), HttpApiProblem> {
+    let outpoint = parse_outpoint(outpoint)?;
+
+    maker.unfreeze_utxo(outpoint).await.map_err(|e| {
+        HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+            .title("Could not unfreeze output")
+            .detail(format!("{e:#}"))
+    })?;
+
+    Ok(())
+}
+
+/// Recover the contracts open with a taker after losing the database
+#[rocket::post("/recover/<peer_id>")]
+#[instrument(name = "POST /recover/<peer_id>", skip(maker, _auth), err)]
+pub async fn post_recover(
+    peer_id: &str,
+    maker: &State<Maker>,
+    _auth: Authenticated,
+) -> Result<Json<Vec<RecoveredContract>>, HttpApiProblem> {
+    let peer_id = peer_id.parse::<PeerId>().map_err(|e| {
+        HttpApiProblem::new(StatusCode::BAD_REQUEST)
+            .title("Invalid peer id")
+            .detail(format!("{e:#}"))
+    })?;
+
+    let contracts = maker.recover(peer_id).await.map_err(|e| {
+        HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+            .title("Could not recover contracts")
+            .detail(format!("{e:#}"))
+    })?;
+
+    Ok(Json(contracts))
+}
+
+fn parse_outpoint(outpoint: &str) -> Result<OutPoint, HttpApiProblem> {
+    outpoint.parse().map_err(|e| {
+        HttpApiProblem::new(StatusCode::BAD_REQUEST)
+            .title("Invalid outpoint")
+            .detail(format!("Expected `<txid>:<vout>`: {e}"))
+    })
+}
+
 #[rocket::get("/cfds")]
 #[instrument(name = "GET /cfds", skip_all, err)]
 pub async fn get_cfds<'r>(
@@ -249,6 +581,16 @@ pub async fn get_cfds<'r>(
     }
 }
 
+#[derive(Debug, Clone, Serialize)]
+pub struct IdentifiedTaker {
+    peer_id: PeerId,
+    daemon_version: String,
+    protocol_version: String,
+    /// The protocols the taker accepts substreams for
+    protocols: Vec<String>,
+    listen_addresses: Vec<String>,
+}
+
 #[rocket::get("/takers")]
 #[instrument(name = "GET /takers", skip_all, err)]
 pub async fn get_takers<'r>(
@@ -262,6 +604,88 @@ pub async fn get_takers<'r>(
     Ok(Json(takers))
 }
 
+/// Takers which told us about themselves through the libp2p identify protocol, sorted by peer id
+#[rocket::get("/takers/identified")]
+#[instrument(name = "GET /takers/identified", skip_all, err)]
+pub async fn get_identified_takers(
+    maker: &State<Maker>,
+    _auth: Authenticated,
+) -> Result<Json<Vec<IdentifiedTaker>>, HttpApiProblem> {
+    let identified = maker.identified_takers().await.map_err(|e| {
+        HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+            .title("Loading identified takers failed")
+            .detail(format!("{e:#}"))
+    })?;
+
+    let mut identified = identified
+        .into_iter()
+        .map(|(peer_id, peer_info)| IdentifiedTaker {
+            peer_id,
+            daemon_version: peer_info.agent_version,
+            protocol_version: peer_info.protocol_version,
+            protocols: peer_info.protocols,
+            listen_addresses: peer_info
+                .listen_addrs
+                .iter()
+                .map(|addr| addr.to_string())
+                .collect(),
+        })
+        .collect::<Vec<_>>();
+    identified.sort_by_key(|taker| taker.peer_id.to_string());
+
+    Ok(Json(identified))
+}
+
+/// Disconnect a taker and reject its libp2p connections until it is unbanned
+///
+/// Bans are stored in the database and survive restarts of the maker.
+#[rocket::put("/takers/<peer_id>/ban")]
+#[instrument(name = "PUT /takers/<peer_id>/ban", skip(maker, _auth), err)]
+pub async fn put_taker_ban(
+    peer_id: &str,
+    maker: &State<Maker>,
+    _auth: Authenticated,
+) -> Result<(), HttpApiProblem> {
+    let peer_id = parse_peer_id(peer_id)?;
+
+    maker.ban_taker(peer_id).await.map_err(|e| {
+        HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+            .title("Banning taker failed")
+            .detail(format!("{e:#}"))
+    })?;
+
+    Ok(())
+}
+
+/// Accept libp2p connections of a banned taker again
+///
+/// Takers given via `--deny-peer` are rejected again once the maker restarts.
+#[rocket::delete("/takers/<peer_id>/ban")]
+#[instrument(name = "DELETE /takers/<peer_id>/ban", skip(maker, _auth), err)]
+pub async fn delete_taker_ban(
+    peer_id: &str,
+    maker: &State<Maker>,
+    _auth: Authenticated,
+) -> Result<(), HttpApiProblem> {
+    let peer_id = parse_peer_id(peer_id)?;
+
+    maker.unban_taker(peer_id).await.map_err(|e| {
+        HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+            .title("Unbanning taker failed")
+            .detail(format!("{e:#}"))
+    })?;
+
+    Ok(())
+}
+
+fn parse_peer_id(peer_id: &str) -> Result<PeerId, HttpApiProblem> {
+    peer_id.parse::<PeerId>().map_err(|e| {
+        HttpApiProblem::new(StatusCode::BAD_REQUEST)
+            .title("Invalid peer id")
+            .detail(format!("{e:#}"))
+    })
+}
+
 #[rocket::get("/metrics")]
 #[instrument(name = "GET /metrics", skip_all, err)]
 pub async fn get_metrics<'r>(_auth: Authenticated) -> Result<String, HttpApiProblem> {
@@ -4,6 +4,8 @@ use crate::libp2p::PeerId;
 use crate::olivia;
 use crate::olivia::BitMexPriceEventId;
 use crate::payout_curve;
+use crate::payout_curve::ContractType;
+use crate::recovery::RecoveredContract;
 use crate::rollover;
 use crate::rollover::BaseDlcParams;
 use crate::rollover::RolloverParams;
@@ -38,6 +40,7 @@ use bdk::bitcoin::Transaction;
 use bdk::bitcoin::TxIn;
 use bdk::bitcoin::TxOut;
 use bdk::bitcoin::Txid;
+use bdk::bitcoin::XOnlyPublicKey;
 use bdk::descriptor::Descriptor;
 use bdk::miniscript::DescriptorTrait;
 use itertools::Itertools;
@@ -115,6 +118,9 @@ impl From<OrderId> for Uuid {
 
 #[derive(Clone, Serialize, Deserialize, PartialEq)]
 pub struct MakerOffers {
+    /// Makers that only offer BTC/USD don't send the trading pair.
+    #[serde(default)]
+    pub trading_pair: TradingPair,
     pub long: Option<Order>,
     pub short: Option<Order>,
     pub tx_fee_rate: TxFeeRate,
@@ -125,6 +131,7 @@ pub struct MakerOffers {
 impl fmt::Debug for MakerOffers {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
         fmt.debug_struct("MakerOffers")
+            .field("trading_pair", &self.trading_pair)
             .field("long_order_id", &self.long.as_ref().map(|o| o.id))
             .field("short_order_id", &self.short.as_ref().map(|o| o.id))
             .field("tx_fee_rate", &self.tx_fee_rate)
@@ -174,12 +181,19 @@ impl MakerOffers {
         (None, self)
     }
 
-    /// Update the orders after one of them got taken.
-    pub fn replicate(&self) -> MakerOffers {
+    /// Replace the orders with new ones at `tx_fee_rate`, e.g. after one of them got taken.
+    pub fn replicate(&self, tx_fee_rate: TxFeeRate) -> MakerOffers {
+        let replicate = |order: &Order| {
+            let mut order = order.replicate();
+            order.tx_fee_rate = tx_fee_rate;
+            order
+        };
+
         MakerOffers {
-            long: self.long.as_ref().map(|order| order.replicate()),
-            short: self.short.as_ref().map(|order| order.replicate()),
-            tx_fee_rate: self.tx_fee_rate,
+            trading_pair: self.trading_pair,
+            long: self.long.as_ref().map(replicate),
+            short: self.short.as_ref().map(replicate),
+            tx_fee_rate,
             funding_rate_long: self.funding_rate_long,
             funding_rate_short: self.funding_rate_short,
         }
@@ -239,6 +253,12 @@ pub struct Order {
     /// A selection of leverages that the maker allows for the taker
     pub leverage_choices: Vec<Leverage>,
 
+    /// The leverage the maker uses for this order
+    ///
+    /// Orders of makers that don't specify a leverage use unity leverage.
+    #[serde(default = "unity_leverage")]
+    pub leverage_maker: Leverage,
+
     /// The creation timestamp as set by the maker
     #[serde(rename = "creation_timestamp")]
     pub creation_timestamp_maker: Timestamp,
@@ -253,6 +273,12 @@ pub struct Order {
     /// The maker includes this into the Order based on the Oracle announcement to be used.
     pub oracle_event_id: BitMexPriceEventId,
 
+    /// The public key of the oracle attesting to `oracle_event_id`
+    ///
+    /// Orders of makers that don't specify an oracle are attested to by olivia.
+    #[serde(default = "olivia::default_public_key")]
+    pub oracle_pk: XOnlyPublicKey,
+
     pub tx_fee_rate: TxFeeRate,
     pub funding_rate: FundingRate,
     pub opening_fee: OpeningFee,
@@ -267,11 +293,13 @@ impl Order {
         max_quantity: Usd,
         origin: Origin,
         oracle_event_id: BitMexPriceEventId,
+        oracle_pk: XOnlyPublicKey,
         settlement_interval: Duration,
         tx_fee_rate: TxFeeRate,
         funding_rate: FundingRate,
         opening_fee: OpeningFee,
         leverage_choices: Vec<Leverage>,
+        leverage_maker: Leverage,
     ) -> Self {
         // allowing deprecated use of field `leverage_taker` here for backwards compatibility.
         #[allow(deprecated)]
@@ -282,12 +310,14 @@ impl Order {
             max_quantity,
             leverage_taker: Leverage::TWO,
             leverage_choices,
-            trading_pair: TradingPair::BtcUsd,
+            leverage_maker,
+            trading_pair: oracle_event_id.trading_pair(),
             position_maker,
             creation_timestamp_maker: Timestamp::now(),
             settlement_interval,
             origin,
             oracle_event_id,
+            oracle_pk,
             tx_fee_rate,
             funding_rate,
             opening_fee,
@@ -303,11 +333,13 @@ impl Order {
             self.max_quantity,
             self.origin,
             self.oracle_event_id,
+            self.oracle_pk,
             self.settlement_interval,
             self.tx_fee_rate,
             self.funding_rate,
             self.opening_fee,
             self.leverage_choices.clone(),
+            self.leverage_maker,
         )
     }
 
@@ -450,8 +482,19 @@ pub enum EventKind {
     CommitConfirmed,
     CetConfirmed,
     RefundConfirmed,
+    /// A revoked commit transaction was confirmed but we could not punish the counterparty
+    ///
+    /// This is only emitted if we fail to build the punish transaction, e.g. because the revoked
+    /// commit was recorded before we started storing the data required to punish.
     RevokeConfirmed,
     CollaborativeSettlementConfirmed,
+    /// The counterparty published a revoked commit transaction and we published the punish
+    /// transaction spending from it.
+    PunishPublished {
+        #[serde(with = "hex_transaction")]
+        punish_tx: Transaction,
+    },
+    PunishConfirmed,
 
     CetTimelockExpiredPriorOracleAttestation,
     CetTimelockExpiredPostOracleAttestation {
@@ -484,6 +527,33 @@ pub enum EventKind {
         #[serde(with = "hex_transaction")]
         tx: Transaction,
     },
+    /// We published a transaction spending our output of a transaction that is stuck in the
+    /// mempool, so that miners confirm both at a higher fee rate.
+    ///
+    /// Every bump replaces the previous child transaction of the same parent.
+    FeeBumped {
+        parent_txid: Txid,
+        #[serde(with = "hex_transaction")]
+        cpfp_tx: Transaction,
+        /// Fee rate of the parent and child transaction together
+        fee_rate: TxFeeRate,
+    },
+    /// The maker's policy decided on a request of the taker without operator involvement.
+    ///
+    /// Only emitted by the maker.
+    PolicyDecided {
+        request: PolicyRequest,
+        accepted: bool,
+        reason: String,
+    },
+}
+
+/// Request of a taker that the maker decides on
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub enum PolicyRequest {
+    Order,
+    Settlement,
+    Rollover,
 }
 
 impl fmt::Display for EventKind {
@@ -511,6 +581,8 @@ impl fmt::Display for EventKind {
             RefundConfirmed => "RefundConfirmed",
             RevokeConfirmed => "RevokeConfirmed",
             CollaborativeSettlementConfirmed => "CollaborativeSettlementConfirmed",
+            PunishPublished { .. } => "PunishPublished",
+            PunishConfirmed => "PunishConfirmed",
             CetTimelockExpiredPriorOracleAttestation => "CetTimelockExpiredPriorOracleAttestation",
             CetTimelockExpiredPostOracleAttestation { .. } => {
                 "CetTimelockExpiredPostOracleAttestation"
@@ -519,6 +591,8 @@ impl fmt::Display for EventKind {
             OracleAttestedPriorCetTimelock { .. } => "OracleAttestedPriorCetTimelock",
             OracleAttestedPostCetTimelock { .. } => "OracleAttestedPostCetTimelock",
             ManualCommit { .. } => "ManualCommit",
+            FeeBumped { .. } => "FeeBumped",
+            PolicyDecided { .. } => "PolicyDecided",
         };
 
         s.fmt(f)
@@ -532,6 +606,7 @@ impl EventKind {
     pub const COLLABORATIVE_SETTLEMENT_CONFIRMED: &'static str = "CollaborativeSettlementConfirmed";
     pub const CET_CONFIRMED: &'static str = "CetConfirmed";
     pub const REFUND_CONFIRMED: &'static str = "RefundConfirmed";
+    pub const PUNISH_CONFIRMED: &'static str = "PunishConfirmed";
     pub const CONTRACT_SETUP_FAILED: &'static str = "ContractSetupFailed";
     pub const OFFER_REJECTED: &'static str = "OfferRejected";
 
@@ -564,6 +639,29 @@ impl EventKind {
     }
 }
 
+/// The dynamic state of a [`Cfd`] at a given version, for inspection
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct CfdSnapshot {
+    pub version: u32,
+    pub lock_txid: Option<Txid>,
+    pub commit_txid: Option<Txid>,
+    pub cet_txid: Option<Txid>,
+    pub refund_txid: Option<Txid>,
+    pub collaborative_settlement_txid: Option<Txid>,
+    pub punish_txid: Option<Txid>,
+    pub lock_finality: bool,
+    pub commit_finality: bool,
+    pub cet_finality: bool,
+    pub refund_finality: bool,
+    pub collaborative_settlement_finality: bool,
+    pub punish_finality: bool,
+    pub cet_timelock_expired: bool,
+    pub refund_timelock_expired: bool,
+    pub during_contract_setup: bool,
+    pub during_rollover: bool,
+    pub during_collaborative_settlement: bool,
+}
+
 /// Models the cfd state of the taker
 ///
 /// Upon `Command`s, that are reaction to something happening in the system, we decide to
@@ -577,6 +675,7 @@ pub struct Cfd {
 
     // static
     id: OrderId,
+    trading_pair: TradingPair,
     position: Position,
     initial_price: Price,
     initial_funding_rate: FundingRate,
@@ -610,12 +709,17 @@ pub struct Cfd {
     collaborative_settlement_spend_tx: Option<Transaction>,
     refund_tx: Option<Transaction>,
 
+    /// Holds the punish transaction if the counterparty published a revoked commit transaction
+    /// and we have previously emitted it as part of an event.
+    punish_tx: Option<Transaction>,
+
     lock_finality: bool,
 
     commit_finality: bool,
     refund_finality: bool,
     cet_finality: bool,
     collaborative_settlement_finality: bool,
+    punish_finality: bool,
     cet_timelock_expired: bool,
 
     refund_timelock_expired: bool,
@@ -629,9 +733,11 @@ impl Cfd {
     #[allow(clippy::too_many_arguments)]
     pub fn new(
         id: OrderId,
+        trading_pair: TradingPair,
         position: Position,
         initial_price: Price,
         taker_leverage: Leverage,
+        maker_leverage: Leverage,
         settlement_interval: Duration, /* TODO: Make a newtype that enforces hours only so
                                         * we don't have to deal with precisions in the
                                         * database. */
@@ -644,9 +750,10 @@ impl Cfd {
         initial_tx_fee_rate: TxFeeRate,
     ) -> Self {
         let (long_leverage, short_leverage) =
-            long_and_short_leverage(taker_leverage, role, position);
+            long_and_short_leverage(taker_leverage, maker_leverage, role, position);
 
         let initial_funding_fee = FundingFee::calculate(
+            trading_pair,
             initial_price,
             quantity,
             long_leverage,
@@ -659,6 +766,7 @@ impl Cfd {
         Cfd {
             version: 0,
             id,
+            trading_pair,
             position,
             initial_price,
             long_leverage,
@@ -676,11 +784,13 @@ impl Cfd {
             commit_tx: None,
             collaborative_settlement_spend_tx: None,
             refund_tx: None,
+            punish_tx: None,
             lock_finality: false,
             commit_finality: false,
             refund_finality: false,
             cet_finality: false,
             collaborative_settlement_finality: false,
+            punish_finality: false,
             cet_timelock_expired: false,
             refund_timelock_expired: false,
             during_contract_setup: false,
@@ -708,9 +818,11 @@ impl Cfd {
 
         Cfd::new(
             order.id,
+            order.trading_pair,
             position,
             order.price,
             taker_leverage,
+            order.leverage_maker,
             order.settlement_interval,
             role,
             quantity,
@@ -728,33 +840,47 @@ impl Cfd {
             .map(|dlc| dlc.settlement_event_id.timestamp())
     }
 
-    fn margin(&self) -> Amount {
+    /// Our margin in this CFD.
+    pub fn margin(&self) -> Amount {
         match self.position {
-            Position::Long => {
-                calculate_margin(self.initial_price, self.quantity, self.long_leverage)
-            }
-            Position::Short => {
-                calculate_margin(self.initial_price, self.quantity, self.short_leverage)
-            }
+            Position::Long => calculate_margin(
+                self.trading_pair,
+                self.initial_price,
+                self.quantity,
+                self.long_leverage,
+            ),
+            Position::Short => calculate_margin(
+                self.trading_pair,
+                self.initial_price,
+                self.quantity,
+                self.short_leverage,
+            ),
         }
     }
 
     fn counterparty_margin(&self) -> Amount {
         match self.position {
-            Position::Long => {
-                calculate_margin(self.initial_price, self.quantity, self.short_leverage)
-            }
-            Position::Short => {
-                calculate_margin(self.initial_price, self.quantity, self.long_leverage)
-            }
+            Position::Long => calculate_margin(
+                self.trading_pair,
+                self.initial_price,
+                self.quantity,
+                self.short_leverage,
+            ),
+            Position::Short => calculate_margin(
+                self.trading_pair,
+                self.initial_price,
+                self.quantity,
+                self.long_leverage,
+            ),
         }
     }
 
-    fn is_in_collaborative_settlement(&self) -> bool {
+    pub fn is_in_collaborative_settlement(&self) -> bool {
         self.settlement_proposal.is_some()
     }
 
-    fn is_in_force_close(&self) -> bool {
+    /// Whether we published, or are about to publish, the commit transaction.
+    pub fn is_in_force_close(&self) -> bool {
         self.commit_tx.is_some()
     }
 
@@ -801,7 +927,7 @@ impl Cfd {
         Ok(())
     }
 
-    fn can_settle_collaboratively(&self) -> bool {
+    pub fn can_settle_collaboratively(&self) -> bool {
         !self.is_closed()
             && !self.commit_finality
             && !self.is_attested()
@@ -814,7 +940,10 @@ impl Cfd {
 
     /// Any transaction spending from lock has reached finality on the blockchain
     fn is_final(&self) -> bool {
-        self.collaborative_settlement_finality || self.cet_finality || self.refund_finality
+        self.collaborative_settlement_finality
+            || self.cet_finality
+            || self.refund_finality
+            || self.punish_finality
     }
 
     fn is_collaboratively_closed(&self) -> bool {
@@ -825,6 +954,10 @@ impl Cfd {
         self.refund_tx.is_some()
     }
 
+    fn is_punished(&self) -> bool {
+        self.punish_tx.is_some()
+    }
+
     /// Aggregate that defines if a CFD is considered closed
     ///
     /// A CFD is considered closed when the closing price can't change anymore, which means that we
@@ -834,11 +967,13 @@ impl Cfd {
     /// - the cfd was attested (i.e.a CET is set)
     /// - the cfd was collaboratively close (i.e. the collab close transaction is set)
     /// - the cfd was refunded (i.e. the refund transaction is set)
+    /// - the counterparty was punished (i.e. the punish transaction is set)
     fn is_closed(&self) -> bool {
         self.is_final()
             || self.is_attested()
             || self.is_collaboratively_closed()
             || self.is_refunded()
+            || self.is_punished()
     }
 
     pub fn start_contract_setup(&self) -> Result<(CfdEvent, SetupParams, Position)> {
@@ -929,7 +1064,8 @@ impl Cfd {
         }
 
         let now = OffsetDateTime::now_utc();
-        let to_event_id = olivia::next_announcement_after(now + self.settlement_interval);
+        let to_event_id =
+            olivia::next_announcement_after(self.trading_pair, now + self.settlement_interval);
 
         // If a `from_event_id` was specified we use it, otherwise we use the
         // `settlement_event_id` of the current dlc to calculate the costs.
@@ -961,6 +1097,7 @@ impl Cfd {
         };
 
         let funding_fee = FundingFee::calculate(
+            self.trading_pair,
             self.initial_price,
             self.quantity,
             self.long_leverage,
@@ -1014,7 +1151,8 @@ impl Cfd {
 
         let now = OffsetDateTime::now_utc();
 
-        let to_event_id = olivia::next_announcement_after(now + self.settlement_interval);
+        let to_event_id =
+            olivia::next_announcement_after(self.trading_pair, now + self.settlement_interval);
 
         // TODO: This should not be calculated here but we should just rely on `complete_fee`
         //  This requires more refactoring because the `RolloverCompleted` event currently depends
@@ -1022,6 +1160,7 @@ impl Cfd {
         let hours_to_charge =
             self.hours_to_extend_in_rollover_based_on_event(to_event_id, now, from_event_id)?;
         let funding_fee = FundingFee::calculate(
+            self.trading_pair,
             self.initial_price,
             self.quantity,
             self.long_leverage,
@@ -1133,6 +1272,7 @@ impl Cfd {
         n_payouts: usize,
     ) -> Result<(SettlementTransaction, SettlementProposal)> {
         let payout_curve = calculate_payouts(
+            self.trading_pair,
             self.position,
             self.role,
             self.initial_price,
@@ -1186,6 +1326,7 @@ impl Cfd {
         // Validate that the amounts sent by the taker are sane according to the payout curve
 
         let payout_curve_long = calculate_payouts(
+            self.trading_pair,
             self.position,
             self.role,
             self.initial_price,
@@ -1328,7 +1469,11 @@ impl Cfd {
     ///
     /// In case the Cfd was already closed we return `Ok(None)`, because then the attestation is not
     /// relevant anymore. We don't treat this as error because it is not an error scenario.
-    pub fn decrypt_cet(self, attestation: &olivia::Attestation) -> Result<Option<CfdEvent>> {
+    pub fn decrypt_cet(
+        self,
+        oracle_pk: &XOnlyPublicKey,
+        attestation: &olivia::Attestation,
+    ) -> Result<Option<CfdEvent>> {
         if self.is_closed() {
             return Ok(None);
         }
@@ -1338,7 +1483,7 @@ impl Cfd {
             None => return Ok(None),
         };
 
-        let cet = dlc.signed_cet(attestation)?;
+        let cet = dlc.signed_cet(oracle_pk, attestation)?;
 
         let cet = match cet {
             Ok(cet) => cet,
@@ -1425,8 +1570,48 @@ impl Cfd {
         self.event(EventKind::RefundConfirmed)
     }
 
-    pub fn handle_revoke_confirmed(self) -> CfdEvent {
-        self.event(EventKind::RevokeConfirmed)
+    /// Punish the counterparty for publishing a revoked commit transaction.
+    ///
+    /// If we cannot build the punish transaction we still record that the revoked commit
+    /// transaction was confirmed.
+    pub fn handle_revoke_confirmed(
+        self,
+        revoked_commit_tx: Transaction,
+    ) -> Result<Option<CfdEvent>> {
+        if self.is_punished() || self.punish_finality {
+            return Ok(None);
+        }
+
+        let dlc = self.dlc.as_ref().context("CFD does not have a DLC")?;
+
+        let event = match dlc.punish_tx(self.role, &revoked_commit_tx) {
+            Ok(punish_tx) => {
+                tracing::info!(
+                    order_id = %self.id,
+                    commit_txid = %revoked_commit_tx.txid(),
+                    "Counterparty published revoked commit transaction, punishing"
+                );
+
+                EventKind::PunishPublished { punish_tx }
+            }
+            Err(e) => {
+                tracing::error!(
+                    order_id = %self.id,
+                    commit_txid = %revoked_commit_tx.txid(),
+                    "Failed to punish counterparty for publishing revoked commit transaction: {e:#}"
+                );
+
+                EventKind::RevokeConfirmed
+            }
+        };
+
+        Ok(Some(self.event(event)))
+    }
+
+    pub fn handle_punish_confirmed(self) -> CfdEvent {
+        tracing::info!(order_id=%self.id, "Punish transaction confirmed");
+
+        self.event(EventKind::PunishConfirmed)
     }
 
     pub fn manual_commit_to_blockchain(&self) -> Result<CfdEvent> {
@@ -1439,6 +1624,96 @@ impl Cfd {
         }))
     }
 
+    /// Share the contract with the counterparty after it lost its database.
+    ///
+    /// Sharing does not change the CFD. The counterparty can only publish the refund transaction
+    /// once the contract was committed to the blockchain, which is left to the operator.
+    pub fn recover_counterparty(&self, peer_id: &PeerId) -> Result<RecoveredContract> {
+        anyhow::ensure!(
+            self.counterparty_peer_id.as_ref() == Some(peer_id),
+            "CFD was not set up with peer {peer_id}"
+        );
+        anyhow::ensure!(!self.is_closed(), "Cannot recover closed CFD");
+
+        let dlc = self
+            .dlc
+            .as_ref()
+            .context("Cannot recover CFD without a DLC")?;
+
+        let contract = RecoveredContract {
+            order_id: self.id,
+            role: match self.role {
+                Role::Maker => Role::Taker,
+                Role::Taker => Role::Maker,
+            },
+            position: self.position.counter_position(),
+            lock_tx: dlc.lock.0.clone(),
+            lock_descriptor: dlc.lock.1.clone(),
+            commit_tx: dlc.commit.0.clone(),
+            commit_descriptor: dlc.commit.2.clone(),
+            refund_tx: dlc.refund.0.clone(),
+            refund_signature: dlc.refund_signature()?,
+            counterparty_identity_pk: dlc.identity_pk(),
+            refund_timelock: dlc.refund_timelock,
+        };
+
+        Ok(contract)
+    }
+
+    /// Record that we published `cpfp_tx` to speed up the confirmation of `parent_txid`.
+    pub fn bump_fee(
+        &self,
+        parent_txid: Txid,
+        cpfp_tx: Transaction,
+        fee_rate: TxFeeRate,
+    ) -> Result<CfdEvent> {
+        anyhow::ensure!(
+            !self.is_final(),
+            "Cannot bump fee of transaction {parent_txid} because the CFD is final"
+        );
+        anyhow::ensure!(
+            cpfp_tx
+                .input
+                .iter()
+                .any(|input| input.previous_output.txid == parent_txid),
+            "Transaction {} does not spend from {parent_txid}",
+            cpfp_tx.txid()
+        );
+
+        tracing::info!(
+            order_id = %self.id,
+            %parent_txid,
+            cpfp_txid = %cpfp_tx.txid(),
+            %fee_rate,
+            "Bumping fee of stuck transaction"
+        );
+
+        Ok(self.event(EventKind::FeeBumped {
+            parent_txid,
+            cpfp_tx,
+            fee_rate,
+        }))
+    }
+
+    /// Record the decision of the maker's policy on a request of the taker.
+    pub fn record_policy_decision(
+        &self,
+        request: PolicyRequest,
+        accepted: bool,
+        reason: String,
+    ) -> Result<CfdEvent> {
+        anyhow::ensure!(
+            !self.is_final(),
+            "Cannot decide on {request:?} request because the CFD is final"
+        );
+
+        Ok(self.event(EventKind::PolicyDecided {
+            request,
+            accepted,
+            reason,
+        }))
+    }
+
     fn event(&self, event: EventKind) -> CfdEvent {
         CfdEvent::new(self.id, event)
     }
@@ -1469,6 +1744,10 @@ impl Cfd {
         self.id
     }
 
+    pub fn trading_pair(&self) -> TradingPair {
+        self.trading_pair
+    }
+
     pub fn position(&self) -> Position {
         self.position
     }
@@ -1484,6 +1763,13 @@ impl Cfd {
         }
     }
 
+    pub fn maker_leverage(&self) -> Leverage {
+        match (self.role, self.position) {
+            (Role::Taker, Position::Long) | (Role::Maker, Position::Short) => self.short_leverage,
+            (Role::Taker, Position::Short) | (Role::Maker, Position::Long) => self.long_leverage,
+        }
+    }
+
     pub fn settlement_time_interval_hours(&self) -> Duration {
         self.settlement_interval
     }
@@ -1609,6 +1895,33 @@ impl Cfd {
         self.version
     }
 
+    /// The state of the CFD derived from the events applied so far
+    pub fn snapshot(&self) -> CfdSnapshot {
+        CfdSnapshot {
+            version: self.version,
+            lock_txid: self.dlc.as_ref().map(|dlc| dlc.lock.0.txid()),
+            commit_txid: self.commit_tx.as_ref().map(Transaction::txid),
+            cet_txid: self.cet.as_ref().map(Transaction::txid),
+            refund_txid: self.refund_tx.as_ref().map(Transaction::txid),
+            collaborative_settlement_txid: self
+                .collaborative_settlement_spend_tx
+                .as_ref()
+                .map(Transaction::txid),
+            punish_txid: self.punish_tx.as_ref().map(Transaction::txid),
+            lock_finality: self.lock_finality,
+            commit_finality: self.commit_finality,
+            cet_finality: self.cet_finality,
+            refund_finality: self.refund_finality,
+            collaborative_settlement_finality: self.collaborative_settlement_finality,
+            punish_finality: self.punish_finality,
+            cet_timelock_expired: self.cet_timelock_expired,
+            refund_timelock_expired: self.refund_timelock_expired,
+            during_contract_setup: self.during_contract_setup,
+            during_rollover: self.during_rollover,
+            during_collaborative_settlement: self.settlement_proposal.is_some(),
+        }
+    }
+
     pub fn apply(mut self, evt: CfdEvent) -> Cfd {
         use EventKind::*;
 
@@ -1688,11 +2001,15 @@ impl Cfd {
             }
             ManualCommit { tx } => self.commit_tx = Some(tx),
             RevokeConfirmed => {
-                tracing::error!(order_id = %self.id, "Revoked logic not implemented");
-                // TODO: we should punish the other party instead. For now, we pretend we are in
-                // commit finalized and will receive our money based on an old CET.
+                tracing::error!(order_id = %self.id, "Revoked commit confirmed without punishing");
+                // We could not punish the other party. We pretend we are in commit finalized and
+                // will receive our money based on an old CET.
                 self.commit_finality = true;
             }
+            PunishPublished { punish_tx } => self.punish_tx = Some(punish_tx),
+            PunishConfirmed => self.punish_finality = true,
+            FeeBumped { .. } => {}
+            PolicyDecided { .. } => {}
         }
 
         self
@@ -1714,21 +2031,29 @@ impl AsBlocks for Duration {
 }
 
 /// Determine the leverage based on role and position
+///
+/// The maker's leverage is set per order. Orders of makers that don't specify it use unity
+/// leverage, which used to be the only choice.
 pub fn long_and_short_leverage(
     taker_leverage: Leverage,
+    maker_leverage: Leverage,
     role: Role,
     position: Position,
 ) -> (Leverage, Leverage) {
     match (role, position) {
         (Role::Maker, Position::Long) | (Role::Taker, Position::Short) => {
-            (Leverage::ONE, taker_leverage)
+            (maker_leverage, taker_leverage)
         }
         (Role::Maker, Position::Short) | (Role::Taker, Position::Long) => {
-            (taker_leverage, Leverage::ONE)
+            (taker_leverage, maker_leverage)
         }
     }
 }
 
+fn unity_leverage() -> Leverage {
+    Leverage::ONE
+}
+
 /// Calculate the closing price used to collaboratively settle a CFD.
 /// This value is akin to the one used for a market close order in a
 /// centralised exchange.
@@ -1756,23 +2081,56 @@ pub fn market_closing_price(bid: Price, ask: Price, role: Role, position: Positi
 ///
 /// The initial margin represents the collateral both parties have to come up with
 /// to satisfy the contract.
-pub fn calculate_margin(price: Price, quantity: Usd, leverage: Leverage) -> Amount {
-    quantity / (price * leverage)
+pub fn calculate_margin(
+    trading_pair: TradingPair,
+    price: Price,
+    quantity: Usd,
+    leverage: Leverage,
+) -> Amount {
+    match trading_pair.contract_type() {
+        ContractType::Inverse => quantity / (price * leverage),
+        ContractType::Linear => {
+            let mut btc = quantity.into_decimal()
+                * payout_curve::LINEAR_CONTRACT_MULTIPLIER
+                * price.into_decimal()
+                / Decimal::from(leverage.get());
+            btc.rescale(8);
+
+            Amount::from_str_in(&btc.to_string(), bitcoin::Denomination::Bitcoin)
+                .expect("Error computing BTC amount")
+        }
+    }
 }
 
-pub fn calculate_long_liquidation_price(leverage: Leverage, price: Price) -> Price {
-    price * leverage / (leverage + 1)
+pub fn calculate_long_liquidation_price(
+    trading_pair: TradingPair,
+    leverage: Leverage,
+    price: Price,
+) -> Price {
+    match trading_pair.contract_type() {
+        ContractType::Inverse => price * leverage / (leverage + 1),
+        ContractType::Linear => price - price / leverage,
+    }
 }
 
 /// calculates short liquidation price
 ///
-/// Note: if leverage == 1, then the liquidation price will go towards infinity.
-/// This is represented as Price::INFINITE
-pub fn calculate_short_liquidation_price(leverage: Leverage, price: Price) -> Price {
-    if leverage == Leverage::ONE {
-        return Price::INFINITE;
+/// Note: for an inverse contract with leverage == 1 the liquidation price will go towards
+/// infinity. This is represented as Price::INFINITE
+pub fn calculate_short_liquidation_price(
+    trading_pair: TradingPair,
+    leverage: Leverage,
+    price: Price,
+) -> Price {
+    match trading_pair.contract_type() {
+        ContractType::Inverse => {
+            if leverage == Leverage::ONE {
+                return Price::INFINITE;
+            }
+            price * leverage / (leverage - 1)
+        }
+        ContractType::Linear => price + price / leverage,
     }
-    price * leverage / (leverage - 1)
 }
 
 pub fn calculate_profit(payout: SignedAmount, margin: SignedAmount) -> (SignedAmount, Percent) {
@@ -1790,6 +2148,7 @@ pub fn calculate_profit(payout: SignedAmount, margin: SignedAmount) -> (SignedAm
 /// All values are calculated without using the payout curve.
 /// Profit/loss is returned as signed bitcoin amount and percent.
 pub fn calculate_profit_at_price(
+    trading_pair: TradingPair,
     opening_price: Price,
     closing_price: Price,
     quantity: Usd,
@@ -1797,19 +2156,34 @@ pub fn calculate_profit_at_price(
     short_leverage: Leverage,
     fee_account: FeeAccount,
 ) -> Result<(SignedAmount, Percent, SignedAmount)> {
-    let inv_initial_price =
-        InversePrice::new(opening_price).context("cannot invert invalid price")?;
-    let inv_closing_price =
-        InversePrice::new(closing_price).context("cannot invert invalid price")?;
-    let long_liquidation_price = calculate_long_liquidation_price(long_leverage, opening_price);
+    let long_liquidation_price =
+        calculate_long_liquidation_price(trading_pair, long_leverage, opening_price);
     let long_is_liquidated = closing_price <= long_liquidation_price;
 
-    let amount_changed = (quantity * inv_initial_price)
-        .to_signed()
-        .context("Unable to convert to SignedAmount")?
-        - (quantity * inv_closing_price)
-            .to_signed()
-            .context("Unable to convert to SignedAmount")?;
+    let amount_changed = match trading_pair.contract_type() {
+        ContractType::Inverse => {
+            let inv_initial_price =
+                InversePrice::new(opening_price).context("cannot invert invalid price")?;
+            let inv_closing_price =
+                InversePrice::new(closing_price).context("cannot invert invalid price")?;
+
+            (quantity * inv_initial_price)
+                .to_signed()
+                .context("Unable to convert to SignedAmount")?
+                - (quantity * inv_closing_price)
+                    .to_signed()
+                    .context("Unable to convert to SignedAmount")?
+        }
+        ContractType::Linear => {
+            let mut btc = quantity.into_decimal()
+                * payout_curve::LINEAR_CONTRACT_MULTIPLIER
+                * (closing_price.into_decimal() - opening_price.into_decimal());
+            btc.rescale(8);
+
+            SignedAmount::from_str_in(&btc.to_string(), bitcoin::Denomination::Bitcoin)
+                .context("Unable to convert to SignedAmount")?
+        }
+    };
 
     // calculate profit/loss (P and L) in BTC
     let (margin, payout) = match fee_account.position {
@@ -1840,9 +2214,10 @@ pub fn calculate_profit_at_price(
         //          0 if xc >= b
         //     }
         Position::Long => {
-            let long_margin = calculate_margin(opening_price, quantity, long_leverage)
-                .to_signed()
-                .context("Unable to compute long margin")?;
+            let long_margin =
+                calculate_margin(trading_pair, opening_price, quantity, long_leverage)
+                    .to_signed()
+                    .context("Unable to compute long margin")?;
 
             let payout = match long_is_liquidated {
                 true => SignedAmount::ZERO,
@@ -1851,12 +2226,14 @@ pub fn calculate_profit_at_price(
             (long_margin, payout)
         }
         Position::Short => {
-            let long_margin = calculate_margin(opening_price, quantity, long_leverage)
-                .to_signed()
-                .context("Unable to compute long margin")?;
-            let short_margin = calculate_margin(opening_price, quantity, short_leverage)
-                .to_signed()
-                .context("Unable to compute long margin")?;
+            let long_margin =
+                calculate_margin(trading_pair, opening_price, quantity, long_leverage)
+                    .to_signed()
+                    .context("Unable to compute long margin")?;
+            let short_margin =
+                calculate_margin(trading_pair, opening_price, quantity, short_leverage)
+                    .to_signed()
+                    .context("Unable to compute long margin")?;
 
             let payout = match long_is_liquidated {
                 true => long_margin + short_margin,
@@ -1968,6 +2345,10 @@ pub struct Dlc {
     // and create an internal structure that depicts this properly and avoids duplication.
     pub settlement_event_id: BitMexPriceEventId,
     pub refund_timelock: u32,
+
+    /// The public key of the oracle the CETs are encrypted for
+    #[serde(default = "olivia::default_public_key")]
+    pub oracle_pk: XOnlyPublicKey,
 }
 
 #[derive(Clone, Debug)]
@@ -2164,16 +2545,8 @@ impl Dlc {
     }
 
     pub fn signed_refund_tx(&self) -> Result<Transaction> {
-        let sig_hash = spending_tx_sighash(
-            &self.refund.0,
-            &self.commit.2,
-            Amount::from_sat(self.commit.0.output[0].value),
-        )
-        .context("could not obtain sighash")?;
-        let our_sig = SECP256K1.sign_ecdsa(&sig_hash, &self.identity);
-        let our_pubkey = bitcoin::util::key::PublicKey::new(
-            bdk::bitcoin::secp256k1::PublicKey::from_secret_key(SECP256K1, &self.identity),
-        );
+        let our_sig = self.refund_signature()?;
+        let our_pubkey = self.identity_pk();
         let counterparty_sig = self.refund.1;
         let counterparty_pubkey = self.identity_counterparty;
         let signed_refund_tx = maia::finalize_spend_transaction(
@@ -2186,6 +2559,62 @@ impl Dlc {
         Ok(signed_refund_tx)
     }
 
+    /// Our signature on the refund transaction.
+    fn refund_signature(&self) -> Result<Signature> {
+        let sig_hash = spending_tx_sighash(
+            &self.refund.0,
+            &self.commit.2,
+            Amount::from_sat(self.commit.0.output[0].value),
+        )
+        .context("could not obtain sighash")?;
+
+        Ok(SECP256K1.sign_ecdsa(&sig_hash, &self.identity))
+    }
+
+    fn identity_pk(&self) -> PublicKey {
+        PublicKey::new(bdk::bitcoin::secp256k1::PublicKey::from_secret_key(
+            SECP256K1,
+            &self.identity,
+        ))
+    }
+
+    /// Build and sign the punish transaction spending the output of a revoked commit transaction
+    /// published by the counterparty.
+    ///
+    /// The punish transaction sweeps the entire output of the revoked commit transaction to our
+    /// address.
+    pub fn punish_tx(&self, role: Role, revoked_commit_tx: &Transaction) -> Result<Transaction> {
+        let commit_txid = revoked_commit_tx.txid();
+        let revoked_commit = self
+            .revoked_commit
+            .iter()
+            .find(|revoked_commit| revoked_commit.txid == commit_txid)
+            .with_context(|| format!("Unknown revoked commit TXID {commit_txid}"))?;
+
+        let commit_descriptor = revoked_commit
+            .commit_descriptor
+            .as_ref()
+            .with_context(|| format!("Missing descriptor for revoked commit TXID {commit_txid}"))?;
+
+        let address = match role {
+            Role::Maker => &self.maker_address,
+            Role::Taker => &self.taker_address,
+        };
+
+        let punish_tx = maia::punish_transaction(
+            commit_descriptor,
+            address,
+            revoked_commit.encsig_ours,
+            self.identity,
+            revoked_commit.revocation_sk_theirs,
+            revoked_commit.publication_pk_theirs,
+            revoked_commit_tx,
+        )
+        .context("Failed to build punish transaction")?;
+
+        Ok(punish_tx)
+    }
+
     pub fn signed_commit_tx(&self) -> Result<Transaction> {
         let sig_hash = spending_tx_sighash(
             &self.commit.0,
@@ -2213,11 +2642,12 @@ impl Dlc {
 
     pub fn signed_cet(
         &self,
+        oracle_pk: &XOnlyPublicKey,
         attestation: &olivia::Attestation,
     ) -> Result<Result<Transaction, IrrelevantAttestation>> {
         let cets = match self.cets.get(&attestation.id) {
-            Some(cets) => cets,
-            None => {
+            Some(cets) if &self.oracle_pk == oracle_pk => cets,
+            _ => {
                 return Ok(Err(IrrelevantAttestation {
                     id: attestation.id,
                     tx_id: self.lock.0.txid(),
@@ -2312,6 +2742,14 @@ pub struct RevokedCommit {
     /// Used by the maker to determine the fees when a taker triggers a rollover from a previous
     /// commit-txid.
     pub complete_fee: Option<CompleteFee>,
+
+    /// The descriptor of the output of this commit tx
+    ///
+    /// This is needed to spend the output of the revoked commit tx when building the punish
+    /// transaction. It is not available for revoked commits that were stored before we started
+    /// recording it.
+    #[serde(default)]
+    pub commit_descriptor: Option<Descriptor<PublicKey>>,
 }
 
 /// Used when transactions (e.g. collaborative close) are recorded as a part of
@@ -2364,6 +2802,7 @@ impl CollaborativeSettlement {
 #[allow(clippy::too_many_arguments)]
 #[tracing::instrument(err)]
 pub fn calculate_payouts(
+    trading_pair: TradingPair,
     position: Position,
     role: Role,
     price: Price,
@@ -2374,6 +2813,7 @@ pub fn calculate_payouts(
     fee: CompleteFee,
 ) -> Result<Vec<Payout>> {
     let payouts = payout_curve::calculate(
+        trading_pair.contract_type(),
         price,
         quantity,
         long_leverage,
@@ -2419,7 +2859,8 @@ mod tests {
         let leverage = Leverage::new(5).unwrap();
         let expected = Price::new(dec!(38437.5)).unwrap();
 
-        let liquidation_price = calculate_long_liquidation_price(leverage, price);
+        let liquidation_price =
+            calculate_long_liquidation_price(TradingPair::BtcUsd, leverage, price);
 
         assert_eq!(liquidation_price, expected);
     }
@@ -2430,7 +2871,7 @@ mod tests {
         let quantity = Usd::new(dec!(40000));
         let leverage = Leverage::new(1).unwrap();
 
-        let long_margin = calculate_margin(price, quantity, leverage);
+        let long_margin = calculate_margin(TradingPair::BtcUsd, price, quantity, leverage);
 
         assert_eq!(long_margin, Amount::ONE_BTC);
     }
@@ -2441,7 +2882,7 @@ mod tests {
         let quantity = Usd::new(dec!(40000));
         let leverage = Leverage::new(10).unwrap();
 
-        let long_margin = calculate_margin(price, quantity, leverage);
+        let long_margin = calculate_margin(TradingPair::BtcUsd, price, quantity, leverage);
 
         assert_eq!(long_margin, Amount::from_btc(0.1).unwrap());
     }
@@ -2453,7 +2894,7 @@ mod tests {
         let price = Price::new(dec!(40000)).unwrap();
         let quantity = Usd::new(dec!(40000));
 
-        let short_margin = calculate_margin(price, quantity, Leverage::ONE);
+        let short_margin = calculate_margin(TradingPair::BtcUsd, price, quantity, Leverage::ONE);
 
         assert_eq!(short_margin, Amount::ONE_BTC);
     }
@@ -2463,7 +2904,7 @@ mod tests {
         let price = Price::new(dec!(40000)).unwrap();
         let quantity = Usd::new(dec!(20000));
 
-        let short_margin = calculate_margin(price, quantity, Leverage::ONE);
+        let short_margin = calculate_margin(TradingPair::BtcUsd, price, quantity, Leverage::ONE);
 
         assert_eq!(short_margin, Amount::from_btc(0.5).unwrap());
     }
@@ -2473,7 +2914,7 @@ mod tests {
         let price = Price::new(dec!(40000)).unwrap();
         let quantity = Usd::new(dec!(80000));
 
-        let short_margin = calculate_margin(price, quantity, Leverage::ONE);
+        let short_margin = calculate_margin(TradingPair::BtcUsd, price, quantity, Leverage::ONE);
 
         assert_eq!(short_margin, Amount::from_btc(2.0).unwrap());
     }
@@ -2618,6 +3059,7 @@ mod tests {
         // TODO: Assert on payout as well
 
         let (profit, in_percent, _) = calculate_profit_at_price(
+            TradingPair::BtcUsd,
             initial_price,
             closing_price,
             quantity,
@@ -2654,6 +3096,7 @@ mod tests {
             .add_funding_fee(funding_fee);
 
         let (profit, profit_in_percent, _) = calculate_profit_at_price(
+            TradingPair::BtcUsd,
             initial_price,
             closing_price,
             quantity,
@@ -2663,6 +3106,7 @@ mod tests {
         )
         .unwrap();
         let (loss, loss_in_percent, _) = calculate_profit_at_price(
+            TradingPair::BtcUsd,
             initial_price,
             closing_price,
             quantity,
@@ -2688,12 +3132,13 @@ mod tests {
         let leverage = Leverage::TWO;
         let counterpart_leverage = Leverage::ONE;
 
-        let long_margin = calculate_margin(initial_price, quantity, leverage)
-            .to_signed()
-            .unwrap();
-        let short_margin = calculate_margin(initial_price, quantity, Leverage::ONE)
+        let long_margin = calculate_margin(TradingPair::BtcUsd, initial_price, quantity, leverage)
             .to_signed()
             .unwrap();
+        let short_margin =
+            calculate_margin(TradingPair::BtcUsd, initial_price, quantity, Leverage::ONE)
+                .to_signed()
+                .unwrap();
         let pool_amount = SignedAmount::ONE_BTC;
         let closing_prices = [
             Price::new(dec!(0.15)).unwrap(),
@@ -2723,6 +3168,7 @@ mod tests {
 
         for price in closing_prices {
             let (long_profit, _, _) = calculate_profit_at_price(
+                TradingPair::BtcUsd,
                 initial_price,
                 price,
                 quantity,
@@ -2732,6 +3178,7 @@ mod tests {
             )
             .unwrap();
             let (short_profit, _, _) = calculate_profit_at_price(
+                TradingPair::BtcUsd,
                 initial_price,
                 price,
                 quantity,
@@ -2946,10 +3393,82 @@ mod tests {
         assert!(matches!(cannot_roll_over, NoRolloverReason::Closed))
     }
 
+    #[test]
+    fn given_counterparty_punished_then_no_rollover_and_no_collab_settlement() {
+        let cfd = Cfd::dummy_punished(BitMexPriceEventId::with_20_digits(
+            datetime!(2021-11-19 10:00:00).assume_utc(),
+        ));
+
+        let cannot_roll_over = cfd.can_rollover().unwrap_err();
+
+        assert!(matches!(cannot_roll_over, NoRolloverReason::Closed));
+        assert!(!cfd.can_settle_collaboratively());
+    }
+
+    #[test]
+    fn given_cfd_final_then_cannot_bump_fee() {
+        let event_id =
+            BitMexPriceEventId::with_20_digits(datetime!(2021-11-19 10:00:00).assume_utc());
+        let parent_txid = dummy_transaction().txid();
+        let cpfp_tx = Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![TxIn {
+                previous_output: bitcoin::OutPoint::new(parent_txid, 0),
+                ..Default::default()
+            }],
+            output: vec![],
+        };
+
+        let open = Cfd::dummy_taker_long().dummy_open(event_id);
+        let final_cet = Cfd::dummy_final(event_id);
+
+        open.bump_fee(parent_txid, cpfp_tx.clone(), TxFeeRate::default())
+            .unwrap();
+        open.bump_fee(cpfp_tx.txid(), dummy_transaction(), TxFeeRate::default())
+            .unwrap_err();
+        final_cet
+            .bump_fee(parent_txid, cpfp_tx, TxFeeRate::default())
+            .unwrap_err();
+    }
+
+    #[test]
+    fn given_other_peer_then_cannot_recover_counterparty() {
+        let event_id =
+            BitMexPriceEventId::with_20_digits(datetime!(2021-11-19 10:00:00).assume_utc());
+        let open = Cfd::dummy_taker_long().dummy_open(event_id);
+
+        open.recover_counterparty(&PeerId::random()).unwrap_err();
+    }
+
+    #[test]
+    fn given_cfd_not_open_then_cannot_recover_counterparty() {
+        let cfd = Cfd::dummy_not_open_yet();
+        let peer_id = cfd.counterparty_peer_id.unwrap();
+
+        cfd.recover_counterparty(&peer_id).unwrap_err();
+    }
+
+    #[test]
+    fn given_open_cfd_then_snapshot_reports_confirmed_lock() {
+        let event_id =
+            BitMexPriceEventId::with_20_digits(datetime!(2021-11-19 10:00:00).assume_utc());
+        let open = Cfd::dummy_taker_long().dummy_open(event_id);
+
+        let snapshot = open.snapshot();
+
+        assert_eq!(snapshot.version, 3);
+        assert!(snapshot.lock_txid.is_some());
+        assert!(snapshot.lock_finality);
+        assert!(!snapshot.during_contract_setup);
+        assert_eq!(snapshot.commit_txid, None);
+    }
+
     #[test]
     fn can_calculate_funding_fee_with_negative_funding_rate() {
         let funding_rate = FundingRate::new(Decimal::NEGATIVE_ONE).unwrap();
         let funding_fee = FundingFee::calculate(
+            TradingPair::BtcUsd,
             Price::new(dec!(1)).unwrap(),
             Usd::new(dec!(1)),
             Leverage::ONE,
@@ -3134,7 +3653,7 @@ mod tests {
             "Manual commit to blockchain did not error"
         );
         assert!(
-            cfd.decrypt_cet(&olivia::Attestation::dummy())
+            cfd.decrypt_cet(&olivia::PUBLIC_KEY, &olivia::Attestation::dummy())
                 .unwrap()
                 .is_none(),
             "The decrypted CET is not expected to be Some"
@@ -3411,7 +3930,8 @@ mod tests {
         let leverage = Leverage::new(2).unwrap();
         let price = Price::new(dec!(60_000)).unwrap();
 
-        let is_liquidation_price = calculate_long_liquidation_price(leverage, price);
+        let is_liquidation_price =
+            calculate_long_liquidation_price(TradingPair::BtcUsd, leverage, price);
 
         let should_liquidation_price = Price::new(dec!(40_000)).unwrap();
         assert_eq!(is_liquidation_price, should_liquidation_price);
@@ -3422,18 +3942,62 @@ mod tests {
         let leverage = Leverage::new(2).unwrap();
         let price = Price::new(dec!(60_000)).unwrap();
 
-        let is_liquidation_price = calculate_short_liquidation_price(leverage, price);
+        let is_liquidation_price =
+            calculate_short_liquidation_price(TradingPair::BtcUsd, leverage, price);
 
         let should_liquidation_price = Price::new(dec!(120_000)).unwrap();
         assert_eq!(is_liquidation_price, should_liquidation_price);
     }
 
+    #[test]
+    fn given_order_with_maker_leverage_then_cfd_uses_it_for_the_maker() {
+        let mut order = Order::dummy_short();
+        order.leverage_maker = Leverage::new(3).unwrap();
+
+        let maker_cfd =
+            Cfd::maker_short_from_order(order.clone(), Usd::new(dec!(100)), Leverage::TWO);
+        let taker_cfd = Cfd::taker_long_from_order(order, Usd::new(dec!(100)), Leverage::TWO);
+
+        for cfd in [maker_cfd, taker_cfd] {
+            assert_eq!(cfd.long_leverage, Leverage::TWO);
+            assert_eq!(cfd.short_leverage, Leverage::new(3).unwrap());
+            assert_eq!(cfd.maker_leverage(), Leverage::new(3).unwrap());
+            assert_eq!(cfd.taker_leverage(), Leverage::TWO);
+        }
+    }
+
+    #[test]
+    fn given_linear_contract_then_margin_scales_with_price() {
+        let price = Price::new(dec!(1500)).unwrap();
+        let quantity = Usd::new(dec!(1000));
+        let leverage = Leverage::TWO;
+
+        let margin = calculate_margin(TradingPair::EthUsd, price, quantity, leverage);
+
+        assert_eq!(margin, Amount::from_btc(0.75).unwrap());
+    }
+
+    #[test]
+    fn given_linear_contract_then_liquidation_prices_are_symmetric() {
+        let leverage = Leverage::TWO;
+        let price = Price::new(dec!(1500)).unwrap();
+
+        let long_liquidation_price =
+            calculate_long_liquidation_price(TradingPair::EthUsd, leverage, price);
+        let short_liquidation_price =
+            calculate_short_liquidation_price(TradingPair::EthUsd, leverage, price);
+
+        assert_eq!(long_liquidation_price, Price::new(dec!(750)).unwrap());
+        assert_eq!(short_liquidation_price, Price::new(dec!(2250)).unwrap());
+    }
+
     #[test]
     fn test_calculate_infite_liquidation_price() {
         let leverage = Leverage::new(1).unwrap();
         let price = Price::new(dec!(60_000)).unwrap();
 
-        let is_liquidation_price = calculate_short_liquidation_price(leverage, price);
+        let is_liquidation_price =
+            calculate_short_liquidation_price(TradingPair::BtcUsd, leverage, price);
 
         let should_liquidation_price = Price::INFINITE;
         assert_eq!(is_liquidation_price, should_liquidation_price);
@@ -3719,10 +4283,11 @@ mod tests {
 
             let funding_fee_for_whole_interval =
                 FundingFee::calculate(
+                    TradingPair::BtcUsd,
                     price,
                     quantity, leverage , leverage, funding_rate, SETTLEMENT_INTERVAL.whole_hours()).unwrap();
             let funding_fee_for_one_hour =
-                FundingFee::calculate(price, quantity, leverage, leverage, funding_rate, 1).unwrap();
+                FundingFee::calculate(TradingPair::BtcUsd, price, quantity, leverage, leverage, funding_rate, 1).unwrap();
             let fee_account = FeeAccount::new(Position::Long, Role::Taker);
 
             let fee_account_whole_interval = fee_account.add_funding_fee(funding_fee_for_whole_interval);
@@ -3940,6 +4505,19 @@ mod tests {
             }]
         }
 
+        fn dummy_punish(event_id: BitMexPriceEventId) -> Vec<Self> {
+            let mut open = Self::dummy_open(event_id);
+            open.push(CfdEvent {
+                timestamp: Timestamp::now(),
+                id: Default::default(),
+                event: EventKind::PunishPublished {
+                    punish_tx: dummy_transaction(),
+                },
+            });
+
+            open
+        }
+
         fn dummy_final_cet(event_id: BitMexPriceEventId) -> Vec<Self> {
             let mut open = Self::dummy_open(event_id);
             open.push(CfdEvent {
@@ -4202,6 +4780,21 @@ mod tests {
                 .fold(cfd, Cfd::apply)
         }
 
+        fn dummy_punished(event_id: BitMexPriceEventId) -> Self {
+            let cfd = Cfd::from_order(
+                &Order::dummy_short(),
+                Usd::new(dec!(1000)),
+                dummy_identity(),
+                dummy_peer_id(),
+                Role::Taker,
+                Leverage::TWO,
+            );
+
+            CfdEvent::dummy_punish(event_id)
+                .into_iter()
+                .fold(cfd, Cfd::apply)
+        }
+
         fn with_id(mut self, order_id: OrderId) -> Self {
             self.id = order_id;
             self
@@ -4232,11 +4825,13 @@ mod tests {
                 Usd::new(dec!(1000)),
                 Origin::Ours,
                 dummy_event_id(),
+                *olivia::PUBLIC_KEY,
                 time::Duration::hours(24),
                 TxFeeRate::default(),
                 FundingRate::default(),
                 OpeningFee::default(),
                 vec![Leverage::TWO],
+                Leverage::ONE,
             )
         }
 
@@ -4387,6 +4982,7 @@ mod tests {
                     None => dummy_event_id(),
                 },
                 refund_timelock: 0,
+                oracle_pk: *olivia::PUBLIC_KEY,
             }
         }
     }
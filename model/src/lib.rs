@@ -1,4 +1,5 @@
 use crate::libp2p::PeerId;
+use anyhow::bail;
 use anyhow::Context;
 use anyhow::Result;
 use bdk::bitcoin::Address;
@@ -31,10 +32,15 @@ pub mod hex_transaction;
 pub mod libp2p;
 pub mod olivia;
 pub mod payout_curve;
+mod position_triggers;
+mod recovery;
 mod rollover;
 
 pub use cfd::*;
 pub use contract_setup::SetupParams;
+pub use position_triggers::PositionTriggers;
+pub use position_triggers::Trigger;
+pub use recovery::RecoveredContract;
 pub use rollover::BaseDlcParams;
 pub use rollover::RolloverParams;
 pub use rollover::Version as RolloverVersion;
@@ -448,9 +454,82 @@ impl From<Decimal> for Percent {
     }
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum TradingPair {
     BtcUsd,
+    EthUsd,
+}
+
+impl TradingPair {
+    /// All trading pairs that can be offered.
+    pub const ALL: [TradingPair; 2] = [TradingPair::BtcUsd, TradingPair::EthUsd];
+
+    /// The symbol used to label this trading pair, e.g. in metrics.
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            TradingPair::BtcUsd => "BTCUSD",
+            TradingPair::EthUsd => "ETHUSD",
+        }
+    }
+
+    /// The BitMEX index the oracle attests to for this trading pair.
+    ///
+    /// This is part of the path of the oracle event id.
+    pub fn oracle_index(&self) -> &'static str {
+        match self {
+            TradingPair::BtcUsd => "BXBT",
+            TradingPair::EthUsd => "BETH",
+        }
+    }
+
+    pub fn from_oracle_index(index: &str) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|pair| pair.oracle_index() == index)
+            .with_context(|| format!("Unknown oracle index {index}"))
+    }
+
+    /// The BitMEX instrument we quote prices from for this trading pair.
+    pub fn bitmex_instrument(&self) -> &'static str {
+        match self {
+            TradingPair::BtcUsd => "XBTUSD",
+            TradingPair::EthUsd => "ETHUSD",
+        }
+    }
+
+    /// How the payout of a CFD on this trading pair depends on the price.
+    ///
+    /// BTC is used as collateral for all trading pairs, which makes BTCUSD an inverse contract.
+    /// Other trading pairs are quoted as linear (quanto) contracts.
+    pub fn contract_type(&self) -> payout_curve::ContractType {
+        match self {
+            TradingPair::BtcUsd => payout_curve::ContractType::Inverse,
+            TradingPair::EthUsd => payout_curve::ContractType::Linear,
+        }
+    }
+}
+
+impl Default for TradingPair {
+    fn default() -> Self {
+        TradingPair::BtcUsd
+    }
+}
+
+impl fmt::Display for TradingPair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.symbol().fmt(f)
+    }
+}
+
+impl str::FromStr for TradingPair {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|pair| pair.symbol() == s)
+            .with_context(|| format!("Unknown trading pair {s}"))
+    }
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
@@ -459,6 +538,27 @@ pub enum Position {
     Short,
 }
 
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Position::Long => "Long".fmt(f),
+            Position::Short => "Short".fmt(f),
+        }
+    }
+}
+
+impl str::FromStr for Position {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Long" => Ok(Position::Long),
+            "Short" => Ok(Position::Short),
+            _ => bail!("Unknown position {s}"),
+        }
+    }
+}
+
 impl Position {
     /// Determines the counter position to the current position.
     pub fn counter_position(&self) -> Position {
@@ -677,6 +777,7 @@ pub struct FundingFee {
 
 impl FundingFee {
     pub fn calculate(
+        trading_pair: TradingPair,
         price: Price,
         quantity: Usd,
         long_leverage: Leverage,
@@ -692,9 +793,9 @@ impl FundingFee {
         }
 
         let margin = if funding_rate.short_pays_long() {
-            calculate_margin(price, quantity, long_leverage)
+            calculate_margin(trading_pair, price, quantity, long_leverage)
         } else {
-            calculate_margin(price, quantity, short_leverage)
+            calculate_margin(trading_pair, price, quantity, short_leverage)
         };
 
         let fraction_of_funding_period =
@@ -897,6 +998,11 @@ impl TxFeeRate {
         Self(fee_rate)
     }
 
+    /// Fee rate of `sat_per_vbyte`, but at least 1 satoshi per vbyte.
+    pub fn from_sat_per_vbyte(sat_per_vbyte: u32) -> Self {
+        Self(NonZeroU32::new(sat_per_vbyte.max(1)).expect("at least 1"))
+    }
+
     pub fn to_u32(self) -> u32 {
         self.0.into()
     }
@@ -1054,9 +1160,11 @@ impl From<&Payout> for i64 {
 #[derive(Debug, Clone, Copy)]
 pub struct FailedCfd {
     pub id: OrderId,
+    pub trading_pair: TradingPair,
     pub position: Position,
     pub initial_price: Price,
     pub taker_leverage: Leverage,
+    pub maker_leverage: Leverage,
     pub n_contracts: Contracts,
     pub counterparty_network_identity: Identity,
     pub counterparty_peer_id: PeerId,
@@ -1099,15 +1207,25 @@ pub enum Settlement {
         vout: Vout,
         payout: Payout,
     },
+    /// The counterparty published a revoked commit transaction and we
+    /// swept its output with a punish transaction.
+    Punish {
+        commit_txid: Txid,
+        txid: Txid,
+        vout: Vout,
+        payout: Payout,
+    },
 }
 
 /// Data loaded from the database about a closed CFD.
 #[derive(Debug, Clone, Copy)]
 pub struct ClosedCfd {
     pub id: OrderId,
+    pub trading_pair: TradingPair,
     pub position: Position,
     pub initial_price: Price,
     pub taker_leverage: Leverage,
+    pub maker_leverage: Leverage,
     pub n_contracts: Contracts,
     pub counterparty_network_identity: Identity,
     pub counterparty_peer_id: PeerId,
@@ -1491,6 +1609,7 @@ mod tests {
 
         let funding_rate_pos = FundingRate::new(dec!(0.01)).unwrap();
         let long_pays_short_fee = FundingFee::calculate(
+            TradingPair::BtcUsd,
             dummy_price(),
             dummy_n_contracts(),
             long_leverage,
@@ -1502,6 +1621,7 @@ mod tests {
 
         let funding_rate_neg = FundingRate::new(dec!(-0.01)).unwrap();
         let short_pays_long_fee = FundingFee::calculate(
+            TradingPair::BtcUsd,
             dummy_price(),
             dummy_n_contracts(),
             long_leverage,
@@ -1522,6 +1642,7 @@ mod tests {
 
         let dummy_leverage = Leverage::new(1).unwrap();
         let fee = FundingFee::calculate(
+            TradingPair::BtcUsd,
             dummy_price(),
             dummy_n_contracts(),
             dummy_leverage,
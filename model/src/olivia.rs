@@ -1,7 +1,10 @@
+use crate::TradingPair;
 use anyhow::Context;
+use bdk::bitcoin::secp256k1::PublicKey;
 use bdk::bitcoin::XOnlyPublicKey;
 use conquer_once::Lazy;
 use maia_core::secp256k1_zkp::SecretKey;
+use maia_core::secp256k1_zkp::SECP256K1;
 use serde::Deserialize;
 use serde_with::DeserializeFromStr;
 use serde_with::SerializeDisplay;
@@ -25,13 +28,70 @@ pub static PUBLIC_KEY: Lazy<XOnlyPublicKey> = Lazy::new(|| {
         .expect("static key to be valid")
 });
 
+pub static URL: Lazy<Url> =
+    Lazy::new(|| "https://h00.ooo".parse().expect("valid URL from constant"));
+
+/// The public key of the oracle we used before oracles became configurable.
+///
+/// Used as default when deserializing data that was created before we recorded the oracle.
+pub fn default_public_key() -> XOnlyPublicKey {
+    *PUBLIC_KEY
+}
+
+/// An oracle attesting to BitMEX index prices using the olivia protocol.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Oracle {
+    /// The base URL under which the oracle serves its events.
+    pub url: Url,
+    pub public_key: XOnlyPublicKey,
+}
+
+impl Oracle {
+    /// The olivia instance run at <https://h00.ooo>.
+    pub fn olivia() -> Self {
+        Self {
+            url: URL.clone(),
+            public_key: *PUBLIC_KEY,
+        }
+    }
+
+    pub fn event_url(&self, event_id: BitMexPriceEventId) -> Url {
+        event_id.to_url(&self.url)
+    }
+}
+
+/// Parses an oracle from `<public-key>@<url>`.
+impl FromStr for Oracle {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (public_key, url) = s
+            .split_once('@')
+            .context("Expected oracle in the format <public-key>@<url>")?;
+        let url = url
+            .parse::<Url>()
+            .with_context(|| format!("Invalid oracle URL {url}"))?;
+        anyhow::ensure!(
+            !url.cannot_be_a_base(),
+            "Oracle URL {url} cannot be the base of event URLs"
+        );
+
+        Ok(Self {
+            url,
+            public_key: public_key
+                .parse()
+                .with_context(|| format!("Invalid oracle public key {public_key}"))?,
+        })
+    }
+}
+
 #[derive(Debug, Clone, serde::Deserialize, PartialEq)]
 #[serde(try_from = "olivia_api::Response")]
 pub struct Announcement {
     /// Identifier for an oracle event.
     ///
     /// Doubles up as the path of the URL for this event i.e.
-    /// <https://h00.ooo/>{id}.
+    /// <https://h00.ooo/>{id} for olivia.
     pub id: BitMexPriceEventId,
     pub expected_outcome_time: OffsetDateTime,
     pub nonce_pks: Vec<XOnlyPublicKey>,
@@ -45,6 +105,85 @@ pub struct Attestation {
     pub scalars: Vec<SecretKey>,
 }
 
+impl Attestation {
+    /// Verify the attestation against the oracle's public key and the announced nonces.
+    ///
+    /// Olivia attests to every binary digit `d` of the price, most significant digit first, with
+    /// the scalar `s = x + (d + 1) * r`, where `x` is the oracle's secret key and `r` the secret
+    /// nonce announced for that digit. Hence, we check that `s * G = X + (d + 1) * R`.
+    pub fn verify(
+        &self,
+        oracle_pk: &XOnlyPublicKey,
+        nonce_pks: &[XOnlyPublicKey],
+    ) -> Result<(), InvalidAttestation> {
+        let n_digits = nonce_pks.len();
+
+        if self.scalars.len() != n_digits {
+            return Err(InvalidAttestation::WrongNumberOfScalars {
+                expected: n_digits,
+                actual: self.scalars.len(),
+            });
+        }
+
+        if n_digits < 64 && self.price >> n_digits != 0 {
+            return Err(InvalidAttestation::PriceOutOfRange {
+                price: self.price,
+                n_digits,
+            });
+        }
+
+        for (index, (scalar, nonce_pk)) in self.scalars.iter().zip(nonce_pks).enumerate() {
+            let digit = (self.price >> (n_digits - 1 - index)) & 1;
+
+            let expected = attestation_point(oracle_pk, nonce_pk, digit as u8);
+            let actual = PublicKey::from_secret_key(SECP256K1, scalar);
+
+            if expected != Some(actual) {
+                return Err(InvalidAttestation::ScalarMismatch { index });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvalidAttestation {
+    #[error("Expected {expected} attested scalars but got {actual}")]
+    WrongNumberOfScalars { expected: usize, actual: usize },
+    #[error("Attested price {price} does not fit into {n_digits} digits")]
+    PriceOutOfRange { price: u64, n_digits: usize },
+    #[error("Attested scalar for digit {index} does not match the announced nonce")]
+    ScalarMismatch { index: usize },
+}
+
+/// The point `X + (d + 1) * R` an attestation to `digit` using `nonce_pk` commits to.
+///
+/// Returns `None` in the negligible case of the point being at infinity.
+fn attestation_point(
+    oracle_pk: &XOnlyPublicKey,
+    nonce_pk: &XOnlyPublicKey,
+    digit: u8,
+) -> Option<PublicKey> {
+    let mut factor = [0u8; 32];
+    factor[31] = digit + 1;
+
+    let mut nonce_point = even_y_point(nonce_pk);
+    nonce_point
+        .mul_assign(SECP256K1, &factor)
+        .expect("factor to be a valid non-zero scalar");
+
+    even_y_point(oracle_pk).combine(&nonce_point).ok()
+}
+
+/// The point with even y-coordinate that corresponds to the x-only public key.
+fn even_y_point(pk: &XOnlyPublicKey) -> PublicKey {
+    let mut serialized = [0x02; 33];
+    serialized[1..].copy_from_slice(&pk.serialize());
+
+    PublicKey::from_slice(&serialized).expect("x-only public key to be a valid point")
+}
+
 #[derive(
     Debug, Clone, Copy, SerializeDisplay, DeserializeFromStr, PartialEq, Eq, Hash, PartialOrd, Ord,
 )]
@@ -52,6 +191,8 @@ pub struct BitMexPriceEventId {
     /// The timestamp this price event refers to.
     timestamp: OffsetDateTime,
     digits: usize,
+    /// The trading pair whose index is attested to in this price event.
+    trading_pair: TradingPair,
 }
 
 impl BitMexPriceEventId {
@@ -65,6 +206,7 @@ impl BitMexPriceEventId {
         Self {
             timestamp: timestamp_without_nanos,
             digits,
+            trading_pair: TradingPair::BtcUsd,
         }
     }
 
@@ -72,6 +214,14 @@ impl BitMexPriceEventId {
         Self::new(timestamp, 20)
     }
 
+    /// Refer to the price event of the index of the given trading pair instead.
+    pub fn with_trading_pair(self, trading_pair: TradingPair) -> Self {
+        Self {
+            trading_pair,
+            ..self
+        }
+    }
+
     /// Checks whether this event has likely already occurred.
     ///
     /// We can't be sure about it because our local clock might be off from the oracle's clock.
@@ -81,12 +231,30 @@ impl BitMexPriceEventId {
         now > self.timestamp + Duration::minutes(1)
     }
 
-    pub fn to_olivia_url(self) -> Url {
-        "https://h00.ooo"
-            .parse::<Url>()
-            .expect("valid URL from constant")
-            .join(&self.to_string())
-            .expect("Event id can be joined")
+    /// The URL of this event on the oracle at `oracle_url`.
+    ///
+    /// The event path is appended to the path of `oracle_url`, so oracles served under a path
+    /// prefix such as `https://host/olivia/` are supported.
+    pub fn to_url(self, oracle_url: &Url) -> Url {
+        let timestamp = self
+            .timestamp
+            .format(&EVENT_TIME_FORMAT)
+            .expect("should always format and we can't return an error here");
+        let price = format!("{timestamp}.price");
+
+        let mut url = oracle_url.clone();
+        url.path_segments_mut()
+            .expect("oracle URL to be a base URL")
+            .pop_if_empty()
+            .extend([
+                "x",
+                "BitMEX",
+                self.trading_pair.oracle_index(),
+                price.as_str(),
+            ]);
+        url.set_query(Some(&format!("n={}", self.digits)));
+
+        url
     }
 
     pub fn timestamp(&self) -> OffsetDateTime {
@@ -96,13 +264,18 @@ impl BitMexPriceEventId {
     pub fn digits(&self) -> usize {
         self.digits
     }
+
+    pub fn trading_pair(&self) -> TradingPair {
+        self.trading_pair
+    }
 }
 
 impl fmt::Display for BitMexPriceEventId {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "/x/BitMEX/BXBT/{}.price?n={}",
+            "/x/BitMEX/{}/{}.price?n={}",
+            self.trading_pair.oracle_index(),
             self.timestamp
                 .format(&EVENT_TIME_FORMAT)
                 .expect("should always format and we can't return an error here"),
@@ -115,15 +288,20 @@ impl FromStr for BitMexPriceEventId {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let remaining = s.trim_start_matches("/x/BitMEX/BXBT/");
-        let (timestamp, rest) = remaining.split_at(19);
-        let digits = rest.trim_start_matches(".price?n=");
+        let remaining = s.trim_start_matches("/x/BitMEX/");
+        let (index, remaining) = remaining
+            .split_once('/')
+            .with_context(|| format!("Failed to parse index from {s}"))?;
+        let (timestamp, digits) = remaining
+            .split_once(".price?n=")
+            .with_context(|| format!("Failed to parse timestamp and digits from {s}"))?;
 
         Ok(Self {
             timestamp: PrimitiveDateTime::parse(timestamp, &EVENT_TIME_FORMAT)
                 .with_context(|| format!("Failed to parse {timestamp} as timestamp"))?
                 .assume_utc(),
             digits: digits.parse()?,
+            trading_pair: TradingPair::from_oracle_index(index)?,
         })
     }
 }
@@ -137,10 +315,13 @@ impl From<Announcement> for maia_core::Announcement {
     }
 }
 
-pub fn next_announcement_after(timestamp: OffsetDateTime) -> BitMexPriceEventId {
+pub fn next_announcement_after(
+    trading_pair: TradingPair,
+    timestamp: OffsetDateTime,
+) -> BitMexPriceEventId {
     let adjusted = ceil_to_next_hour(timestamp);
 
-    BitMexPriceEventId::with_20_digits(adjusted)
+    BitMexPriceEventId::with_20_digits(adjusted).with_trading_pair(trading_pair)
 }
 
 fn ceil_to_next_hour(original: OffsetDateTime) -> OffsetDateTime {
@@ -154,6 +335,7 @@ fn ceil_to_next_hour(original: OffsetDateTime) -> OffsetDateTime {
 mod olivia_api {
     use super::*;
     use anyhow::Context;
+    use serde::de::Error as _;
     use std::convert::TryFrom;
     use time::OffsetDateTime;
 
@@ -167,11 +349,22 @@ mod olivia_api {
         type Error = serde_json::Error;
 
         fn try_from(response: Response) -> Result<Self, Self::Error> {
-            // TODO: Verify signature here
+            // The announcement signature does not verify under `PUBLIC_KEY`, the key olivia
+            // attests with, so we can't check it here. Tampered nonces are detected instead when
+            // verifying the attestation against the nonces our CETs were built with.
 
             let data =
                 serde_json::from_str::<AnnouncementData>(&response.announcement.oracle_event.data)?;
 
+            if data.schemes.olivia_v1.nonces.len() != data.id.digits() {
+                return Err(serde_json::Error::custom(format!(
+                    "Expected {} nonces for {} but got {}",
+                    data.id.digits(),
+                    data.id,
+                    data.schemes.olivia_v1.nonces.len()
+                )));
+            }
+
             Ok(Self {
                 id: data.id,
                 expected_outcome_time: data.expected_outcome_time,
@@ -184,7 +377,8 @@ mod olivia_api {
         type Error = anyhow::Error;
 
         fn try_from(response: Response) -> Result<Self, Self::Error> {
-            // TODO: Verify signature here
+            // The scalars are only checked against the nonces once we know which announcement
+            // they have to match, see `Attestation::verify`.
 
             let data =
                 serde_json::from_str::<AnnouncementData>(&response.announcement.oracle_event.data)?;
@@ -263,6 +457,7 @@ mod olivia_api {
 
         use crate::olivia;
         use crate::olivia::BitMexPriceEventId;
+        use crate::olivia::InvalidAttestation;
         use time::macros::datetime;
 
         #[test]
@@ -342,7 +537,7 @@ mod olivia_api {
 
         #[test]
         fn deserialize_attestation() {
-            let json = r#"{"announcement":{"oracle_event":{"encoding":"json","data":"{\"id\":\"/x/BitMEX/BXBT/2021-10-04T22:00:00.price?n=20\",\"expected-outcome-time\":\"2021-10-04T22:00:00\",\"descriptor\":{\"type\":\"digit-decomposition\",\"is_signed\":false,\"n_digits\":20,\"unit\":null},\"schemes\":{\"olivia-v1\":{\"nonces\":[\"8d72028eeaf4b85aec0f750f05a4a320cac193f5d8494bfe05cd4b29f3df4239\",\"77240f79a0042adae35ad24284b18b906f17a979fcec3c90d11ed682c6b9261e\",\"e42332407b58f7c6e860b886acfe8d19636fb21a1e20722522206b30a2424d89\",\"ce1158e02dc265751887edae9bdcf8d06ad40489c7643324ccb6a46e4e740f5a\",\"52a5751a43046217bcf009df917c24e400c6da645474a654a5f89499df7154d4\",\"e7b97360a952c2b239d1bfeaade73da4a38e83d20f5deb5b054bcbbc78c91e40\",\"612ce13fd61be10e8de77976c6d479865bc3d2ebdc212946f1e5d93e3f504d2e\",\"e40decd0ea27003b873dde9b6be02f1b344e7e74bc5299144fa0f37b1cf12e90\",\"281a829e05d5f8b96eaf620c7b26115bfb29013d503b6bb40068cdb413a87197\",\"3c87eed0a3852953b0f3ac8a47ff194de66c7229c42e6578e0f6464ba240f033\",\"29028525277cb39adab9ac145d6ce61f2e10306e7b6ce95970a22ea3b201a5d9\",\"20971b4d2069d8b9b5c5678290ab7624821cf32ffe32a20d58428ca90da02523\",\"667a9af33ed45bfb5c4fc7adacea15bbe26df90e0df7dd5b8235e14dfd0da38f\",\"224df2d2706b5c629173b84927e2b206dad7a72e132eb86912d9464dad4b41d1\",\"85296962b9d1f7699c248467ce94ce4aa6e00d26fe01af3a507bcd3a303855d4\",\"96813c9f4d136f0f64be79e73d657fecc43d8b6c463163913b4fa31f96b1ae6b\",\"9d5971aa596923560b12f367fb2f4e192d8906bf6ed3a58b093f50d3cad27493\",\"b7f2c135db80cee02b4436557c78dc1dd2343c1a3688ba736c6c40e9531547b6\",\"bd6236fc18f1dc96f9755cc5c435adaf3952ff810d3ad5b96a03464a61eecfde\",\"20b2922ce326e5e2f4ed683723a879e467edd1068bf5a3c4f331525216227abe\"]},\"ecdsa-v1\":{}}}"},"signature":"743ed9900aba5a1ba3ba9d862628cdc5cca27974c40c4ab64618709021b3fbb13216a3efc733be260025da487ae9b63a8290d555bdc8da6324deff149fc7b110"},"attestation":{"outcome":"48935","schemes":{"olivia-v1":{"scalars":["1327b3bd0f1faf45d6fed6c96d0c158da22a2033a6fed98bed036df0a4eef484","72659c6beebd45e299bc4260a1c1ffd708ed33771459563502f25fc4f537cef6","051eec45417e2493f36b13f4fdf83fb981be42901bf876e4ac594ff2daa4c30e","847d8c7204335b1dbc2078cfb56118b1977162e7b997f2029f490929bbd603c7","5b695846292b6d69d9beedcc7dd2b7e49fd49ec4fcf262d9357f52b049fa8998","368a1f2206fcedcde37381b272fa5a400f55ef720ee2b8fff558e3b0dce729ee","9e1c015c0e827037f18681937764f4973ef22d6fbbd82f6bde3bf5198f6b8999","fe9620c9ad9862b5615f8cf3e20e8d9f422e7410914ce8af2b8bad8937b75738","44297ae831898f8f5c7e57720f233a717e9034a5b41d6c89cce6d9058c4ee086","587fc9b71f1920df825138f00bc625e6610e61b1fec0a64e2800fc05b3a2e96d","010377f6b885ae48d62e7863c8038240aafe0a7fb97d58ac6173186c95335955","5243782226739f59b0ac01a56a63537289ffe81b87b33eca42f89f7848623520","06184cb8e46b5d520cd9b5829feeb73b688d61e5f37b91ff88d3f9b8664a5cdd","fe48f4b568bb501732c4e8f1919940c9bca0ad909f4624658b14664af823ccfe","0841f121e7a54f88a844227cd0ae62171b49d004120c16d1a1d619f0b76f7068","c4ac3c8751a63f7c40062b9b84f2bb953b0e6bd8f2cf3b2bcaf711321e92df8f","86a2b1a31bf80f17c00ab28420c636c1ed604d0b1f0a33adda99a0cf1e510269","fb892eba992b723a06bccad6a2a1bb875d548a275a987266fceed097b9fd88db","41991fb15fdb013ccab3e6674b91546a0e1e56a1e212c8795c76d0b43f4c884d","ab6a4368d2e5e7cea23fd648662769facc1c37f1d1613225e9010af07cd74711"]},"ecdsa-v1":{"signature":"1d9a5e2336883cc6b440ff40e16ee44f8af2ba9313e46f1e4cd417f7dba7686279b0216e4b0b5fcf0c650dbad98fdefcf5ef16b49d63651a87f80caddd472384"}},"time":"2021-10-04T22:00:15"}}"#;
+            let json = ATTESTATION_RESPONSE;
 
             let deserialized = serde_json::from_str::<olivia::Attestation>(json).unwrap();
             let expected = olivia::Attestation {
@@ -414,6 +609,80 @@ mod olivia_api {
 
             assert_eq!(deserialized, expected)
         }
+
+        #[test]
+        fn attestation_verifies_against_announced_nonces() {
+            let announcement =
+                serde_json::from_str::<olivia::Announcement>(ATTESTATION_RESPONSE).unwrap();
+            let attestation =
+                serde_json::from_str::<olivia::Attestation>(ATTESTATION_RESPONSE).unwrap();
+
+            let result = attestation.verify(&olivia::PUBLIC_KEY, &announcement.nonce_pks);
+
+            assert_eq!(result, Ok(()));
+        }
+
+        #[test]
+        fn attestation_to_different_price_is_rejected() {
+            let announcement =
+                serde_json::from_str::<olivia::Announcement>(ATTESTATION_RESPONSE).unwrap();
+            let mut attestation =
+                serde_json::from_str::<olivia::Attestation>(ATTESTATION_RESPONSE).unwrap();
+            attestation.price += 1;
+
+            let result = attestation.verify(&olivia::PUBLIC_KEY, &announcement.nonce_pks);
+
+            assert_eq!(
+                result,
+                Err(InvalidAttestation::ScalarMismatch { index: 16 })
+            );
+        }
+
+        #[test]
+        fn attestation_with_swapped_scalars_is_rejected() {
+            let announcement =
+                serde_json::from_str::<olivia::Announcement>(ATTESTATION_RESPONSE).unwrap();
+            let mut attestation =
+                serde_json::from_str::<olivia::Attestation>(ATTESTATION_RESPONSE).unwrap();
+            attestation.scalars.swap(0, 1);
+
+            let result = attestation.verify(&olivia::PUBLIC_KEY, &announcement.nonce_pks);
+
+            assert_eq!(result, Err(InvalidAttestation::ScalarMismatch { index: 0 }));
+        }
+
+        #[test]
+        fn attestation_with_missing_scalars_is_rejected() {
+            let announcement =
+                serde_json::from_str::<olivia::Announcement>(ATTESTATION_RESPONSE).unwrap();
+            let mut attestation =
+                serde_json::from_str::<olivia::Attestation>(ATTESTATION_RESPONSE).unwrap();
+            attestation.scalars.pop();
+
+            let result = attestation.verify(&olivia::PUBLIC_KEY, &announcement.nonce_pks);
+
+            assert_eq!(
+                result,
+                Err(InvalidAttestation::WrongNumberOfScalars {
+                    expected: 20,
+                    actual: 19
+                })
+            );
+        }
+
+        #[test]
+        fn attestation_under_different_oracle_key_is_rejected() {
+            let announcement =
+                serde_json::from_str::<olivia::Announcement>(ATTESTATION_RESPONSE).unwrap();
+            let attestation =
+                serde_json::from_str::<olivia::Attestation>(ATTESTATION_RESPONSE).unwrap();
+
+            let result = attestation.verify(&announcement.nonce_pks[0], &announcement.nonce_pks);
+
+            assert!(result.is_err());
+        }
+
+        const ATTESTATION_RESPONSE: &str = r#"{"announcement":{"oracle_event":{"encoding":"json","data":"{\"id\":\"/x/BitMEX/BXBT/2021-10-04T22:00:00.price?n=20\",\"expected-outcome-time\":\"2021-10-04T22:00:00\",\"descriptor\":{\"type\":\"digit-decomposition\",\"is_signed\":false,\"n_digits\":20,\"unit\":null},\"schemes\":{\"olivia-v1\":{\"nonces\":[\"8d72028eeaf4b85aec0f750f05a4a320cac193f5d8494bfe05cd4b29f3df4239\",\"77240f79a0042adae35ad24284b18b906f17a979fcec3c90d11ed682c6b9261e\",\"e42332407b58f7c6e860b886acfe8d19636fb21a1e20722522206b30a2424d89\",\"ce1158e02dc265751887edae9bdcf8d06ad40489c7643324ccb6a46e4e740f5a\",\"52a5751a43046217bcf009df917c24e400c6da645474a654a5f89499df7154d4\",\"e7b97360a952c2b239d1bfeaade73da4a38e83d20f5deb5b054bcbbc78c91e40\",\"612ce13fd61be10e8de77976c6d479865bc3d2ebdc212946f1e5d93e3f504d2e\",\"e40decd0ea27003b873dde9b6be02f1b344e7e74bc5299144fa0f37b1cf12e90\",\"281a829e05d5f8b96eaf620c7b26115bfb29013d503b6bb40068cdb413a87197\",\"3c87eed0a3852953b0f3ac8a47ff194de66c7229c42e6578e0f6464ba240f033\",\"29028525277cb39adab9ac145d6ce61f2e10306e7b6ce95970a22ea3b201a5d9\",\"20971b4d2069d8b9b5c5678290ab7624821cf32ffe32a20d58428ca90da02523\",\"667a9af33ed45bfb5c4fc7adacea15bbe26df90e0df7dd5b8235e14dfd0da38f\",\"224df2d2706b5c629173b84927e2b206dad7a72e132eb86912d9464dad4b41d1\",\"85296962b9d1f7699c248467ce94ce4aa6e00d26fe01af3a507bcd3a303855d4\",\"96813c9f4d136f0f64be79e73d657fecc43d8b6c463163913b4fa31f96b1ae6b\",\"9d5971aa596923560b12f367fb2f4e192d8906bf6ed3a58b093f50d3cad27493\",\"b7f2c135db80cee02b4436557c78dc1dd2343c1a3688ba736c6c40e9531547b6\",\"bd6236fc18f1dc96f9755cc5c435adaf3952ff810d3ad5b96a03464a61eecfde\",\"20b2922ce326e5e2f4ed683723a879e467edd1068bf5a3c4f331525216227abe\"]},\"ecdsa-v1\":{}}}"},"signature":"743ed9900aba5a1ba3ba9d862628cdc5cca27974c40c4ab64618709021b3fbb13216a3efc733be260025da487ae9b63a8290d555bdc8da6324deff149fc7b110"},"attestation":{"outcome":"48935","schemes":{"olivia-v1":{"scalars":["1327b3bd0f1faf45d6fed6c96d0c158da22a2033a6fed98bed036df0a4eef484","72659c6beebd45e299bc4260a1c1ffd708ed33771459563502f25fc4f537cef6","051eec45417e2493f36b13f4fdf83fb981be42901bf876e4ac594ff2daa4c30e","847d8c7204335b1dbc2078cfb56118b1977162e7b997f2029f490929bbd603c7","5b695846292b6d69d9beedcc7dd2b7e49fd49ec4fcf262d9357f52b049fa8998","368a1f2206fcedcde37381b272fa5a400f55ef720ee2b8fff558e3b0dce729ee","9e1c015c0e827037f18681937764f4973ef22d6fbbd82f6bde3bf5198f6b8999","fe9620c9ad9862b5615f8cf3e20e8d9f422e7410914ce8af2b8bad8937b75738","44297ae831898f8f5c7e57720f233a717e9034a5b41d6c89cce6d9058c4ee086","587fc9b71f1920df825138f00bc625e6610e61b1fec0a64e2800fc05b3a2e96d","010377f6b885ae48d62e7863c8038240aafe0a7fb97d58ac6173186c95335955","5243782226739f59b0ac01a56a63537289ffe81b87b33eca42f89f7848623520","06184cb8e46b5d520cd9b5829feeb73b688d61e5f37b91ff88d3f9b8664a5cdd","fe48f4b568bb501732c4e8f1919940c9bca0ad909f4624658b14664af823ccfe","0841f121e7a54f88a844227cd0ae62171b49d004120c16d1a1d619f0b76f7068","c4ac3c8751a63f7c40062b9b84f2bb953b0e6bd8f2cf3b2bcaf711321e92df8f","86a2b1a31bf80f17c00ab28420c636c1ed604d0b1f0a33adda99a0cf1e510269","fb892eba992b723a06bccad6a2a1bb875d548a275a987266fceed097b9fd88db","41991fb15fdb013ccab3e6674b91546a0e1e56a1e212c8795c76d0b43f4c884d","ab6a4368d2e5e7cea23fd648662769facc1c37f1d1613225e9010af07cd74711"]},"ecdsa-v1":{"signature":"1d9a5e2336883cc6b440ff40e16ee44f8af2ba9313e46f1e4cd417f7dba7686279b0216e4b0b5fcf0c650dbad98fdefcf5ef16b49d63651a87f80caddd472384"}},"time":"2021-10-04T22:00:15"}}"#;
     }
 }
 
@@ -424,8 +693,9 @@ mod tests {
 
     #[test]
     fn to_olivia_url() {
-        let url = BitMexPriceEventId::with_20_digits(datetime!(2021-09-23 10:00:00).assume_utc())
-            .to_olivia_url();
+        let url = Oracle::olivia().event_url(BitMexPriceEventId::with_20_digits(
+            datetime!(2021-09-23 10:00:00).assume_utc(),
+        ));
 
         assert_eq!(
             url,
@@ -435,6 +705,55 @@ mod tests {
         );
     }
 
+    #[test]
+    fn to_url_of_local_oracle() {
+        let url = BitMexPriceEventId::with_20_digits(datetime!(2021-09-23 10:00:00).assume_utc())
+            .to_url(&"http://localhost:3000".parse().unwrap());
+
+        assert_eq!(
+            url,
+            "http://localhost:3000/x/BitMEX/BXBT/2021-09-23T10:00:00.price?n=20"
+                .parse()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn to_url_of_oracle_with_path_prefix() {
+        let event_id =
+            BitMexPriceEventId::with_20_digits(datetime!(2021-09-23 10:00:00).assume_utc());
+        let expected: Url = "https://host/olivia/x/BitMEX/BXBT/2021-09-23T10:00:00.price?n=20"
+            .parse()
+            .unwrap();
+
+        assert_eq!(
+            event_id.to_url(&"https://host/olivia/".parse().unwrap()),
+            expected
+        );
+        assert_eq!(
+            event_id.to_url(&"https://host/olivia".parse().unwrap()),
+            expected
+        );
+    }
+
+    #[test]
+    fn parse_oracle() {
+        let oracle =
+            "ddd4636845a90185991826be5a494cde9f4a6947b1727217afedc6292fa4caf7@https://h00.ooo"
+                .parse::<Oracle>()
+                .unwrap();
+
+        assert_eq!(oracle, Oracle::olivia());
+    }
+
+    #[test]
+    fn parse_oracle_without_url_fails() {
+        let result =
+            "ddd4636845a90185991826be5a494cde9f4a6947b1727217afedc6292fa4caf7".parse::<Oracle>();
+
+        assert!(result.is_err());
+    }
+
     #[test]
     fn parse_event_id() {
         let parsed = "/x/BitMEX/BXBT/2021-09-23T10:00:00.price?n=20"
@@ -446,6 +765,48 @@ mod tests {
         assert_eq!(parsed, expected);
     }
 
+    #[test]
+    fn parse_event_id_of_other_index() {
+        let parsed = "/x/BitMEX/BETH/2021-09-23T10:00:00.price?n=20"
+            .parse::<BitMexPriceEventId>()
+            .unwrap();
+        let expected =
+            BitMexPriceEventId::with_20_digits(datetime!(2021-09-23 10:00:00).assume_utc())
+                .with_trading_pair(TradingPair::EthUsd);
+
+        assert_eq!(parsed, expected);
+    }
+
+    #[test]
+    fn parse_event_id_of_unknown_index_fails() {
+        let result = "/x/BitMEX/FOO/2021-09-23T10:00:00.price?n=20".parse::<BitMexPriceEventId>();
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn parse_truncated_event_id_fails() {
+        for event_id in [
+            "/x/BitMEX/BXBT/2021-09-23",
+            "/x/BitMEX/BXBT/2021-09-23T10:00:ää",
+        ] {
+            assert!(event_id.parse::<BitMexPriceEventId>().is_err());
+        }
+    }
+
+    #[test]
+    fn next_event_id_for_trading_pair() {
+        let event_id = next_announcement_after(
+            TradingPair::EthUsd,
+            datetime!(2021-09-23 10:40:00).assume_utc(),
+        );
+
+        assert_eq!(
+            event_id.to_string(),
+            "/x/BitMEX/BETH/2021-09-23T11:00:00.price?n=20"
+        );
+    }
+
     #[test]
     fn new_event_has_no_nanos() {
         let now = BitMexPriceEventId::with_20_digits(OffsetDateTime::now_utc());
@@ -463,7 +824,10 @@ mod tests {
 
     #[test]
     fn next_event_id_after_timestamp() {
-        let event_id = next_announcement_after(datetime!(2021-09-23 10:40:00).assume_utc());
+        let event_id = next_announcement_after(
+            TradingPair::BtcUsd,
+            datetime!(2021-09-23 10:40:00).assume_utc(),
+        );
 
         assert_eq!(
             event_id.to_string(),
@@ -473,7 +837,10 @@ mod tests {
 
     #[test]
     fn next_event_id_is_midnight_next_day() {
-        let event_id = next_announcement_after(datetime!(2021-09-23 23:40:00).assume_utc());
+        let event_id = next_announcement_after(
+            TradingPair::BtcUsd,
+            datetime!(2021-09-23 23:40:00).assume_utc(),
+        );
 
         assert_eq!(
             event_id.to_string(),
@@ -28,17 +28,18 @@ mod utils;
 /// that maker goes short every time, there is no reason to make the math
 /// have this imposition as well.
 ///
-/// As well, the POC has also demanded that the Maker always has unity
-/// leverage, hence why the ability to to specify this amount has been
-/// omitted from the parameters. Internally, it is hard-coded to unity
-/// in the call to PayoutCurve::new(), so this behaviour can be changed in
-/// the future trivially.
+/// The math makes no assumption about which party is the maker either:
+/// both the long and the short side can use any leverage. The maker's
+/// leverage is set per order via `Order::leverage_maker` and defaults to
+/// unity, which used to be hard-coded.
 ///
 /// ### Parameters
 ///
-/// * price: BTC-USD exchange rate used to create CFD contract
-/// * quantity: Interger number of one-dollar USD contracts contained in the
-/// CFD; expressed as a Usd amount
+/// * contract_type: how the payout of the contract relates to the price
+/// * price: exchange rate used to create CFD contract, e.g. BTC-USD
+/// * quantity: Interger number of contracts contained in the CFD; expressed as a Usd amount. An
+/// inverse contract is worth one USD, a linear contract [`LINEAR_CONTRACT_MULTIPLIER`] BTC per USD
+/// of the price
 /// * long_leverage: leverage used by the party with the long position
 /// * short_leverage: leverage used by the party with the short position
 /// * n_payouts: number of segments into which the payout curve is discretised
@@ -48,6 +49,7 @@ mod utils;
 ///
 /// The list of [`Payout`]s for the given price, quantity and leverage.
 pub fn calculate(
+    contract_type: ContractType,
     price: Price,
     quantity: Usd,
     long_leverage: Leverage,
@@ -56,6 +58,7 @@ pub fn calculate(
     fee: CompleteFee,
 ) -> Result<Vec<Payout>> {
     let payouts = calculate_payout_parameters(
+        contract_type,
         price,
         quantity,
         long_leverage,
@@ -76,13 +79,104 @@ pub struct Payout {
     pub range: RangeInclusive<u64>,
 }
 
-const CONTRACT_VALUE: f64 = 1.;
+/// The type of contract a payout curve is generated for.
+///
+/// All contracts are collateralised in BTC and quoted in USD. They differ in how the payout in BTC
+/// depends on the price at settlement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContractType {
+    /// Each contract is worth a fixed amount of USD, so the payout in BTC is proportional to the
+    /// inverse of the price, e.g. BitMEX' XBTUSD perpetual.
+    Inverse,
+    /// Each contract is worth [`LINEAR_CONTRACT_MULTIPLIER`] BTC per USD of the price, so the
+    /// payout in BTC moves linearly with the price, e.g. BitMEX' ETHUSD quanto perpetual.
+    Linear,
+}
+
+/// The BTC value of a linear contract per USD of the price.
+///
+/// This is the quanto multiplier of BitMEX' ETHUSD perpetual.
+pub const LINEAR_CONTRACT_MULTIPLIER: Decimal = rust_decimal_macros::dec!(0.000001);
+
+impl ContractType {
+    /// The value of a single contract as used by the payout functions.
+    ///
+    /// These express the payout relative to the initial price, hence the BTC value of a linear
+    /// contract has to be scaled by the square of the initial price.
+    fn contract_value(&self, initial_rate: f64) -> Result<f64> {
+        match self {
+            ContractType::Inverse => Ok(1.),
+            ContractType::Linear => {
+                let multiplier = LINEAR_CONTRACT_MULTIPLIER
+                    .to_f64()
+                    .context("Cannot convert contract multiplier to f64")?;
+
+                Ok(multiplier * initial_rate.powi(2))
+            }
+        }
+    }
+
+    /// The prices at which the long and short party lose their entire margin respectively.
+    ///
+    /// The last element states whether there is an upper cutoff at all, which is not the case for
+    /// an inverse contract where the short party uses unity leverage.
+    fn cutoffs(
+        &self,
+        initial_rate: f64,
+        leverage_long: usize,
+        leverage_short: usize,
+    ) -> (f64, f64, bool) {
+        let ll_64 = leverage_long as f64;
+        let ls_64 = leverage_short as f64;
+
+        match self {
+            ContractType::Inverse => {
+                let a = initial_rate * ll_64 / (ll_64 + 1_f64);
+                if leverage_short == 1 {
+                    let b = 2. * initial_rate;
+                    return (a, b, false);
+                }
+                let b = initial_rate * ls_64 / (ls_64 - 1_f64);
+
+                (a, b, true)
+            }
+            ContractType::Linear => {
+                let a = initial_rate * (1_f64 - 1_f64 / ll_64);
+                let b = initial_rate * (1_f64 + 1_f64 / ls_64);
+
+                (a, b, true)
+            }
+        }
+    }
+
+    /// The payout of the long party in BTC at the given price, before applying the cutoffs.
+    fn long_payout(
+        &self,
+        price: f64,
+        initial_rate: f64,
+        n_contracts: usize,
+        contract_value: f64,
+        leverage_long: usize,
+    ) -> f64 {
+        let n_64 = n_contracts as f64;
+        let ll_64 = leverage_long as f64;
+        let margin = 1_f64 / (initial_rate * ll_64);
+
+        let pnl = match self {
+            ContractType::Inverse => 1_f64 / initial_rate - 1_f64 / price,
+            ContractType::Linear => (price - initial_rate) / initial_rate.powi(2),
+        };
+
+        (n_64 * contract_value) * (margin + pnl)
+    }
+}
 
 /// Internal calculate function for the payout curve.
 ///
 /// To ease testing, we write our tests against this function because it has a more human-friendly
 /// output. The design goal here is that the the above `calculate` function is as thin as possible.
 fn calculate_payout_parameters(
+    contract_type: ContractType,
     price: Price,
     quantity: Usd,
     long_leverage: Leverage,
@@ -98,11 +192,12 @@ fn calculate_payout_parameters(
         .context("Cannot convert quantity to u64")? as usize;
 
     let payout_curve = PayoutCurve::new(
+        contract_type,
         initial_rate,
         long_leverage.get() as usize,
         short_leverage.get() as usize,
         quantity,
-        CONTRACT_VALUE,
+        contract_type.contract_value(initial_rate)?,
         None,
     )?;
 
@@ -238,6 +333,7 @@ struct PayoutCurve {
 
 impl PayoutCurve {
     fn new(
+        contract_type: ContractType,
         initial_rate: f64,
         leverage_long: usize,
         leverage_short: usize,
@@ -246,7 +342,7 @@ impl PayoutCurve {
         tolerance: Option<f64>,
     ) -> Result<Self, Error> {
         let tolerance = tolerance.unwrap_or(1e-6);
-        let bounds = cutoffs(initial_rate, leverage_long, leverage_short);
+        let bounds = contract_type.cutoffs(initial_rate, leverage_long, leverage_short);
         let total_value = pool_value(
             initial_rate,
             n_contracts,
@@ -256,8 +352,13 @@ impl PayoutCurve {
         );
         let mut curve = curve_factory::line((0., 0.), (bounds.0, 0.), false)?;
 
-        let payout =
-            create_long_payout_function(initial_rate, n_contracts, contract_value, leverage_long);
+        let payout = create_long_payout_function(
+            contract_type,
+            initial_rate,
+            n_contracts,
+            contract_value,
+            leverage_long,
+        );
         let variable_payout =
             curve_factory::fit(payout, bounds.0, bounds.1, Some(tolerance), None)?;
         curve.append(variable_payout)?;
@@ -434,19 +535,6 @@ impl PayoutCurve {
     }
 }
 
-fn cutoffs(initial_rate: f64, leverage_long: usize, leverage_short: usize) -> (f64, f64, bool) {
-    let ll_64 = leverage_long as f64;
-    let ls_64 = leverage_short as f64;
-    let a = initial_rate * ll_64 / (ll_64 + 1_f64);
-    if leverage_short == 1 {
-        let b = 2. * initial_rate;
-        return (a, b, false);
-    }
-    let b = initial_rate * ls_64 / (ls_64 - 1_f64);
-
-    (a, b, true)
-}
-
 fn pool_value(
     initial_rate: f64,
     n_contracts: usize,
@@ -462,19 +550,22 @@ fn pool_value(
 }
 
 fn create_long_payout_function(
+    contract_type: ContractType,
     initial_rate: f64,
     n_contracts: usize,
     contract_value: f64,
     leverage_long: usize,
 ) -> impl Fn(&Array1<f64>) -> Array2<f64> {
-    let n_64 = n_contracts as f64;
-    let ll_64 = leverage_long as f64;
-
     move |t: &Array1<f64>| {
         let mut vec = Vec::<f64>::with_capacity(2 * t.len());
         for e in t.iter() {
-            let eval = (n_64 * contract_value)
-                * (1_f64 / (initial_rate * ll_64) + (1_f64 / initial_rate - 1_f64 / e));
+            let eval = contract_type.long_payout(
+                *e,
+                initial_rate,
+                n_contracts,
+                contract_value,
+                leverage_long,
+            );
             vec.push(*e);
             vec.push(eval);
         }
@@ -500,6 +591,7 @@ mod tests {
         let contract_value = 100.;
 
         let payout = PayoutCurve::new(
+            ContractType::Inverse,
             initial_rate,
             leverage_long,
             leverage_short,
@@ -523,6 +615,7 @@ mod tests {
         let contract_value = 100.;
 
         let payout = PayoutCurve::new(
+            ContractType::Inverse,
             initial_rate,
             leverage_long,
             leverage_short,
@@ -541,6 +634,7 @@ mod tests {
     #[test]
     fn calculate_snapshot() {
         let actual_payouts = calculate_payout_parameters(
+            ContractType::Inverse,
             Price::new(dec!(54000.00)).unwrap(),
             Usd::new(dec!(3500.00)),
             Leverage::new(5).unwrap(),
@@ -763,6 +857,7 @@ mod tests {
         let quantity = Usd::new(dec!(3500.00));
 
         let payouts = calculate_payout_parameters(
+            ContractType::Inverse,
             price,
             quantity,
             Leverage::new(5).unwrap(),
@@ -775,6 +870,7 @@ mod tests {
         let fee = CompleteFee::LongPaysShort(Amount::from_sat(100));
 
         let payouts_with_fee = calculate_payout_parameters(
+            ContractType::Inverse,
             price,
             quantity,
             Leverage::new(5).unwrap(),
@@ -827,6 +923,7 @@ mod tests {
     #[test]
     fn verify_tails() {
         let actual_payouts = calculate_payout_parameters(
+            ContractType::Inverse,
             Price::new(dec!(54000.00)).unwrap(),
             Usd::new(dec!(3500.00)),
             Leverage::new(5).unwrap(),
@@ -843,6 +940,98 @@ mod tests {
         pretty_assertions::assert_eq!(actual_payouts.last().unwrap(), &upper_tail);
     }
 
+    #[test]
+    fn given_maker_leverage_above_one_then_both_tails_are_flat() {
+        let payouts = calculate_payout_parameters(
+            ContractType::Inverse,
+            Price::new(dec!(54000.00)).unwrap(),
+            Usd::new(dec!(3500.00)),
+            Leverage::new(2).unwrap(),
+            Leverage::new(3).unwrap(),
+            200,
+            CompleteFee::None,
+        )
+        .unwrap();
+
+        let lower_tail = payouts.first().unwrap();
+        let upper_tail = payouts.last().unwrap();
+
+        // long is liquidated at 54000 * 2 / 3
+        assert_eq!(lower_tail.right_bound, 36000);
+        assert_eq!(lower_tail.long_amount, 0);
+
+        // short is liquidated at 54000 * 3 / 2, the curve ends at 4 * 54000
+        assert!(upper_tail.left_bound > 81000);
+        assert_eq!(upper_tail.right_bound, 216000);
+        assert_eq!(upper_tail.short_amount, 0);
+    }
+
+    #[test]
+    fn given_linear_contract_then_tails_at_liquidation_prices() {
+        let payouts = calculate_payout_parameters(
+            ContractType::Linear,
+            Price::new(dec!(50000.00)).unwrap(),
+            Usd::new(dec!(5000.00)),
+            Leverage::new(5).unwrap(),
+            Leverage::new(2).unwrap(),
+            200,
+            CompleteFee::None,
+        )
+        .unwrap();
+
+        let lower_tail = payouts.first().unwrap();
+        let upper_tail = payouts.last().unwrap();
+
+        // long is liquidated at 50000 * (1 - 1 / 5)
+        assert_eq!(lower_tail.right_bound, 40000);
+        assert_eq!(lower_tail.long_amount, 0);
+
+        // short is liquidated at 50000 * (1 + 1 / 2)
+        assert!(upper_tail.left_bound > 75000);
+        assert_eq!(upper_tail.right_bound, 200000);
+        assert_eq!(upper_tail.short_amount, 0);
+    }
+
+    #[test]
+    fn given_linear_contract_then_long_payout_is_linear_in_price() {
+        let initial_price = 50000.;
+        let quantity = 5000.;
+        let leverage_long = 5.;
+
+        let payouts = calculate_payout_parameters(
+            ContractType::Linear,
+            Price::new(dec!(50000.00)).unwrap(),
+            Usd::new(dec!(5000.00)),
+            Leverage::new(5).unwrap(),
+            Leverage::new(2).unwrap(),
+            200,
+            CompleteFee::None,
+        )
+        .unwrap();
+
+        let multiplier = LINEAR_CONTRACT_MULTIPLIER.to_f64().unwrap();
+        let sats_per_usd = quantity * multiplier * 100_000_000.;
+        let expected_long_amount = |price: f64| {
+            let long_margin = quantity * multiplier * initial_price / leverage_long * 100_000_000.;
+
+            long_margin + sats_per_usd * (price - initial_price)
+        };
+
+        for payout in payouts
+            .iter()
+            .filter(|payout| payout.left_bound > 40000 && payout.right_bound < 75000)
+        {
+            let mid = (payout.left_bound + payout.right_bound) as f64 / 2.;
+            let width = (payout.right_bound - payout.left_bound) as f64;
+            let deviation = (payout.long_amount as f64 - expected_long_amount(mid)).abs();
+
+            assert!(
+                deviation <= sats_per_usd * width + 2.,
+                "{payout:?} deviates from linear payout by {deviation} sats"
+            );
+        }
+    }
+
     proptest! {
         /// By similar we mean that they're at most 1 satoshi off the
         /// next payout sum.
@@ -856,6 +1045,7 @@ mod tests {
             fee_flow in arb_fee_flow(-100_000_000, 100_000_000),
         ) {
             let payouts = calculate_payout_parameters(
+                ContractType::Inverse,
                 price,
                 n_contracts,
                 long_leverage,
@@ -0,0 +1,170 @@
+use crate::Position;
+use crate::Price;
+use anyhow::bail;
+use anyhow::Result;
+use serde::Deserialize;
+use serde::Serialize;
+use std::fmt;
+
+/// Prices at which an open position is closed automatically
+///
+/// The prices are compared against the price the position would be closed at, i.e. the market
+/// closing price from the perspective of the party owning the position.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct PositionTriggers {
+    pub stop_loss: Option<Price>,
+    pub take_profit: Option<Price>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Trigger {
+    StopLoss,
+    TakeProfit,
+}
+
+impl PositionTriggers {
+    /// Ensure the triggers make sense for the given position
+    ///
+    /// A stop-loss has to be on the losing side of a take-profit, otherwise the position would be
+    /// closed immediately.
+    pub fn validate(&self, position: Position) -> Result<()> {
+        if let (Some(stop_loss), Some(take_profit)) = (self.stop_loss, self.take_profit) {
+            let is_valid = match position {
+                Position::Long => stop_loss < take_profit,
+                Position::Short => stop_loss > take_profit,
+            };
+
+            if !is_valid {
+                bail!("Stop-loss {stop_loss} and take-profit {take_profit} are on the wrong side of each other for a {position:?} position");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The trigger that fires when closing `position` at `closing_price`, if any
+    ///
+    /// If both triggers would fire, the stop-loss wins.
+    pub fn triggered(&self, position: Position, closing_price: Price) -> Option<Trigger> {
+        let (is_loss, is_profit) = match position {
+            Position::Long => (
+                self.stop_loss
+                    .map_or(false, |stop_loss| closing_price <= stop_loss),
+                self.take_profit
+                    .map_or(false, |take_profit| closing_price >= take_profit),
+            ),
+            Position::Short => (
+                self.stop_loss
+                    .map_or(false, |stop_loss| closing_price >= stop_loss),
+                self.take_profit
+                    .map_or(false, |take_profit| closing_price <= take_profit),
+            ),
+        };
+
+        if is_loss {
+            return Some(Trigger::StopLoss);
+        }
+
+        if is_profit {
+            return Some(Trigger::TakeProfit);
+        }
+
+        None
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stop_loss.is_none() && self.take_profit.is_none()
+    }
+}
+
+impl fmt::Display for Trigger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Trigger::StopLoss => "stop-loss".fmt(f),
+            Trigger::TakeProfit => "take-profit".fmt(f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn given_long_when_price_drops_to_stop_loss_then_stop_loss_triggered() {
+        let triggers = triggers(Some(dec!(19_000)), Some(dec!(25_000)));
+
+        assert_eq!(
+            triggers.triggered(Position::Long, price(dec!(19_000))),
+            Some(Trigger::StopLoss)
+        );
+        assert_eq!(
+            triggers.triggered(Position::Long, price(dec!(19_001))),
+            None
+        );
+    }
+
+    #[test]
+    fn given_long_when_price_rises_to_take_profit_then_take_profit_triggered() {
+        let triggers = triggers(Some(dec!(19_000)), Some(dec!(25_000)));
+
+        assert_eq!(
+            triggers.triggered(Position::Long, price(dec!(25_500))),
+            Some(Trigger::TakeProfit)
+        );
+        assert_eq!(
+            triggers.triggered(Position::Long, price(dec!(24_999))),
+            None
+        );
+    }
+
+    #[test]
+    fn given_short_then_triggers_are_mirrored() {
+        let triggers = triggers(Some(dec!(25_000)), Some(dec!(19_000)));
+
+        assert_eq!(
+            triggers.triggered(Position::Short, price(dec!(25_000))),
+            Some(Trigger::StopLoss)
+        );
+        assert_eq!(
+            triggers.triggered(Position::Short, price(dec!(18_000))),
+            Some(Trigger::TakeProfit)
+        );
+        assert_eq!(
+            triggers.triggered(Position::Short, price(dec!(20_000))),
+            None
+        );
+    }
+
+    #[test]
+    fn given_no_triggers_then_nothing_triggered() {
+        let triggers = PositionTriggers::default();
+
+        assert!(triggers.is_empty());
+        assert_eq!(triggers.triggered(Position::Long, price(dec!(1))), None);
+        assert_eq!(triggers.triggered(Position::Short, price(dec!(1))), None);
+    }
+
+    #[test]
+    fn given_stop_loss_above_take_profit_for_long_then_invalid() {
+        let triggers = triggers(Some(dec!(25_000)), Some(dec!(19_000)));
+
+        assert!(triggers.validate(Position::Long).is_err());
+        assert!(triggers.validate(Position::Short).is_ok());
+    }
+
+    fn triggers(
+        stop_loss: Option<rust_decimal::Decimal>,
+        take_profit: Option<rust_decimal::Decimal>,
+    ) -> PositionTriggers {
+        PositionTriggers {
+            stop_loss: stop_loss.map(price),
+            take_profit: take_profit.map(price),
+        }
+    }
+
+    fn price(value: rust_decimal::Decimal) -> Price {
+        Price::new(value).unwrap()
+    }
+}
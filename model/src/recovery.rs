@@ -0,0 +1,131 @@
+use crate::OrderId;
+use crate::Position;
+use crate::Role;
+use anyhow::Context;
+use anyhow::Result;
+use bdk::bitcoin::secp256k1::SecretKey;
+use bdk::bitcoin::util::key::PublicKey;
+use bdk::bitcoin::Amount;
+use bdk::bitcoin::OutPoint;
+use bdk::bitcoin::Script;
+use bdk::bitcoin::Transaction;
+use bdk::descriptor::Descriptor;
+use bdk::miniscript::DescriptorTrait;
+use maia::lock_descriptor;
+use maia::spending_tx_sighash;
+use maia_core::secp256k1_zkp::ecdsa::Signature;
+use maia_core::secp256k1_zkp::SECP256K1;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// An open contract as shared by the counterparty after we lost our database.
+///
+/// Only our identity key is needed to spend the contract via the refund transaction, and it can
+/// be derived from the seed. Everything else is provided by the counterparty.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecoveredContract {
+    pub order_id: OrderId,
+    /// Our role in the contract.
+    pub role: Role,
+    /// Our position in the contract.
+    pub position: Position,
+    pub lock_tx: Transaction,
+    pub lock_descriptor: Descriptor<PublicKey>,
+    pub commit_tx: Transaction,
+    pub commit_descriptor: Descriptor<PublicKey>,
+    pub refund_tx: Transaction,
+    /// Signature of the counterparty on the refund transaction.
+    pub refund_signature: Signature,
+    pub counterparty_identity_pk: PublicKey,
+    /// Number of blocks after the confirmation of the commit transaction after which the refund
+    /// transaction can be published.
+    pub refund_timelock: u32,
+}
+
+impl RecoveredContract {
+    /// Verify that the contract is locked with our identity key and that the refund transaction
+    /// is signed by the counterparty.
+    pub fn verify(&self, identity_pk: PublicKey) -> Result<()> {
+        let (maker_pk, taker_pk) = match self.role {
+            Role::Maker => (identity_pk, self.counterparty_identity_pk),
+            Role::Taker => (self.counterparty_identity_pk, identity_pk),
+        };
+        anyhow::ensure!(
+            lock_descriptor(maker_pk, taker_pk) == self.lock_descriptor,
+            "Lock descriptor does not contain our identity key"
+        );
+
+        let lock_txid = self.lock_tx.txid();
+        anyhow::ensure!(
+            self.lock_tx
+                .output
+                .iter()
+                .any(|output| output.script_pubkey == self.lock_descriptor.script_pubkey()),
+            "Lock transaction {lock_txid} does not pay to the lock descriptor"
+        );
+        anyhow::ensure!(
+            self.commit_tx
+                .input
+                .iter()
+                .any(|input| input.previous_output.txid == lock_txid),
+            "Commit transaction does not spend lock transaction {lock_txid}"
+        );
+
+        let sighash = spending_tx_sighash(
+            &self.refund_tx,
+            &self.commit_descriptor,
+            self.commit_amount()?,
+        )
+        .context("could not obtain sighash")?;
+        SECP256K1
+            .verify_ecdsa(
+                &sighash,
+                &self.refund_signature,
+                &self.counterparty_identity_pk.inner,
+            )
+            .context("Invalid refund signature of counterparty")?;
+
+        Ok(())
+    }
+
+    pub fn signed_refund_tx(&self, identity_sk: &SecretKey) -> Result<Transaction> {
+        let sighash = spending_tx_sighash(
+            &self.refund_tx,
+            &self.commit_descriptor,
+            self.commit_amount()?,
+        )
+        .context("could not obtain sighash")?;
+        let our_sig = SECP256K1.sign_ecdsa(&sighash, identity_sk);
+        let our_pubkey = PublicKey::new(bdk::bitcoin::secp256k1::PublicKey::from_secret_key(
+            SECP256K1,
+            identity_sk,
+        ));
+
+        let signed_refund_tx = maia::finalize_spend_transaction(
+            self.refund_tx.clone(),
+            &self.commit_descriptor,
+            (our_pubkey, our_sig),
+            (self.counterparty_identity_pk, self.refund_signature),
+        )?;
+
+        Ok(signed_refund_tx)
+    }
+
+    /// The output of the commit transaction spent by the refund transaction and the CETs.
+    pub fn commit_output(&self) -> (OutPoint, Script) {
+        (
+            OutPoint::new(self.commit_tx.txid(), 0),
+            self.commit_descriptor.script_pubkey(),
+        )
+    }
+
+    fn commit_amount(&self) -> Result<Amount> {
+        let output = self
+            .commit_tx
+            .output
+            .first()
+            .context("Commit transaction has no output")?;
+
+        Ok(Amount::from_sat(output.value))
+    }
+}
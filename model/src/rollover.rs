@@ -15,6 +15,7 @@ use anyhow::Result;
 use bdk::bitcoin::secp256k1::SecretKey;
 use bdk::bitcoin::PublicKey;
 use bdk::bitcoin::Script;
+use bdk::descriptor::Descriptor;
 use bdk::miniscript::DescriptorTrait;
 use bdk_ext::SecretKeyExt;
 use maia_core::secp256k1_zkp;
@@ -127,6 +128,7 @@ pub struct BaseCommitParams {
     // To monitor.
     pub commit_txid: Txid,
     pub commit_script_pubkey: Script,
+    pub commit_descriptor: Option<Descriptor<PublicKey>>,
 
     // To allow rolling over from arbitrary base.
     pub settlement_event_id: BitMexPriceEventId,
@@ -162,6 +164,7 @@ impl Dlc {
                 publish_pk_theirs: self.publish_pk_counterparty,
                 commit_txid: self.commit.0.txid(),
                 commit_script_pubkey: self.commit.2.script_pubkey(),
+                commit_descriptor: Some(self.commit.2.clone()),
                 settlement_event_id: self.settlement_event_id,
                 revocation_sk_ours: self.revocation,
                 complete_fee,
@@ -188,6 +191,7 @@ impl Dlc {
             script_pubkey: commit_script_pubkey,
             settlement_event_id,
             complete_fee,
+            commit_descriptor,
         } = self
             .revoked_commit
             .iter()
@@ -214,6 +218,7 @@ impl Dlc {
                 publish_pk_theirs,
                 commit_txid,
                 commit_script_pubkey,
+                commit_descriptor,
                 settlement_event_id,
                 complete_fee,
             },
@@ -248,6 +253,7 @@ impl BaseDlcParams {
             script_pubkey: self.base_commit_params.commit_script_pubkey,
             settlement_event_id: Some(self.base_commit_params.settlement_event_id),
             complete_fee: Some(self.base_commit_params.complete_fee),
+            commit_descriptor: self.base_commit_params.commit_descriptor,
         };
 
         let mut revoked_commits = self.revoked_commits;
@@ -1,13 +1,30 @@
+use std::path::Path;
 use std::path::PathBuf;
 
+use anyhow::bail;
+use anyhow::ensure;
+use anyhow::Context;
+use anyhow::Result;
+use clap::Args;
 use clap::Parser;
 use clap::Subcommand;
 use daemon::bdk::bitcoin;
+use daemon::bdk::bitcoin::util::psbt::PartiallySignedTransaction;
 use daemon::bdk::bitcoin::Address;
 use daemon::bdk::bitcoin::Amount;
+use daemon::bdk::bitcoin::OutPoint;
+use daemon::blockchain;
+use daemon::seed::FileSeed;
+use daemon::seed::MnemonicSeed;
+use daemon::seed::Passphrase;
+use model::olivia;
 
 const MAINNET_ELECTRUM: &str = "ssl://blockstream.info:700";
 const TESTNET_ELECTRUM: &str = "ssl://blockstream.info:993";
+/// Environment variable to read the passphrase of an encrypted seed file from.
+const SEED_PASSPHRASE_ENV: &str = "ITCHYSATS_SEED_PASSPHRASE";
+/// Environment variable to read the password to authenticate with Bitcoin Core from.
+const BITCOIN_RPC_PASSWORD_ENV: &str = "ITCHYSATS_BITCOIN_RPC_PASSWORD";
 
 #[derive(Parser, Clone)]
 pub enum Network {
@@ -17,8 +34,14 @@ pub enum Network {
         #[clap(long, default_value = MAINNET_ELECTRUM)]
         electrum: String,
 
+        #[clap(flatten)]
+        blockchain: Blockchain,
+
+        #[clap(flatten)]
+        oracles: Oracles,
+
         #[clap(subcommand)]
-        withdraw: Option<Withdraw>,
+        command: Option<Command>,
     },
     /// Run on testnet
     Testnet {
@@ -26,26 +49,46 @@ pub enum Network {
         #[clap(long, default_value = TESTNET_ELECTRUM)]
         electrum: String,
 
+        #[clap(flatten)]
+        blockchain: Blockchain,
+
+        #[clap(flatten)]
+        oracles: Oracles,
+
         #[clap(subcommand)]
-        withdraw: Option<Withdraw>,
+        command: Option<Command>,
     },
     /// Run on signet
     Signet {
-        /// URL to the electrum backend to use for the wallet.
+        /// URL to the electrum backend to use for the wallet. Required unless `--esplora` or
+        /// `--bitcoin-rpc` is given.
         #[clap(long)]
-        electrum: String,
+        electrum: Option<String>,
+
+        #[clap(flatten)]
+        blockchain: Blockchain,
+
+        #[clap(flatten)]
+        oracles: Oracles,
 
         #[clap(subcommand)]
-        withdraw: Option<Withdraw>,
+        command: Option<Command>,
     },
     /// Run on regtest
     Regtest {
-        /// URL to the electrum backend to use for the wallet.
+        /// URL to the electrum backend to use for the wallet. Required unless `--esplora` or
+        /// `--bitcoin-rpc` is given.
         #[clap(long)]
-        electrum: String,
+        electrum: Option<String>,
+
+        #[clap(flatten)]
+        blockchain: Blockchain,
+
+        #[clap(flatten)]
+        oracles: Oracles,
 
         #[clap(subcommand)]
-        withdraw: Option<Withdraw>,
+        command: Option<Command>,
     },
 }
 
@@ -53,13 +96,174 @@ impl Default for Network {
     fn default() -> Self {
         Network::Mainnet {
             electrum: MAINNET_ELECTRUM.to_string(),
-            withdraw: None,
+            blockchain: Blockchain::default(),
+            oracles: Oracles::default(),
+            command: None,
+        }
+    }
+}
+
+/// Blockchain backends to use instead of Electrum.
+#[derive(Args, Clone, Default)]
+pub struct Blockchain {
+    /// URL of an Esplora HTTP API to use instead of Electrum, e.g. https://blockstream.info/api.
+    #[clap(long)]
+    esplora: Option<String>,
+
+    /// URL of the RPC interface of a Bitcoin Core node to use instead of Electrum, e.g.
+    /// http://127.0.0.1:8332. The transactions of CFDs are followed through a watch-only wallet
+    /// on the node, so `-txindex` is not required.
+    #[clap(long)]
+    bitcoin_rpc: Option<String>,
+
+    /// Cookie file to authenticate with Bitcoin Core, e.g. ~/.bitcoin/.cookie.
+    #[clap(long)]
+    bitcoin_rpc_cookie: Option<PathBuf>,
+
+    /// User to authenticate with Bitcoin Core. The password is read from
+    /// `--bitcoin-rpc-password-file` or the `ITCHYSATS_BITCOIN_RPC_PASSWORD` environment variable.
+    #[clap(long)]
+    bitcoin_rpc_user: Option<String>,
+
+    /// File containing the password to authenticate with Bitcoin Core, together with
+    /// `--bitcoin-rpc-user`.
+    #[clap(long)]
+    bitcoin_rpc_password_file: Option<PathBuf>,
+}
+
+impl Blockchain {
+    fn backend(&self, electrum: Option<&str>) -> Result<blockchain::Backend> {
+        let backend = match (&self.esplora, &self.bitcoin_rpc) {
+            (Some(_), Some(_)) => bail!("Only one of --esplora and --bitcoin-rpc can be given"),
+            (Some(url), None) => blockchain::Backend::Esplora { url: url.clone() },
+            (None, Some(url)) => {
+                let auth = match (&self.bitcoin_rpc_cookie, &self.bitcoin_rpc_user) {
+                    (Some(file), None) => blockchain::Auth::Cookie(file.clone()),
+                    (None, Some(username)) => blockchain::Auth::UserPass {
+                        username: username.clone(),
+                        password: self.bitcoin_rpc_password()?,
+                    },
+                    _ => bail!(
+                        "Bitcoin Core requires either --bitcoin-rpc-cookie or --bitcoin-rpc-user"
+                    ),
+                };
+
+                blockchain::Backend::BitcoinCore {
+                    url: url.clone(),
+                    auth,
+                }
+            }
+            (None, None) => blockchain::Backend::Electrum {
+                url: electrum
+                    .context("One of --electrum, --esplora or --bitcoin-rpc is required")?
+                    .to_string(),
+            },
+        };
+
+        Ok(backend)
+    }
+
+    /// The password to authenticate with Bitcoin Core, read from `--bitcoin-rpc-password-file`
+    /// or the environment so that it does not show up in the process list.
+    fn bitcoin_rpc_password(&self) -> Result<String> {
+        if let Some(file) = &self.bitcoin_rpc_password_file {
+            let password = std::fs::read_to_string(file).with_context(|| {
+                format!(
+                    "Failed to read Bitcoin Core password from {}",
+                    file.display()
+                )
+            })?;
+
+            return Ok(password.trim_end_matches(&['\n', '\r'][..]).to_string());
+        }
+
+        std::env::var(BITCOIN_RPC_PASSWORD_ENV).with_context(|| {
+            format!("--bitcoin-rpc-user requires --bitcoin-rpc-password-file or {BITCOIN_RPC_PASSWORD_ENV}")
+        })
+    }
+}
+
+/// The oracles to use for attestations.
+#[derive(Args, Clone, Default)]
+pub struct Oracles {
+    /// Oracle to use for attestations, specified as `<public-key>@<url>`. Can be given multiple
+    /// times. Defaults to olivia at https://h00.ooo.
+    #[clap(long = "oracle")]
+    oracles: Vec<olivia::Oracle>,
+}
+
+/// Unlocking and encrypting the seed file with a passphrase.
+#[derive(Args, Clone)]
+pub struct SeedEncryption {
+    /// Read the passphrase of an encrypted seed file from this file descriptor. If not given,
+    /// the passphrase is read from the `ITCHYSATS_SEED_PASSPHRASE` environment variable or
+    /// prompted for.
+    #[clap(long)]
+    seed_passphrase_fd: Option<u32>,
+
+    /// Encrypt the plaintext seed file with a passphrase and exit.
+    #[clap(long)]
+    pub encrypt_seed: bool,
+}
+
+impl SeedEncryption {
+    /// The passphrase given via file descriptor or environment variable.
+    ///
+    /// If neither is given, the passphrase is prompted for if `prompt` is set.
+    pub fn passphrase(&self, prompt: bool) -> Result<Option<Passphrase>> {
+        if let Some(fd) = self.seed_passphrase_fd {
+            let mut passphrase = std::fs::read_to_string(format!("/dev/fd/{fd}"))
+                .with_context(|| format!("Failed to read seed passphrase from fd {fd}"))?;
+            // Truncate in place instead of copying so that no copy outlives the passphrase
+            let len = passphrase.trim_end_matches(&['\n', '\r'][..]).len();
+            passphrase.truncate(len);
+            let passphrase = Passphrase::from(passphrase);
+            ensure!(
+                !passphrase.is_empty(),
+                "Seed passphrase read from fd {fd} must not be empty"
+            );
+
+            return Ok(Some(passphrase));
+        }
+
+        if let Ok(passphrase) = std::env::var(SEED_PASSPHRASE_ENV) {
+            let passphrase = Passphrase::from(passphrase);
+            ensure!(
+                !passphrase.is_empty(),
+                "Seed passphrase in {SEED_PASSPHRASE_ENV} must not be empty"
+            );
+
+            return Ok(Some(passphrase));
+        }
+
+        if !prompt {
+            return Ok(None);
         }
+
+        let passphrase = Passphrase::from(rpassword::prompt_password("Seed passphrase: ")?);
+
+        Ok(Some(passphrase))
+    }
+
+    /// The passphrase to encrypt the seed file with, prompted for twice if not given via file
+    /// descriptor or environment variable.
+    pub fn new_passphrase(&self) -> Result<Passphrase> {
+        if let Some(passphrase) = self.passphrase(false)? {
+            return Ok(passphrase);
+        }
+
+        let passphrase = Passphrase::from(rpassword::prompt_password("New seed passphrase: ")?);
+        let confirmation =
+            Passphrase::from(rpassword::prompt_password("Repeat seed passphrase: ")?);
+        ensure!(!passphrase.is_empty(), "Seed passphrase must not be empty");
+        ensure!(passphrase == confirmation, "Seed passphrases do not match");
+
+        Ok(passphrase)
     }
 }
 
 #[derive(Subcommand, Clone)]
-pub enum Withdraw {
+pub enum Command {
     Withdraw {
         /// Optionally specify the amount of Bitcoin to be withdrawn. If not specified the wallet
         /// will be drained. Amount is to be specified with denomination, e.g. "0.1 BTC"
@@ -72,19 +276,141 @@ pub enum Withdraw {
         /// The address to receive the Bitcoin.
         #[clap(long)]
         address: Address,
+        /// Spend only this output, specified as `<txid>:<vout>`. Can be given multiple times.
+        /// If not specified the outputs to spend are chosen automatically.
+        #[clap(long = "utxo")]
+        utxos: Vec<OutPoint>,
+        /// Print the unsigned transaction as base64 encoded PSBT instead of signing and
+        /// broadcasting it, e.g. to sign it with a hardware wallet.
+        #[clap(long)]
+        psbt: bool,
+    },
+    /// Broadcast a withdrawal PSBT signed by an external signer.
+    BroadcastPsbt {
+        /// The signed PSBT, base64 encoded.
+        #[clap(long)]
+        psbt: PartiallySignedTransaction,
+    },
+    /// Print the seed as BIP39 mnemonic words for a backup.
+    ///
+    /// Seeds created before mnemonic backups were supported are printed as 8 lines of 24 words.
+    ExportMnemonic,
+    /// Restore the seed file of an empty data directory from the BIP39 mnemonic words of a
+    /// backup. The words are prompted for; the words of a backup of several lines are entered
+    /// in order, separated by spaces.
+    RestoreMnemonic,
+    /// Restore the CFDs of a backup file into the database before starting the daemon.
+    ImportBackup {
+        /// The backup file, e.g. `cfds.backup` of the lost data directory.
+        #[clap(long)]
+        file: PathBuf,
     },
 }
 
+/// Load the seed from `seed_file`, generating a new one if there is none.
+///
+/// Runs `--encrypt-seed` and the mnemonic commands on the seed file instead, returning `None`
+/// as the daemon is supposed to exit afterwards.
+pub async fn load_seed(
+    seed_file: &Path,
+    seed_encryption: &SeedEncryption,
+    command: &Option<Command>,
+) -> Result<Option<FileSeed>> {
+    if seed_encryption.encrypt_seed {
+        let passphrase = seed_encryption.new_passphrase()?;
+        FileSeed::encrypt(seed_file, &passphrase).await?;
+
+        return Ok(None);
+    }
+
+    let passphrase = match command {
+        Some(Command::RestoreMnemonic) => {
+            let mnemonics = read_mnemonics()?;
+            let passphrase = seed_encryption.passphrase(false)?;
+            FileSeed::restore(seed_file, &mnemonics, passphrase).await?;
+
+            return Ok(None);
+        }
+        _ => seed_encryption.passphrase(FileSeed::is_encrypted(seed_file).await?)?,
+    };
+
+    if let Some(Command::ExportMnemonic) = command {
+        // Unlike starting the daemon, exporting must not generate a seed that was never backed up
+        let seed = FileSeed::read_from(seed_file, passphrase.as_ref()).await?;
+        print_mnemonics(&seed.mnemonics());
+
+        return Ok(None);
+    }
+
+    let seed = FileSeed::initialize(seed_file, passphrase).await?;
+
+    Ok(Some(seed))
+}
+
+/// Prompt for the BIP39 mnemonic words of a seed backup.
+pub fn read_mnemonics() -> Result<Vec<MnemonicSeed>> {
+    let words = rpassword::prompt_password("Mnemonic words: ")?;
+
+    MnemonicSeed::parse_all(&words)
+}
+
+/// Print the BIP39 mnemonic words of a seed for a backup, one mnemonic per line.
+pub fn print_mnemonics(mnemonics: &[MnemonicSeed]) {
+    #[allow(clippy::print_stdout)]
+    {
+        for mnemonic in mnemonics {
+            println!("{mnemonic}");
+        }
+    }
+}
+
 impl Network {
-    pub fn electrum(&self) -> &str {
+    /// The backend to sync the wallet with and to monitor transactions.
+    ///
+    /// Esplora or Bitcoin Core take precedence over the Electrum server if given.
+    pub fn blockchain(&self) -> Result<blockchain::Backend> {
         match self {
-            Network::Mainnet { electrum, .. } => electrum,
-            Network::Testnet { electrum, .. } => electrum,
-            Network::Signet { electrum, .. } => electrum,
-            Network::Regtest { electrum, .. } => electrum,
+            Network::Mainnet {
+                electrum,
+                blockchain,
+                ..
+            }
+            | Network::Testnet {
+                electrum,
+                blockchain,
+                ..
+            } => blockchain.backend(Some(electrum)),
+            Network::Signet {
+                electrum,
+                blockchain,
+                ..
+            }
+            | Network::Regtest {
+                electrum,
+                blockchain,
+                ..
+            } => blockchain.backend(electrum.as_deref()),
         }
     }
 
+    /// The oracles to sync announcements and attestations from.
+    ///
+    /// The first oracle is the one the maker creates offers with.
+    pub fn oracles(&self) -> Vec<olivia::Oracle> {
+        let Oracles { oracles } = match self {
+            Network::Mainnet { oracles, .. } => oracles,
+            Network::Testnet { oracles, .. } => oracles,
+            Network::Signet { oracles, .. } => oracles,
+            Network::Regtest { oracles, .. } => oracles,
+        };
+
+        if oracles.is_empty() {
+            return vec![olivia::Oracle::olivia()];
+        }
+
+        oracles.clone()
+    }
+
     pub fn bitcoin_network(&self) -> bitcoin::Network {
         match self {
             Network::Mainnet { .. } => bitcoin::Network::Bitcoin,
@@ -112,12 +438,12 @@ impl Network {
         }
     }
 
-    pub fn withdraw(&self) -> &Option<Withdraw> {
+    pub fn command(&self) -> &Option<Command> {
         match self {
-            Network::Mainnet { withdraw, .. } => withdraw,
-            Network::Testnet { withdraw, .. } => withdraw,
-            Network::Signet { withdraw, .. } => withdraw,
-            Network::Regtest { withdraw, .. } => withdraw,
+            Network::Mainnet { command, .. } => command,
+            Network::Testnet { command, .. } => command,
+            Network::Signet { command, .. } => command,
+            Network::Regtest { command, .. } => command,
         }
     }
 
@@ -3,6 +3,7 @@ use crate::ConnectionCloseReason::TakerVersionOutdated;
 use daemon::bdk::bitcoin::Amount;
 use daemon::connection;
 use daemon::projection::Cfd;
+use daemon::projection::MakerOffers;
 use daemon::projection::Quote;
 use model::Identity;
 use model::Timestamp;
@@ -90,3 +91,15 @@ impl ToSseEvent for Option<Quote> {
         Event::json(self).event("quote")
     }
 }
+
+impl ToSseEvent for Vec<Quote> {
+    fn to_sse_event(&self) -> Event {
+        Event::json(self).event("quotes")
+    }
+}
+
+impl ToSseEvent for Vec<MakerOffers> {
+    fn to_sse_event(&self) -> Event {
+        Event::json(self).event("offers")
+    }
+}
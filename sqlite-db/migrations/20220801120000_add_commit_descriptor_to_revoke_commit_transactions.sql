@@ -0,0 +1,5 @@
+ALTER TABLE
+    revoked_commit_transactions
+ADD
+    -- We allow NULL values to ensure backwards compatibility (we cannot derive the descriptor for already revoked commit transactions)
+    COLUMN commit_descriptor text NULL;
@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS closed_punish_txs (
+    id integer PRIMARY KEY autoincrement,
+    cfd_id integer NOT NULL,
+    txid text NOT NULL,
+    vout integer NOT NULL,
+    payout integer NOT NULL,
+    FOREIGN KEY (cfd_id) REFERENCES closed_cfds (id)
+);
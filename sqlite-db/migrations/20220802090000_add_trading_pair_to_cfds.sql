@@ -0,0 +1,13 @@
+-- All CFDs created before this migration were BTC/USD CFDs.
+ALTER TABLE
+    cfds
+ADD
+    COLUMN trading_pair TEXT NOT NULL DEFAULT 'BtcUsd';
+ALTER TABLE
+    closed_cfds
+ADD
+    COLUMN trading_pair TEXT NOT NULL DEFAULT 'BtcUsd';
+ALTER TABLE
+    failed_cfds
+ADD
+    COLUMN trading_pair TEXT NOT NULL DEFAULT 'BtcUsd';
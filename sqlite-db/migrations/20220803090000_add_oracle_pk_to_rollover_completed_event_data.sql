@@ -0,0 +1,5 @@
+-- All rollovers before this migration used olivia as oracle.
+ALTER TABLE
+    rollover_completed_event_data
+ADD
+    COLUMN oracle_pk TEXT NOT NULL DEFAULT 'ddd4636845a90185991826be5a494cde9f4a6947b1727217afedc6292fa4caf7';
@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS position_triggers (
+    id integer PRIMARY KEY autoincrement,
+    order_id text UNIQUE NOT NULL,
+    stop_loss text NULL,
+    take_profit text NULL,
+    -- The trigger that caused us to propose a settlement, if any.
+    fired text NULL,
+    -- Why closing the position failed after a trigger fired.
+    failure text NULL
+);
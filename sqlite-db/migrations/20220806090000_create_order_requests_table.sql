@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS order_requests (
+    id integer PRIMARY KEY autoincrement,
+    -- Chosen by the client to make placing the order idempotent.
+    idempotency_key text UNIQUE NOT NULL,
+    order_id text NOT NULL,
+    quantity text NOT NULL,
+    leverage integer NOT NULL,
+    created_at integer NOT NULL
+);
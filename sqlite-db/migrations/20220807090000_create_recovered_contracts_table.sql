@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS recovered_contracts (
+    id integer PRIMARY KEY autoincrement,
+    order_id text UNIQUE NOT NULL,
+    -- JSON serialized `model::RecoveredContract`.
+    data text NOT NULL
+);
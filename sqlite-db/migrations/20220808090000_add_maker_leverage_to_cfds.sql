@@ -0,0 +1,13 @@
+-- All CFDs created before this migration used unity leverage for the maker.
+ALTER TABLE
+    cfds
+ADD
+    COLUMN maker_leverage INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE
+    closed_cfds
+ADD
+    COLUMN maker_leverage INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE
+    failed_cfds
+ADD
+    COLUMN maker_leverage INTEGER NOT NULL DEFAULT 1;
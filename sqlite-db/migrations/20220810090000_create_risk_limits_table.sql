@@ -0,0 +1,8 @@
+-- Holds at most one row with the risk limits last set by the operator.
+CREATE TABLE IF NOT EXISTS risk_limits (
+    id integer PRIMARY KEY CHECK (id = 1),
+    max_taker_quantity text NULL,
+    max_net_open_interest text NULL,
+    -- In satoshis.
+    max_locked_margin integer NULL
+);
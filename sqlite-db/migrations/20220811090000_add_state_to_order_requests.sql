@@ -0,0 +1,10 @@
+-- Order requests recorded before this migration were only kept if the order was placed.
+ALTER TABLE
+    order_requests
+ADD
+    COLUMN state text NOT NULL DEFAULT 'Placed';
+-- Why placing the order failed, if it did.
+ALTER TABLE
+    order_requests
+ADD
+    COLUMN failure text NULL;
@@ -0,0 +1,4 @@
+-- Peers banned by the operator, rejected until they are unbanned.
+CREATE TABLE IF NOT EXISTS banned_peers (
+    peer_id text PRIMARY KEY NOT NULL
+);
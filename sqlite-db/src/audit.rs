@@ -0,0 +1,240 @@
+use crate::event_log::EventLogEntry;
+use crate::load_cfd_row;
+use crate::models;
+use crate::rollover;
+use crate::Cfd;
+use crate::CfdAggregate;
+use crate::ClosedCfdAggregate;
+use crate::Connection;
+use crate::FailedCfdAggregate;
+use anyhow::Context;
+use anyhow::Result;
+use model::CfdEvent;
+use model::ClosedCfd;
+use model::EventKind;
+use model::EventKind::RolloverCompleted;
+use model::FailedCfd;
+use model::OrderId;
+use model::Timestamp;
+use sqlx::Connection as _;
+
+/// An event of a CFD as it is stored in the `events` table
+#[derive(Debug)]
+pub struct AuditEvent {
+    pub name: String,
+    pub data: String,
+    pub timestamp: Timestamp,
+    /// The decoded event, or why it could not be decoded
+    pub event: Result<CfdEvent>,
+}
+
+/// A CFD that was moved out of the `cfds` table
+///
+/// Only the names and times of the events of these CFDs are kept, see [`EventLogEntry`].
+#[derive(Debug, Clone, Copy)]
+pub enum ArchivedCfd {
+    Closed(ClosedCfd),
+    Failed(FailedCfd),
+}
+
+impl Connection {
+    /// Load a CFD together with its raw events, oldest first
+    ///
+    /// In contrast to loading the aggregate, events that fail to decode are returned alongside
+    /// the error instead of failing the whole CFD.
+    pub async fn load_cfd_audit(&self, id: OrderId) -> Result<(Cfd, Vec<AuditEvent>)> {
+        let mut conn = self.inner.acquire().await?;
+        let mut db_tx = conn.begin().await?;
+
+        let cfd = load_cfd_row(&mut db_tx, id).await?;
+
+        let order_id = models::OrderId::from(id);
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                cfds.id as cfd_row_id,
+                events.id as event_row_id,
+                events.name,
+                events.data,
+                events.created_at as "created_at: models::Timestamp"
+            FROM
+                events
+            JOIN
+                cfds on cfds.id = events.cfd_id
+            WHERE
+                cfds.uuid = $1
+            ORDER BY
+                events.id
+            "#,
+            order_id
+        )
+        .fetch_all(&mut *db_tx)
+        .await?;
+
+        let mut events = Vec::with_capacity(rows.len());
+        for row in rows {
+            let cfd_row_id = row
+                .cfd_row_id
+                .with_context(|| format!("CFD {id} not found"))?;
+            let timestamp = Timestamp::from(row.created_at);
+
+            // Only the latest rollover of a CFD keeps its DLC, earlier ones are loaded without
+            let event = match EventKind::from_json(row.name.clone(), row.data.clone()) {
+                Ok(event @ RolloverCompleted { .. }) => {
+                    match rollover::load(&mut db_tx, cfd_row_id, row.event_row_id).await {
+                        Ok(Some((dlc, funding_fee, complete_fee))) => Ok(RolloverCompleted {
+                            dlc: Some(dlc),
+                            funding_fee,
+                            complete_fee,
+                        }),
+                        Ok(None) => Ok(event),
+                        Err(e) => Err(e),
+                    }
+                }
+                other => other,
+            };
+
+            events.push(AuditEvent {
+                name: row.name,
+                data: row.data,
+                timestamp,
+                event: event.map(|event| CfdEvent {
+                    timestamp,
+                    id,
+                    event,
+                }),
+            });
+        }
+
+        db_tx.commit().await?;
+
+        Ok((cfd, events))
+    }
+
+    /// Load all CFDs of the `closed_cfds` and `failed_cfds` tables together with their event log
+    pub async fn load_archived_cfd_audits(&self) -> Result<Vec<(ArchivedCfd, Vec<EventLogEntry>)>> {
+        let mut cfds = Vec::new();
+
+        for id in self.load_closed_cfd_ids().await? {
+            let Archived(cfd) = self
+                .load_closed_cfd(id, ())
+                .await
+                .with_context(|| format!("Failed to load closed CFD {id}"))?;
+            cfds.push(cfd);
+        }
+
+        for id in self.load_failed_cfd_ids().await? {
+            let Archived(cfd) = self
+                .load_failed_cfd(id, ())
+                .await
+                .with_context(|| format!("Failed to load failed CFD {id}"))?;
+            cfds.push(cfd);
+        }
+
+        let mut audits = Vec::with_capacity(cfds.len());
+        for cfd in cfds.into_iter().flatten() {
+            let id = match cfd {
+                ArchivedCfd::Closed(closed) => closed.id,
+                ArchivedCfd::Failed(failed) => failed.id,
+            };
+
+            audits.push((cfd, self.load_event_log(id).await?));
+        }
+
+        Ok(audits)
+    }
+}
+
+/// Aggregate to load the rows of archived CFDs with the existing loaders
+///
+/// Archived CFDs never have events applied, hence open CFDs load as `None`.
+#[derive(Clone)]
+struct Archived(Option<ArchivedCfd>);
+
+impl CfdAggregate for Archived {
+    type CtorArgs = ();
+
+    fn new(_: Self::CtorArgs, _: Cfd) -> Self {
+        Self(None)
+    }
+
+    fn apply(self, _: CfdEvent) -> Self {
+        self
+    }
+
+    fn version(&self) -> u32 {
+        0
+    }
+}
+
+impl ClosedCfdAggregate for Archived {
+    fn new_closed(_: Self::CtorArgs, cfd: ClosedCfd) -> Self {
+        Self(Some(ArchivedCfd::Closed(cfd)))
+    }
+}
+
+impl FailedCfdAggregate for Archived {
+    fn new_failed(_: Self::CtorArgs, cfd: FailedCfd) -> Self {
+        Self(Some(ArchivedCfd::Failed(cfd)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory;
+    use crate::tests::dummy_cfd;
+    use crate::tests::lock_confirmed;
+    use crate::tests::order_rejected;
+
+    #[tokio::test]
+    async fn undecodable_event_is_reported_alongside_others() {
+        let db = memory().await.unwrap();
+        let cfd = dummy_cfd();
+        db.insert_cfd(&cfd).await.unwrap();
+        db.append_event(lock_confirmed(&cfd)).await.unwrap();
+
+        let mut conn = db.inner.acquire().await.unwrap();
+        sqlx::query(
+            r#"
+            insert into events (cfd_id, name, data, created_at)
+            values ((select id from cfds where cfds.uuid = $1), $2, $3, $4)
+            "#,
+        )
+        .bind(models::OrderId::from(cfd.id()))
+        .bind("NotAnEvent")
+        .bind("null")
+        .bind(models::Timestamp::from(Timestamp::now()))
+        .execute(&mut conn)
+        .await
+        .unwrap();
+
+        let (loaded, events) = db.load_cfd_audit(cfd.id()).await.unwrap();
+
+        assert_eq!(loaded.id, cfd.id());
+        assert_eq!(events.len(), 2);
+        assert!(matches!(
+            events[0].event.as_ref().unwrap().event,
+            EventKind::LockConfirmed
+        ));
+        assert_eq!(events[1].name, "NotAnEvent");
+        assert!(events[1].event.is_err());
+    }
+
+    #[tokio::test]
+    async fn failed_cfd_is_listed_with_its_event_log() {
+        let db = memory().await.unwrap();
+        let cfd = dummy_cfd();
+        db.insert_cfd(&cfd).await.unwrap();
+        db.append_event(order_rejected(&cfd)).await.unwrap();
+        db.move_to_failed_cfds().await.unwrap();
+
+        let audits = db.load_archived_cfd_audits().await.unwrap();
+
+        assert_eq!(audits.len(), 1);
+        let (archived, event_log) = &audits[0];
+        assert!(matches!(archived, ArchivedCfd::Failed(failed) if failed.id == cfd.id()));
+        assert_eq!(event_log.len(), 1);
+        assert_eq!(event_log[0].name, EventKind::OfferRejected.to_string());
+    }
+}
@@ -0,0 +1,244 @@
+use crate::load_cfd_events;
+use crate::load_cfd_row;
+use crate::Connection;
+use anyhow::Context;
+use anyhow::Result;
+use model::libp2p::PeerId;
+use model::CfdEvent;
+use model::Dlc;
+use model::EventKind;
+use model::FundingRate;
+use model::Identity;
+use model::Leverage;
+use model::OpeningFee;
+use model::OrderId;
+use model::Position;
+use model::Price;
+use model::Role;
+use model::Timestamp;
+use model::TradingPair;
+use model::TxFeeRate;
+use model::Usd;
+use serde::Deserialize;
+use serde::Serialize;
+use sqlx::Connection as _;
+use time::Duration;
+
+/// Everything needed to restore an open CFD into a fresh database
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CfdBackup {
+    pub id: OrderId,
+    pub trading_pair: TradingPair,
+    pub position: Position,
+    pub initial_price: Price,
+    pub taker_leverage: Leverage,
+    /// Backups written before the maker could choose its leverage lack this field
+    #[serde(default = "unity_leverage")]
+    pub maker_leverage: Leverage,
+    pub settlement_interval_hours: i64,
+    pub quantity_usd: Usd,
+    pub counterparty_network_identity: Identity,
+    pub counterparty_peer_id: Option<PeerId>,
+    pub role: Role,
+    pub opening_fee: OpeningFee,
+    pub initial_funding_rate: FundingRate,
+    pub initial_tx_fee_rate: TxFeeRate,
+    /// The events of the CFD, stripped of the DLCs they carry
+    pub events: Vec<BackupEvent>,
+    /// The latest DLC of the CFD
+    pub dlc: Dlc,
+}
+
+/// An event as it is stored in the `events` table
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupEvent {
+    pub name: String,
+    pub data: String,
+    pub timestamp: Timestamp,
+}
+
+impl Connection {
+    /// Load the backup of a CFD
+    ///
+    /// Returns `None` if the contract of the CFD was not set up yet or if the CFD is already
+    /// closed according to the blockchain.
+    pub async fn load_cfd_backup(&self, id: OrderId) -> Result<Option<CfdBackup>> {
+        let mut conn = self.inner.acquire().await?;
+        let mut db_tx = conn.begin().await?;
+
+        let cfd = load_cfd_row(&mut db_tx, id).await?;
+        let events = load_cfd_events(&mut db_tx, id, 0).await?;
+
+        db_tx.commit().await?;
+
+        let mut latest_dlc = None;
+        let mut closed = false;
+        let events = events
+            .into_iter()
+            .map(|mut event| {
+                if let EventKind::CollaborativeSettlementConfirmed
+                | EventKind::CetConfirmed
+                | EventKind::RefundConfirmed
+                | EventKind::PunishConfirmed = event.event
+                {
+                    closed = true;
+                }
+
+                if let EventKind::ContractSetupCompleted { dlc }
+                | EventKind::RolloverCompleted { dlc, .. } = &mut event.event
+                {
+                    if let Some(dlc) = dlc.take() {
+                        latest_dlc = Some(dlc);
+                    }
+                }
+
+                let (name, data) = event.event.to_json();
+                BackupEvent {
+                    name,
+                    data,
+                    timestamp: event.timestamp,
+                }
+            })
+            .collect();
+
+        let dlc = match latest_dlc {
+            Some(dlc) if !closed => dlc,
+            _ => return Ok(None),
+        };
+
+        Ok(Some(CfdBackup {
+            id: cfd.id,
+            trading_pair: cfd.trading_pair,
+            position: cfd.position,
+            initial_price: cfd.initial_price,
+            taker_leverage: cfd.taker_leverage,
+            maker_leverage: cfd.maker_leverage,
+            settlement_interval_hours: cfd.settlement_interval.whole_hours(),
+            quantity_usd: cfd.quantity_usd,
+            counterparty_network_identity: cfd.counterparty_network_identity,
+            counterparty_peer_id: cfd.counterparty_peer_id,
+            role: cfd.role,
+            opening_fee: cfd.opening_fee,
+            initial_funding_rate: cfd.initial_funding_rate,
+            initial_tx_fee_rate: cfd.initial_tx_fee_rate,
+            events,
+            dlc,
+        }))
+    }
+
+    /// Insert a CFD from its backup, unless it is already in the database
+    ///
+    /// Returns `false` if the CFD was already in the database.
+    pub async fn restore_cfd_backup(&self, backup: CfdBackup) -> Result<bool> {
+        if self.load_open_cfd_ids().await?.contains(&backup.id) {
+            return Ok(false);
+        }
+
+        let cfd = model::Cfd::new(
+            backup.id,
+            backup.trading_pair,
+            backup.position,
+            backup.initial_price,
+            backup.taker_leverage,
+            backup.maker_leverage,
+            Duration::hours(backup.settlement_interval_hours),
+            backup.role,
+            backup.quantity_usd,
+            backup.counterparty_network_identity,
+            backup.counterparty_peer_id,
+            backup.opening_fee,
+            backup.initial_funding_rate,
+            backup.initial_tx_fee_rate,
+        );
+        self.insert_cfd(&cfd).await?;
+
+        let mut events = backup
+            .events
+            .into_iter()
+            .map(|event| {
+                Ok(CfdEvent {
+                    timestamp: event.timestamp,
+                    id: backup.id,
+                    event: EventKind::from_json(event.name, event.data)?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let latest_dlc_event = events
+            .iter_mut()
+            .rev()
+            .find_map(|event| match &mut event.event {
+                EventKind::ContractSetupCompleted { dlc }
+                | EventKind::RolloverCompleted { dlc, .. } => Some(dlc),
+                _ => None,
+            })
+            .context("Backup does not contain an event with a DLC")?;
+        *latest_dlc_event = Some(backup.dlc);
+
+        for event in events {
+            self.append_event(event).await?;
+        }
+
+        Ok(true)
+    }
+}
+
+fn unity_leverage() -> Leverage {
+    Leverage::ONE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory;
+    use crate::tests::dummy_taker_with_counterparty_peer_id;
+
+    #[tokio::test]
+    async fn restored_cfd_equals_backed_up_cfd() {
+        let db = memory().await.unwrap();
+        let cfd = dummy_taker_with_counterparty_peer_id();
+        db.insert_cfd(&cfd).await.unwrap();
+
+        let event =
+            std::fs::read_to_string("./src/test_events/contract_setup_completed.json").unwrap();
+        let event = serde_json::from_str::<EventKind>(&event).unwrap();
+        db.append_event(CfdEvent {
+            timestamp: Timestamp::now(),
+            id: cfd.id(),
+            event,
+        })
+        .await
+        .unwrap();
+
+        let backup = db.load_cfd_backup(cfd.id()).await.unwrap().unwrap();
+
+        let restored_db = memory().await.unwrap();
+        assert!(restored_db
+            .restore_cfd_backup(backup.clone())
+            .await
+            .unwrap());
+        assert!(!restored_db
+            .restore_cfd_backup(backup.clone())
+            .await
+            .unwrap());
+
+        let restored = restored_db
+            .load_cfd_backup(cfd.id())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(restored.dlc, backup.dlc);
+        assert_eq!(restored.events.len(), backup.events.len());
+    }
+
+    #[tokio::test]
+    async fn no_backup_before_contract_setup() {
+        let db = memory().await.unwrap();
+        let cfd = dummy_taker_with_counterparty_peer_id();
+        db.insert_cfd(&cfd).await.unwrap();
+
+        let backup = db.load_cfd_backup(cfd.id()).await.unwrap();
+
+        assert!(backup.is_none());
+    }
+}
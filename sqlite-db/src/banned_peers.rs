@@ -0,0 +1,86 @@
+use crate::models;
+use crate::Connection;
+use anyhow::Result;
+use model::libp2p::PeerId;
+
+impl Connection {
+    /// Remember that the peer is banned
+    pub async fn insert_banned_peer(&self, peer_id: PeerId) -> Result<()> {
+        let mut conn = self.inner.acquire().await?;
+
+        let peer_id = models::PeerId::from(peer_id);
+
+        sqlx::query!(
+            r#"
+            INSERT INTO banned_peers (peer_id)
+            VALUES ($1)
+            ON CONFLICT(peer_id) DO NOTHING
+            "#,
+            peer_id,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Forget that the peer was banned
+    pub async fn delete_banned_peer(&self, peer_id: PeerId) -> Result<()> {
+        let mut conn = self.inner.acquire().await?;
+
+        let peer_id = models::PeerId::from(peer_id);
+
+        sqlx::query!(
+            r#"
+            DELETE FROM banned_peers
+            WHERE peer_id = $1
+            "#,
+            peer_id,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Load all banned peers
+    pub async fn load_banned_peers(&self) -> Result<Vec<PeerId>> {
+        let mut conn = self.inner.acquire().await?;
+
+        let peer_ids = sqlx::query!(
+            r#"
+            SELECT
+                peer_id as "peer_id: models::PeerId"
+            FROM
+                banned_peers
+            "#
+        )
+        .fetch_all(&mut *conn)
+        .await?
+        .into_iter()
+        .map(|row| row.peer_id.into())
+        .collect();
+
+        Ok(peer_ids)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::memory;
+    use model::libp2p::PeerId;
+
+    #[tokio::test]
+    async fn banned_peers_are_remembered_until_unbanned() {
+        let db = memory().await.unwrap();
+        let banned = PeerId::random();
+        let unbanned = PeerId::random();
+
+        db.insert_banned_peer(banned).await.unwrap();
+        db.insert_banned_peer(banned).await.unwrap();
+        db.insert_banned_peer(unbanned).await.unwrap();
+        db.delete_banned_peer(unbanned).await.unwrap();
+
+        assert_eq!(db.load_banned_peers().await.unwrap(), vec![banned]);
+    }
+}
@@ -45,6 +45,7 @@ use model::Price;
 use model::Role;
 use model::Settlement;
 use model::Timestamp;
+use model::TradingPair;
 use model::SETTLEMENT_INTERVAL;
 use models::Payout;
 use models::Vout;
@@ -117,9 +118,11 @@ impl Connection {
             r#"
             SELECT
                 uuid as "uuid: models::OrderId",
+                trading_pair as "trading_pair: models::TradingPair",
                 position as "position: models::Position",
                 initial_price as "initial_price: models::Price",
                 taker_leverage as "taker_leverage: models::Leverage",
+                maker_leverage as "maker_leverage: models::Leverage",
                 n_contracts as "n_contracts: models::Contracts",
                 counterparty_network_identity as "counterparty_network_identity: models::Identity",
                 counterparty_peer_id as "counterparty_peer_id: models::PeerId",
@@ -143,17 +146,25 @@ impl Connection {
         let collaborative_settlement = load_collaborative_settlement(&mut conn, id).await?;
         let cet_settlement = load_cet_settlement(&mut conn, id).await?;
         let refund_settlement = load_refund_settlement(&mut conn, id).await?;
+        let punish_settlement = load_punish_settlement(&mut conn, id).await?;
 
-        let settlement = match (collaborative_settlement, cet_settlement, refund_settlement) {
-            (Some(collaborative_settlement), None, None) => collaborative_settlement,
-            (None, Some(cet), None) => cet,
-            (None, None, Some(refund)) => refund,
+        let settlement = match (
+            collaborative_settlement,
+            cet_settlement,
+            refund_settlement,
+            punish_settlement,
+        ) {
+            (Some(collaborative_settlement), None, None, None) => collaborative_settlement,
+            (None, Some(cet), None, None) => cet,
+            (None, None, Some(refund), None) => refund,
+            (None, None, None, Some(punish)) => punish,
             _ => {
                 bail!(
                     "Closed CFD has insane combination of transactions:
                        {collaborative_settlement:?},
                        {cet_settlement:?},
-                       {refund_settlement:?}"
+                       {refund_settlement:?},
+                       {punish_settlement:?}"
                 )
             }
         };
@@ -162,9 +173,11 @@ impl Connection {
 
         let cfd = ClosedCfd {
             id,
+            trading_pair: cfd.trading_pair.into(),
             position: cfd.position.into(),
             initial_price: cfd.initial_price.into(),
             taker_leverage: cfd.taker_leverage.into(),
+            maker_leverage: cfd.maker_leverage.into(),
             n_contracts: cfd.n_contracts.into(),
             counterparty_network_identity: cfd.counterparty_network_identity.into(),
             counterparty_peer_id: cfd.counterparty_peer_id.into(),
@@ -211,9 +224,11 @@ impl Connection {
 #[derive(Debug, Clone)]
 struct ClosedCfdInputAggregate {
     id: OrderId,
+    trading_pair: TradingPair,
     position: Position,
     initial_price: Price,
     taker_leverage: Leverage,
+    maker_leverage: Leverage,
     n_contracts: Contracts,
     counterparty_network_identity: Identity,
     counterparty_peer_id: Option<PeerId>,
@@ -223,18 +238,22 @@ struct ClosedCfdInputAggregate {
     latest_dlc: Option<Dlc>,
     collaborative_settlement: Option<(bdk::bitcoin::Transaction, Script, Price)>,
     cet: Option<(bdk::bitcoin::Transaction, Price)>,
+    punish_tx: Option<bdk::bitcoin::Transaction>,
     cet_confirmed: bool,
     collaborative_settlement_confirmed: bool,
     refund_confirmed: bool,
+    punish_confirmed: bool,
 }
 
 impl ClosedCfdInputAggregate {
     fn new(cfd: Cfd) -> Self {
         let Cfd {
             id,
+            trading_pair,
             position,
             initial_price,
             taker_leverage,
+            maker_leverage,
             settlement_interval: _,
             quantity_usd,
             counterparty_network_identity,
@@ -251,9 +270,10 @@ impl ClosedCfdInputAggregate {
 
         let initial_funding_fee = {
             let (long_leverage, short_leverage) =
-                long_and_short_leverage(taker_leverage, role, position);
+                long_and_short_leverage(taker_leverage, maker_leverage, role, position);
 
             FundingFee::calculate(
+                trading_pair,
                 initial_price,
                 quantity_usd,
                 long_leverage,
@@ -266,9 +286,11 @@ impl ClosedCfdInputAggregate {
 
         Self {
             id,
+            trading_pair,
             position,
             initial_price,
             taker_leverage,
+            maker_leverage,
             n_contracts,
             counterparty_network_identity,
             counterparty_peer_id,
@@ -278,9 +300,11 @@ impl ClosedCfdInputAggregate {
             latest_dlc: None,
             collaborative_settlement: None,
             cet: None,
+            punish_tx: None,
             cet_confirmed: false,
             collaborative_settlement_confirmed: false,
             refund_confirmed: false,
+            punish_confirmed: false,
         }
     }
 
@@ -333,6 +357,12 @@ impl ClosedCfdInputAggregate {
             CollaborativeSettlementConfirmed => {
                 self.collaborative_settlement_confirmed = true;
             }
+            PunishPublished { punish_tx } => {
+                self.punish_tx = Some(punish_tx);
+            }
+            PunishConfirmed => {
+                self.punish_confirmed = true;
+            }
             CetTimelockExpiredPriorOracleAttestation => {}
             CetTimelockExpiredPostOracleAttestation { cet: _ } => {
                 // if we have an attestation we have already updated
@@ -355,6 +385,8 @@ impl ClosedCfdInputAggregate {
                 self.cet = Some((cet, price));
             }
             ManualCommit { .. } => {}
+            FeeBumped { .. } => {}
+            PolicyDecided { .. } => {}
         }
 
         Ok(self)
@@ -464,12 +496,46 @@ impl ClosedCfdInputAggregate {
         })
     }
 
+    fn punish(&self) -> Result<Settlement> {
+        let punish_tx = self.punish_tx.as_ref().context("Punish TX not set")?;
+
+        let commit_txid = punish_tx
+            .input
+            .first()
+            .context("Punish TX without input")?
+            .previous_output
+            .txid;
+
+        let own_script_pubkey = self.latest_dlc()?.script_pubkey_for(self.role);
+
+        let OutPoint { txid, vout } = punish_tx
+            .outpoint(&own_script_pubkey)
+            .context("Missing spend script in punish TX")?;
+
+        let payout = &punish_tx
+            .output
+            .get(vout as usize)
+            .with_context(|| format!("No output at vout {vout}"))?;
+        let payout = model::Payout::new(Amount::from_sat(payout.value));
+
+        let vout = model::Vout::new(vout);
+
+        Ok(Settlement::Punish {
+            commit_txid,
+            txid,
+            vout,
+            payout,
+        })
+    }
+
     fn build(self) -> Result<ClosedCfdInput> {
         let Self {
             id,
+            trading_pair,
             position,
             initial_price,
             taker_leverage,
+            maker_leverage,
             n_contracts,
             counterparty_network_identity,
             counterparty_peer_id,
@@ -485,23 +551,28 @@ impl ClosedCfdInputAggregate {
             self.collaborative_settlement_confirmed,
             self.cet_confirmed,
             self.refund_confirmed,
+            self.punish_confirmed,
         ) {
-            (true, false, false) => self.collaborative_settlement()?,
-            (false, true, false) => self.cet()?,
-            (false, false, true) => self.refund()?,
-            (collaborative_settlement, cet, refund) => bail!(
+            (true, false, false, false) => self.collaborative_settlement()?,
+            (false, true, false, false) => self.cet()?,
+            (false, false, true, false) => self.refund()?,
+            (false, false, false, true) => self.punish()?,
+            (collaborative_settlement, cet, refund, punish) => bail!(
                 "Insane transaction combination:
                     Collaborative settlement: {collaborative_settlement:?},
                     CET: {cet:?},
-                    Refund: {refund:?},"
+                    Refund: {refund:?},
+                    Punish: {punish:?},"
             ),
         };
 
         Ok(ClosedCfdInput {
             id,
+            trading_pair,
             position,
             initial_price: models::Price::from(initial_price),
             taker_leverage,
+            maker_leverage,
             n_contracts,
             counterparty_network_identity,
             counterparty_peer_id,
@@ -519,9 +590,11 @@ impl ClosedCfdInputAggregate {
 #[derive(Debug, Clone, Copy)]
 struct ClosedCfdInput {
     id: OrderId,
+    trading_pair: TradingPair,
     position: Position,
     initial_price: models::Price,
     taker_leverage: Leverage,
+    maker_leverage: Leverage,
     n_contracts: Contracts,
     counterparty_network_identity: Identity,
     counterparty_peer_id: Option<PeerId>,
@@ -541,8 +614,10 @@ async fn insert_closed_cfd(conn: &mut Transaction<'_, Sqlite>, cfd: ClosedCfdInp
         Some(peer_id) => peer_id,
     };
     let id = models::OrderId::from(cfd.id);
+    let trading_pair = models::TradingPair::from(cfd.trading_pair);
     let role = models::Role::from(cfd.role);
     let taker_leverage = models::Leverage::from(cfd.taker_leverage);
+    let maker_leverage = models::Leverage::from(cfd.maker_leverage);
     let position = models::Position::from(cfd.position);
     let counterparty_network_identity = models::Identity::from(cfd.counterparty_network_identity);
     let fees = models::Fees::from(cfd.fees);
@@ -559,6 +634,7 @@ async fn insert_closed_cfd(conn: &mut Transaction<'_, Sqlite>, cfd: ClosedCfdInp
             position,
             initial_price,
             taker_leverage,
+            maker_leverage,
             n_contracts,
             counterparty_network_identity,
             counterparty_peer_id,
@@ -566,14 +642,16 @@ async fn insert_closed_cfd(conn: &mut Transaction<'_, Sqlite>, cfd: ClosedCfdInp
             fees,
             expiry_timestamp,
             lock_txid,
-            lock_dlc_vout
+            lock_dlc_vout,
+            trading_pair
         )
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
         "#,
         id,
         position,
         cfd.initial_price,
         taker_leverage,
+        maker_leverage,
         contracts,
         counterparty_network_identity,
         counterparty_peer_id,
@@ -582,6 +660,7 @@ async fn insert_closed_cfd(conn: &mut Transaction<'_, Sqlite>, cfd: ClosedCfdInp
         expiry_timestamp,
         lock_txid,
         dlc_vout,
+        trading_pair,
     )
     .execute(&mut *conn)
     .await?;
@@ -649,6 +728,22 @@ async fn insert_settlement(
             )
             .await?
         }
+        Settlement::Punish {
+            commit_txid,
+            txid,
+            vout,
+            payout,
+        } => {
+            insert_punish_settlement(
+                conn,
+                id,
+                commit_txid.into(),
+                txid.into(),
+                vout.into(),
+                payout.into(),
+            )
+            .await?
+        }
     };
 
     Ok(())
@@ -783,6 +878,48 @@ async fn insert_refund_settlement(
     Ok(())
 }
 
+async fn insert_punish_settlement(
+    conn: &mut Transaction<'_, Sqlite>,
+    id: OrderId,
+    commit_txid: Txid,
+    txid: Txid,
+    vout: Vout,
+    payout: Payout,
+) -> Result<()> {
+    insert_commit_tx(conn, id, commit_txid).await?;
+
+    let id = models::OrderId::from(id);
+
+    let query_result = sqlx::query!(
+        r#"
+        INSERT INTO closed_punish_txs
+        (
+            cfd_id,
+            txid,
+            vout,
+            payout
+        )
+        VALUES
+        (
+            (SELECT id FROM closed_cfds WHERE closed_cfds.uuid = $1),
+            $2, $3, $4
+        )
+        "#,
+        id,
+        txid,
+        vout,
+        payout,
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    if query_result.rows_affected() != 1 {
+        anyhow::bail!("failed to insert into closed_punish_txs");
+    }
+
+    Ok(())
+}
+
 async fn insert_commit_tx(
     conn: &mut Transaction<'_, Sqlite>,
     id: OrderId,
@@ -908,6 +1045,37 @@ async fn load_refund_settlement(
     Ok(row.map(|settlement| settlement.into()))
 }
 
+async fn load_punish_settlement(
+    conn: &mut PoolConnection<Sqlite>,
+    id: OrderId,
+) -> Result<Option<Settlement>> {
+    let id = models::OrderId::from(id);
+
+    let row = sqlx::query_as!(
+        models::Settlement::Punish,
+        r#"
+        SELECT
+            closed_commit_txs.txid as "commit_txid!: models::Txid",
+            closed_punish_txs.txid as "txid: models::Txid",
+            closed_punish_txs.vout as "vout: models::Vout",
+            closed_punish_txs.payout as "payout: models::Payout"
+        FROM
+            closed_punish_txs
+        JOIN
+            closed_commit_txs on closed_commit_txs.cfd_id = closed_punish_txs.cfd_id
+        JOIN
+            closed_cfds on closed_cfds.id = closed_punish_txs.cfd_id
+        WHERE
+            closed_cfds.uuid = $1
+        "#,
+        id
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    Ok(row.map(|settlement| settlement.into()))
+}
+
 async fn insert_event_log(
     conn: &mut Transaction<'_, Sqlite>,
     id: OrderId,
@@ -1237,6 +1405,35 @@ mod tests {
         assert_eq!(inserted, loaded);
     }
 
+    #[tokio::test]
+    async fn insert_punish_tx_roundtrip() {
+        let db = memory().await.unwrap();
+
+        let mut conn = db.inner.acquire().await.unwrap();
+        let mut db_tx = conn.begin().await.unwrap();
+
+        let id = OrderId::default();
+
+        insert_dummy_closed_cfd(&mut db_tx, id).await.unwrap();
+
+        let inserted = Settlement::Punish {
+            commit_txid: bdk::bitcoin::Txid::default(),
+            txid: bdk::bitcoin::Txid::default(),
+            vout: Vout::new(0),
+            payout: Payout::new(Amount::ONE_BTC),
+        };
+
+        insert_settlement(&mut db_tx, id, inserted).await.unwrap();
+        db_tx.commit().await.unwrap();
+
+        let loaded = load_punish_settlement(&mut conn, id)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(inserted, loaded);
+    }
+
     #[tokio::test]
     async fn given_confirmed_settlement_when_move_cfds_to_closed_table_then_creation_timestamp_is_that_of_first_event(
     ) {
@@ -1275,9 +1472,11 @@ mod tests {
     ) -> Result<()> {
         let cfd = ClosedCfdInput {
             id,
+            trading_pair: TradingPair::BtcUsd,
             position: Position::Long,
             initial_price: models::Price::from(Decimal::ONE),
             taker_leverage: Leverage::TWO,
+            maker_leverage: Leverage::ONE,
             n_contracts: Contracts::new(100),
             counterparty_network_identity: dummy_identity(),
             counterparty_peer_id: Some(PeerId::random()),
@@ -1307,9 +1506,11 @@ mod tests {
         let order_id = OrderId::default();
         let cfd = Cfd::new(
             order_id,
+            TradingPair::BtcUsd,
             Position::Long,
             Price::new(dec!(41_772.8325)).unwrap(),
             Leverage::TWO,
+            Leverage::ONE,
             Duration::hours(24),
             Role::Taker,
             Usd::new(dec!(100)),
@@ -1,5 +1,9 @@
+use crate::models;
+use crate::Connection;
+use anyhow::Result;
 use model::CfdEvent;
 use model::EventKind;
+use model::OrderId;
 
 pub(super) struct EventLog(pub Vec<EventLogEntry>);
 
@@ -15,7 +19,8 @@ impl EventLog {
     }
 }
 
-pub(super) struct EventLogEntry {
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventLogEntry {
     pub name: String,
     pub created_at: i64,
 }
@@ -28,3 +33,126 @@ impl From<&CfdEvent> for EventLogEntry {
         Self { name, created_at }
     }
 }
+
+impl Connection {
+    /// Load the names and times of all events of a CFD, oldest first
+    ///
+    /// Works for open CFDs as well as CFDs that were already moved to the closed or failed
+    /// tables. Returns an empty list if the CFD is not known.
+    pub async fn load_event_log(&self, id: OrderId) -> Result<Vec<EventLogEntry>> {
+        let mut conn = self.inner.acquire().await?;
+
+        let id = models::OrderId::from(id);
+
+        let open = sqlx::query!(
+            r#"
+            SELECT
+                events.name,
+                events.created_at as "created_at: models::Timestamp"
+            FROM
+                events
+            JOIN
+                cfds on cfds.id = events.cfd_id
+            WHERE
+                cfds.uuid = $1
+            ORDER BY
+                events.id
+            "#,
+            id
+        )
+        .fetch_all(&mut *conn)
+        .await?
+        .into_iter()
+        .map(|row| EventLogEntry {
+            name: row.name,
+            created_at: model::Timestamp::from(row.created_at).seconds(),
+        });
+
+        let closed = sqlx::query!(
+            r#"
+            SELECT
+                event_log.name as "name!",
+                event_log.created_at as "created_at!: i64"
+            FROM
+                event_log
+            JOIN
+                closed_cfds on closed_cfds.id = event_log.cfd_id
+            WHERE
+                closed_cfds.uuid = $1
+            ORDER BY
+                event_log.id
+            "#,
+            id
+        )
+        .fetch_all(&mut *conn)
+        .await?
+        .into_iter()
+        .map(|row| EventLogEntry {
+            name: row.name,
+            created_at: row.created_at,
+        });
+
+        let failed = sqlx::query!(
+            r#"
+            SELECT
+                event_log_failed.name as "name!",
+                event_log_failed.created_at as "created_at!: i64"
+            FROM
+                event_log_failed
+            JOIN
+                failed_cfds on failed_cfds.id = event_log_failed.cfd_id
+            WHERE
+                failed_cfds.uuid = $1
+            ORDER BY
+                event_log_failed.id
+            "#,
+            id
+        )
+        .fetch_all(&mut *conn)
+        .await?
+        .into_iter()
+        .map(|row| EventLogEntry {
+            name: row.name,
+            created_at: row.created_at,
+        });
+
+        Ok(open.chain(closed).chain(failed).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory;
+    use crate::tests::dummy_cfd;
+    use crate::tests::order_rejected;
+
+    #[tokio::test]
+    async fn given_rejected_cfd_when_moved_to_failed_table_then_event_log_is_retained() {
+        let db = memory().await.unwrap();
+
+        let cfd = dummy_cfd();
+        let order_id = cfd.id();
+        let rejected = order_rejected(&cfd);
+
+        db.insert_cfd(&cfd).await.unwrap();
+        db.append_event(rejected.clone()).await.unwrap();
+
+        let expected = vec![EventLogEntry::from(&rejected)];
+
+        assert_eq!(db.load_event_log(order_id).await.unwrap(), expected);
+
+        db.move_to_failed_cfds().await.unwrap();
+
+        assert_eq!(db.load_event_log(order_id).await.unwrap(), expected);
+    }
+
+    #[tokio::test]
+    async fn given_unknown_cfd_then_event_log_is_empty() {
+        let db = memory().await.unwrap();
+
+        let event_log = db.load_event_log(OrderId::default()).await.unwrap();
+
+        assert!(event_log.is_empty());
+    }
+}
@@ -95,9 +95,11 @@ impl Connection {
             r#"
             SELECT
                 uuid as "id: models::OrderId",
+                trading_pair as "trading_pair: models::TradingPair",
                 position as "position: models::Position",
                 initial_price as "initial_price: models::Price",
                 taker_leverage as "taker_leverage: models::Leverage",
+                maker_leverage as "maker_leverage: models::Leverage",
                 n_contracts as "n_contracts: models::Contracts",
                 counterparty_network_identity as "counterparty_network_identity: models::Identity",
                 counterparty_peer_id as "counterparty_peer_id: models::PeerId",
@@ -118,9 +120,11 @@ impl Connection {
 
         let cfd = FailedCfd {
             id,
+            trading_pair: cfd.trading_pair.into(),
             position: cfd.position.into(),
             initial_price: cfd.initial_price.into(),
             taker_leverage: cfd.taker_leverage.into(),
+            maker_leverage: cfd.maker_leverage.into(),
             n_contracts: cfd.n_contracts.into(),
             counterparty_network_identity: cfd.counterparty_network_identity.into(),
             counterparty_peer_id: cfd.counterparty_peer_id.into(),
@@ -174,10 +178,15 @@ async fn insert_failed_cfd(
     let n_contracts = models::Contracts::from(n_contracts);
 
     let fees = {
-        let (long_leverage, short_leverage) =
-            long_and_short_leverage(cfd.taker_leverage, cfd.role, cfd.position);
+        let (long_leverage, short_leverage) = long_and_short_leverage(
+            cfd.taker_leverage,
+            cfd.maker_leverage,
+            cfd.role,
+            cfd.position,
+        );
 
         let initial_funding_fee = FundingFee::calculate(
+            cfd.trading_pair,
             cfd.initial_price,
             cfd.quantity_usd,
             long_leverage,
@@ -201,9 +210,11 @@ async fn insert_failed_cfd(
     };
 
     let id = models::OrderId::from(cfd.id);
+    let trading_pair = models::TradingPair::from(cfd.trading_pair);
     let role = models::Role::from(cfd.role);
     let initial_price = models::Price::from(cfd.initial_price);
     let taker_leverage = models::Leverage::from(cfd.taker_leverage);
+    let maker_leverage = models::Leverage::from(cfd.maker_leverage);
     let position = models::Position::from(cfd.position);
     let counterparty_network_identity = models::Identity::from(cfd.counterparty_network_identity);
     let counterparty_peer_id = models::PeerId::from(counterparty_peer_id);
@@ -216,25 +227,29 @@ async fn insert_failed_cfd(
             position,
             initial_price,
             taker_leverage,
+            maker_leverage,
             n_contracts,
             counterparty_network_identity,
             counterparty_peer_id,
             role,
             fees,
-            kind
+            kind,
+            trading_pair
         )
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
         "#,
         id,
         position,
         initial_price,
         taker_leverage,
+        maker_leverage,
         n_contracts,
         counterparty_network_identity,
         counterparty_peer_id,
         role,
         fees,
         kind,
+        trading_pair,
     )
     .execute(&mut *conn)
     .await?;
@@ -7,9 +7,11 @@ impl crate::CfdAggregate for model::Cfd {
         _: Self::CtorArgs,
         crate::Cfd {
             id,
+            trading_pair,
             position,
             initial_price,
-            taker_leverage: leverage,
+            taker_leverage,
+            maker_leverage,
             settlement_interval,
             counterparty_network_identity,
             counterparty_peer_id,
@@ -22,9 +24,11 @@ impl crate::CfdAggregate for model::Cfd {
     ) -> Self {
         model::Cfd::new(
             id,
+            trading_pair,
             position,
             initial_price,
-            leverage,
+            taker_leverage,
+            maker_leverage,
             settlement_interval,
             role,
             quantity_usd,
@@ -21,6 +21,7 @@ use model::OrderId;
 use model::Position;
 use model::Price;
 use model::Role;
+use model::TradingPair;
 use model::TxFeeRate;
 use model::Usd;
 use sqlx::migrate::MigrateError;
@@ -40,11 +41,18 @@ pub use closed::*;
 pub use failed::*;
 use model::EventKind::RolloverCompleted;
 
+pub mod audit;
+pub mod backup;
+pub mod banned_peers;
 pub mod closed;
 pub mod event_log;
 pub mod failed;
 mod impls;
 mod models;
+pub mod order_requests;
+pub mod position_triggers;
+pub mod recovered_contracts;
+pub mod risk_limits;
 mod rollover;
 pub mod time_to_first_position;
 
@@ -129,6 +137,18 @@ pub fn connect(
     .boxed()
 }
 
+/// Opens the SQLite database at the given path without modifying it.
+///
+/// No migrations are applied, the database is expected to be up to date.
+pub async fn connect_read_only(path: PathBuf) -> Result<Connection> {
+    let pool =
+        SqlitePool::connect_with(SqliteConnectOptions::new().read_only(true).filename(&path))
+            .await
+            .with_context(|| format!("Failed to open database at {}", path.display()))?;
+
+    Ok(Connection::new(pool))
+}
+
 pub async fn memory() -> Result<Connection> {
     // Note: Every :memory: database is distinct from every other. So, opening two database
     // connections each with the filename ":memory:" will create two independent in-memory
@@ -154,11 +174,13 @@ impl Connection {
         let mut conn = self.inner.acquire().await?;
 
         let id = models::OrderId::from(cfd.id());
+        let trading_pair = models::TradingPair::from(cfd.trading_pair());
 
         let role = models::Role::from(cfd.role());
         let quantity = models::Usd::from(cfd.quantity());
         let initial_price = models::Price::from(cfd.initial_price());
         let leverage = models::Leverage::from(cfd.taker_leverage());
+        let maker_leverage = models::Leverage::from(cfd.maker_leverage());
 
         let position = models::Position::from(cfd.position());
         let counterparty_network_identity =
@@ -182,8 +204,10 @@ impl Connection {
             role,
             opening_fee,
             initial_funding_rate,
-            initial_tx_fee_rate
-        ) values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)"#,
+            initial_tx_fee_rate,
+            trading_pair,
+            maker_leverage
+        ) values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)"#,
         )
         .bind(&id)
         .bind(&position)
@@ -204,6 +228,8 @@ impl Connection {
         .bind(&opening_fee)
         .bind(&initial_funding_rate)
         .bind(&tx_fee_rate)
+        .bind(&trading_pair)
+        .bind(&maker_leverage)
         .execute(&mut conn)
         .await?;
 
@@ -429,13 +455,15 @@ impl Connection {
                 (
                     events.name = $1 or
                     events.name = $2 or
-                    events.name= $3
+                    events.name= $3 or
+                    events.name = $4
                 )
             )
             "#,
             EventKind::COLLABORATIVE_SETTLEMENT_CONFIRMED,
             EventKind::CET_CONFIRMED,
             EventKind::REFUND_CONFIRMED,
+            EventKind::PUNISH_CONFIRMED,
         )
         .fetch_all(&mut *conn)
         .await?
@@ -483,9 +511,11 @@ impl Connection {
 #[derive(Clone, Copy)]
 pub struct Cfd {
     pub id: OrderId,
+    pub trading_pair: TradingPair,
     pub position: Position,
     pub initial_price: Price,
     pub taker_leverage: Leverage,
+    pub maker_leverage: Leverage,
     pub settlement_interval: Duration,
     pub quantity_usd: Usd,
     pub counterparty_network_identity: Identity,
@@ -542,6 +572,7 @@ async fn load_cfd_row(conn: &mut Transaction<'_, Sqlite>, id: OrderId) -> Result
                 position as "position: models::Position",
                 initial_price as "initial_price: models::Price",
                 leverage as "leverage: models::Leverage",
+                maker_leverage as "maker_leverage: models::Leverage",
                 settlement_time_interval_hours,
                 quantity_usd as "quantity_usd: models::Usd",
                 counterparty_network_identity as "counterparty_network_identity: models::Identity",
@@ -549,7 +580,8 @@ async fn load_cfd_row(conn: &mut Transaction<'_, Sqlite>, id: OrderId) -> Result
                 role as "role: models::Role",
                 opening_fee as "opening_fee: models::OpeningFee",
                 initial_funding_rate as "initial_funding_rate: models::FundingRate",
-                initial_tx_fee_rate as "initial_tx_fee_rate: models::TxFeeRate"
+                initial_tx_fee_rate as "initial_tx_fee_rate: models::TxFeeRate",
+                trading_pair as "trading_pair: models::TradingPair"
             from
                 cfds
             where
@@ -573,9 +605,11 @@ async fn load_cfd_row(conn: &mut Transaction<'_, Sqlite>, id: OrderId) -> Result
 
     Ok(Cfd {
         id: cfd_row.uuid.into(),
+        trading_pair: cfd_row.trading_pair.into(),
         position: cfd_row.position.into(),
         initial_price: cfd_row.initial_price.into(),
         taker_leverage: cfd_row.leverage.into(),
+        maker_leverage: cfd_row.maker_leverage.into(),
         settlement_interval: Duration::hours(cfd_row.settlement_time_interval_hours),
         quantity_usd: cfd_row.quantity_usd.into(),
         counterparty_network_identity,
@@ -757,9 +791,11 @@ mod tests {
 
         let super::Cfd {
             id,
+            trading_pair,
             position,
             initial_price,
             taker_leverage: leverage,
+            maker_leverage,
             settlement_interval,
             quantity_usd,
             counterparty_network_identity,
@@ -773,9 +809,11 @@ mod tests {
         db_tx.commit().await.unwrap();
 
         assert_eq!(cfd.id(), id);
+        assert_eq!(cfd.trading_pair(), trading_pair);
         assert_eq!(cfd.position(), position);
         assert_eq!(cfd.initial_price(), initial_price);
         assert_eq!(cfd.taker_leverage(), leverage);
+        assert_eq!(cfd.maker_leverage(), maker_leverage);
         assert_eq!(cfd.settlement_time_interval_hours(), settlement_interval);
         assert_eq!(cfd.quantity(), quantity_usd);
         assert_eq!(
@@ -923,9 +961,11 @@ mod tests {
     pub fn dummy_taker_with_counterparty_peer_id() -> Cfd {
         Cfd::new(
             OrderId::default(),
+            TradingPair::BtcUsd,
             Position::Long,
             Price::new(dec!(60_000)).unwrap(),
             Leverage::TWO,
+            Leverage::ONE,
             Duration::hours(24),
             Role::Taker,
             Usd::new(dec!(1_000)),
@@ -942,9 +982,11 @@ mod tests {
     pub fn dummy_taker_with_legacy_identity(identity: &str) -> Cfd {
         Cfd::new(
             OrderId::default(),
+            TradingPair::BtcUsd,
             Position::Long,
             Price::new(dec!(60_000)).unwrap(),
             Leverage::TWO,
+            Leverage::ONE,
             Duration::hours(24),
             Role::Taker,
             Usd::new(dec!(1_000)),
@@ -162,6 +162,38 @@ impl From<bitcoin::util::key::PublicKey> for PublicKey {
 
 impl_sqlx_type_display_from_str!(PublicKey);
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct XOnlyPublicKey(bitcoin::XOnlyPublicKey);
+
+impl fmt::Display for XOnlyPublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl FromStr for XOnlyPublicKey {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let pk = bitcoin::XOnlyPublicKey::from_str(s)?;
+        Ok(Self(pk))
+    }
+}
+
+impl From<XOnlyPublicKey> for bitcoin::XOnlyPublicKey {
+    fn from(pk: XOnlyPublicKey) -> Self {
+        pk.0
+    }
+}
+
+impl From<bitcoin::XOnlyPublicKey> for XOnlyPublicKey {
+    fn from(pk: bitcoin::XOnlyPublicKey) -> Self {
+        Self(pk)
+    }
+}
+
+impl_sqlx_type_display_from_str!(XOnlyPublicKey);
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct AdaptorSignature(secp256k1_zkp::EcdsaAdaptorSignature);
 
@@ -348,6 +380,63 @@ impl From<Position> for model::Position {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, sqlx::Type)]
+pub enum Trigger {
+    StopLoss,
+    TakeProfit,
+}
+
+impl From<model::Trigger> for Trigger {
+    fn from(trigger: model::Trigger) -> Self {
+        match trigger {
+            model::Trigger::StopLoss => Trigger::StopLoss,
+            model::Trigger::TakeProfit => Trigger::TakeProfit,
+        }
+    }
+}
+
+impl From<Trigger> for model::Trigger {
+    fn from(trigger: Trigger) -> Self {
+        match trigger {
+            Trigger::StopLoss => model::Trigger::StopLoss,
+            Trigger::TakeProfit => model::Trigger::TakeProfit,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, sqlx::Type)]
+pub enum OrderRequestState {
+    Pending,
+    Placed,
+    Failed,
+}
+
+#[derive(
+    Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord, sqlx::Type,
+)]
+pub enum TradingPair {
+    BtcUsd,
+    EthUsd,
+}
+
+impl From<model::TradingPair> for TradingPair {
+    fn from(trading_pair: model::TradingPair) -> Self {
+        match trading_pair {
+            model::TradingPair::BtcUsd => TradingPair::BtcUsd,
+            model::TradingPair::EthUsd => TradingPair::EthUsd,
+        }
+    }
+}
+
+impl From<TradingPair> for model::TradingPair {
+    fn from(trading_pair: TradingPair) -> Self {
+        match trading_pair {
+            TradingPair::BtcUsd => model::TradingPair::BtcUsd,
+            TradingPair::EthUsd => model::TradingPair::EthUsd,
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct Identity(x25519_dalek::PublicKey);
 
@@ -745,13 +834,15 @@ pub struct BitMexPriceEventId {
     /// The timestamp this price event refers to.
     timestamp: OffsetDateTime,
     digits: usize,
+    trading_pair: TradingPair,
 }
 
 impl fmt::Display for BitMexPriceEventId {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "/x/BitMEX/BXBT/{}.price?n={}",
+            "/x/BitMEX/{}/{}.price?n={}",
+            model::TradingPair::from(self.trading_pair).oracle_index(),
             self.timestamp
                 .format(&EVENT_TIME_FORMAT)
                 .expect("should always format and we can't return an error here"),
@@ -764,7 +855,10 @@ impl FromStr for BitMexPriceEventId {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let remaining = s.trim_start_matches("/x/BitMEX/BXBT/");
+        let remaining = s.trim_start_matches("/x/BitMEX/");
+        let (index, remaining) = remaining
+            .split_once('/')
+            .with_context(|| format!("Failed to parse index from {s}"))?;
         let (timestamp, rest) = remaining.split_at(19);
         let digits = rest.trim_start_matches(".price?n=");
 
@@ -773,6 +867,7 @@ impl FromStr for BitMexPriceEventId {
                 .with_context(|| format!("Failed to parse {timestamp} as timestamp"))?
                 .assume_utc(),
             digits: digits.parse()?,
+            trading_pair: model::TradingPair::from_oracle_index(index)?.into(),
         })
     }
 }
@@ -782,6 +877,7 @@ impl From<model::olivia::BitMexPriceEventId> for BitMexPriceEventId {
         Self {
             timestamp: id.timestamp(),
             digits: id.digits(),
+            trading_pair: id.trading_pair().into(),
         }
     }
 }
@@ -789,6 +885,7 @@ impl From<model::olivia::BitMexPriceEventId> for BitMexPriceEventId {
 impl From<BitMexPriceEventId> for model::olivia::BitMexPriceEventId {
     fn from(id: BitMexPriceEventId) -> Self {
         model::olivia::BitMexPriceEventId::new(id.timestamp, id.digits)
+            .with_trading_pair(id.trading_pair.into())
     }
 }
 
@@ -858,6 +955,12 @@ pub enum Settlement {
         vout: Vout,
         payout: Payout,
     },
+    Punish {
+        commit_txid: Txid,
+        txid: Txid,
+        vout: Vout,
+        payout: Payout,
+    },
 }
 
 impl From<Settlement> for model::Settlement {
@@ -898,6 +1001,17 @@ impl From<Settlement> for model::Settlement {
                 vout: vout.into(),
                 payout: payout.into(),
             },
+            Settlement::Punish {
+                commit_txid,
+                txid,
+                vout,
+                payout,
+            } => model::Settlement::Punish {
+                commit_txid: commit_txid.into(),
+                txid: txid.into(),
+                vout: vout.into(),
+                payout: payout.into(),
+            },
         }
     }
 }
@@ -0,0 +1,265 @@
+use crate::models;
+use crate::Connection;
+use anyhow::Result;
+use model::Leverage;
+use model::OrderId;
+use model::Timestamp;
+use model::Usd;
+
+/// An order placed on behalf of a client, identified by a key chosen by the client
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderRequest {
+    pub idempotency_key: String,
+    pub order_id: OrderId,
+    pub quantity: Usd,
+    pub leverage: Leverage,
+    pub timestamp: Timestamp,
+    pub state: OrderRequestState,
+}
+
+/// How far placing the order of an [`OrderRequest`] got
+#[derive(Debug, Clone, PartialEq)]
+pub enum OrderRequestState {
+    /// The offer is being taken
+    Pending,
+    /// The offer was taken
+    Placed,
+    /// Taking the offer failed for the given reason
+    Failed { reason: String },
+}
+
+impl OrderRequestState {
+    fn to_columns(&self) -> (models::OrderRequestState, Option<&str>) {
+        match self {
+            OrderRequestState::Pending => (models::OrderRequestState::Pending, None),
+            OrderRequestState::Placed => (models::OrderRequestState::Placed, None),
+            OrderRequestState::Failed { reason } => {
+                (models::OrderRequestState::Failed, Some(reason.as_str()))
+            }
+        }
+    }
+
+    fn from_columns(state: models::OrderRequestState, failure: Option<String>) -> Self {
+        match state {
+            models::OrderRequestState::Pending => OrderRequestState::Pending,
+            models::OrderRequestState::Placed => OrderRequestState::Placed,
+            models::OrderRequestState::Failed => OrderRequestState::Failed {
+                reason: failure.unwrap_or_default(),
+            },
+        }
+    }
+}
+
+impl Connection {
+    /// Record an order request unless there already is one with the same idempotency key
+    ///
+    /// Returns `false` if the idempotency key was already used.
+    pub async fn try_insert_order_request(&self, request: &OrderRequest) -> Result<bool> {
+        let mut conn = self.inner.acquire().await?;
+
+        let order_id = models::OrderId::from(request.order_id);
+        let quantity = models::Usd::from(request.quantity);
+        let leverage = models::Leverage::from(request.leverage);
+        let timestamp = models::Timestamp::from(request.timestamp);
+        let (state, failure) = request.state.to_columns();
+
+        let query_result = sqlx::query!(
+            r#"
+            INSERT OR IGNORE INTO order_requests
+            (
+                idempotency_key,
+                order_id,
+                quantity,
+                leverage,
+                created_at,
+                state,
+                failure
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+            request.idempotency_key,
+            order_id,
+            quantity,
+            leverage,
+            timestamp,
+            state,
+            failure,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(query_result.rows_affected() == 1)
+    }
+
+    pub async fn load_order_request(&self, idempotency_key: &str) -> Result<Option<OrderRequest>> {
+        let mut conn = self.inner.acquire().await?;
+
+        let row = sqlx::query!(
+            r#"
+            SELECT
+                idempotency_key,
+                order_id as "order_id: models::OrderId",
+                quantity as "quantity: models::Usd",
+                leverage as "leverage: models::Leverage",
+                created_at as "created_at: models::Timestamp",
+                state as "state: models::OrderRequestState",
+                failure
+            FROM
+                order_requests
+            WHERE
+                idempotency_key = $1
+            "#,
+            idempotency_key
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        let request = row.map(|row| OrderRequest {
+            idempotency_key: row.idempotency_key,
+            order_id: row.order_id.into(),
+            quantity: row.quantity.into(),
+            leverage: row.leverage.into(),
+            timestamp: row.created_at.into(),
+            state: OrderRequestState::from_columns(row.state, row.failure),
+        });
+
+        Ok(request)
+    }
+
+    /// Record how far placing the order of a request got
+    pub async fn update_order_request_state(
+        &self,
+        idempotency_key: &str,
+        state: OrderRequestState,
+    ) -> Result<()> {
+        let mut conn = self.inner.acquire().await?;
+
+        let (state, failure) = state.to_columns();
+
+        sqlx::query!(
+            r#"
+            UPDATE order_requests
+            SET state = $2, failure = $3
+            WHERE idempotency_key = $1
+            "#,
+            idempotency_key,
+            state,
+            failure,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Mark all pending order requests as failed
+    ///
+    /// Meant to be called on startup: requests that are still pending were interrupted and will
+    /// never complete.
+    pub async fn fail_pending_order_requests(&self, reason: &str) -> Result<()> {
+        let mut conn = self.inner.acquire().await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE order_requests
+            SET state = 'Failed', failure = $1
+            WHERE state = 'Pending'
+            "#,
+            reason,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Forget an order request, allowing the client to retry with the same idempotency key
+    pub async fn delete_order_request(&self, idempotency_key: &str) -> Result<()> {
+        let mut conn = self.inner.acquire().await?;
+
+        sqlx::query!(
+            r#"
+            DELETE FROM order_requests
+            WHERE idempotency_key = $1
+            "#,
+            idempotency_key
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory;
+    use rust_decimal_macros::dec;
+
+    #[tokio::test]
+    async fn given_used_idempotency_key_then_request_is_not_inserted_again() {
+        let db = memory().await.unwrap();
+
+        let request = OrderRequest {
+            idempotency_key: "bot-order-1".to_string(),
+            order_id: OrderId::default(),
+            quantity: Usd::new(dec!(100)),
+            leverage: Leverage::TWO,
+            timestamp: Timestamp::new(1),
+            state: OrderRequestState::Pending,
+        };
+        let retry = OrderRequest {
+            order_id: OrderId::default(),
+            timestamp: Timestamp::new(2),
+            ..request.clone()
+        };
+
+        assert!(db.try_insert_order_request(&request).await.unwrap());
+        assert!(!db.try_insert_order_request(&retry).await.unwrap());
+
+        let loaded = db.load_order_request("bot-order-1").await.unwrap();
+        assert_eq!(loaded, Some(request));
+
+        db.delete_order_request("bot-order-1").await.unwrap();
+
+        assert!(db.try_insert_order_request(&retry).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn given_pending_request_when_failing_pending_requests_then_only_pending_ones_fail() {
+        let db = memory().await.unwrap();
+
+        let pending = OrderRequest {
+            idempotency_key: "bot-order-1".to_string(),
+            order_id: OrderId::default(),
+            quantity: Usd::new(dec!(100)),
+            leverage: Leverage::TWO,
+            timestamp: Timestamp::new(1),
+            state: OrderRequestState::Pending,
+        };
+        let placed = OrderRequest {
+            idempotency_key: "bot-order-2".to_string(),
+            order_id: OrderId::default(),
+            ..pending.clone()
+        };
+
+        db.try_insert_order_request(&pending).await.unwrap();
+        db.try_insert_order_request(&placed).await.unwrap();
+        db.update_order_request_state("bot-order-2", OrderRequestState::Placed)
+            .await
+            .unwrap();
+
+        db.fail_pending_order_requests("Interrupted").await.unwrap();
+
+        let pending = db.load_order_request("bot-order-1").await.unwrap().unwrap();
+        let placed = db.load_order_request("bot-order-2").await.unwrap().unwrap();
+        assert_eq!(
+            pending.state,
+            OrderRequestState::Failed {
+                reason: "Interrupted".to_string()
+            }
+        );
+        assert_eq!(placed.state, OrderRequestState::Placed);
+    }
+}
@@ -0,0 +1,268 @@
+use crate::models;
+use crate::Connection;
+use anyhow::Result;
+use model::OrderId;
+use model::PositionTriggers;
+use model::Trigger;
+
+/// Stop-loss and take-profit triggers of a position, alongside their progress
+#[derive(Debug, Clone, PartialEq)]
+pub struct PositionTriggersState {
+    pub order_id: OrderId,
+    pub triggers: PositionTriggers,
+    /// The trigger that fired, if any.
+    ///
+    /// A fired trigger is not evaluated again.
+    pub fired: Option<Trigger>,
+    /// Why closing the position failed after a trigger fired.
+    pub failure: Option<String>,
+}
+
+impl Connection {
+    /// Set the triggers for the position identified by `order_id`
+    ///
+    /// Replaces any triggers set before and resets their progress.
+    pub async fn upsert_position_triggers(
+        &self,
+        order_id: OrderId,
+        triggers: PositionTriggers,
+    ) -> Result<()> {
+        let mut conn = self.inner.acquire().await?;
+
+        let order_id = models::OrderId::from(order_id);
+        let stop_loss = triggers.stop_loss.map(models::Price::from);
+        let take_profit = triggers.take_profit.map(models::Price::from);
+
+        sqlx::query!(
+            r#"
+            INSERT INTO position_triggers
+            (
+                order_id,
+                stop_loss,
+                take_profit
+            )
+            VALUES ($1, $2, $3)
+            ON CONFLICT(order_id) DO UPDATE SET
+                stop_loss = $2,
+                take_profit = $3,
+                fired = NULL,
+                failure = NULL
+            "#,
+            order_id,
+            stop_loss,
+            take_profit,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn load_position_triggers(
+        &self,
+        order_id: OrderId,
+    ) -> Result<Option<PositionTriggersState>> {
+        let mut conn = self.inner.acquire().await?;
+
+        let order_id = models::OrderId::from(order_id);
+
+        let row = sqlx::query!(
+            r#"
+            SELECT
+                order_id as "order_id: models::OrderId",
+                stop_loss as "stop_loss: models::Price",
+                take_profit as "take_profit: models::Price",
+                fired as "fired: models::Trigger",
+                failure
+            FROM
+                position_triggers
+            WHERE
+                order_id = $1
+            "#,
+            order_id
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        let state = row.map(|row| PositionTriggersState {
+            order_id: row.order_id.into(),
+            triggers: PositionTriggers {
+                stop_loss: row.stop_loss.map(Into::into),
+                take_profit: row.take_profit.map(Into::into),
+            },
+            fired: row.fired.map(Into::into),
+            failure: row.failure,
+        });
+
+        Ok(state)
+    }
+
+    pub async fn load_all_position_triggers(&self) -> Result<Vec<PositionTriggersState>> {
+        let mut conn = self.inner.acquire().await?;
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                order_id as "order_id: models::OrderId",
+                stop_loss as "stop_loss: models::Price",
+                take_profit as "take_profit: models::Price",
+                fired as "fired: models::Trigger",
+                failure
+            FROM
+                position_triggers
+            ORDER BY
+                id
+            "#
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let states = rows
+            .into_iter()
+            .map(|row| PositionTriggersState {
+                order_id: row.order_id.into(),
+                triggers: PositionTriggers {
+                    stop_loss: row.stop_loss.map(Into::into),
+                    take_profit: row.take_profit.map(Into::into),
+                },
+                fired: row.fired.map(Into::into),
+                failure: row.failure,
+            })
+            .collect();
+
+        Ok(states)
+    }
+
+    /// Record that `trigger` fired for the position identified by `order_id`
+    pub async fn mark_position_trigger_fired(
+        &self,
+        order_id: OrderId,
+        trigger: Trigger,
+    ) -> Result<()> {
+        let mut conn = self.inner.acquire().await?;
+
+        let order_id = models::OrderId::from(order_id);
+        let trigger = models::Trigger::from(trigger);
+
+        sqlx::query!(
+            r#"
+            UPDATE position_triggers
+            SET fired = $2
+            WHERE order_id = $1
+            "#,
+            order_id,
+            trigger,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Record that closing the position identified by `order_id` failed after a trigger fired
+    pub async fn mark_position_trigger_failed(
+        &self,
+        order_id: OrderId,
+        reason: String,
+    ) -> Result<()> {
+        let mut conn = self.inner.acquire().await?;
+
+        let order_id = models::OrderId::from(order_id);
+
+        sqlx::query!(
+            r#"
+            UPDATE position_triggers
+            SET failure = $2
+            WHERE order_id = $1
+            "#,
+            order_id,
+            reason,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn delete_position_triggers(&self, order_id: OrderId) -> Result<()> {
+        let mut conn = self.inner.acquire().await?;
+
+        let order_id = models::OrderId::from(order_id);
+
+        sqlx::query!(
+            r#"
+            DELETE FROM position_triggers
+            WHERE order_id = $1
+            "#,
+            order_id
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory;
+    use model::Price;
+    use rust_decimal_macros::dec;
+
+    #[tokio::test]
+    async fn given_fired_trigger_when_upserting_then_progress_is_reset() {
+        let db = memory().await.unwrap();
+        let order_id = OrderId::default();
+
+        db.upsert_position_triggers(order_id, triggers(dec!(19_000)))
+            .await
+            .unwrap();
+        db.mark_position_trigger_fired(order_id, Trigger::StopLoss)
+            .await
+            .unwrap();
+        db.mark_position_trigger_failed(order_id, "rejected".to_string())
+            .await
+            .unwrap();
+
+        let fired = db.load_position_triggers(order_id).await.unwrap().unwrap();
+        assert_eq!(fired.fired, Some(Trigger::StopLoss));
+        assert_eq!(fired.failure.as_deref(), Some("rejected"));
+
+        db.upsert_position_triggers(order_id, triggers(dec!(18_000)))
+            .await
+            .unwrap();
+
+        let reset = db.load_position_triggers(order_id).await.unwrap().unwrap();
+        assert_eq!(
+            reset,
+            PositionTriggersState {
+                order_id,
+                triggers: triggers(dec!(18_000)),
+                fired: None,
+                failure: None,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn given_deleted_triggers_then_nothing_is_loaded() {
+        let db = memory().await.unwrap();
+        let order_id = OrderId::default();
+
+        db.upsert_position_triggers(order_id, triggers(dec!(19_000)))
+            .await
+            .unwrap();
+        db.delete_position_triggers(order_id).await.unwrap();
+
+        assert_eq!(db.load_position_triggers(order_id).await.unwrap(), None);
+        assert!(db.load_all_position_triggers().await.unwrap().is_empty());
+    }
+
+    fn triggers(stop_loss: rust_decimal::Decimal) -> PositionTriggers {
+        PositionTriggers {
+            stop_loss: Some(Price::new(stop_loss).unwrap()),
+            take_profit: None,
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use crate::models;
+use crate::Connection;
+use anyhow::Result;
+use model::OrderId;
+use model::RecoveredContract;
+
+impl Connection {
+    /// Store a contract recovered from the counterparty unless it was recovered before
+    pub async fn insert_recovered_contract(&self, contract: &RecoveredContract) -> Result<()> {
+        let mut conn = self.inner.acquire().await?;
+
+        let order_id = models::OrderId::from(contract.order_id);
+        let data = serde_json::to_string(contract)?;
+
+        sqlx::query!(
+            r#"
+            INSERT OR IGNORE INTO recovered_contracts
+            (
+                order_id,
+                data
+            )
+            VALUES ($1, $2)
+            "#,
+            order_id,
+            data,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn load_recovered_contracts(&self) -> Result<Vec<RecoveredContract>> {
+        let mut conn = self.inner.acquire().await?;
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                data
+            FROM
+                recovered_contracts
+            "#
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let contracts = rows
+            .into_iter()
+            .map(|row| serde_json::from_str(&row.data))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(contracts)
+    }
+
+    /// Forget a recovered contract once its refund transaction was published
+    pub async fn delete_recovered_contract(&self, order_id: OrderId) -> Result<()> {
+        let mut conn = self.inner.acquire().await?;
+
+        let order_id = models::OrderId::from(order_id);
+
+        sqlx::query!(
+            r#"
+            DELETE FROM recovered_contracts
+            WHERE order_id = $1
+            "#,
+            order_id
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,110 @@
+use crate::models;
+use crate::Connection;
+use anyhow::Result;
+use bdk::bitcoin::Amount;
+use model::Usd;
+
+/// Limits on the exposure of the maker, as last set by the operator
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RiskLimits {
+    pub max_taker_quantity: Option<Usd>,
+    pub max_net_open_interest: Option<Usd>,
+    pub max_locked_margin: Option<Amount>,
+}
+
+impl Connection {
+    /// Store the risk limits, replacing the ones stored before
+    pub async fn upsert_risk_limits(&self, risk_limits: RiskLimits) -> Result<()> {
+        let mut conn = self.inner.acquire().await?;
+
+        let max_taker_quantity = risk_limits.max_taker_quantity.map(models::Usd::from);
+        let max_net_open_interest = risk_limits.max_net_open_interest.map(models::Usd::from);
+        let max_locked_margin = risk_limits
+            .max_locked_margin
+            .map(|amount| amount.to_sat() as i64);
+
+        sqlx::query!(
+            r#"
+            INSERT INTO risk_limits
+            (
+                id,
+                max_taker_quantity,
+                max_net_open_interest,
+                max_locked_margin
+            )
+            VALUES (1, $1, $2, $3)
+            ON CONFLICT(id) DO UPDATE SET
+                max_taker_quantity = $1,
+                max_net_open_interest = $2,
+                max_locked_margin = $3
+            "#,
+            max_taker_quantity,
+            max_net_open_interest,
+            max_locked_margin,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Load the stored risk limits, if they were ever set
+    pub async fn load_risk_limits(&self) -> Result<Option<RiskLimits>> {
+        let mut conn = self.inner.acquire().await?;
+
+        let row = sqlx::query!(
+            r#"
+            SELECT
+                max_taker_quantity as "max_taker_quantity: models::Usd",
+                max_net_open_interest as "max_net_open_interest: models::Usd",
+                max_locked_margin
+            FROM
+                risk_limits
+            WHERE
+                id = 1
+            "#
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        let risk_limits = row.map(|row| RiskLimits {
+            max_taker_quantity: row.max_taker_quantity.map(Into::into),
+            max_net_open_interest: row.max_net_open_interest.map(Into::into),
+            max_locked_margin: row
+                .max_locked_margin
+                .map(|sats| Amount::from_sat(sats as u64)),
+        });
+
+        Ok(risk_limits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory;
+    use rust_decimal_macros::dec;
+
+    #[tokio::test]
+    async fn given_risk_limits_stored_twice_then_latest_are_loaded() {
+        let db = memory().await.unwrap();
+
+        assert_eq!(db.load_risk_limits().await.unwrap(), None);
+
+        let first = RiskLimits {
+            max_taker_quantity: Some(Usd::new(dec!(1_000))),
+            max_net_open_interest: None,
+            max_locked_margin: Some(Amount::from_sat(100_000)),
+        };
+        let second = RiskLimits {
+            max_taker_quantity: None,
+            max_net_open_interest: Some(Usd::new(dec!(5_000))),
+            max_locked_margin: None,
+        };
+
+        db.upsert_risk_limits(first).await.unwrap();
+        db.upsert_risk_limits(second).await.unwrap();
+
+        assert_eq!(db.load_risk_limits().await.unwrap(), Some(second));
+    }
+}
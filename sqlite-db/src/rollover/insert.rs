@@ -113,6 +113,7 @@ async fn insert_rollover_completed_event_data(
     let revocation_pk_counterparty = models::PublicKey::from(dlc.revocation_pk_counterparty);
     let rate = models::FundingRate::from(funding_fee.rate);
     let settlement_event_id = models::BitMexPriceEventId::from(dlc.settlement_event_id);
+    let oracle_pk = models::XOnlyPublicKey::from(dlc.oracle_pk);
 
     let (complete_fee, complete_fee_flow) = into_complete_fee_and_flow(complete_fee);
 
@@ -143,10 +144,11 @@ async fn insert_rollover_completed_event_data(
                 refund_tx,
                 refund_signature,
                 complete_fee,
-                complete_fee_flow
+                complete_fee_flow,
+                oracle_pk
             ) values (
             (select id from cfds where cfds.uuid = $1),
-            $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23, $24, $25
+            $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23, $24, $25, $26
             )
         "#,
         offer_id,
@@ -174,6 +176,7 @@ async fn insert_rollover_completed_event_data(
         refund_signature,
         complete_fee,
         complete_fee_flow,
+        oracle_pk,
     )
     .execute(&mut *inner_transaction)
     .await?;
@@ -200,6 +203,9 @@ async fn insert_revoked_commit_transaction(
         .map(models::BitMexPriceEventId::from);
 
     let (complete_fee, complete_fee_flow) = into_complete_fee_and_flow(revoked.complete_fee);
+    let commit_descriptor = revoked
+        .commit_descriptor
+        .map(|descriptor| descriptor.to_string());
 
     let query_result = sqlx::query!(
         r#"
@@ -213,8 +219,9 @@ async fn insert_revoked_commit_transaction(
                     settlement_event_id,
                     complete_fee,
                     complete_fee_flow,
-                    revocation_sk_ours
-                ) values ( (select id from cfds where cfds.uuid = $1), $2, $3, $4, $5, $6, $7, $8, $9, $10 )
+                    revocation_sk_ours,
+                    commit_descriptor
+                ) values ( (select id from cfds where cfds.uuid = $1), $2, $3, $4, $5, $6, $7, $8, $9, $10, $11 )
             "#,
         offer_id,
         encsig_ours,
@@ -225,7 +232,8 @@ async fn insert_revoked_commit_transaction(
         settlement_event_id,
         complete_fee,
         complete_fee_flow,
-        revocation_sk_ours
+        revocation_sk_ours,
+        commit_descriptor
     )
     .execute(&mut *inner_transaction)
     .await?;
@@ -56,7 +56,8 @@ pub async fn load(
                 refund_tx as "refund_tx: models::Transaction",
                 refund_signature,
                 complete_fee as "complete_fee: i64",
-                complete_fee_flow as "complete_fee_flow: models::FeeFlow"
+                complete_fee_flow as "complete_fee_flow: models::FeeFlow",
+                oracle_pk as "oracle_pk: models::XOnlyPublicKey"
             FROM
                 rollover_completed_event_data
             WHERE
@@ -102,6 +103,7 @@ pub async fn load(
         revoked_commit,
         settlement_event_id: row.settlement_event_id.into(),
         refund_timelock: row.refund_timelock as u32,
+        oracle_pk: row.oracle_pk.into(),
     };
     let funding_fee = FundingFee {
         fee: Amount::from_sat(row.funding_fee as u64),
@@ -128,7 +130,8 @@ async fn load_revoked_commit_transactions(
                 settlement_event_id as "settlement_event_id: models::BitMexPriceEventId",
                 txid as "txid: models::Txid",
                 complete_fee as "complete_fee: i64",
-                complete_fee_flow as "complete_fee_flow: models::FeeFlow"
+                complete_fee_flow as "complete_fee_flow: models::FeeFlow",
+                commit_descriptor
             FROM
                 revoked_commit_transactions
             WHERE
@@ -154,6 +157,10 @@ async fn load_revoked_commit_transactions(
                 .settlement_event_id
                 .map(|settlement_event_id| settlement_event_id.into()),
             complete_fee: into_complete_fee(row.complete_fee_flow, row.complete_fee),
+            commit_descriptor: row
+                .commit_descriptor
+                .map(|descriptor| Descriptor::from_str(descriptor.as_str()))
+                .transpose()?,
         })
     })
     .collect::<Result<Vec<_>>>()?;
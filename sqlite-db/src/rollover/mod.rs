@@ -26,6 +26,7 @@ mod tests {
     use model::Price;
     use model::Role;
     use model::Timestamp;
+    use model::TradingPair;
     use model::TxFeeRate;
     use model::Usd;
     use rust_decimal_macros::dec;
@@ -39,9 +40,11 @@ mod tests {
     pub fn dummy_cfd() -> Cfd {
         Cfd::new(
             OrderId::default(),
+            TradingPair::BtcUsd,
             Position::Long,
             Price::new(dec!(60_000)).unwrap(),
             Leverage::TWO,
+            Leverage::ONE,
             Duration::hours(24),
             Role::Taker,
             Usd::new(dec!(1_000)),
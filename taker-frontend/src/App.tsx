@@ -17,17 +17,20 @@ import {
     BXBTData,
     Cfd,
     ConnectionStatus,
+    DEFAULT_TRADING_PAIR,
     IdentityInfo,
     intoCfd,
     intoMakerOffer,
     LeverageDetails,
     MakerOffer,
+    MakerOffers,
     WalletInfo,
 } from "./types";
 import { useEventSource } from "./useEventSource";
 import useLatestEvent from "./useLatestEvent";
 
 export interface Offer {
+    tradingPair: string;
     id?: string;
     price?: number;
     fundingRateAnnualized?: number;
@@ -82,17 +85,26 @@ export const App = () => {
     const [source, isConnected] = useEventSource("/api/feed");
     const walletInfo = useLatestEvent<WalletInfo>(source, "wallet");
 
-    const makerLong = useLatestEvent<MakerOffer>(source, "long_offer", intoMakerOffer);
-    const makerShort = useLatestEvent<MakerOffer>(source, "short_offer", intoMakerOffer);
+    const makerOffersOrNull = useLatestEvent<MakerOffers[]>(source, "offers", intoMakerOffer);
+    const makerOffers = makerOffersOrNull ? makerOffersOrNull : [];
+    let [tradingPair, setTradingPair] = useState(DEFAULT_TRADING_PAIR);
+
+    // The selected trading pair stays selectable even if the maker stops offering it
+    const tradingPairs = makerOffers.map((offers) => offers.trading_pair);
+    if (!tradingPairs.includes(tradingPair)) {
+        tradingPairs.unshift(tradingPair);
+    }
 
     const identityOrUndefined = useLatestEvent<IdentityInfo>(source, "identity");
 
-    const shortOffer = makerOfferToTakerOffer(makerLong);
-    const longOffer = makerOfferToTakerOffer(makerShort);
+    const selectedOffers = makerOffers.find((offers) => offers.trading_pair === tradingPair);
+    const shortOffer = makerOfferToTakerOffer(selectedOffers?.long);
+    const longOffer = makerOfferToTakerOffer(selectedOffers?.short);
 
-    function makerOfferToTakerOffer(offer: MakerOffer | null): Offer {
+    function makerOfferToTakerOffer(offer: MakerOffer | undefined): Offer {
         if (offer) {
             return {
+                tradingPair: offer.trading_pair,
                 id: offer.id,
                 price: offer.price,
                 fundingRateAnnualized: offer.funding_rate_annualized_percent,
@@ -105,6 +117,7 @@ export const App = () => {
         }
 
         return {
+            tradingPair,
             leverageDetails: [],
             minQuantity: 0,
             maxQuantity: 0,
@@ -221,6 +234,8 @@ export const App = () => {
                         element={
                             <Trade
                                 offer={longOffer}
+                                tradingPairs={tradingPairs}
+                                onTradingPairChange={setTradingPair}
                                 connectedToMaker={connectedToMaker}
                                 walletBalance={walletInfo ? walletInfo.balance : 0}
                                 isLong={true}
@@ -232,6 +247,8 @@ export const App = () => {
                         element={
                             <Trade
                                 offer={shortOffer}
+                                tradingPairs={tradingPairs}
+                                onTradingPairChange={setTradingPair}
                                 connectedToMaker={connectedToMaker}
                                 walletBalance={walletInfo ? walletInfo.balance : 0}
                                 isLong={false}
@@ -17,13 +17,14 @@ import {
 } from "@chakra-ui/react";
 import * as React from "react";
 import { useRef } from "react";
-import { CfdOrderRequestPayload } from "../types";
+import { CfdOrderRequestPayload, tradingPairLabel } from "../types";
 import BitcoinAmount from "./BitcoinAmount";
 import DollarAmount from "./DollarAmount";
 import { FundingRateTooltip } from "./FundingRateTooltip";
 
 interface Props {
     orderId: string;
+    tradingPair: string;
     position: "long" | "short";
     isOpen: boolean;
     onClose: any;
@@ -41,6 +42,7 @@ interface Props {
 
 export default function ConfirmOrderModal({
     orderId,
+    tradingPair,
     position,
     isOpen,
     onClose,
@@ -74,7 +76,7 @@ export default function ConfirmOrderModal({
                     <HStack>
                         <Text>
                             Market {buy_or_sell}&nbsp;
-                            <b>{quantity}</b> of BTC/USD @
+                            <b>{quantity}</b> of {tradingPairLabel(tradingPair)} @
                         </Text>
                         <DollarAmount amount={price} />
                     </HStack>
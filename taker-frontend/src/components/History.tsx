@@ -84,6 +84,7 @@ const CfdDetails = ({ cfd, connectedToMaker, displayCloseButton, showExtraInfo }
     const txRefund = cfd.details.tx_url_list.find((tx) => tx.label === TxLabel.Refund);
     const txCet = cfd.details.tx_url_list.find((tx) => tx.label === TxLabel.Cet);
     const txSettled = cfd.details.tx_url_list.find((tx) => tx.label === TxLabel.Collaborative);
+    const txPunish = cfd.details.tx_url_list.find((tx) => tx.label === TxLabel.Punish);
 
     let [settle, isSettling] = usePostRequest(`/api/cfd/${cfd.order_id}/settle`);
     let [commit, isCommiting] = usePostRequest(`/api/cfd/${cfd.order_id}/commit`);
@@ -255,6 +256,17 @@ const CfdDetails = ({ cfd, connectedToMaker, displayCloseButton, showExtraInfo }
                                     </Td>
                                 </Tr>
                             )
+                            : txPunish
+                            ? (
+                                <Tr>
+                                    <Td>
+                                        <Text>Punish</Text>
+                                    </Td>
+                                    <Td>
+                                        <TxIcon tx={txPunish} />
+                                    </Td>
+                                </Tr>
+                            )
                             : txCommit || (cfd.state.key === StateKey.OPEN && !connectedToMaker.online)
                             ? (
                                 <>
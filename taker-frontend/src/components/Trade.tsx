@@ -17,6 +17,7 @@ import {
     NumberInput,
     NumberInputField,
     NumberInputStepper,
+    Select,
     Skeleton,
     Slider,
     SliderFilledTrack,
@@ -39,7 +40,7 @@ import { useEffect, useState } from "react";
 import { FaWallet } from "react-icons/all";
 import { useNavigate } from "react-router-dom";
 import { Offer } from "../App";
-import { CfdOrderRequestPayload, ConnectionStatus } from "../types";
+import { CfdOrderRequestPayload, ConnectionStatus, tradingPairLabel } from "../types";
 import usePostRequest from "../usePostRequest";
 import AlertBox from "./AlertBox";
 import BitcoinAmount from "./BitcoinAmount";
@@ -53,6 +54,8 @@ const MotionBox = motion<BoxProps>(Box);
 //  Recommendation: Inline, see how it feels and then potentially carve out some new abstraction if there is one clearly visible
 interface TradeProps {
     offer: Offer;
+    tradingPairs: string[];
+    onTradingPairChange: (tradingPair: string) => void;
     connectedToMaker: ConnectionStatus;
     walletBalance: number;
     isLong: boolean;
@@ -60,6 +63,7 @@ interface TradeProps {
 
 export default function Trade({
     offer: {
+        tradingPair,
         id: orderId,
         price: priceAsNumber,
         fundingRateAnnualized,
@@ -69,6 +73,8 @@ export default function Trade({
         lotSize,
         leverageDetails,
     },
+    tradingPairs,
+    onTradingPairChange,
     connectedToMaker,
     walletBalance,
     isLong,
@@ -162,6 +168,14 @@ export default function Trade({
                     gap={4}
                     maxWidth={"500px"}
                 >
+                    <GridItem colSpan={1} paddingLeft={5} paddingRight={5}>
+                        <TradingPair
+                            tradingPairs={tradingPairs}
+                            tradingPair={tradingPair}
+                            onChange={onTradingPairChange}
+                            isLong={isLong}
+                        />
+                    </GridItem>
                     <GridItem colSpan={1}>
                         <Center>
                             <MotionBox
@@ -206,6 +220,7 @@ export default function Trade({
                                 setUserHasEdited(true);
                             }}
                             lotSize={lotSize}
+                            tradingPair={tradingPair}
                             isLong={isLong}
                         />
                     </GridItem>
@@ -283,6 +298,7 @@ export default function Trade({
                             </Button>
                             <ConfirmOrderModal
                                 orderId={orderId!}
+                                tradingPair={tradingPair}
                                 position={isLong ? "long" : "short"}
                                 price={priceAsNumber || 0}
                                 isOpen={isOpen}
@@ -306,20 +322,47 @@ export default function Trade({
     );
 }
 
+interface TradingPairProps {
+    tradingPairs: string[];
+    tradingPair: string;
+    onChange: (tradingPair: string) => void;
+    isLong: boolean;
+}
+
+function TradingPair({ tradingPairs, tradingPair, onChange, isLong }: TradingPairProps) {
+    return (
+        <FormControl id="tradingPair">
+            <Center>
+                <FormLabel>Market</FormLabel>
+            </Center>
+            <Select
+                id={isLong ? "longTradingPair" : "shortTradingPair"}
+                value={tradingPair}
+                onChange={(event) => onChange(event.target.value)}
+            >
+                {tradingPairs.map(tradingPair => (
+                    <option key={tradingPair} value={tradingPair}>{tradingPairLabel(tradingPair)}</option>
+                ))}
+            </Select>
+        </FormControl>
+    );
+}
+
 interface QuantityProps {
     min: number;
     max: number;
     quantity: number;
     lotSize: number;
+    tradingPair: string;
     onChange: (valueAsString: string, valueAsNumber: number) => void;
     isLong: boolean;
 }
 
-function Quantity({ min, max, onChange, quantity, lotSize, isLong }: QuantityProps) {
+function Quantity({ min, max, onChange, quantity, lotSize, tradingPair, isLong }: QuantityProps) {
     return (
         <FormControl id="quantity">
             <Center>
-                <FormLabel>BTC/USD Contracts</FormLabel>
+                <FormLabel>{tradingPairLabel(tradingPair)} Contracts</FormLabel>
             </Center>
             <InputGroup id={isLong ? "longQuantityInput" : "shortQuantityInput"}>
                 <NumberInput
@@ -33,6 +33,20 @@ export interface MakerOffer {
     creation_timestamp: number;
 }
 
+// the maker's offers of a single trading pair
+export interface MakerOffers {
+    trading_pair: string;
+    long?: MakerOffer;
+    short?: MakerOffer;
+}
+
+export const DEFAULT_TRADING_PAIR = "BtcUsd";
+
+// e.g. "BtcUsd" becomes "BTC/USD"
+export function tradingPairLabel(tradingPair: string): string {
+    return `${tradingPair.slice(0, 3)}/${tradingPair.slice(3)}`.toUpperCase();
+}
+
 export interface LeverageDetails {
     leverage: number;
     liquidation_price: number;
@@ -104,6 +118,8 @@ export enum TxLabel {
     Cet = "Cet",
     Refund = "Refund",
     Collaborative = "Collaborative",
+    Punish = "Punish",
+    Cpfp = "Cpfp",
 }
 
 export class State {
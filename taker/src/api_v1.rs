@@ -0,0 +1,605 @@
+//! Versioned REST API for automated trading
+//!
+//! Unlike the routes in [`crate::routes`], which serve the bundled frontend, the types in this
+//! module are a stable contract: fields are only ever added. Breaking changes require a new
+//! version prefix. The OpenAPI specification is generated from these types and served at
+//! `/api/v1/openapi.json`.
+
+use daemon::bdk::bitcoin::OutPoint;
+use daemon::bdk::blockchain::AnyBlockchain;
+use daemon::bdk::sled;
+use daemon::oracle;
+use daemon::projection;
+use daemon::projection::CfdState;
+use daemon::projection::Feeds;
+use daemon::taker_cfd::InvalidOrder;
+use daemon::wallet;
+use daemon::IdempotencyKeyReused;
+use daemon::OrderRequestPending;
+use daemon::TakerActorSystem;
+use http_api_problem::HttpApiProblem;
+use http_api_problem::StatusCode;
+use model::Leverage;
+use model::OrderId;
+use model::Position;
+use model::TradingPair;
+use model::Usd;
+use rocket::http::ContentType;
+use rocket::http::Status;
+use rocket::request::FromRequest;
+use rocket::request::Outcome;
+use rocket::serde::json::Json;
+use rocket::serde::uuid::Uuid;
+use rocket::Request;
+use rocket::State;
+use rocket_basicauth::Authenticated;
+use serde::Deserialize;
+use serde::Serialize;
+use std::str::FromStr;
+use tracing::instrument;
+use utoipa::Component;
+use utoipa::OpenApi;
+
+type Taker = TakerActorSystem<
+    oracle::Actor,
+    wallet::Actor<AnyBlockchain, sled::Tree>,
+    xtra_bitmex_price_feed::Actor,
+>;
+
+const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+
+/// Longest accepted idempotency key, in bytes
+const MAX_IDEMPOTENCY_KEY_LEN: usize = 255;
+
+#[derive(OpenApi)]
+#[openapi(
+    handlers(
+        get_offers,
+        get_cfds,
+        get_cfd,
+        get_utxos,
+        put_frozen_utxo,
+        delete_frozen_utxo,
+        post_order
+    ),
+    components(Offer, Cfd, CfdDetails, Event, Utxo, OrderRequest, OrderResponse)
+)]
+struct ApiDoc;
+
+/// An offer of the maker that can be taken
+#[derive(Debug, Clone, PartialEq, Serialize, Component)]
+pub struct Offer {
+    /// Id to use when placing an order for this offer
+    pub id: String,
+    /// Symbol of the traded pair, e.g. `BTCUSD`
+    pub trading_pair: String,
+    /// Position the taker gets when taking this offer: `Long` or `Short`
+    pub position: String,
+    /// Price at which the position is opened, in USD
+    pub price: String,
+    /// Smallest quantity that can be ordered, in USD
+    pub min_quantity: String,
+    /// Largest quantity that can be ordered, in USD
+    pub max_quantity: String,
+    /// Ordered quantities must be a multiple of this, in USD
+    pub lot_size: String,
+    /// Leverages the taker can choose from
+    pub leverages: Vec<u8>,
+    /// Flat fee charged by the maker for opening a position, in satoshis
+    pub opening_fee_sat: Option<u64>,
+    /// Estimated funding rate per hour, in percent
+    pub funding_rate_hourly_percent: String,
+    /// Unix timestamp at which the maker created the offer
+    pub created_at: i64,
+}
+
+impl Offer {
+    fn new(offer: &projection::CfdOrder) -> Self {
+        Self {
+            id: offer.id.to_string(),
+            trading_pair: offer.trading_pair.symbol().to_string(),
+            position: offer.position_maker.counter_position().to_string(),
+            price: offer.price.to_string(),
+            min_quantity: offer.min_quantity.to_string(),
+            max_quantity: offer.max_quantity.to_string(),
+            lot_size: offer.lot_size.to_string(),
+            leverages: offer
+                .leverage_details
+                .iter()
+                .map(|details| details.leverage.get())
+                .collect(),
+            opening_fee_sat: offer.opening_fee.map(|fee| fee.as_sat()),
+            funding_rate_hourly_percent: offer.funding_rate_hourly_percent.clone(),
+            created_at: offer.creation_timestamp.seconds(),
+        }
+    }
+}
+
+/// A CFD of the taker, in any state
+#[derive(Debug, Clone, PartialEq, Serialize, Component)]
+pub struct Cfd {
+    pub id: String,
+    /// Symbol of the traded pair, e.g. `BTCUSD`
+    pub trading_pair: String,
+    /// Position of the taker: `Long` or `Short`
+    pub position: String,
+    /// Current state, e.g. `PendingSetup`, `Open` or `Closed`
+    pub state: String,
+    /// Quantity in USD
+    pub quantity: String,
+    pub leverage: u8,
+    /// Price at which the position was opened, in USD
+    pub initial_price: String,
+    /// Price at which the position is liquidated, in USD
+    pub liquidation_price: String,
+    /// Margin of the taker, in satoshis
+    pub margin_sat: u64,
+    /// Sum of all fees paid so far, in satoshis
+    pub accumulated_fees_sat: i64,
+    /// Projected or final profit, in satoshis
+    pub profit_sat: Option<i64>,
+    /// Projected or final payout, in satoshis
+    pub payout_sat: Option<i64>,
+    /// Price at which the position was closed, in USD
+    pub closing_price: Option<String>,
+    /// Unix timestamp at which the contract expires
+    pub expiry_timestamp: Option<i64>,
+    /// Price at which the position is closed to limit the loss, in USD
+    pub stop_loss: Option<String>,
+    /// Price at which the position is closed to realise the profit, in USD
+    pub take_profit: Option<String>,
+}
+
+impl Cfd {
+    fn new(cfd: &projection::Cfd) -> Self {
+        Self {
+            id: cfd.order_id.to_string(),
+            trading_pair: cfd.trading_pair.symbol().to_string(),
+            position: cfd.position.to_string(),
+            state: cfd.state.to_string(),
+            quantity: cfd.quantity_usd.to_string(),
+            leverage: cfd.leverage_taker.get(),
+            initial_price: cfd.initial_price.to_string(),
+            liquidation_price: cfd.liquidation_price.to_string(),
+            margin_sat: cfd.margin.as_sat(),
+            accumulated_fees_sat: cfd.accumulated_fees.as_sat(),
+            profit_sat: cfd.profit_btc.map(|profit| profit.as_sat()),
+            payout_sat: cfd.payout.map(|payout| payout.as_sat()),
+            closing_price: cfd.closing_price.map(|price| price.to_string()),
+            expiry_timestamp: cfd
+                .expiry_timestamp
+                .map(|timestamp| timestamp.unix_timestamp()),
+            stop_loss: cfd.stop_loss.map(|price| price.to_string()),
+            take_profit: cfd.take_profit.map(|price| price.to_string()),
+        }
+    }
+}
+
+/// A CFD together with the history of everything that happened to it
+#[derive(Debug, Clone, PartialEq, Serialize, Component)]
+pub struct CfdDetails {
+    pub cfd: Cfd,
+    /// Events of the CFD, oldest first
+    pub events: Vec<Event>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Component)]
+pub struct Event {
+    /// Name of the event, e.g. `ContractSetupCompleted`
+    pub name: String,
+    /// Unix timestamp at which the event was recorded
+    pub timestamp: i64,
+}
+
+/// An unspent output of the wallet
+#[derive(Debug, Clone, PartialEq, Serialize, Component)]
+pub struct Utxo {
+    /// Outpoint in the format `<txid>:<vout>`
+    pub outpoint: String,
+    pub amount_sat: u64,
+    /// Whether the output is change of a transaction of the wallet
+    pub change: bool,
+    /// Whether the output is reserved for a contract that is being set up
+    pub locked: bool,
+    /// Whether the output is frozen and not spent until it is unfrozen
+    pub frozen: bool,
+    /// Height of the block that confirmed the output, absent if it is unconfirmed
+    pub confirmation_height: Option<u32>,
+}
+
+impl From<wallet::Utxo> for Utxo {
+    fn from(utxo: wallet::Utxo) -> Self {
+        Self {
+            outpoint: utxo.outpoint.to_string(),
+            amount_sat: utxo.amount.as_sat(),
+            change: utxo.change,
+            locked: utxo.locked,
+            frozen: utxo.frozen,
+            confirmation_height: utxo.confirmation_height,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Component)]
+pub struct OrderRequest {
+    /// Id of the offer to take
+    pub offer_id: String,
+    /// Quantity in USD
+    pub quantity: String,
+    pub leverage: u8,
+}
+
+#[derive(Debug, Clone, Serialize, Component)]
+pub struct OrderResponse {
+    /// Id of the CFD, equal to the id of the taken offer
+    pub cfd_id: String,
+}
+
+/// Client-chosen key that makes placing an order safe to retry
+///
+/// Read from the `Idempotency-Key` header.
+pub struct IdempotencyKey(String);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for IdempotencyKey {
+    type Error = HttpApiProblem;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let headers = req
+            .headers()
+            .get(IDEMPOTENCY_KEY_HEADER)
+            .collect::<Vec<_>>();
+
+        match headers.as_slice() {
+            [key] if !key.is_empty() && key.len() <= MAX_IDEMPOTENCY_KEY_LEN => {
+                Outcome::Success(IdempotencyKey(key.to_string()))
+            }
+            _ => Outcome::Failure((
+                Status::BadRequest,
+                HttpApiProblem::new(StatusCode::BAD_REQUEST)
+                    .title("Invalid idempotency key")
+                    .detail(format!(
+                        "Expected exactly one {IDEMPOTENCY_KEY_HEADER} header of at most {MAX_IDEMPOTENCY_KEY_LEN} bytes"
+                    )),
+            )),
+        }
+    }
+}
+
+/// List the offers of the maker that can currently be taken.
+#[utoipa::path(
+    get,
+    path = "/api/v1/offers",
+    responses(
+        (status = 200, description = "Offers that can be taken", body = [Offer])
+    )
+)]
+#[rocket::get("/offers")]
+#[instrument(name = "GET /api/v1/offers", skip_all)]
+pub fn get_offers(feeds: &State<Feeds>, _auth: Authenticated) -> Json<Vec<Offer>> {
+    let offers = feeds.offers.borrow().clone();
+
+    let offers = offers
+        .iter()
+        .flat_map(|offers| [&offers.long, &offers.short])
+        .flatten()
+        .map(Offer::new)
+        .collect();
+
+    Json(offers)
+}
+
+/// List all CFDs, optionally filtered by state, position and trading pair.
+#[utoipa::path(
+    get,
+    path = "/api/v1/cfds",
+    params(
+        ("state" = Option<String>, query, description = "Only CFDs in this state, e.g. `Open`"),
+        ("position" = Option<String>, query, description = "Only CFDs of this position: `Long` or `Short`"),
+        ("trading_pair" = Option<String>, query, description = "Only CFDs of this trading pair, e.g. `BTCUSD`")
+    ),
+    responses(
+        (status = 200, description = "CFDs matching all filters", body = [Cfd]),
+        (status = 400, description = "A filter is invalid"),
+        (status = 503, description = "CFDs are not loaded yet")
+    )
+)]
+#[rocket::get("/cfds?<state>&<position>&<trading_pair>")]
+#[instrument(name = "GET /api/v1/cfds", skip(feeds, _auth), err)]
+pub fn get_cfds(
+    state: Option<String>,
+    position: Option<String>,
+    trading_pair: Option<String>,
+    feeds: &State<Feeds>,
+    _auth: Authenticated,
+) -> Result<Json<Vec<Cfd>>, HttpApiProblem> {
+    let state = parse_filter::<CfdState>("state", state)?;
+    let position = parse_filter::<Position>("position", position)?;
+    let trading_pair = parse_filter::<TradingPair>("trading_pair", trading_pair)?;
+
+    let cfds = feeds.cfds.borrow().clone().ok_or_else(cfds_not_loaded)?;
+
+    let cfds = cfds
+        .iter()
+        .filter(|cfd| state.map_or(true, |state| cfd.state == state))
+        .filter(|cfd| position.map_or(true, |position| cfd.position == position))
+        .filter(|cfd| trading_pair.map_or(true, |trading_pair| cfd.trading_pair == trading_pair))
+        .map(Cfd::new)
+        .collect();
+
+    Ok(Json(cfds))
+}
+
+/// Fetch a single CFD together with its event history.
+#[utoipa::path(
+    get,
+    path = "/api/v1/cfds/{id}",
+    params(
+        ("id" = String, path, description = "Id of the CFD")
+    ),
+    responses(
+        (status = 200, description = "The CFD and its events", body = CfdDetails),
+        (status = 404, description = "No CFD with this id"),
+        (status = 503, description = "CFDs are not loaded yet")
+    )
+)]
+#[rocket::get("/cfds/<id>")]
+#[instrument(name = "GET /api/v1/cfds/<id>", skip(feeds, taker, _auth), err)]
+pub async fn get_cfd(
+    id: Uuid,
+    feeds: &State<Feeds>,
+    taker: &State<Taker>,
+    _auth: Authenticated,
+) -> Result<Json<CfdDetails>, HttpApiProblem> {
+    let id = OrderId::from(id);
+
+    let cfd = feeds
+        .cfds
+        .borrow()
+        .as_ref()
+        .ok_or_else(cfds_not_loaded)?
+        .iter()
+        .find(|cfd| cfd.order_id == id)
+        .map(Cfd::new)
+        .ok_or_else(|| {
+            HttpApiProblem::new(StatusCode::NOT_FOUND)
+                .title("CFD not found")
+                .detail(format!("No CFD with id {id}"))
+        })?;
+
+    let events = taker
+        .event_log(id)
+        .await
+        .map_err(|e| {
+            HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+                .title("Could not load events")
+                .detail(format!("{e:#}"))
+        })?
+        .into_iter()
+        .map(|entry| Event {
+            name: entry.name,
+            timestamp: entry.created_at,
+        })
+        .collect();
+
+    Ok(Json(CfdDetails { cfd, events }))
+}
+
+/// List the unspent outputs of the wallet.
+#[utoipa::path(
+    get,
+    path = "/api/v1/wallet/utxos",
+    responses(
+        (status = 200, description = "Unspent outputs of the wallet", body = [Utxo])
+    )
+)]
+#[rocket::get("/wallet/utxos")]
+#[instrument(name = "GET /api/v1/wallet/utxos", skip_all, err)]
+pub async fn get_utxos(
+    taker: &State<Taker>,
+    _auth: Authenticated,
+) -> Result<Json<Vec<Utxo>>, HttpApiProblem> {
+    let utxos = taker.list_utxos().await.map_err(|e| {
+        HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+            .title("Could not list unspent outputs")
+            .detail(format!("{e:#}"))
+    })?;
+
+    Ok(Json(utxos.into_iter().map(Utxo::from).collect()))
+}
+
+/// Freeze an unspent output, so that it is neither used to fund contracts nor withdrawals.
+#[utoipa::path(
+    put,
+    path = "/api/v1/wallet/utxos/{outpoint}/frozen",
+    params(
+        ("outpoint" = String, path, description = "Outpoint in the format `<txid>:<vout>`")
+    ),
+    responses(
+        (status = 200, description = "The output is frozen"),
+        (status = 400, description = "Invalid outpoint")
+    )
+)]
+#[rocket::put("/wallet/utxos/<outpoint>/frozen")]
+#[instrument(
+    name = "PUT /api/v1/wallet/utxos/<outpoint>/frozen",
+    skip(taker, _auth),
+    err
+)]
+pub async fn put_frozen_utxo(
+    outpoint: &str,
+    taker: &State<Taker>,
+    _auth: Authenticated,
+) -> Result<(), HttpApiProblem> {
+    let outpoint = parse_outpoint(outpoint)?;
+
+    taker.freeze_utxo(outpoint).await.map_err(|e| {
+        HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+            .title("Could not freeze output")
+            .detail(format!("{e:#}"))
+    })?;
+
+    Ok(())
+}
+
+/// Unfreeze an output, allowing it to be spent again.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/wallet/utxos/{outpoint}/frozen",
+    params(
+        ("outpoint" = String, path, description = "Outpoint in the format `<txid>:<vout>`")
+    ),
+    responses(
+        (status = 200, description = "The output is no longer frozen"),
+        (status = 400, description = "Invalid outpoint")
+    )
+)]
+#[rocket::delete("/wallet/utxos/<outpoint>/frozen")]
+#[instrument(
+    name = "DELETE /api/v1/wallet/utxos/<outpoint>/frozen",
+    skip(taker, _auth),
+    err
+)]
+pub async fn delete_frozen_utxo(
+    outpoint: &str,
+    taker: &State<Taker>,
+    _auth: Authenticated,
+) -> Result<(), HttpApiProblem> {
+    let outpoint = parse_outpoint(outpoint)?;
+
+    taker.unfreeze_utxo(outpoint).await.map_err(|e| {
+        HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+            .title("Could not unfreeze output")
+            .detail(format!("{e:#}"))
+    })?;
+
+    Ok(())
+}
+
+fn parse_outpoint(outpoint: &str) -> Result<OutPoint, HttpApiProblem> {
+    outpoint.parse().map_err(|e| {
+        HttpApiProblem::new(StatusCode::BAD_REQUEST)
+            .title("Invalid outpoint")
+            .detail(format!("Expected `<txid>:<vout>`: {e}"))
+    })
+}
+
+/// Take an offer of the maker.
+///
+/// Requests with an `Idempotency-Key` that was already used for the same order do not place the
+/// order again but return the outcome of the first request, waiting for it if necessary.
+#[utoipa::path(
+    post,
+    path = "/api/v1/orders",
+    request_body = OrderRequest,
+    params(
+        ("Idempotency-Key" = String, header, description = "Client-chosen key of at most 255 bytes, unique per order")
+    ),
+    responses(
+        (status = 200, description = "The order was placed", body = OrderResponse),
+        (status = 400, description = "The request is invalid or the offer cannot be taken"),
+        (status = 409, description = "A request with the same idempotency key is still in progress"),
+        (status = 422, description = "The idempotency key was already used for a different order"),
+        (status = 500, description = "The order could not be placed")
+    )
+)]
+#[rocket::post("/orders", data = "<order_request>")]
+#[instrument(name = "POST /api/v1/orders", skip(taker, idempotency_key, _auth), err)]
+pub async fn post_order(
+    order_request: Json<OrderRequest>,
+    idempotency_key: IdempotencyKey,
+    taker: &State<Taker>,
+    _auth: Authenticated,
+) -> Result<Json<OrderResponse>, HttpApiProblem> {
+    let order_request = order_request.into_inner();
+
+    let order_id = Uuid::from_str(&order_request.offer_id)
+        .map(OrderId::from)
+        .map_err(|e| invalid_parameter("offer_id", e))?;
+    let quantity =
+        Usd::from_str(&order_request.quantity).map_err(|e| invalid_parameter("quantity", e))?;
+    let leverage =
+        Leverage::new(order_request.leverage).map_err(|e| invalid_parameter("leverage", e))?;
+
+    taker
+        .take_offer_idempotent(idempotency_key.0, order_id, quantity, leverage)
+        .await
+        .map_err(|e| {
+            let status = if e.is::<InvalidOrder>() {
+                StatusCode::BAD_REQUEST
+            } else if e.is::<OrderRequestPending>() {
+                StatusCode::CONFLICT
+            } else if e.is::<IdempotencyKeyReused>() {
+                StatusCode::UNPROCESSABLE_ENTITY
+            } else {
+                StatusCode::INTERNAL_SERVER_ERROR
+            };
+
+            HttpApiProblem::new(status)
+                .title("Order request failed")
+                .detail(format!("{e:#}"))
+        })?;
+
+    Ok(Json(OrderResponse {
+        cfd_id: order_id.to_string(),
+    }))
+}
+
+/// Serve the OpenAPI specification of this API.
+#[rocket::get("/openapi.json")]
+#[instrument(name = "GET /api/v1/openapi.json", skip_all, err)]
+pub fn get_openapi_spec() -> Result<(ContentType, String), HttpApiProblem> {
+    let spec = ApiDoc::openapi().to_pretty_json().map_err(|e| {
+        HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+            .title("Failed to encode OpenAPI specification")
+            .detail(e.to_string())
+    })?;
+
+    Ok((ContentType::JSON, spec))
+}
+
+fn parse_filter<T>(name: &str, value: Option<String>) -> Result<Option<T>, HttpApiProblem>
+where
+    T: FromStr,
+    T::Err: std::fmt::Display,
+{
+    value
+        .map(|value| value.parse().map_err(|e| invalid_parameter(name, e)))
+        .transpose()
+}
+
+fn invalid_parameter(name: &str, e: impl std::fmt::Display) -> HttpApiProblem {
+    HttpApiProblem::new(StatusCode::BAD_REQUEST)
+        .title(format!("Invalid {name}"))
+        .detail(e.to_string())
+}
+
+fn cfds_not_loaded() -> HttpApiProblem {
+    HttpApiProblem::new(StatusCode::SERVICE_UNAVAILABLE)
+        .title("CFDs not loaded yet")
+        .detail("Try again once the CFDs are loaded from the database")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn openapi_spec_documents_all_endpoints() {
+        let spec = ApiDoc::openapi();
+
+        let paths = spec.paths.paths.keys().cloned().collect::<Vec<_>>();
+
+        assert_eq!(
+            paths,
+            vec![
+                "/api/v1/cfds",
+                "/api/v1/cfds/{id}",
+                "/api/v1/offers",
+                "/api/v1/orders",
+                "/api/v1/wallet/utxos",
+                "/api/v1/wallet/utxos/{outpoint}/frozen",
+            ]
+        );
+    }
+}
@@ -7,28 +7,31 @@ use clap::Parser;
 use daemon::bdk::bitcoin;
 use daemon::bdk::FeeRate;
 use daemon::connection::connect;
-use daemon::libp2p_utils::create_connect_tcp_multiaddr;
-use daemon::libp2p_utils::libp2p_socket_from_legacy_networking;
+use daemon::libp2p_utils::create_connect_host_multiaddr;
 use daemon::monitor;
 use daemon::oracle;
 use daemon::projection;
-use daemon::seed::RandomSeed;
+use daemon::seed::MnemonicSeed;
 use daemon::seed::Seed;
 use daemon::seed::UmbrelSeed;
+use daemon::tor;
 use daemon::wallet;
 use daemon::wallet::TAKER_WALLET_ID;
 use daemon::Environment;
 use daemon::TakerActorSystem;
 use daemon::N_PAYOUTS;
 use libp2p_core::PeerId;
-use model::olivia;
 use model::Identity;
+use model::TradingPair;
 use model::SETTLEMENT_INTERVAL;
 use rocket::fairing::AdHoc;
 use rocket::fairing::Fairing;
 use shared_bin::catchers::default_catchers;
+use shared_bin::cli::load_seed;
+use shared_bin::cli::print_mnemonics;
+use shared_bin::cli::Command;
 use shared_bin::cli::Network;
-use shared_bin::cli::Withdraw;
+use shared_bin::cli::SeedEncryption;
 use shared_bin::fairings;
 use shared_bin::logger;
 use shared_bin::logger::LevelFilter;
@@ -39,6 +42,7 @@ use std::path::PathBuf;
 use std::time::Duration;
 use tokio_extras::Tasks;
 
+mod api_v1;
 mod routes;
 
 pub const ANNOUNCEMENT_LOOKAHEAD: time::Duration = time::Duration::hours(24);
@@ -71,6 +75,12 @@ struct Opts {
     #[clap(long)]
     maker_peer_id: Option<PeerId>,
 
+    /// Connect to the maker through the SOCKS5 proxy of a Tor daemon, e.g. `127.0.0.1:9050`.
+    ///
+    /// The maker is then never connected to directly, and `--maker` can be an `.onion` address.
+    #[clap(long)]
+    tor_socks_proxy: Option<SocketAddr>,
+
     /// The IP address to listen on for the HTTP API.
     #[clap(long, default_value = "127.0.0.1:8000")]
     http_address: SocketAddr,
@@ -79,6 +89,11 @@ struct Opts {
     #[clap(long)]
     data_dir: Option<PathBuf>,
 
+    /// Where to write the encrypted backup of the open CFDs, defaults to `cfds.backup` in the
+    /// data directory.
+    #[clap(long)]
+    backup_path: Option<PathBuf>,
+
     /// If enabled logs will be in json format
     #[clap(short, long)]
     json: bool,
@@ -133,6 +148,9 @@ struct Opts {
     /// keys will be derived according to Bip84.
     #[clap(short, long)]
     pub wallet_xprv: Option<ExtendedPrivKey>,
+
+    #[clap(flatten)]
+    seed_encryption: SeedEncryption,
 }
 
 impl Opts {
@@ -223,6 +241,10 @@ async fn main() -> Result<()> {
         .unwrap_or_else(|| std::env::current_dir().expect("unable to get cwd"));
 
     let data_dir = network.data_dir(data_dir);
+    let backup_path = opts
+        .backup_path
+        .clone()
+        .unwrap_or_else(|| data_dir.join("cfds.backup"));
 
     if !data_dir.exists() {
         tokio::fs::create_dir_all(&data_dir).await?;
@@ -234,13 +256,36 @@ async fn main() -> Result<()> {
     let (ext_priv_key, identities, web_password) = match opts.umbrel_seed {
         Some(seed_bytes) => {
             let seed = UmbrelSeed::from(seed_bytes);
+
+            match network.command() {
+                Some(Command::ExportMnemonic) => {
+                    print_mnemonics(&[MnemonicSeed::from(&seed)]);
+
+                    return Ok(());
+                }
+                Some(Command::RestoreMnemonic) => {
+                    bail!("Cannot restore a seed file when running with an umbrel seed")
+                }
+                _ => {}
+            }
+
             let ext_priv_key = seed.derive_extended_priv_key(bitcoin_network)?;
             let identities = seed.derive_identities();
             let web_password = opts.password.unwrap_or_else(|| seed.derive_auth_password());
             (ext_priv_key, identities, web_password)
         }
         None => {
-            let seed = RandomSeed::initialize(&data_dir.join("taker_seed")).await?;
+            let seed = match load_seed(
+                &data_dir.join("taker_seed"),
+                &opts.seed_encryption,
+                network.command(),
+            )
+            .await?
+            {
+                Some(seed) => seed,
+                None => return Ok(()),
+            };
+
             let ext_priv_key = seed.derive_extended_priv_key(bitcoin_network)?;
             let identities = seed.derive_identities();
             let web_password = opts.password.unwrap_or_else(|| seed.derive_auth_password());
@@ -257,28 +302,54 @@ async fn main() -> Result<()> {
 
     let mut wallet_dir = data_dir.clone();
     wallet_dir.push(TAKER_WALLET_ID);
+    let blockchain = network.blockchain()?;
+    tracing::info!("Using {} as blockchain backend", blockchain.name());
+
     let (wallet, wallet_feed_receiver) = wallet::Actor::spawn(
-        network.electrum(),
+        &blockchain,
         ext_priv_key,
         wallet_dir,
         TAKER_WALLET_ID.to_string(),
+        None,
     )?;
 
-    if let Some(Withdraw::Withdraw {
-        amount,
-        address,
-        fee,
-    }) = network.withdraw()
-    {
-        wallet
-            .send(wallet::Withdraw {
+    match network.command() {
+        Some(Command::Withdraw {
+            amount,
+            address,
+            fee,
+            utxos,
+            psbt,
+        }) => {
+            let withdraw = wallet::Withdraw {
                 amount: *amount,
                 address: address.clone(),
                 fee: fee.map(FeeRate::from_sat_per_vb),
-            })
-            .await??;
+                utxos: utxos.clone(),
+            };
+
+            if *psbt {
+                let psbt = wallet.send(wallet::BuildWithdrawPsbt(withdraw)).await??;
+
+                #[allow(clippy::print_stdout)]
+                {
+                    println!("{psbt}");
+                }
+            } else {
+                wallet.send(withdraw).await??;
+            }
 
-        return Ok(());
+            return Ok(());
+        }
+        Some(Command::BroadcastPsbt { psbt }) => {
+            wallet
+                .send(wallet::BroadcastPsbt { psbt: psbt.clone() })
+                .await??;
+
+            return Ok(());
+        }
+        Some(Command::ExportMnemonic | Command::RestoreMnemonic | Command::ImportBackup { .. })
+        | None => {}
     }
 
     let auth_username = rocket_basicauth::Username("itchysats");
@@ -290,21 +361,34 @@ async fn main() -> Result<()> {
         .merge(("cli_colors", false));
 
     let db = sqlite_db::connect(data_dir.join("taker.sqlite"), true).await?;
+    db.fail_pending_order_requests("Interrupted by a restart of the taker")
+        .await?;
+
+    if let Some(Command::ImportBackup { file }) = network.command() {
+        let restored = daemon::backup::import(&db, file, &identities.backup_key).await?;
+        tracing::info!(?restored, "Restored CFDs from backup");
+    }
 
     // Create actors
 
     let (projection_actor, projection_context) = xtra::Context::new(None);
 
-    let possible_addresses = resolve_maker_addresses(maker_url.as_str()).await?;
-
-    // Assume that the first resolved ipv4 address is good enough for libp2p.
-    let first_maker_address = possible_addresses
-        .iter()
-        .find(|x| x.is_ipv4())
-        .context("Could not resolve maker URL")?;
-
-    let maker_libp2p_address = libp2p_socket_from_legacy_networking(first_maker_address);
-    let maker_multiaddr = create_connect_tcp_multiaddr(&maker_libp2p_address, maker_peer_id)?;
+    let tor = opts.tor_socks_proxy.map(|socks_proxy| tor::Config {
+        socks_proxy: Some(socks_proxy),
+        hidden_service_port: None,
+    });
+
+    // The maker address is resolved when dialing, through Tor if configured.
+    let (host, port) = split_maker_address(&maker_url)?;
+    // By convention the libp2p port is the legacy port incremented by 1.
+    let libp2p_port = port.checked_add(1).context("Invalid maker port")?;
+    let maker_multiaddr = create_connect_host_multiaddr(host, libp2p_port, maker_peer_id)?;
+
+    // With Tor we skip the legacy connection, which would reveal our IP address.
+    let possible_addresses = match tor {
+        Some(_) => None,
+        None => Some(resolve_maker_addresses(maker_url.as_str()).await?),
+    };
 
     let hex_pk = hex::encode(identities.identity_pk.to_bytes());
     let peer_id = identities.libp2p.public().to_peer_id().to_string();
@@ -325,22 +409,32 @@ async fn main() -> Result<()> {
     let taker = TakerActorSystem::new(
         db.clone(),
         wallet.clone(),
-        *olivia::PUBLIC_KEY,
         identities,
-        |executor| oracle::Actor::new(db.clone(), executor),
+        |executor| oracle::Actor::new(network.oracles(), db.clone(), executor),
         {
             |executor| {
-                let electrum = network.electrum().to_string();
-                monitor::Actor::new(db.clone(), electrum, executor)
+                monitor::Actor::new(
+                    db.clone(),
+                    blockchain.clone(),
+                    wallet.clone().into(),
+                    executor,
+                )
             }
         },
-        move || xtra_bitmex_price_feed::Actor::new(bitmex_network),
+        move || {
+            xtra_bitmex_price_feed::Actor::with_symbols(
+                bitmex_network,
+                TradingPair::ALL.map(|pair| pair.bitmex_instrument()),
+            )
+        },
         N_PAYOUTS,
         Duration::from_secs(10),
         projection_actor.clone(),
         Identity::new(maker_id),
         maker_multiaddr,
         environment,
+        backup_path,
+        tor,
     )?;
 
     let (proj_actor, projection_feeds) = projection::Actor::new(
@@ -350,12 +444,15 @@ async fn main() -> Result<()> {
     );
     tasks.add(projection_context.run(proj_actor));
 
-    tasks.add(connect(
-        taker.maker_online_status_feed_receiver.clone(),
-        taker.connection_actor.clone(),
-        maker_identity,
-        possible_addresses,
-    ));
+    match possible_addresses {
+        Some(possible_addresses) => tasks.add(connect(
+            taker.maker_online_status_feed_receiver.clone(),
+            taker.connection_actor.clone(),
+            maker_identity,
+            possible_addresses,
+        )),
+        None => tracing::info!("Connecting to the maker through Tor, legacy connection disabled"),
+    }
 
     let mission_success = rocket::custom(figment)
         .manage(projection_feeds)
@@ -373,13 +470,28 @@ async fn main() -> Result<()> {
                 routes::post_order_request,
                 routes::get_health_check,
                 routes::post_cfd_action,
+                routes::put_position_triggers,
                 routes::post_withdraw_request,
                 routes::get_metrics,
                 routes::put_sync_wallet,
+                routes::post_recover,
                 routes::get_version,
             ],
         )
         .register("/api", default_catchers())
+        .mount(
+            "/api/v1",
+            rocket::routes![
+                api_v1::get_offers,
+                api_v1::get_cfds,
+                api_v1::get_cfd,
+                api_v1::get_utxos,
+                api_v1::put_frozen_utxo,
+                api_v1::delete_frozen_utxo,
+                api_v1::post_order,
+                api_v1::get_openapi_spec,
+            ],
+        )
         .mount("/", rocket::routes![routes::dist, routes::index])
         .register("/", default_catchers())
         .attach(fairings::log_launch())
@@ -408,6 +520,19 @@ async fn resolve_maker_addresses(maker_addr: &str) -> Result<Vec<SocketAddr>> {
     Ok(possible_addresses)
 }
 
+/// Split the maker address into host and port without resolving it
+fn split_maker_address(maker_addr: &str) -> Result<(&str, u16)> {
+    let (host, port) = maker_addr
+        .rsplit_once(':')
+        .with_context(|| format!("Maker address {maker_addr} is not of the form <host>:<port>"))?;
+    let port = port
+        .parse()
+        .with_context(|| format!("Invalid port in maker address {maker_addr}"))?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+
+    Ok((host, port))
+}
+
 /// Attach this fairing to enable loading the UI in the system default browser
 pub fn ui_browser_launch() -> impl Fairing {
     AdHoc::on_liftoff("ui browser launch", move |rocket| {
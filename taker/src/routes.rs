@@ -1,7 +1,7 @@
 use daemon::bdk;
 use daemon::bdk::bitcoin::Amount;
 use daemon::bdk::bitcoin::Network;
-use daemon::bdk::blockchain::ElectrumBlockchain;
+use daemon::bdk::blockchain::AnyBlockchain;
 use daemon::bdk::sled;
 use daemon::connection::ConnectionStatus;
 use daemon::oracle;
@@ -14,7 +14,9 @@ use http_api_problem::HttpApiProblem;
 use http_api_problem::StatusCode;
 use model::Leverage;
 use model::OrderId;
+use model::PositionTriggers;
 use model::Price;
+use model::RecoveredContract;
 use model::Timestamp;
 use model::Usd;
 use model::WalletInfo;
@@ -40,7 +42,7 @@ use tracing::instrument;
 
 type Taker = TakerActorSystem<
     oracle::Actor,
-    wallet::Actor<ElectrumBlockchain, sled::Tree>,
+    wallet::Actor<AnyBlockchain, sled::Tree>,
     xtra_bitmex_price_feed::Actor,
 >;
 
@@ -66,7 +68,7 @@ pub async fn feed(
     let rx = rx.inner();
     let mut rx_cfds = rx.cfds.clone();
     let mut rx_offers = rx.offers.clone();
-    let mut rx_quote = rx.quote.clone();
+    let mut rx_quotes = rx.quotes.clone();
     let mut rx_wallet = rx_wallet.inner().clone();
     let mut rx_maker_status = rx_maker_status.inner().clone();
     let identity = identity_info.inner().clone();
@@ -83,11 +85,10 @@ pub async fn feed(
         yield Event::json(&identity).event("identity");
 
         let offers = rx_offers.borrow().clone();
-        yield Event::json(&offers.long).event("long_offer");
-        yield Event::json(&offers.short).event("short_offer");
+        yield offers.to_sse_event();
 
-        let quote = rx_quote.borrow().clone();
-        yield quote.to_sse_event();
+        let quotes = rx_quotes.borrow().clone();
+        yield quotes.to_sse_event();
 
         let cfds = rx_cfds.borrow().clone();
         if let Some(cfds) = cfds {
@@ -106,8 +107,7 @@ pub async fn feed(
                 },
                 Ok(()) = rx_offers.changed() => {
                     let offers = rx_offers.borrow().clone();
-                    yield Event::json(&offers.long).event("long_offer");
-                    yield Event::json(&offers.short).event("short_offer");
+                    yield offers.to_sse_event();
                 }
                 Ok(()) = rx_cfds.changed() => {
                     let cfds = rx_cfds.borrow().clone();
@@ -115,9 +115,9 @@ pub async fn feed(
                         yield cfds.to_sse_event()
                     }
                 }
-                Ok(()) = rx_quote.changed() => {
-                    let quote = rx_quote.borrow().clone();
-                    yield quote.to_sse_event();
+                Ok(()) = rx_quotes.changed() => {
+                    let quotes = rx_quotes.borrow().clone();
+                    yield quotes.to_sse_event();
                 }
                 _ = heartbeat.tick() => {
                     yield Event::json(&Heartbeat::new()).event("heartbeat")
@@ -208,6 +208,26 @@ pub async fn post_cfd_action(
     Ok(())
 }
 
+#[rocket::put("/cfd/<id>/triggers", data = "<triggers>")]
+#[instrument(name = "PUT /cfd/<id>/triggers", skip(taker, _auth), err)]
+pub async fn put_position_triggers(
+    id: Uuid,
+    triggers: Json<PositionTriggers>,
+    taker: &State<Taker>,
+    _auth: Authenticated,
+) -> Result<(), HttpApiProblem> {
+    taker
+        .set_position_triggers(OrderId::from(id), triggers.into_inner())
+        .await
+        .map_err(|e| {
+            HttpApiProblem::new(StatusCode::BAD_REQUEST)
+                .title("Could not set stop-loss and take-profit")
+                .detail(format!("{e:#}"))
+        })?;
+
+    Ok(())
+}
+
 #[rocket::get("/alive")]
 #[instrument(name = "GET /alive")]
 pub fn get_health_check() {}
@@ -257,6 +277,9 @@ pub struct WithdrawRequest {
     #[serde(with = "bdk::bitcoin::util::amount::serde::as_btc")]
     amount: Amount,
     fee: f32,
+    /// Outputs to spend; if empty, they are chosen automatically
+    #[serde(default)]
+    utxos: Vec<bdk::bitcoin::OutPoint>,
 }
 
 #[rocket::post("/withdraw", data = "<withdraw_request>")]
@@ -275,6 +298,7 @@ pub async fn post_withdraw_request(
             amount,
             withdraw_request.address.clone(),
             bdk::FeeRate::from_sat_per_vb(withdraw_request.fee),
+            withdraw_request.utxos.clone(),
         )
         .await
         .map_err(|e| {
@@ -286,6 +310,22 @@ pub async fn post_withdraw_request(
     Ok(projection::to_mempool_url(txid, *network.inner()))
 }
 
+/// Recover the contracts open with the maker after losing the database
+#[rocket::post("/recover")]
+#[instrument(name = "POST /recover", skip_all, err)]
+pub async fn post_recover(
+    taker: &State<Taker>,
+    _auth: Authenticated,
+) -> Result<Json<Vec<RecoveredContract>>, HttpApiProblem> {
+    let contracts = taker.recover().await.map_err(|e| {
+        HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR)
+            .title("Could not recover contracts")
+            .detail(format!("{e:#}"))
+    })?;
+
+    Ok(Json(contracts))
+}
+
 #[rocket::get("/metrics")]
 #[instrument(name = "GET /metrics", skip_all, err)]
 pub async fn get_metrics<'r>(_auth: Authenticated) -> Result<String, HttpApiProblem> {
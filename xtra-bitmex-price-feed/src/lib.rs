@@ -3,6 +3,7 @@ use async_trait::async_trait;
 pub use bitmex_stream::Network;
 use futures::TryStreamExt;
 use rust_decimal::Decimal;
+use std::collections::HashMap;
 use std::fmt;
 use time::OffsetDateTime;
 use tracing::Instrument;
@@ -10,8 +11,15 @@ use xtra_productivity::xtra_productivity;
 
 pub const QUOTE_INTERVAL_MINUTES: i64 = 1;
 
+/// The BitMEX symbol of the bitcoin perpetual swap.
+pub const XBTUSD: &str = "XBTUSD";
+
+/// The latest quotes, keyed by BitMEX symbol.
+pub type Quotes = HashMap<String, Quote>;
+
 pub struct Actor {
-    latest_quote: Option<Quote>,
+    latest_quotes: Quotes,
+    symbols: Vec<String>,
 
     /// Contains the reason we are stopping.
     stop_reason: Option<Error>,
@@ -20,8 +28,17 @@ pub struct Actor {
 
 impl Actor {
     pub fn new(network: Network) -> Self {
+        Self::with_symbols(network, [XBTUSD])
+    }
+
+    /// Subscribe to the quotes of all the given BitMEX symbols.
+    pub fn with_symbols(
+        network: Network,
+        symbols: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
         Self {
-            latest_quote: None,
+            latest_quotes: Quotes::new(),
+            symbols: symbols.into_iter().map(Into::into).collect(),
             stop_reason: None,
             network,
         }
@@ -40,12 +57,14 @@ impl xtra::Actor for Actor {
             {
                 let this = this.clone();
                 let network = self.network;
+                let topics = self
+                    .symbols
+                    .iter()
+                    .map(|symbol| format!("quoteBin{QUOTE_INTERVAL_MINUTES}m:{symbol}"))
+                    .collect::<Vec<_>>();
 
                 async move {
-                    let mut stream = bitmex_stream::subscribe(
-                        [format!("quoteBin{QUOTE_INTERVAL_MINUTES}m:XBTUSD")],
-                        network,
-                    );
+                    let mut stream = bitmex_stream::subscribe(topics, network);
 
                     while let Some(text) = stream
                         .try_next()
@@ -56,16 +75,17 @@ impl xtra::Actor for Actor {
                             .map_err(|e| Error::FailedToParseQuote { source: e })?;
 
                         match quote {
-                            Some(quote) => {
+                            Some((symbol, quote)) => {
                                 let span = tracing::debug_span!(
                                     "Received new quote",
+                                    %symbol,
                                     bid = %quote.bid,
                                     ask = %quote.ask,
                                     timestamp = %quote.timestamp,
                                 );
 
                                 let is_our_address_disconnected = this
-                                    .send(NewQuoteReceived(quote))
+                                    .send(NewQuoteReceived { symbol, quote })
                                     .instrument(span)
                                     .await
                                     .is_err();
@@ -104,11 +124,11 @@ impl Actor {
     }
 
     async fn handle(&mut self, msg: NewQuoteReceived) {
-        self.latest_quote = Some(msg.0);
+        self.latest_quotes.insert(msg.symbol, msg.quote);
     }
 
-    async fn handle(&mut self, _: LatestQuote) -> Option<Quote> {
-        self.latest_quote
+    async fn handle(&mut self, _: LatestQuotes) -> Quotes {
+        self.latest_quotes.clone()
     }
 }
 
@@ -126,11 +146,16 @@ pub enum Error {
 
 /// Private message to update our internal state with the latest quote.
 #[derive(Debug)]
-struct NewQuoteReceived(Quote);
+struct NewQuoteReceived {
+    symbol: String,
+    quote: Quote,
+}
 
-/// Request the latest quote from the price feed.
+/// Request the latest quote of every subscribed symbol from the price feed.
+///
+/// Symbols for which no quote has been received yet are absent.
 #[derive(Debug, Clone, Copy)]
-pub struct LatestQuote;
+pub struct LatestQuotes;
 
 #[derive(Clone, Copy)]
 pub struct Quote {
@@ -155,7 +180,7 @@ impl fmt::Debug for Quote {
 }
 
 impl Quote {
-    fn from_str(text: &str) -> Result<Option<Self>> {
+    fn from_str(text: &str) -> Result<Option<(String, Self)>> {
         let table_message = match serde_json::from_str::<wire::TableMessage>(text) {
             Ok(table_message) => table_message,
             Err(_) => {
@@ -166,11 +191,14 @@ impl Quote {
 
         let [quote] = table_message.data;
 
-        Ok(Some(Self {
-            timestamp: quote.timestamp,
-            bid: quote.bid_price,
-            ask: quote.ask_price,
-        }))
+        Ok(Some((
+            quote.symbol,
+            Self {
+                timestamp: quote.timestamp,
+                bid: quote.bid_price,
+                ask: quote.ask_price,
+            },
+        )))
     }
 
     pub fn bid(&self) -> Decimal {
@@ -222,8 +250,9 @@ mod tests {
 
     #[test]
     fn can_deserialize_quote_message() {
-        let quote = Quote::from_str(r#"{"table":"quoteBin1m","action":"insert","data":[{"timestamp":"2021-09-21T02:40:00.000Z","symbol":"XBTUSD","bidSize":50200,"bidPrice":42640.5,"askPrice":42641,"askSize":363600}]}"#).unwrap().unwrap();
+        let (symbol, quote) = Quote::from_str(r#"{"table":"quoteBin1m","action":"insert","data":[{"timestamp":"2021-09-21T02:40:00.000Z","symbol":"XBTUSD","bidSize":50200,"bidPrice":42640.5,"askPrice":42641,"askSize":363600}]}"#).unwrap().unwrap();
 
+        assert_eq!(symbol, XBTUSD);
         assert_eq!(quote.bid, dec!(42640.5));
         assert_eq!(quote.ask, dec!(42641));
         assert_eq!(quote.timestamp.unix_timestamp(), 1632192000)
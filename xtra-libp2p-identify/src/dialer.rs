@@ -0,0 +1,157 @@
+use crate::protocol;
+use crate::PeerInfo;
+use crate::PROTOCOL_NAME;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::time::Duration;
+use tokio_extras::spawn_fallible;
+use xtra::prelude::async_trait;
+use xtra::Address;
+use xtra::Context;
+use xtra_libp2p::endpoint;
+use xtra_libp2p::libp2p::PeerId;
+use xtra_libp2p::Endpoint;
+use xtra_libp2p::GetConnectionStats;
+use xtra_libp2p::OpenSubstream;
+use xtra_productivity::xtra_productivity;
+use xtras::SendAsyncNext;
+
+/// An actor implementing the dialing end of the ipfs/libp2p identify protocol.
+///
+/// Every peer is identified once a connection to it is established. The received [`PeerInfo`] is
+/// kept for as long as the connection lasts and can be requested with [`GetPeerInfo`] and
+/// [`GetPeerInfos`].
+pub struct Actor {
+    endpoint: Address<Endpoint>,
+    connected_peers: HashSet<PeerId>,
+    peer_infos: HashMap<PeerId, PeerInfo>,
+}
+
+impl Actor {
+    pub fn new(endpoint: Address<Endpoint>) -> Self {
+        Self {
+            endpoint,
+            connected_peers: HashSet::default(),
+            peer_infos: HashMap::default(),
+        }
+    }
+
+    fn identify(&self, peer: PeerId, ctx: &mut Context<Self>) {
+        let endpoint = self.endpoint.clone();
+        let this = ctx.address().expect("we are alive");
+
+        let identify_fut = {
+            let this = this.clone();
+
+            async move {
+                let stream = endpoint
+                    .send(OpenSubstream::single_protocol(peer, PROTOCOL_NAME))
+                    .await??
+                    .await?;
+                let peer_info = protocol::recv(stream).await?.into_peer_info(peer)?;
+
+                this.send_async_next(RecordPeerInfo { peer, peer_info })
+                    .await;
+                anyhow::Ok(())
+            }
+        };
+
+        let err_handler = move |e| async move {
+            tracing::warn!(%peer, "Outbound identify protocol failed: {e:#}")
+        };
+
+        spawn_fallible(&this, identify_fut, err_handler);
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    #[tracing::instrument("Identify actor started", skip_all)]
+    async fn started(&mut self, ctx: &mut Context<Self>) {
+        match self.endpoint.send(GetConnectionStats).await {
+            Ok(connection_stats) => {
+                for peer in connection_stats.connected_peers {
+                    self.connected_peers.insert(peer);
+                    self.identify(peer, ctx);
+                }
+            }
+            Err(e) => {
+                tracing::error!(
+                    "Unable to receive connection stats from the endpoint upon startup: {e:#}"
+                );
+                // This code path should not be hit, but in case we run into an error this sleep
+                // prevents a continuous endless loop of restarts.
+                tokio_extras::time::sleep(Duration::from_secs(2)).await;
+
+                ctx.stop_self();
+            }
+        }
+    }
+
+    async fn stopped(self) -> Self::Stop {}
+}
+
+/// Private message to record the identify message of a peer.
+struct RecordPeerInfo {
+    peer: PeerId,
+    peer_info: PeerInfo,
+}
+
+/// Get the [`PeerInfo`] of a connected peer.
+///
+/// Returns `None` if the peer has not (yet) been identified.
+#[derive(Clone, Copy, Debug)]
+pub struct GetPeerInfo(pub PeerId);
+
+/// Get the [`PeerInfo`] of all identified peers.
+#[derive(Clone, Copy, Debug)]
+pub struct GetPeerInfos;
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, msg: RecordPeerInfo) {
+        let RecordPeerInfo { peer, peer_info } = msg;
+
+        if !self.connected_peers.contains(&peer) {
+            tracing::debug!(%peer, "Ignoring identify message of disconnected peer");
+            return;
+        }
+
+        tracing::info!(
+            %peer,
+            agent_version = %peer_info.agent_version,
+            protocol_version = %peer_info.protocol_version,
+            protocols = ?peer_info.protocols,
+            "Identified peer"
+        );
+
+        self.peer_infos.insert(peer, peer_info);
+    }
+
+    async fn handle(&mut self, GetPeerInfo(peer): GetPeerInfo) -> Option<PeerInfo> {
+        self.peer_infos.get(&peer).cloned()
+    }
+
+    async fn handle(&mut self, _: GetPeerInfos) -> HashMap<PeerId, PeerInfo> {
+        self.peer_infos.clone()
+    }
+}
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle_connection_established(
+        &mut self,
+        msg: endpoint::ConnectionEstablished,
+        ctx: &mut Context<Self>,
+    ) {
+        self.connected_peers.insert(msg.peer);
+        self.identify(msg.peer, ctx);
+    }
+
+    async fn handle_connection_dropped(&mut self, msg: endpoint::ConnectionDropped) {
+        self.connected_peers.remove(&msg.peer);
+        self.peer_infos.remove(&msg.peer);
+    }
+}
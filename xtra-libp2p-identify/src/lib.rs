@@ -0,0 +1,146 @@
+use xtra_libp2p::libp2p::Multiaddr;
+
+pub mod dialer;
+pub mod listener;
+mod protocol;
+
+/// The name of the official ipfs/libp2p identify protocol.
+///
+/// Using this indicates that we are wire-compatible with other libp2p/ipfs nodes.
+pub const PROTOCOL_NAME: &str = "/ipfs/id/1.0.0";
+
+/// What a peer told us about itself through the identify protocol.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PeerInfo {
+    /// The family of protocols the peer speaks, e.g. `itchysats/1.0.0`.
+    pub protocol_version: String,
+    /// The software the peer runs, usually its name and version.
+    pub agent_version: String,
+    pub listen_addrs: Vec<Multiaddr>,
+    /// The protocols the peer accepts substreams for.
+    pub protocols: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::Future;
+    use futures::FutureExt;
+    use std::time::Duration;
+    use xtra::spawn::TokioGlobalSpawnExt;
+    use xtra::Actor as _;
+    use xtra::Address;
+    use xtra::Context;
+    use xtra_libp2p::endpoint::Subscribers;
+    use xtra_libp2p::libp2p::identity::Keypair;
+    use xtra_libp2p::libp2p::multiaddr::Protocol;
+    use xtra_libp2p::libp2p::transport::MemoryTransport;
+    use xtra_libp2p::libp2p::PeerId;
+    use xtra_libp2p::Connect;
+    use xtra_libp2p::Endpoint;
+    use xtra_libp2p::ListenOn;
+
+    #[tokio::test]
+    async fn peers_identify_each_other() {
+        tracing_subscriber::fmt()
+            .with_env_filter("xtra_libp2p_identify=trace")
+            .with_test_writer()
+            .init();
+
+        let (alice_peer_id, alice_dialer, alice_endpoint) =
+            create_endpoint_with_identify("alice/0.1.0");
+        let (bob_peer_id, bob_dialer, bob_endpoint) = create_endpoint_with_identify("bob/0.2.0");
+
+        let alice_listen_addr = Multiaddr::empty().with(Protocol::Memory(1000));
+        alice_endpoint
+            .send(ListenOn(alice_listen_addr.clone()))
+            .await
+            .unwrap();
+        bob_endpoint
+            .send(Connect(
+                alice_listen_addr
+                    .clone()
+                    .with(Protocol::P2p(alice_peer_id.into())),
+            ))
+            .await
+            .unwrap()
+            .unwrap();
+
+        let bob_info = retry_until_some(|| {
+            let alice_dialer = alice_dialer.clone();
+            async move {
+                alice_dialer
+                    .send(dialer::GetPeerInfo(bob_peer_id))
+                    .map(|res| res.unwrap())
+                    .await
+            }
+        })
+        .await;
+        let alice_info = retry_until_some(|| {
+            let bob_dialer = bob_dialer.clone();
+            async move {
+                bob_dialer
+                    .send(dialer::GetPeerInfo(alice_peer_id))
+                    .map(|res| res.unwrap())
+                    .await
+            }
+        })
+        .await;
+
+        assert_eq!(bob_info.agent_version, "bob/0.2.0");
+        assert_eq!(bob_info.protocols, vec![PROTOCOL_NAME.to_owned()]);
+        assert_eq!(alice_info.agent_version, "alice/0.1.0");
+        assert_eq!(alice_info.listen_addrs, vec![alice_listen_addr]);
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn create_endpoint_with_identify(
+        agent_version: &str,
+    ) -> (PeerId, Address<dialer::Actor>, Address<Endpoint>) {
+        let (endpoint_address, endpoint_context) = Context::new(None);
+
+        let id = Keypair::generate_ed25519();
+        let dialer_address = dialer::Actor::new(endpoint_address.clone())
+            .create(None)
+            .spawn_global();
+        let listener_address = listener::Actor::new(
+            endpoint_address.clone(),
+            id.public(),
+            "test/1.0.0".to_owned(),
+            agent_version.to_owned(),
+        )
+        .create(None)
+        .spawn_global();
+
+        let endpoint = Endpoint::new(
+            Box::new(MemoryTransport::default),
+            id.clone(),
+            Duration::from_secs(10),
+            [(PROTOCOL_NAME, listener_address.into())],
+            Subscribers::new(
+                vec![dialer_address.clone().into()],
+                vec![dialer_address.clone().into()],
+                vec![],
+                vec![],
+            ),
+        );
+
+        #[allow(clippy::disallowed_methods)]
+        tokio::spawn(endpoint_context.run(endpoint));
+
+        (id.public().to_peer_id(), dialer_address, endpoint_address)
+    }
+
+    async fn retry_until_some<F, FUT, T>(mut fut: F) -> T
+    where
+        F: FnMut() -> FUT,
+        FUT: Future<Output = Option<T>>,
+    {
+        loop {
+            match fut().await {
+                Some(t) => return t,
+                None => tokio_extras::time::sleep(Duration::from_millis(200)).await,
+            }
+        }
+    }
+}
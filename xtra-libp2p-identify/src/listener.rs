@@ -0,0 +1,86 @@
+use crate::protocol;
+use crate::protocol::Identify;
+use anyhow::Context as _;
+use async_trait::async_trait;
+use xtra::Address;
+use xtra::Context;
+use xtra_libp2p::libp2p::identity::PublicKey;
+use xtra_libp2p::Endpoint;
+use xtra_libp2p::GetConnectionStats;
+use xtra_libp2p::NewInboundSubstream;
+use xtra_productivity::xtra_productivity;
+
+/// An actor implementing the listening end of the ipfs/libp2p identify protocol.
+///
+/// Whenever a peer opens a substream, we send it our identify message. The listen addresses and
+/// protocols we announce are taken from the provided [`Endpoint`] at that time.
+pub struct Actor {
+    endpoint: Address<Endpoint>,
+    public_key: PublicKey,
+    protocol_version: String,
+    agent_version: String,
+}
+
+impl Actor {
+    pub fn new(
+        endpoint: Address<Endpoint>,
+        public_key: PublicKey,
+        protocol_version: String,
+        agent_version: String,
+    ) -> Self {
+        Self {
+            endpoint,
+            public_key,
+            protocol_version,
+            agent_version,
+        }
+    }
+}
+
+#[xtra_productivity]
+impl Actor {
+    async fn handle(&mut self, message: NewInboundSubstream, ctx: &mut Context<Self>) {
+        let NewInboundSubstream { stream, peer } = message;
+
+        let endpoint = self.endpoint.clone();
+        let public_key = self.public_key.clone();
+        let protocol_version = self.protocol_version.clone();
+        let agent_version = self.agent_version.clone();
+
+        let future = async move {
+            let connection_stats = endpoint
+                .send(GetConnectionStats)
+                .await
+                .context("Endpoint is disconnected")?;
+
+            let message = Identify::new(
+                &public_key,
+                protocol_version,
+                agent_version,
+                connection_stats.listen_addresses,
+                connection_stats
+                    .supported_protocols
+                    .into_iter()
+                    .map(String::from),
+            );
+            protocol::send(stream, message).await?;
+
+            anyhow::Ok(())
+        };
+
+        tokio_extras::spawn_fallible(
+            &ctx.address().expect("self to be alive"),
+            future,
+            move |e| async move {
+                tracing::debug!(%peer, "Inbound identify protocol failed: {e:#}");
+            },
+        );
+    }
+}
+
+#[async_trait]
+impl xtra::Actor for Actor {
+    type Stop = ();
+
+    async fn stopped(self) -> Self::Stop {}
+}
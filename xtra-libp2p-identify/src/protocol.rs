@@ -0,0 +1,174 @@
+//! The actual protocol functions for exchanging identify messages.
+//!
+//! The wire format follows the specification at https://github.com/libp2p/specs/blob/master/identify/README.md:
+//! A single, length-prefixed protobuf message is sent by the listening side, which closes the
+//! substream afterwards.
+
+use crate::PeerInfo;
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use futures::AsyncRead;
+use futures::AsyncReadExt;
+use futures::AsyncWrite;
+use futures::AsyncWriteExt;
+use prost::Message;
+use std::io;
+use xtra_libp2p::libp2p::identity::PublicKey;
+use xtra_libp2p::libp2p::Multiaddr;
+use xtra_libp2p::libp2p::PeerId;
+
+/// The maximum size of an identify message we are willing to read.
+const MAX_MESSAGE_SIZE: u64 = 4096;
+
+/// The identify message as defined in https://github.com/libp2p/specs/blob/master/identify/README.md#the-identify-message.
+#[derive(Clone, PartialEq, Message)]
+pub(crate) struct Identify {
+    #[prost(string, optional, tag = "5")]
+    pub protocol_version: Option<String>,
+    #[prost(string, optional, tag = "6")]
+    pub agent_version: Option<String>,
+    #[prost(bytes = "vec", optional, tag = "1")]
+    pub public_key: Option<Vec<u8>>,
+    #[prost(bytes = "vec", repeated, tag = "2")]
+    pub listen_addrs: Vec<Vec<u8>>,
+    #[prost(bytes = "vec", optional, tag = "4")]
+    pub observed_addr: Option<Vec<u8>>,
+    #[prost(string, repeated, tag = "3")]
+    pub protocols: Vec<String>,
+}
+
+impl Identify {
+    pub(crate) fn new(
+        public_key: &PublicKey,
+        protocol_version: String,
+        agent_version: String,
+        listen_addrs: impl IntoIterator<Item = Multiaddr>,
+        protocols: impl IntoIterator<Item = String>,
+    ) -> Self {
+        Self {
+            protocol_version: Some(protocol_version),
+            agent_version: Some(agent_version),
+            public_key: Some(public_key.to_protobuf_encoding()),
+            listen_addrs: listen_addrs.into_iter().map(|addr| addr.to_vec()).collect(),
+            observed_addr: None,
+            protocols: protocols.into_iter().collect(),
+        }
+    }
+
+    /// Convert the message into [`PeerInfo`], verifying that it was sent by the given peer.
+    ///
+    /// Listen addresses that cannot be decoded are skipped.
+    pub(crate) fn into_peer_info(self, peer: PeerId) -> Result<PeerInfo> {
+        let public_key = self
+            .public_key
+            .context("Identify message without public key")?;
+        let public_key = PublicKey::from_protobuf_encoding(&public_key)
+            .context("Failed to decode public key of identify message")?;
+
+        let identified_peer = public_key.to_peer_id();
+        if identified_peer != peer {
+            bail!("Identify message of peer {peer} contains public key of peer {identified_peer}");
+        }
+
+        let listen_addrs = self
+            .listen_addrs
+            .into_iter()
+            .filter_map(|addr| match Multiaddr::try_from(addr) {
+                Ok(addr) => Some(addr),
+                Err(e) => {
+                    tracing::debug!(%peer, "Skipping invalid listen address: {e}");
+                    None
+                }
+            })
+            .collect();
+
+        Ok(PeerInfo {
+            protocol_version: self.protocol_version.unwrap_or_default(),
+            agent_version: self.agent_version.unwrap_or_default(),
+            listen_addrs,
+            protocols: self.protocols,
+        })
+    }
+}
+
+/// Sends our identify message and closes the substream.
+pub(crate) async fn send<S>(mut stream: S, message: Identify) -> io::Result<()>
+where
+    S: AsyncWrite + Unpin,
+{
+    stream
+        .write_all(&message.encode_length_delimited_to_vec())
+        .await?;
+    stream.flush().await?;
+    stream.close().await?;
+
+    Ok(())
+}
+
+/// Waits for the identify message of the other peer.
+pub(crate) async fn recv<S>(stream: S) -> io::Result<Identify>
+where
+    S: AsyncRead + Unpin,
+{
+    let mut bytes = Vec::new();
+    stream
+        .take(MAX_MESSAGE_SIZE)
+        .read_to_end(&mut bytes)
+        .await?;
+
+    let message = Identify::decode_length_delimited(bytes.as_slice())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    Ok(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xtra_libp2p::libp2p::identity::Keypair;
+
+    #[test]
+    fn identify_message_roundtrip() {
+        let keypair = Keypair::generate_ed25519();
+        let peer = keypair.public().to_peer_id();
+        let listen_addr = "/ip4/127.0.0.1/tcp/10000".parse::<Multiaddr>().unwrap();
+
+        let message = Identify::new(
+            &keypair.public(),
+            "itchysats/1.0.0".to_owned(),
+            "0.5.0".to_owned(),
+            vec![listen_addr.clone()],
+            vec!["/ipfs/ping/1.0.0".to_owned()],
+        );
+        let decoded =
+            Identify::decode_length_delimited(message.encode_length_delimited_to_vec().as_slice())
+                .unwrap();
+
+        assert_eq!(
+            decoded.into_peer_info(peer).unwrap(),
+            PeerInfo {
+                protocol_version: "itchysats/1.0.0".to_owned(),
+                agent_version: "0.5.0".to_owned(),
+                listen_addrs: vec![listen_addr],
+                protocols: vec!["/ipfs/ping/1.0.0".to_owned()],
+            }
+        );
+    }
+
+    #[test]
+    fn identify_message_of_other_peer_is_rejected() {
+        let keypair = Keypair::generate_ed25519();
+        let other_peer = Keypair::generate_ed25519().public().to_peer_id();
+
+        let message = Identify::new(
+            &keypair.public(),
+            "itchysats/1.0.0".to_owned(),
+            "0.5.0".to_owned(),
+            vec![],
+            vec![],
+        );
+
+        assert!(message.into_peer_info(other_peer).is_err());
+    }
+}
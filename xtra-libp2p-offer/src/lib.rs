@@ -2,7 +2,11 @@ pub mod maker;
 mod protocol;
 pub mod taker;
 
-pub const PROTOCOL_NAME: &str = "/itchysats/offer/1.0.0";
+/// Exchanges the offers of every trading pair.
+pub const PROTOCOL_NAME: &str = "/itchysats/offer/2.0.0";
+
+/// Exchanges the BTC/USD offers only, spoken by peers that don't support [`PROTOCOL_NAME`] yet.
+pub const DEPRECATED_PROTOCOL_NAME: &str = "/itchysats/offer/1.0.0";
 
 #[cfg(test)]
 mod tests {
@@ -11,6 +15,7 @@ mod tests {
     use async_trait::async_trait;
     use futures::Future;
     use futures::FutureExt;
+    use model::olivia;
     use model::olivia::BitMexPriceEventId;
     use model::FundingRate;
     use model::Leverage;
@@ -20,6 +25,7 @@ mod tests {
     use model::Origin;
     use model::Position;
     use model::Price;
+    use model::TradingPair;
     use model::TxFeeRate;
     use model::Usd;
     use rust_decimal::Decimal;
@@ -50,7 +56,8 @@ mod tests {
 
         let (maker_peer_id, maker_offer_addr, maker_endpoint_addr) =
             create_endpoint_with_offer_maker();
-        let (offer_receiver_addr, taker_endpoint_addr) = create_endpoint_with_offer_taker();
+        let (offer_receiver_addr, taker_endpoint_addr) =
+            create_endpoint_with_offer_taker(PROTOCOL_NAME);
 
         maker_endpoint_addr
             .send(ListenOn(Multiaddr::empty().with(Protocol::Memory(1000))))
@@ -98,7 +105,63 @@ mod tests {
         })
         .await;
 
-        assert_eq!(new_offers, Some(received_offers))
+        assert_eq!(new_offers, received_offers)
+    }
+
+    #[tokio::test]
+    async fn given_taker_with_deprecated_protocol_then_receives_btc_usd_offers() {
+        let (maker_peer_id, maker_offer_addr, maker_endpoint_addr) =
+            create_endpoint_with_offer_maker();
+        let (offer_receiver_addr, taker_endpoint_addr) =
+            create_endpoint_with_offer_taker(DEPRECATED_PROTOCOL_NAME);
+
+        maker_endpoint_addr
+            .send(ListenOn(Multiaddr::empty().with(Protocol::Memory(1001))))
+            .await
+            .unwrap();
+        taker_endpoint_addr
+            .send(Connect(
+                Multiaddr::empty()
+                    .with(Protocol::Memory(1001))
+                    .with(Protocol::P2p(maker_peer_id.into())),
+            ))
+            .await
+            .unwrap()
+            .unwrap();
+
+        let new_offers = dummy_maker_offers();
+
+        #[allow(clippy::disallowed_methods)]
+        tokio::spawn({
+            let new_offers = new_offers.clone();
+            async move {
+                loop {
+                    maker_offer_addr
+                        .send(crate::maker::NewOffers::new(new_offers.clone()))
+                        .await
+                        .unwrap();
+
+                    tokio_extras::time::sleep(Duration::from_millis(200)).await;
+                }
+            }
+        });
+
+        let received_offers = retry_until_some(|| {
+            let offer_receiver_addr = offer_receiver_addr.clone();
+            async move {
+                offer_receiver_addr
+                    .send(GetLatestOffers)
+                    .map(|res| res.unwrap())
+                    .await
+            }
+        })
+        .await;
+
+        let btc_usd_offers = new_offers
+            .into_iter()
+            .filter(|offers| offers.trading_pair == TradingPair::BtcUsd)
+            .collect::<Vec<_>>();
+        assert_eq!(btc_usd_offers, received_offers)
     }
 
     fn create_endpoint_with_offer_maker(
@@ -129,18 +192,25 @@ mod tests {
         (id.public().to_peer_id(), offer_maker_addr, endpoint_addr)
     }
 
-    fn create_endpoint_with_offer_taker() -> (Address<OffersReceiver>, Address<Endpoint>) {
+    fn create_endpoint_with_offer_taker(
+        protocol: &'static str,
+    ) -> (Address<OffersReceiver>, Address<Endpoint>) {
         let offers_receiver_addr = OffersReceiver::new().create(None).spawn_global();
 
-        let offer_taker_addr = crate::taker::Actor::new(offers_receiver_addr.clone().into())
-            .create(None)
-            .spawn_global();
+        let offer_taker = match protocol {
+            PROTOCOL_NAME => crate::taker::Actor::new(offers_receiver_addr.clone().into()),
+            DEPRECATED_PROTOCOL_NAME => {
+                crate::taker::Actor::deprecated(offers_receiver_addr.clone().into())
+            }
+            _ => unreachable!("unknown offer protocol {protocol}"),
+        };
+        let offer_taker_addr = offer_taker.create(None).spawn_global();
 
         let endpoint_addr = Endpoint::new(
             Box::new(MemoryTransport::default),
             Keypair::generate_ed25519(),
             Duration::from_secs(10),
-            [(PROTOCOL_NAME, offer_taker_addr.into())],
+            [(protocol, offer_taker_addr.into())],
             Subscribers::default(),
         )
         .create(None)
@@ -150,7 +220,7 @@ mod tests {
     }
 
     struct OffersReceiver {
-        latest_offers: Option<MakerOffers>,
+        latest_offers: Option<Vec<MakerOffers>>,
     }
 
     impl OffersReceiver {
@@ -171,7 +241,7 @@ mod tests {
     #[xtra_productivity]
     impl OffersReceiver {
         async fn handle(&mut self, msg: LatestMakerOffers) {
-            self.latest_offers = msg.0;
+            self.latest_offers = Some(msg.0);
         }
     }
 
@@ -179,7 +249,7 @@ mod tests {
 
     #[xtra_productivity]
     impl OffersReceiver {
-        async fn handle(&mut self, _: GetLatestOffers) -> Option<MakerOffers> {
+        async fn handle(&mut self, _: GetLatestOffers) -> Option<Vec<MakerOffers>> {
             self.latest_offers.clone()
         }
     }
@@ -197,29 +267,36 @@ mod tests {
         }
     }
 
-    pub fn dummy_maker_offers() -> Option<MakerOffers> {
-        Some(MakerOffers {
-            long: Some(dummy_order(Position::Long)),
-            short: Some(dummy_order(Position::Short)),
-            tx_fee_rate: TxFeeRate::default(),
-            funding_rate_long: FundingRate::new(Decimal::ONE).unwrap(),
-            funding_rate_short: FundingRate::new(Decimal::NEGATIVE_ONE).unwrap(),
-        })
+    pub fn dummy_maker_offers() -> Vec<MakerOffers> {
+        TradingPair::ALL
+            .into_iter()
+            .map(|trading_pair| MakerOffers {
+                trading_pair,
+                long: Some(dummy_order(trading_pair, Position::Long)),
+                short: Some(dummy_order(trading_pair, Position::Short)),
+                tx_fee_rate: TxFeeRate::default(),
+                funding_rate_long: FundingRate::new(Decimal::ONE).unwrap(),
+                funding_rate_short: FundingRate::new(Decimal::NEGATIVE_ONE).unwrap(),
+            })
+            .collect()
     }
 
-    fn dummy_order(position: Position) -> Order {
+    fn dummy_order(trading_pair: TradingPair, position: Position) -> Order {
         Order::new(
             position,
             Price::new(dec!(1000)).unwrap(),
             Usd::new(dec!(100)),
             Usd::new(dec!(1000)),
             Origin::Ours,
-            BitMexPriceEventId::with_20_digits(datetime!(2021-10-04 22:00:00).assume_utc()),
+            BitMexPriceEventId::with_20_digits(datetime!(2021-10-04 22:00:00).assume_utc())
+                .with_trading_pair(trading_pair),
+            *olivia::PUBLIC_KEY,
             time::Duration::hours(24),
             TxFeeRate::default(),
             FundingRate::default(),
             OpeningFee::default(),
             vec![Leverage::TWO],
+            Leverage::ONE,
         )
     }
 }
@@ -1,7 +1,9 @@
 use crate::protocol;
+use crate::DEPRECATED_PROTOCOL_NAME;
 use crate::PROTOCOL_NAME;
 use async_trait::async_trait;
 use model::MakerOffers;
+use model::TradingPair;
 use std::collections::HashSet;
 use std::time::Duration;
 use tokio_extras::spawn_fallible;
@@ -18,7 +20,7 @@ pub const LIBP2P_BROADCAST_OFFERS_SPAN: &str = "Broadcast offers to taker (libp2
 pub struct Actor {
     endpoint: xtra::Address<Endpoint>,
     connected_peers: HashSet<PeerId>,
-    latest_offers: Option<MakerOffers>,
+    latest_offers: Vec<MakerOffers>,
 }
 
 impl Actor {
@@ -26,7 +28,7 @@ impl Actor {
         Self {
             endpoint,
             connected_peers: HashSet::default(),
-            latest_offers: None,
+            latest_offers: Vec::new(),
         }
     }
 
@@ -36,12 +38,25 @@ impl Actor {
 
         let span = tracing::debug_span!("Send offers", %peer).or_current();
         let task = async move {
-            let stream = endpoint
-                .send(OpenSubstream::single_protocol(peer, PROTOCOL_NAME))
+            let (negotiated_protocol, stream) = endpoint
+                .send(OpenSubstream::multiple_protocols(
+                    peer,
+                    vec![PROTOCOL_NAME, DEPRECATED_PROTOCOL_NAME],
+                ))
                 .await??
                 .await?;
 
-            protocol::send(stream, offers).await?;
+            match negotiated_protocol {
+                PROTOCOL_NAME => protocol::send(stream, offers).await?,
+                DEPRECATED_PROTOCOL_NAME => {
+                    let offers = offers
+                        .into_iter()
+                        .find(|offers| offers.trading_pair == TradingPair::BtcUsd);
+
+                    protocol::send_deprecated(stream, offers).await?
+                }
+                _ => unreachable!("Negotiated protocol we did not ask for"),
+            }
 
             anyhow::Ok(())
         };
@@ -87,10 +102,13 @@ impl Actor {
 
 /// Instruct the `offer::maker::Actor` to broadcast to all
 /// connected peers an update to the current offers.
-pub struct NewOffers(Option<MakerOffers>);
+///
+/// Contains the offers of every trading pair, peers that only speak
+/// the deprecated protocol receive the BTC/USD offers.
+pub struct NewOffers(Vec<MakerOffers>);
 
 impl NewOffers {
-    pub fn new(offers: Option<MakerOffers>) -> Self {
+    pub fn new(offers: Vec<MakerOffers>) -> Self {
         Self(offers)
     }
 }
@@ -25,7 +25,38 @@ static MESSAGES_RECEIVED: conquer_once::Lazy<prometheus::IntCounter> =
         .unwrap()
     });
 
-pub(crate) async fn send<S>(sink: S, offers: Option<MakerOffers>) -> Result<(), JsonCodecError>
+/// Send the offers of all trading pairs.
+pub(crate) async fn send<S>(sink: S, offers: Vec<MakerOffers>) -> Result<(), JsonCodecError>
+where
+    S: AsyncWriteExt + Unpin,
+{
+    let mut framed = FramedWrite::new(sink, JsonCodec::<Vec<MakerOffers>, ()>::new());
+
+    framed.send(offers).await?;
+    MESSAGES_SENT.inc();
+
+    Ok(())
+}
+
+pub(crate) async fn recv<S>(stream: S) -> Result<Vec<MakerOffers>, ReceiveError>
+where
+    S: AsyncReadExt + Unpin,
+{
+    let mut framed = FramedRead::new(stream, JsonCodec::<(), Vec<MakerOffers>>::new());
+
+    let offers = framed.next().await.ok_or(ReceiveError::Terminated)??;
+    MESSAGES_RECEIVED.inc();
+
+    Ok(offers)
+}
+
+/// Send the offers of a single trading pair as in [`DEPRECATED_PROTOCOL_NAME`].
+///
+/// [`DEPRECATED_PROTOCOL_NAME`]: crate::DEPRECATED_PROTOCOL_NAME
+pub(crate) async fn send_deprecated<S>(
+    sink: S,
+    offers: Option<MakerOffers>,
+) -> Result<(), JsonCodecError>
 where
     S: AsyncWriteExt + Unpin,
 {
@@ -37,7 +68,7 @@ where
     Ok(())
 }
 
-pub(crate) async fn recv<S>(stream: S) -> Result<Option<MakerOffers>, ReceiveError>
+pub(crate) async fn recv_deprecated<S>(stream: S) -> Result<Option<MakerOffers>, ReceiveError>
 where
     S: AsyncReadExt + Unpin,
 {
@@ -76,14 +107,29 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn can_execute_protocol_with_none_offers() {
+    async fn can_execute_protocol_without_offers() {
         let (stream, sink) = pipe();
 
-        let maker_offers = None;
+        let maker_offers = vec![];
 
         let (send_res, recv_res) = tokio::join!(send(sink, maker_offers.clone()), recv(stream));
 
         assert!(send_res.is_ok());
         assert_eq!(recv_res.unwrap(), maker_offers)
     }
+
+    #[tokio::test]
+    async fn can_execute_deprecated_protocol_with_none_offers() {
+        let (stream, sink) = pipe();
+
+        let maker_offers = None;
+
+        let (send_res, recv_res) = tokio::join!(
+            send_deprecated(sink, maker_offers.clone()),
+            recv_deprecated(stream)
+        );
+
+        assert!(send_res.is_ok());
+        assert_eq!(recv_res.unwrap(), maker_offers)
+    }
 }
@@ -8,11 +8,24 @@ use xtra_productivity::xtra_productivity;
 
 pub struct Actor {
     maker_offers: MessageChannel<LatestMakerOffers, ()>,
+    deprecated: bool,
 }
 
 impl Actor {
+    /// Handle substreams of [`PROTOCOL_NAME`](crate::PROTOCOL_NAME).
     pub fn new(maker_offers: MessageChannel<LatestMakerOffers, ()>) -> Self {
-        Self { maker_offers }
+        Self {
+            maker_offers,
+            deprecated: false,
+        }
+    }
+
+    /// Handle substreams of [`DEPRECATED_PROTOCOL_NAME`](crate::DEPRECATED_PROTOCOL_NAME).
+    pub fn deprecated(maker_offers: MessageChannel<LatestMakerOffers, ()>) -> Self {
+        Self {
+            maker_offers,
+            deprecated: true,
+        }
     }
 }
 
@@ -21,11 +34,19 @@ impl Actor {
     async fn handle(&mut self, msg: NewInboundSubstream, ctx: &mut xtra::Context<Self>) {
         let NewInboundSubstream { peer, stream } = msg;
         let maker_offers = self.maker_offers.clone();
+        let deprecated = self.deprecated;
 
         let this = ctx.address().expect("self to be alive");
 
         let task = async move {
-            let offers = protocol::recv(stream).await?;
+            let offers = if deprecated {
+                protocol::recv_deprecated(stream)
+                    .await?
+                    .into_iter()
+                    .collect()
+            } else {
+                protocol::recv(stream).await?
+            };
             let span = tracing::debug_span!("Received new offers from maker", %peer, ?offers);
             maker_offers
                 .send(LatestMakerOffers(offers))
@@ -43,8 +64,8 @@ impl Actor {
 }
 
 /// Message used to inform other actors about the maker's latest
-/// offers.
-pub struct LatestMakerOffers(pub Option<MakerOffers>);
+/// offers, one entry per trading pair.
+pub struct LatestMakerOffers(pub Vec<MakerOffers>);
 
 #[async_trait]
 impl xtra::Actor for Actor {
@@ -1,7 +1,10 @@
+use crate::gating::ConnectionGating;
+use crate::gating::InboundSubstreamLimiter;
 use crate::multiaddress_ext::MultiaddrExt as _;
 use crate::upgrade;
 use crate::Connection;
 use crate::Substream;
+use anyhow::anyhow;
 use anyhow::bail;
 use anyhow::Context as _;
 use anyhow::Result;
@@ -25,7 +28,10 @@ use std::collections::HashMap;
 use std::collections::HashSet;
 use std::marker::PhantomData;
 use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::Mutex;
 use std::time::Duration;
+use std::time::Instant;
 use thiserror::Error;
 use tokio_extras::Tasks;
 use tracing::instrument;
@@ -54,6 +60,10 @@ use xtras::SendAsyncSafe;
 /// connection. Any incoming substream will - assuming the protocol is supported by the endpoint -
 /// trigger a [`NewInboundSubstream`] message to the actor provided in the constructor.
 /// Opening a new substream can be achieved by sending the [`OpenSubstream`] message.
+///
+/// Which peers we connect with and how many substreams they may open is decided by the
+/// [`ConnectionGating`] set through [`Endpoint::with_connection_gating`]. Peers can be banned at
+/// runtime by sending [`BanPeer`].
 pub struct Endpoint {
     transport_fn: Box<dyn Fn() -> Boxed<Connection> + Send + 'static>,
     controls: HashMap<PeerId, (yamux::Control, Tasks)>,
@@ -62,6 +72,9 @@ pub struct Endpoint {
     inflight_connections: HashSet<PeerId>,
     connection_timeout: Duration,
     subscribers: Subscribers,
+    gating: ConnectionGating,
+    /// Kept per peer rather than per connection so that reconnecting does not reset the limit.
+    inbound_substream_limiters: HashMap<PeerId, Arc<Mutex<InboundSubstreamLimiter>>>,
 }
 
 /// Open a substream to the provided peer.
@@ -129,6 +142,16 @@ pub struct Connect(pub Multiaddr);
 #[derive(Clone, Copy, Debug)]
 pub struct Disconnect(pub PeerId);
 
+/// Disconnect from the given peer and refuse any further connections with it.
+#[derive(Clone, Copy, Debug)]
+pub struct BanPeer(pub PeerId);
+
+/// Accept connections with the given peer again after it was banned.
+///
+/// Peers which are not on the allowlist of the [`ConnectionGating`] stay rejected.
+#[derive(Clone, Copy, Debug)]
+pub struct UnbanPeer(pub PeerId);
+
 /// Listen on the provided [`Multiaddr`].
 ///
 /// For this to work, the [`Endpoint`] needs to be constructed with a compatible transport.
@@ -144,6 +167,8 @@ pub struct GetConnectionStats;
 pub struct ConnectionStats {
     pub connected_peers: HashSet<PeerId>,
     pub listen_addresses: HashSet<Multiaddr>,
+    /// The protocols we accept inbound substreams for.
+    pub supported_protocols: HashSet<&'static str>,
 }
 
 /// Notifies an actor of a new, inbound substream from the given peer.
@@ -167,6 +192,10 @@ pub enum Error {
     NoPeerIdInAddress(Multiaddr),
     #[error("Already trying to connect to peer {0}")]
     AlreadyTryingToConnected(PeerId),
+    #[error("Connections with peer {0} are not allowed")]
+    PeerNotAllowed(PeerId),
+    #[error("Maximum number of connections reached")]
+    ConnectionLimitReached,
 }
 
 /// Subscribers that get notified on connection changes
@@ -250,9 +279,42 @@ impl Endpoint {
             inflight_connections: HashSet::default(),
             connection_timeout,
             subscribers,
+            gating: ConnectionGating::default(),
+            inbound_substream_limiters: HashMap::default(),
         }
     }
 
+    /// Restrict the peers we connect with and the substreams they may open.
+    pub fn with_connection_gating(mut self, gating: ConnectionGating) -> Self {
+        self.gating = gating;
+        self
+    }
+
+    fn number_of_connections(&self) -> usize {
+        self.controls.len() + self.inflight_connections.len()
+    }
+
+    /// The inbound substream limiter of the given peer, shared by all its connections.
+    ///
+    /// Limiters of disconnected peers are dropped once they are idle.
+    fn inbound_substream_limiter(&mut self, peer: PeerId) -> Arc<Mutex<InboundSubstreamLimiter>> {
+        let now = Instant::now();
+        let controls = &self.controls;
+        self.inbound_substream_limiters.retain(|peer, limiter| {
+            controls.contains_key(peer)
+                || !limiter
+                    .lock()
+                    .expect("limiter mutex not to be poisoned")
+                    .is_idle(now)
+        });
+
+        let max_per_second = self.gating.max_inbound_substreams_per_second;
+        self.inbound_substream_limiters
+            .entry(peer)
+            .or_insert_with(|| Arc::new(Mutex::new(InboundSubstreamLimiter::new(max_per_second))))
+            .clone()
+    }
+
     async fn drop_connection(&mut self, this: &Address<Self>, peer: &PeerId) {
         let (mut control, tasks) = match self.controls.remove(peer) {
             None => return,
@@ -298,7 +360,7 @@ impl Endpoint {
 #[xtra_productivity]
 impl Endpoint {
     async fn handle(&mut self, msg: NewConnection, ctx: &mut Context<Self>) {
-        self.inflight_connections.remove(&msg.peer);
+        let is_dialed = self.inflight_connections.remove(&msg.peer);
         let this = ctx.address().expect("we are alive");
 
         let NewConnection {
@@ -308,6 +370,36 @@ impl Endpoint {
             worker,
         } = msg;
 
+        // Dropping the connection worker closes the connection. Connections we dialed fail like
+        // any other failed dial.
+        if !self.gating.is_allowed(&peer) {
+            tracing::info!(%peer, "Rejecting connection with peer that is not allowed");
+
+            if is_dialed {
+                this.send_async_next(FailedToConnect {
+                    peer,
+                    error: anyhow!(Error::PeerNotAllowed(peer)),
+                })
+                .await;
+            }
+            return;
+        }
+
+        if !self.gating.has_capacity(self.number_of_connections()) {
+            tracing::warn!(%peer, "Rejecting connection, maximum number of connections reached");
+
+            if is_dialed {
+                this.send_async_next(FailedToConnect {
+                    peer,
+                    error: anyhow!(Error::ConnectionLimitReached),
+                })
+                .await;
+            }
+            return;
+        }
+
+        let limiter = self.inbound_substream_limiter(peer);
+
         let mut tasks = Tasks::default();
         tasks.add(worker);
         tasks.add_fallible(
@@ -317,7 +409,6 @@ impl Endpoint {
                     .iter()
                     .map(|(proto, channel)| (proto.to_owned(), channel.clone()))
                     .collect::<HashMap<_, _>>();
-
                 async move {
                     loop {
                         let (stream, protocol) = match incoming_substreams.try_next().await {
@@ -334,6 +425,19 @@ impl Endpoint {
                             Err(e) => bail!(e),
                         };
 
+                        let acquired = limiter
+                            .lock()
+                            .expect("limiter mutex not to be poisoned")
+                            .try_acquire(protocol, Instant::now());
+                        if !acquired {
+                            tracing::debug!(
+                                %peer,
+                                %protocol,
+                                "Dropping inbound substream, rate limit exceeded"
+                            );
+                            continue;
+                        }
+
                         let channel = inbound_substream_channels
                             .get(&protocol)
                             .expect("Cannot negotiate a protocol that we don't support");
@@ -386,6 +490,7 @@ impl Endpoint {
         ConnectionStats {
             connected_peers: self.controls.keys().copied().collect(),
             listen_addresses: self.listen_addresses.clone(),
+            supported_protocols: self.inbound_substream_channels.keys().copied().collect(),
         }
     }
 
@@ -402,6 +507,14 @@ impl Endpoint {
             return Err(Error::AlreadyTryingToConnected(peer));
         }
 
+        if !self.gating.is_allowed(&peer) {
+            return Err(Error::PeerNotAllowed(peer));
+        }
+
+        if !self.gating.has_capacity(self.number_of_connections()) {
+            return Err(Error::ConnectionLimitReached);
+        }
+
         let mut transport = (self.transport_fn)();
 
         self.inflight_connections.insert(peer);
@@ -446,6 +559,22 @@ impl Endpoint {
             .await;
     }
 
+    async fn handle(&mut self, msg: BanPeer, ctx: &mut Context<Self>) {
+        let peer = msg.0;
+        tracing::info!(%peer, "Banning peer");
+
+        self.gating.denylist.insert(peer);
+        self.drop_connection(&ctx.address().expect("self to be alive"), &peer)
+            .await;
+    }
+
+    async fn handle(&mut self, msg: UnbanPeer) {
+        let peer = msg.0;
+        tracing::info!(%peer, "Unbanning peer");
+
+        self.gating.denylist.remove(&peer);
+    }
+
     async fn handle(&mut self, msg: ListenOn, ctx: &mut Context<Self>) {
         let this = ctx.address().expect("we are alive");
         let listen_address = msg.0.clone();
@@ -0,0 +1,175 @@
+//! Policy for which connections and inbound substreams an [`Endpoint`](crate::Endpoint) accepts.
+
+use libp2p_core::PeerId;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::time::Duration;
+use std::time::Instant;
+
+/// Decides which peers an [`Endpoint`](crate::Endpoint) connects with and how many inbound
+/// substreams they may open.
+///
+/// The default accepts every peer without any limits.
+#[derive(Clone, Debug, Default)]
+pub struct ConnectionGating {
+    /// If set, only connections to and from these peers are accepted.
+    pub allowlist: Option<HashSet<PeerId>>,
+    /// Connections to and from these peers are never accepted, regardless of the allowlist.
+    pub denylist: HashSet<PeerId>,
+    /// The maximum number of concurrent connections, including the ones we are still dialing.
+    pub max_connections: Option<usize>,
+    /// The maximum number of inbound substreams a single peer may open per protocol and second.
+    ///
+    /// Substreams exceeding the limit are dropped before they are handed to the protocol handler.
+    pub max_inbound_substreams_per_second: Option<u32>,
+}
+
+impl ConnectionGating {
+    /// Whether we may be connected to the given peer.
+    pub fn is_allowed(&self, peer: &PeerId) -> bool {
+        if self.denylist.contains(peer) {
+            return false;
+        }
+
+        match &self.allowlist {
+            Some(allowlist) => allowlist.contains(peer),
+            None => true,
+        }
+    }
+
+    /// Whether another connection may be added to the given number of connections.
+    pub(crate) fn has_capacity(&self, connections: usize) -> bool {
+        match self.max_connections {
+            Some(max_connections) => connections < max_connections,
+            None => true,
+        }
+    }
+}
+
+/// Counts the inbound substreams of a single peer per protocol within windows of one second.
+///
+/// The [`Endpoint`](crate::Endpoint) keeps one limiter per peer across its connections, so a peer
+/// cannot reset its limit by reconnecting.
+pub(crate) struct InboundSubstreamLimiter {
+    max_per_second: Option<u32>,
+    windows: HashMap<&'static str, (Instant, u32)>,
+}
+
+impl InboundSubstreamLimiter {
+    pub(crate) fn new(max_per_second: Option<u32>) -> Self {
+        Self {
+            max_per_second,
+            windows: HashMap::default(),
+        }
+    }
+
+    /// Record a new inbound substream for the given protocol.
+    ///
+    /// Returns `false` if the peer exceeded the limit and the substream should be dropped.
+    pub(crate) fn try_acquire(&mut self, protocol: &'static str, now: Instant) -> bool {
+        let max_per_second = match self.max_per_second {
+            Some(max_per_second) => max_per_second,
+            None => return true,
+        };
+
+        let (window_start, count) = self.windows.entry(protocol).or_insert((now, 0));
+
+        if now.saturating_duration_since(*window_start) >= Duration::from_secs(1) {
+            *window_start = now;
+            *count = 0;
+        }
+
+        if *count >= max_per_second {
+            return false;
+        }
+
+        *count += 1;
+
+        true
+    }
+
+    /// Whether the peer did not open any substream within the last second.
+    ///
+    /// Idle limiters don't restrict the peer anymore and can be dropped.
+    pub(crate) fn is_idle(&self, now: Instant) -> bool {
+        self.windows.values().all(|(window_start, _)| {
+            now.saturating_duration_since(*window_start) >= Duration::from_secs(1)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn denylist_takes_precedence_over_allowlist() {
+        let peer = PeerId::random();
+
+        let gating = ConnectionGating {
+            allowlist: Some(HashSet::from([peer])),
+            denylist: HashSet::from([peer]),
+            ..ConnectionGating::default()
+        };
+
+        assert!(!gating.is_allowed(&peer));
+    }
+
+    #[test]
+    fn only_peers_on_allowlist_are_allowed() {
+        let allowed = PeerId::random();
+        let other = PeerId::random();
+
+        let gating = ConnectionGating {
+            allowlist: Some(HashSet::from([allowed])),
+            ..ConnectionGating::default()
+        };
+
+        assert!(gating.is_allowed(&allowed));
+        assert!(!gating.is_allowed(&other));
+    }
+
+    #[test]
+    fn default_allows_everything() {
+        let gating = ConnectionGating::default();
+
+        assert!(gating.is_allowed(&PeerId::random()));
+        assert!(gating.has_capacity(usize::MAX - 1));
+    }
+
+    #[test]
+    fn connection_limit_is_inclusive() {
+        let gating = ConnectionGating {
+            max_connections: Some(2),
+            ..ConnectionGating::default()
+        };
+
+        assert!(gating.has_capacity(1));
+        assert!(!gating.has_capacity(2));
+    }
+
+    #[test]
+    fn substreams_are_limited_per_protocol_and_second() {
+        let mut limiter = InboundSubstreamLimiter::new(Some(2));
+        let start = Instant::now();
+
+        assert!(limiter.try_acquire("/foo/1.0.0", start));
+        assert!(limiter.try_acquire("/foo/1.0.0", start));
+        assert!(!limiter.try_acquire("/foo/1.0.0", start + Duration::from_millis(500)));
+        assert!(limiter.try_acquire("/bar/1.0.0", start + Duration::from_millis(500)));
+        assert!(limiter.try_acquire("/foo/1.0.0", start + Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn limiter_is_idle_one_second_after_last_window_started() {
+        let mut limiter = InboundSubstreamLimiter::new(Some(2));
+        let start = Instant::now();
+
+        assert!(limiter.is_idle(start));
+
+        limiter.try_acquire("/foo/1.0.0", start);
+
+        assert!(!limiter.is_idle(start + Duration::from_millis(500)));
+        assert!(limiter.is_idle(start + Duration::from_secs(1)));
+    }
+}
@@ -1,3 +1,4 @@
+pub use crate::endpoint::BanPeer;
 pub use crate::endpoint::Connect;
 pub use crate::endpoint::ConnectionStats;
 pub use crate::endpoint::Disconnect;
@@ -9,6 +10,8 @@ pub use crate::endpoint::Multiple;
 pub use crate::endpoint::NewInboundSubstream;
 pub use crate::endpoint::OpenSubstream;
 pub use crate::endpoint::Single;
+pub use crate::endpoint::UnbanPeer;
+pub use crate::gating::ConnectionGating;
 pub use crate::substream::Substream;
 pub use libp2p_core as libp2p;
 pub use multistream_select::NegotiationError;
@@ -20,6 +23,7 @@ use libp2p_core::PeerId;
 
 pub mod dialer;
 pub mod endpoint;
+pub mod gating;
 pub mod listener;
 pub mod multiaddress_ext;
 mod substream;
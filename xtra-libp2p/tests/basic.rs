@@ -18,7 +18,9 @@ use xtra_libp2p::endpoint::Subscribers;
 use xtra_libp2p::libp2p::identity::Keypair;
 use xtra_libp2p::libp2p::transport::MemoryTransport;
 use xtra_libp2p::libp2p::PeerId;
+use xtra_libp2p::BanPeer;
 use xtra_libp2p::Connect;
+use xtra_libp2p::ConnectionGating;
 use xtra_libp2p::Disconnect;
 use xtra_libp2p::Endpoint;
 use xtra_libp2p::GetConnectionStats;
@@ -312,6 +314,120 @@ async fn falls_back_to_next_protocol_if_unsupported() {
     assert_eq!(actual_protocol, "/hello-world/1.0.0");
 }
 
+#[tokio::test]
+async fn cannot_connect_to_denied_peer() {
+    let alice = make_node([]);
+    let bob = make_node_with_gating(
+        [],
+        ConnectionGating {
+            denylist: HashSet::from([alice.peer_id]),
+            ..ConnectionGating::default()
+        },
+    );
+
+    let port = rand::random::<u16>();
+    let alice_listen = format!("/memory/{port}").parse::<Multiaddr>().unwrap();
+    alice
+        .endpoint
+        .send(ListenOn(alice_listen.clone()))
+        .await
+        .unwrap();
+
+    let error = bob
+        .endpoint
+        .send(Connect(
+            alice_listen.with(Protocol::P2p(alice.peer_id.into())),
+        ))
+        .await
+        .unwrap()
+        .unwrap_err();
+
+    assert!(matches!(
+        error,
+        xtra_libp2p::Error::PeerNotAllowed(denied) if denied == alice.peer_id
+    ))
+}
+
+#[tokio::test]
+async fn connection_of_peer_not_on_allowlist_is_rejected() {
+    let alice = make_node_with_gating(
+        [],
+        ConnectionGating {
+            allowlist: Some(HashSet::new()),
+            ..ConnectionGating::default()
+        },
+    );
+    let bob = make_node([]);
+
+    let port = rand::random::<u16>();
+    let alice_listen = format!("/memory/{port}").parse::<Multiaddr>().unwrap();
+    alice
+        .endpoint
+        .send(ListenOn(alice_listen.clone()))
+        .await
+        .unwrap();
+
+    bob.endpoint
+        .send(Connect(
+            alice_listen.with(Protocol::P2p(alice.peer_id.into())),
+        ))
+        .await
+        .unwrap()
+        .unwrap();
+    tokio_extras::time::sleep(Duration::from_millis(500)).await;
+
+    let alice_stats = alice.endpoint.send(GetConnectionStats).await.unwrap();
+
+    assert!(alice_stats.connected_peers.is_empty());
+}
+
+#[tokio::test]
+async fn banned_peer_is_disconnected_and_cannot_reconnect() {
+    let (alice, bob, alice_listen) = alice_and_bob([], []).await;
+
+    alice.endpoint.send(BanPeer(bob.peer_id)).await.unwrap();
+
+    let alice_stats = alice.endpoint.send(GetConnectionStats).await.unwrap();
+    assert!(alice_stats.connected_peers.is_empty());
+
+    // Wait for Bob to notice that the connection was closed before reconnecting
+    tokio_extras::time::sleep(Duration::from_millis(500)).await;
+    bob.endpoint
+        .send(Connect(
+            alice_listen.with(Protocol::P2p(alice.peer_id.into())),
+        ))
+        .await
+        .unwrap()
+        .unwrap();
+    tokio_extras::time::sleep(Duration::from_millis(500)).await;
+
+    let alice_stats = alice.endpoint.send(GetConnectionStats).await.unwrap();
+    assert!(alice_stats.connected_peers.is_empty());
+}
+
+#[tokio::test]
+async fn cannot_connect_beyond_connection_limit() {
+    let alice = make_node([]);
+    let bob = make_node_with_gating(
+        [],
+        ConnectionGating {
+            max_connections: Some(0),
+            ..ConnectionGating::default()
+        },
+    );
+
+    let error = bob
+        .endpoint
+        .send(Connect(
+            format!("/memory/1/p2p/{}", alice.peer_id).parse().unwrap(),
+        ))
+        .await
+        .unwrap()
+        .unwrap_err();
+
+    assert!(matches!(error, xtra_libp2p::Error::ConnectionLimitReached))
+}
+
 async fn alice_and_bob<const AN: usize, const BN: usize>(
     alice_inbound_substream_handlers: [(&'static str, MessageChannel<NewInboundSubstream, ()>); AN],
     bob_inbound_substream_handlers: [(&'static str, MessageChannel<NewInboundSubstream, ()>); BN],
@@ -353,6 +469,13 @@ struct Node {
 
 fn make_node<const N: usize>(
     substream_handlers: [(&'static str, MessageChannel<NewInboundSubstream, ()>); N],
+) -> Node {
+    make_node_with_gating(substream_handlers, ConnectionGating::default())
+}
+
+fn make_node_with_gating<const N: usize>(
+    substream_handlers: [(&'static str, MessageChannel<NewInboundSubstream, ()>); N],
+    gating: ConnectionGating,
 ) -> Node {
     let id = Keypair::generate_ed25519();
     let peer_id = id.public().to_peer_id();
@@ -373,6 +496,7 @@ fn make_node<const N: usize>(
             vec![subscriber_stats.clone().into()],
         ),
     )
+    .with_connection_gating(gating)
     .create(None)
     .spawn_global();
 